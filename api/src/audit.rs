@@ -0,0 +1,153 @@
+use actix_web::HttpRequest;
+use diesel::result::QueryResult;
+use diesel::sqlite::SqliteConnection;
+use serde::Serialize;
+use serde_json::Value;
+
+use domain::models::audit::{NewAuditEntry, SYSTEM_ACTOR};
+use domain::traits::{AcquisitionRepositoryTrait, AuditRepositoryTrait, BookRepositoryTrait, CalendarRepositoryTrait, LibraryRepositoryTrait, MaintenanceRepositoryTrait, MemberRepositoryTrait, StaffRepositoryTrait, TransferRepositoryTrait, UserRepositoryTrait};
+use infrastructure::repositories::acquisition_repository::AcquisitionRepository;
+use infrastructure::repositories::audit_repository::AuditRepository;
+use infrastructure::repositories::book_repository::BookRepository;
+use infrastructure::repositories::calendar_repository::CalendarRepository;
+use infrastructure::repositories::library_repository::LibraryRepository;
+use infrastructure::repositories::maintenance_repository::MaintenanceRepository;
+use infrastructure::repositories::member_repository::MemberRepository;
+use infrastructure::repositories::staff_repository::StaffRepository;
+use infrastructure::repositories::transfer_repository::TransferRepository;
+use infrastructure::repositories::user_repository::UserRepository;
+use infrastructure::security::generate_token;
+
+use crate::auth::AuthenticatedUser;
+use crate::request_id::request_id;
+use crate::AppState;
+
+pub fn snapshot<T: Serialize>(value: &T) -> Option<Value> {
+    serde_json::to_value(value).ok()
+}
+
+pub struct AuditEvent<'a> {
+    pub action: &'a str,
+    pub entity: &'a str,
+    pub entity_id: String,
+    pub before: Option<Value>,
+    pub after: Option<Value>,
+}
+
+// Who the entries of a transaction are recorded for: the signed-in user of a request, or the service itself.
+pub struct Actor<'a> {
+    pub request_id: String,
+    pub user_id: Option<i32>,
+    pub username: &'a str,
+}
+
+impl Actor<'_> {
+    pub fn user<'a>(req: &HttpRequest, user: &'a AuthenticatedUser) -> Actor<'a> {
+        Actor { request_id: request_id(req), user_id: Some(user.id), username: user.username.as_str() }
+    }
+
+    // Scheduled jobs and library-admin commands; `source` names the job or command and prefixes the request id.
+    pub fn system(source: &str) -> Actor<'static> {
+        Actor { request_id: format!("{}-{}", source, &generate_token()[..8]), user_id: None, username: SYSTEM_ACTOR }
+    }
+}
+
+// Repositories working on the connection of one open transaction, and the audit entries of the changes made in it.
+pub struct Tx<'c> {
+    conn: &'c mut SqliteConnection,
+    actor: &'c Actor<'c>,
+    audit_log: bool,
+}
+
+impl Tx<'_> {
+    pub fn books(&mut self) -> impl BookRepositoryTrait + '_ {
+        BookRepository::with_connection(self.conn)
+    }
+
+    pub fn libraries(&mut self) -> impl LibraryRepositoryTrait + '_ {
+        LibraryRepository::with_connection(self.conn)
+    }
+
+    pub fn members(&mut self) -> impl MemberRepositoryTrait + '_ {
+        MemberRepository::with_connection(self.conn)
+    }
+
+    pub fn users(&mut self) -> impl UserRepositoryTrait + '_ {
+        UserRepository::with_connection(self.conn)
+    }
+
+    pub fn transfers(&mut self) -> impl TransferRepositoryTrait + '_ {
+        TransferRepository::with_connection(self.conn)
+    }
+
+    pub fn calendar(&mut self) -> impl CalendarRepositoryTrait + '_ {
+        CalendarRepository::with_connection(self.conn)
+    }
+
+    pub fn staff(&mut self) -> impl StaffRepositoryTrait + '_ {
+        StaffRepository::with_connection(self.conn)
+    }
+
+    pub fn acquisitions(&mut self) -> impl AcquisitionRepositoryTrait + '_ {
+        AcquisitionRepository::with_connection(self.conn)
+    }
+
+    pub fn maintenance(&mut self) -> impl MaintenanceRepositoryTrait + '_ {
+        MaintenanceRepository::with_connection(self.conn)
+    }
+
+    // Appends the entry for a change made in this transaction, unless the audit_log feature is off. It is committed
+    // or rolled back together with the change.
+    pub fn record(&mut self, event: AuditEvent) -> QueryResult<()> {
+        if !self.audit_log {
+            return Ok(());
+        }
+
+        let diff = match (&event.before, &event.after) {
+            (Some(before), Some(after)) => serde_json::to_string(&json_patch::diff(before, after)).ok(),
+            _ => None,
+        };
+        let before = event.before.as_ref().map(|value| value.to_string());
+        let after = event.after.as_ref().map(|value| value.to_string());
+        let occurred_at: String = chrono::offset::Utc::now().naive_utc().to_string();
+
+        let entry = NewAuditEntry {
+            occurred_at: occurred_at.as_str(),
+            request_id: self.actor.request_id.as_str(),
+            actor_user_id: self.actor.user_id.as_ref(),
+            actor_username: Some(self.actor.username),
+            action: event.action,
+            entity: event.entity,
+            entity_id: event.entity_id.as_str(),
+            before_json: before.as_deref(),
+            after_json: after.as_deref(),
+            diff_json: diff.as_deref(),
+        };
+
+        AuditRepository::with_connection(self.conn).record(&entry).map(|_| ())
+    }
+}
+
+// Runs `change` and the audit entries it records in one transaction on `conn`: when `change` or any entry fails,
+// nothing of it is stored.
+pub fn in_transaction<T>(
+    conn: &mut SqliteConnection,
+    audit_log: bool,
+    actor: &Actor,
+    change: impl FnOnce(&mut Tx) -> QueryResult<T>,
+) -> QueryResult<T> {
+    // Immediate: the write lock is taken up front, so a read made before the first write cannot go stale.
+    conn.immediate_transaction(|conn| change(&mut Tx { conn, actor, audit_log }))
+}
+
+// `in_transaction` for a request of `user`.
+pub fn audited<T>(repos: &AppState, req: &HttpRequest, user: &AuthenticatedUser, change: impl FnOnce(&mut Tx) -> QueryResult<T>) -> QueryResult<T> {
+    let mut conn = repos.pool.get().expect("Failed to get a connection from the pool");
+    in_transaction(&mut conn, repos.features.audit_log, &Actor::user(req, user), change)
+}
+
+// `in_transaction` for a change the service makes on its own; see `Actor::system`.
+pub fn audited_by_system<T>(repos: &AppState, source: &str, change: impl FnOnce(&mut Tx) -> QueryResult<T>) -> QueryResult<T> {
+    let mut conn = repos.pool.get().expect("Failed to get a connection from the pool");
+    in_transaction(&mut conn, repos.features.audit_log, &Actor::system(source), change)
+}
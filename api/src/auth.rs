@@ -0,0 +1,317 @@
+use std::fmt;
+use std::future::{ready, Ready};
+
+use actix_web::dev::Payload;
+use actix_web::http::header::{self, HeaderValue};
+use actix_web::http::StatusCode;
+use actix_web::{web, FromRequest, HttpRequest, HttpResponse, ResponseError};
+use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, Validation};
+use serde::{Deserialize, Serialize};
+use tracing::error;
+use utoipa::ToSchema;
+
+use domain::models::role::{Permission, Role};
+use domain::models::user::User;
+use infrastructure::security::{generate_token, hash_token};
+
+use crate::AppState;
+
+pub const API_KEY_HEADER: &str = "X-Api-Key";
+
+pub struct AuthSettings {
+    pub jwt_secret: String,
+    pub access_token_ttl_seconds: i64,
+    pub refresh_token_ttl_seconds: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Claims {
+    pub sub: i32,
+    pub username: String,
+    pub account_type: String,
+    pub member_id: Option<i32>,
+    pub iat: i64,
+    pub exp: i64,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct RoleGrant {
+    pub role: Role,
+    pub library_id: Option<i32>,
+}
+
+// The caller behind a request, resolved from a bearer JWT or an API key, with the roles it holds right now.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct AuthenticatedUser {
+    pub id: i32,
+    pub username: String,
+    pub account_type: String,
+    pub member_id: Option<i32>,
+    pub grants: Vec<RoleGrant>,
+}
+
+impl AuthenticatedUser {
+    // A grant without a library applies everywhere; `library_id` None asks for a global grant.
+    pub fn can(&self, permission: Permission, library_id: Option<i32>) -> bool {
+        self.grants
+            .iter()
+            .any(|grant| grant.role.grants(permission) && (grant.library_id.is_none() || grant.library_id == library_id))
+    }
+
+    pub fn can_anywhere(&self, permission: Permission) -> bool {
+        self.grants.iter().any(|grant| grant.role.grants(permission))
+    }
+
+    pub fn is_member(&self, member_id: i32) -> bool {
+        self.member_id == Some(member_id)
+    }
+
+    pub fn require(&self, permission: Permission, library_id: Option<i32>) -> Result<(), AuthError> {
+        if self.can(permission, library_id) {
+            return Ok(());
+        }
+
+        Err(AuthError::Forbidden(match library_id {
+            Some(library_id) => format!("Missing permission '{}' for library {}", permission, library_id),
+            None => format!("Missing permission '{}'", permission),
+        }))
+    }
+
+    pub fn require_anywhere(&self, permission: Permission) -> Result<(), AuthError> {
+        if self.can_anywhere(permission) {
+            return Ok(());
+        }
+
+        Err(AuthError::Forbidden(format!("Missing permission '{}'", permission)))
+    }
+}
+
+#[derive(Debug)]
+pub enum AuthError {
+    MissingCredentials,
+    InvalidCredentials,
+    Forbidden(String),
+    Internal(String),
+}
+
+impl fmt::Display for AuthError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AuthError::MissingCredentials => write!(f, "Authentication required"),
+            AuthError::InvalidCredentials => write!(f, "Invalid or expired credentials"),
+            AuthError::Forbidden(message) => write!(f, "{}", message),
+            AuthError::Internal(message) => write!(f, "Authentication failed: {}", message),
+        }
+    }
+}
+
+impl ResponseError for AuthError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            AuthError::MissingCredentials | AuthError::InvalidCredentials => StatusCode::UNAUTHORIZED,
+            AuthError::Forbidden(_) => StatusCode::FORBIDDEN,
+            AuthError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        let mut response = HttpResponse::build(self.status_code());
+        if self.status_code() == StatusCode::UNAUTHORIZED {
+            response.insert_header((header::WWW_AUTHENTICATE, HeaderValue::from_static("Bearer")));
+        }
+        response.body(self.to_string())
+    }
+}
+
+pub fn issue_access_token(settings: &AuthSettings, user: &User) -> Result<String, AuthError> {
+    let now = chrono::offset::Utc::now().timestamp();
+    let claims = Claims {
+        sub: user.id,
+        username: user.username.clone(),
+        account_type: user.account_type.clone(),
+        member_id: user.member_id,
+        iat: now,
+        exp: now + settings.access_token_ttl_seconds,
+    };
+
+    encode(&Header::default(), &claims, &EncodingKey::from_secret(settings.jwt_secret.as_bytes()))
+        .map_err(|e| AuthError::Internal(e.to_string()))
+}
+
+pub fn decode_access_token(settings: &AuthSettings, token: &str) -> Result<Claims, AuthError> {
+    decode::<Claims>(token, &DecodingKey::from_secret(settings.jwt_secret.as_bytes()), &Validation::default())
+        .map(|data| data.claims)
+        .map_err(|_| AuthError::InvalidCredentials)
+}
+
+// Returns the plaintext refresh token for the client; only its hash is persisted.
+pub fn issue_refresh_token(state: &AppState, user_id: i32) -> Result<String, AuthError> {
+    let token = generate_token();
+    let expires_at = (chrono::offset::Utc::now() + chrono::Duration::seconds(state.auth.refresh_token_ttl_seconds))
+        .naive_utc()
+        .to_string();
+
+    state.user_repo.lock().unwrap()
+        .create_refresh_token(&user_id, &hash_token(&token), &expires_at)
+        .map_err(|e| AuthError::Internal(e.to_string()))?;
+
+    Ok(token)
+}
+
+// Explicit grants, plus the member role for accounts linked to a member, the manager role for every library
+// whose manager_id points at that member and the roles of the member's active staff assignments.
+fn load_grants(state: &AppState, user_id: i32, member_id: Option<i32>) -> Result<Vec<RoleGrant>, AuthError> {
+    let roles = state.user_repo.lock().unwrap()
+        .get_user_roles(&user_id)
+        .map_err(|e| AuthError::Internal(e.to_string()))?;
+    let mut grants: Vec<RoleGrant> = roles
+        .iter()
+        .filter_map(|role| Role::parse(&role.role).map(|parsed| RoleGrant { role: parsed, library_id: role.library_id }))
+        .collect();
+
+    if let Some(member_id) = member_id {
+        grants.push(RoleGrant { role: Role::Member, library_id: None });
+
+        let managed = state.lib_repo.lock().unwrap()
+            .get_libraries_by_manager_id(&member_id)
+            .map_err(|e| AuthError::Internal(e.to_string()))?;
+        grants.extend(managed.iter().map(|library| RoleGrant { role: Role::LibraryManager, library_id: Some(library.id) }));
+
+        let today = chrono::offset::Utc::now().date_naive().to_string();
+        let assignments = state.staff_repo.lock().unwrap()
+            .get_member_assignments(&member_id, &today)
+            .map_err(|e| AuthError::Internal(e.to_string()))?;
+        grants.extend(assignments.iter().filter_map(|assignment| {
+            Role::parse(&assignment.role).map(|role| RoleGrant { role, library_id: Some(assignment.library_id) })
+        }));
+    }
+
+    Ok(grants)
+}
+
+fn identify(state: &AppState, req: &HttpRequest) -> Result<(i32, String, String, Option<i32>), AuthError> {
+    if let Some(value) = req.headers().get(header::AUTHORIZATION) {
+        let token = value
+            .to_str()
+            .ok()
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .ok_or(AuthError::InvalidCredentials)?;
+        let claims = decode_access_token(&state.auth, token.trim())?;
+
+        return Ok((claims.sub, claims.username, claims.account_type, claims.member_id));
+    }
+
+    if let Some(value) = req.headers().get(API_KEY_HEADER).filter(|_| state.features.api_keys) {
+        let key = value.to_str().map_err(|_| AuthError::InvalidCredentials)?;
+        let mut user_repo = state.user_repo.lock().unwrap();
+
+        let api_key = match user_repo.get_api_key_by_hash(&hash_token(key.trim())) {
+            Ok(api_key) if api_key.revoked_at.is_none() => api_key,
+            Ok(_) | Err(diesel::result::Error::NotFound) => return Err(AuthError::InvalidCredentials),
+            Err(e) => return Err(AuthError::Internal(e.to_string())),
+        };
+        let user = user_repo
+            .get_user_by_id(&api_key.user_id)
+            .map_err(|_| AuthError::InvalidCredentials)?;
+        if let Err(e) = user_repo.touch_api_key(&api_key.id) {
+            error!(error = ?e, "Failed to record api key usage"); // Hata mesajını logla
+        }
+
+        return Ok((user.id, user.username, user.account_type, user.member_id));
+    }
+
+    Err(AuthError::MissingCredentials)
+}
+
+fn authenticate(req: &HttpRequest) -> Result<AuthenticatedUser, AuthError> {
+    let state = req
+        .app_data::<web::Data<AppState>>()
+        .ok_or_else(|| AuthError::Internal("application state is not configured".to_string()))?;
+
+    let (id, username, account_type, member_id) = identify(state, req)?;
+    let grants = load_grants(state, id, member_id)?;
+
+    Ok(AuthenticatedUser {
+        id,
+        username,
+        account_type,
+        member_id,
+        grants,
+    })
+}
+
+impl FromRequest for AuthenticatedUser {
+    type Error = AuthError;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        ready(authenticate(req))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use chrono::{Days, Utc};
+    use diesel::connection::SimpleConnection;
+    use infrastructure::config::Config;
+
+    use super::*;
+    use crate::metrics::Metrics;
+
+    // One in-memory database behind a single-connection pool: member 1 manages no library of their own.
+    fn state() -> AppState {
+        let mut config = Config::default();
+        config.database.url = ":memory:".to_string();
+        config.database.pool_max_size = 1;
+        let state = crate::create_app_state(&config, Arc::new(Metrics::new()));
+        state.pool.get().unwrap()
+            .batch_execute("
+                INSERT INTO members (id, name, email, created_at, updated_at) VALUES (1, 'Ada', 'ada@example.com', '2026-01-01', '2026-01-01');
+                INSERT INTO members (id, name, email, created_at, updated_at) VALUES (2, 'Grace', 'grace@example.com', '2026-01-01', '2026-01-01');
+                INSERT INTO library (id, name, address, created_at, updated_at, manager_id) VALUES (1, 'Central', 'Main St 1', '2026-01-01', '2026-01-01', 2);
+                INSERT INTO library (id, name, address, created_at, updated_at, manager_id) VALUES (2, 'North', 'North St 1', '2026-01-01', '2026-01-01', 2);
+            ")
+            .unwrap();
+        state
+    }
+
+    fn assign(state: &AppState, library_id: i32, ended_at: Option<&str>) {
+        let ended_at = ended_at.map_or("NULL".to_string(), |day| format!("'{}'", day));
+        state.pool.get().unwrap()
+            .batch_execute(&format!(
+                "INSERT INTO library_staff (library_id, member_id, role, started_at, ended_at, created_at)
+                     VALUES ({}, 1, 'librarian', '2026-01-01', {}, '2026-01-01')",
+                library_id, ended_at
+            ))
+            .unwrap();
+    }
+
+    fn user(grants: Vec<RoleGrant>) -> AuthenticatedUser {
+        AuthenticatedUser { id: 1, username: "ada".to_string(), account_type: "member".to_string(), member_id: Some(1), grants }
+    }
+
+    #[test]
+    fn an_ended_assignment_grants_nothing() {
+        let state = state();
+        let today = Utc::now().date_naive();
+        assign(&state, 1, Some(&today.to_string()));
+        assign(&state, 2, Some(&today.checked_add_days(Days::new(1)).unwrap().to_string()));
+
+        let user = user(load_grants(&state, 1, Some(1)).unwrap());
+        assert!(!user.can(Permission::ManageLoans, Some(1)));
+        assert!(user.can(Permission::ManageLoans, Some(2)));
+        assert!(user.can(Permission::ViewOwnLoans, None));
+    }
+
+    #[test]
+    fn an_open_ended_assignment_grants_its_role() {
+        let state = state();
+        assign(&state, 1, None);
+
+        let user = user(load_grants(&state, 1, Some(1)).unwrap());
+        assert!(user.can(Permission::ManageLoans, Some(1)));
+        assert!(!user.can(Permission::ManageLoans, Some(2)));
+    }
+}
@@ -0,0 +1,271 @@
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, NaiveTime};
+use diesel::QueryResult;
+use domain::models::calendar::{Closure, OpeningHours};
+use domain::models::library::Library;
+use domain::models::loan::Loan;
+
+use crate::AppState;
+
+// How far a due date is moved at most looking for an open day; a library closed for longer keeps the date.
+const MAX_SHIFT_DAYS: i64 = 366;
+
+// A library's weekly opening hours and closures, answering which days it is open.
+pub struct LibraryCalendar {
+    pub hours: Vec<OpeningHours>,
+    pub closures: Vec<Closure>,
+}
+
+impl LibraryCalendar {
+    pub fn hours_on(&self, date: NaiveDate) -> Option<&OpeningHours> {
+        let weekday = date.weekday().number_from_monday() as i32;
+        self.hours.iter().find(|hours| hours.weekday == weekday)
+    }
+
+    pub fn closure_on(&self, date: NaiveDate) -> Option<&Closure> {
+        let date = date.to_string();
+        self.closures.iter().find(|closure| closure.date == date)
+    }
+
+    // Libraries without opening hours are open every day but their closures.
+    pub fn is_open(&self, date: NaiveDate) -> bool {
+        self.closure_on(date).is_none() && (self.hours.is_empty() || self.hours_on(date).is_some())
+    }
+
+    // `date` itself when the library is open then, otherwise the next day it is.
+    pub fn next_open_day(&self, date: NaiveDate) -> NaiveDate {
+        (0..=MAX_SHIFT_DAYS)
+            .map(|days| date + Duration::days(days))
+            .find(|day| self.is_open(*day))
+            .unwrap_or(date)
+    }
+
+    // Closed days among the `days` days starting at `from`.
+    pub fn closed_days(&self, from: NaiveDate, days: i64) -> i64 {
+        (0..days).filter(|offset| !self.is_open(from + Duration::days(*offset))).count() as i64
+    }
+}
+
+// The library's calendar with its closures on or after `from`, or all of them when None.
+pub fn load_calendar(repos: &AppState, library_id: i32, from: Option<&str>) -> QueryResult<LibraryCalendar> {
+    let mut calendar_repo = repos.calendar_repo.lock().unwrap();
+    Ok(LibraryCalendar {
+        hours: calendar_repo.get_hours(&library_id)?,
+        closures: calendar_repo.get_closures(&library_id, from, None)?,
+    })
+}
+
+// Moves a due date to the next day the library is open, keeping the time of day. Loans not booked to a library
+// keep the date.
+pub fn due_on_open_day(repos: &AppState, library_id: Option<i32>, due: NaiveDateTime) -> QueryResult<NaiveDateTime> {
+    let library_id = match library_id {
+        Some(library_id) => library_id,
+        None => return Ok(due),
+    };
+
+    let calendar = load_calendar(repos, library_id, Some(&due.date().to_string()))?;
+    Ok(calendar.next_open_day(due.date()).and_time(due.time()))
+}
+
+// Calendars of the libraries the loans are booked to, by library id.
+pub fn loan_calendars(repos: &AppState, loans: &[Loan]) -> QueryResult<HashMap<i32, LibraryCalendar>> {
+    let mut calendars = HashMap::new();
+    for library_id in loans.iter().filter_map(|loan| loan.library_id) {
+        if let Entry::Vacant(entry) = calendars.entry(library_id) {
+            entry.insert(load_calendar(repos, library_id, None)?);
+        }
+    }
+    Ok(calendars)
+}
+
+// Escapes TEXT values as RFC 5545 requires.
+fn ics_text(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+// Folds content lines longer than 75 octets, continuing them with a space, and ends each with CRLF.
+fn ics_line(out: &mut String, line: &str) {
+    let mut width = 0;
+    for c in line.chars() {
+        if width + c.len_utf8() > 75 {
+            out.push_str("\r\n ");
+            width = 1;
+        }
+        out.push(c);
+        width += c.len_utf8();
+    }
+    out.push_str("\r\n");
+}
+
+// "09:00" as "090000"; stored times are HH:MM, but a time that does not parse is still written out as given.
+fn ics_time(time: &str) -> String {
+    match NaiveTime::parse_from_str(time, "%H:%M") {
+        Ok(time) => time.format("%H%M%S").to_string(),
+        Err(_) => format!("{}00", time.replace(':', "")),
+    }
+}
+
+// The calendar from `from` until the day before `to` as iCalendar: weekly opening hours as recurring events with
+// the closures left out, and each closure as an all-day event. Times are floating, i.e. local to the library.
+pub fn to_ics(library: &Library, calendar: &LibraryCalendar, from: NaiveDate, to: NaiveDate, now: NaiveDateTime) -> String {
+    let stamp = now.format("%Y%m%dT%H%M%SZ").to_string();
+    let until = (to - Duration::days(1)).format("%Y%m%dT235959").to_string();
+    let mut out = String::new();
+    ics_line(&mut out, "BEGIN:VCALENDAR");
+    ics_line(&mut out, "VERSION:2.0");
+    ics_line(&mut out, "PRODID:-//LibraryAutomation//Library Calendar//EN");
+    ics_line(&mut out, "CALSCALE:GREGORIAN");
+    ics_line(&mut out, &format!("X-WR-CALNAME:{}", ics_text(&library.name)));
+
+    for hours in &calendar.hours {
+        let first = match (0..7).map(|days| from + Duration::days(days)).find(|day| day.weekday().number_from_monday() as i32 == hours.weekday) {
+            Some(first) if first < to => first,
+            _ => continue,
+        };
+        let start = first.format("%Y%m%d").to_string();
+        ics_line(&mut out, "BEGIN:VEVENT");
+        ics_line(&mut out, &format!("UID:hours-{}-{}@library-automation", library.id, hours.weekday));
+        ics_line(&mut out, &format!("DTSTAMP:{}", stamp));
+        ics_line(&mut out, &format!("DTSTART:{}T{}", start, ics_time(&hours.opens_at)));
+        ics_line(&mut out, &format!("DTEND:{}T{}", start, ics_time(&hours.closes_at)));
+        ics_line(&mut out, &format!("RRULE:FREQ=WEEKLY;UNTIL={}", until));
+        for closure in &calendar.closures {
+            if let Ok(date) = NaiveDate::parse_from_str(&closure.date, "%Y-%m-%d") {
+                if date >= first && date < to && date.weekday().number_from_monday() as i32 == hours.weekday {
+                    ics_line(&mut out, &format!("EXDATE:{}T{}", date.format("%Y%m%d"), ics_time(&hours.opens_at)));
+                }
+            }
+        }
+        ics_line(&mut out, &format!("SUMMARY:{} open", ics_text(&library.name)));
+        ics_line(&mut out, &format!("LOCATION:{}", ics_text(&library.address)));
+        ics_line(&mut out, "END:VEVENT");
+    }
+
+    for closure in &calendar.closures {
+        let date = match NaiveDate::parse_from_str(&closure.date, "%Y-%m-%d") {
+            Ok(date) if date >= from && date < to => date,
+            _ => continue,
+        };
+        ics_line(&mut out, "BEGIN:VEVENT");
+        ics_line(&mut out, &format!("UID:closure-{}@library-automation", closure.id));
+        ics_line(&mut out, &format!("DTSTAMP:{}", stamp));
+        ics_line(&mut out, &format!("DTSTART;VALUE=DATE:{}", date.format("%Y%m%d")));
+        ics_line(&mut out, &format!("DTEND;VALUE=DATE:{}", (date + Duration::days(1)).format("%Y%m%d")));
+        ics_line(&mut out, &format!("SUMMARY:{} closed: {}", ics_text(&library.name), ics_text(&closure.reason)));
+        ics_line(&mut out, "TRANSP:TRANSPARENT");
+        ics_line(&mut out, "END:VEVENT");
+    }
+
+    ics_line(&mut out, "END:VCALENDAR");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(value: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(value, "%Y-%m-%d").unwrap()
+    }
+
+    fn hours(weekday: i32, opens_at: &str, closes_at: &str) -> OpeningHours {
+        OpeningHours { library_id: 1, weekday, opens_at: opens_at.to_string(), closes_at: closes_at.to_string() }
+    }
+
+    fn closure(id: i32, on: &str) -> Closure {
+        Closure { id, library_id: 1, date: on.to_string(), reason: "Holiday".to_string() }
+    }
+
+    // Open Monday to Friday; closed on Friday 2026-01-09. 2026-01-05 is a Monday.
+    fn weekdays() -> LibraryCalendar {
+        LibraryCalendar {
+            hours: (1..=5).map(|weekday| hours(weekday, "09:00", "17:00")).collect(),
+            closures: vec![closure(1, "2026-01-09")],
+        }
+    }
+
+    fn library(name: &str) -> Library {
+        Library {
+            id: 1,
+            name: name.to_string(),
+            address: "Main St 1".to_string(),
+            created_at: String::new(),
+            updated_at: String::new(),
+            manager_id: 1,
+            version: 1,
+            loan_retention_days: None,
+            street: None,
+            city: None,
+            postal_code: None,
+            country: None,
+            phone: None,
+            email: None,
+            website: None,
+            latitude: None,
+            longitude: None,
+        }
+    }
+
+    #[test]
+    fn next_open_day_skips_weekends_and_closures() {
+        let calendar = weekdays();
+        assert_eq!(calendar.next_open_day(date("2026-01-05")), date("2026-01-05"));
+        assert_eq!(calendar.next_open_day(date("2026-01-09")), date("2026-01-12"));
+        assert_eq!(calendar.next_open_day(date("2026-01-10")), date("2026-01-12"));
+
+        let always = LibraryCalendar { hours: vec![], closures: vec![closure(1, "2026-01-10")] };
+        assert_eq!(always.next_open_day(date("2026-01-10")), date("2026-01-11"));
+
+        // Never open: the date is kept rather than searched forever.
+        let closures = (0..=MAX_SHIFT_DAYS).map(|days| closure(days as i32, &(date("2026-01-01") + Duration::days(days)).to_string()));
+        let never = LibraryCalendar { hours: vec![], closures: closures.collect() };
+        assert_eq!(never.next_open_day(date("2026-01-01")), date("2026-01-01"));
+    }
+
+    #[test]
+    fn closed_days_counts_weekends_and_closures() {
+        let calendar = weekdays();
+        assert_eq!(calendar.closed_days(date("2026-01-05"), 7), 3);
+        assert_eq!(calendar.closed_days(date("2026-01-05"), 4), 0);
+        assert_eq!(calendar.closed_days(date("2026-01-05"), 0), 0);
+    }
+
+    #[test]
+    fn ics_times_are_padded() {
+        assert_eq!(ics_time("09:00"), "090000");
+        assert_eq!(ics_time("9:00"), "090000");
+        assert_eq!(ics_time("17:30"), "173000");
+    }
+
+    #[test]
+    fn ics_lines_fold_at_75_octets() {
+        let mut out = String::new();
+        ics_line(&mut out, &"a".repeat(160));
+        let lines: Vec<&str> = out.trim_end_matches("\r\n").split("\r\n").collect();
+        assert_eq!(lines.iter().map(|line| line.len()).collect::<Vec<_>>(), vec![75, 75, 12]);
+        assert!(lines[1..].iter().all(|line| line.starts_with(' ')));
+
+        // Multi-byte characters are not split across lines.
+        let mut out = String::new();
+        ics_line(&mut out, &"ü".repeat(40));
+        assert!(out.split("\r\n").all(|line| line.len() <= 75));
+        assert_eq!(out.replace("\r\n ", "").trim_end(), "ü".repeat(40));
+    }
+
+    #[test]
+    fn ics_export_leaves_closures_out_of_the_weekly_hours() {
+        let ics = to_ics(&library("Central"), &weekdays(), date("2026-01-01"), date("2026-02-01"), NaiveDateTime::default());
+        // 2026-01-02 is the first Friday.
+        assert!(ics.contains("DTSTART:20260102T090000\r\n"));
+        assert!(ics.contains("EXDATE:20260109T090000\r\n"));
+        assert!(ics.contains("DTSTART;VALUE=DATE:20260109\r\n"));
+        assert!(ics.contains("RRULE:FREQ=WEEKLY;UNTIL=20260131T235959\r\n"));
+        assert_eq!(ics.matches("BEGIN:VEVENT").count(), 6);
+    }
+}
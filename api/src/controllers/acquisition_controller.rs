@@ -0,0 +1,849 @@
+use std::collections::HashSet;
+
+use actix_web::{web, HttpRequest, HttpResponse, Responder, ResponseError};
+use chrono::Datelike;
+use serde::{Deserialize, Serialize};
+use tracing::error;
+use utoipa::{IntoParams, ToSchema};
+
+use domain::models::acquisition::{
+    Budget, NewOrderLine, NewPurchaseOrder, NewVendor, OrderFilter, OrderLine, PurchaseOrder, Vendor, ORDER_CANCELLED,
+    ORDER_DRAFT, ORDER_ORDERED, ORDER_PARTIALLY_RECEIVED, ORDER_RECEIVED, ORDER_STATUSES,
+};
+use domain::models::role::Permission;
+use domain::traits::AcquisitionRepositoryTrait;
+
+use crate::audit::{audited, snapshot, AuditEvent, Tx};
+use crate::auth::AuthenticatedUser;
+use crate::controllers::book_controller::parse_isbn;
+use crate::versioning::CURRENT_VERSION;
+use crate::AppState;
+
+// Request yapılandırmaları
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct VendorRequest {
+    name: String,
+    #[serde(default)]
+    email: Option<String>,
+    #[serde(default)]
+    phone: Option<String>,
+}
+
+impl VendorRequest {
+    fn validate(&self) -> Result<(), String> {
+        if self.name.trim().is_empty() {
+            return Err("name must not be empty".to_string());
+        }
+        if self.email.as_deref().is_some_and(|email| !email.contains('@')) {
+            return Err("email must be an email address".to_string());
+        }
+        Ok(())
+    }
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Path)]
+pub struct BudgetPath {
+    // Library id
+    id: i32,
+    fiscal_year: i32,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct BudgetRequest {
+    amount_cents: i64,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct BudgetResponse {
+    #[serde(flatten)]
+    budget: Budget,
+    // Total of the year's placed orders, received or not.
+    committed_cents: i64,
+    remaining_cents: i64,
+}
+
+// Either `book_id` of a catalogued book, or the `title` and `author` of a new one with an optional `isbn`.
+#[derive(Deserialize, ToSchema)]
+pub struct OrderLineRequest {
+    #[serde(default)]
+    book_id: Option<i32>,
+    #[serde(default)]
+    isbn: Option<String>,
+    #[serde(default)]
+    title: Option<String>,
+    #[serde(default)]
+    author: Option<String>,
+    #[serde(default = "default_quantity")]
+    quantity: i32,
+    unit_price_cents: i64,
+}
+
+fn default_quantity() -> i32 {
+    1
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct CreateOrderRequest {
+    library_id: i32,
+    vendor_id: i32,
+    // Defaults to the current year.
+    #[serde(default)]
+    fiscal_year: Option<i32>,
+    #[serde(default)]
+    note: Option<String>,
+    lines: Vec<OrderLineRequest>,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Path)]
+pub struct OrderPath {
+    id: i32,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct ReceiptRequest {
+    line_id: i32,
+    quantity: i32,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct ReceiveRequest {
+    // What was delivered; everything outstanding when left out.
+    #[serde(default)]
+    lines: Option<Vec<ReceiptRequest>>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct OrderResponse {
+    #[serde(flatten)]
+    order: PurchaseOrder,
+    total_cents: i64,
+    lines: Vec<OrderLine>,
+}
+
+fn current_year() -> i32 {
+    chrono::offset::Utc::now().year()
+}
+
+// The line as stored. Lines for a catalogued book take its title, author and ISBN, and a new title whose ISBN
+// is already catalogued is linked to that book.
+fn resolve_line(repos: &AppState, line: &OrderLineRequest) -> diesel::QueryResult<Result<NewOrderLine, String>> {
+    if line.quantity < 1 {
+        return Ok(Err("quantity must be at least 1".to_string()));
+    }
+    if line.unit_price_cents < 0 {
+        return Ok(Err("unit_price_cents must not be negative".to_string()));
+    }
+    let isbn = match parse_isbn(line.isbn.as_deref()) {
+        Ok(isbn) => isbn,
+        Err(message) => return Ok(Err(message)),
+    };
+
+    let mut book_repo = repos.book_repo.lock().unwrap();
+    let book = match (line.book_id, &isbn) {
+        (Some(book_id), _) => match book_repo.get_book_by_id(&book_id) {
+            Ok(book) if isbn.is_some() && book.isbn != isbn => return Ok(Err(format!("Book {} has another ISBN", book_id))),
+            Ok(book) => Some(book),
+            Err(diesel::result::Error::NotFound) => return Ok(Err(format!("No book {}", book_id))),
+            Err(e) => return Err(e),
+        },
+        (None, Some(isbn)) => match book_repo.get_book_by_isbn(isbn) {
+            Ok(book) => Some(book),
+            Err(diesel::result::Error::NotFound) => None,
+            Err(e) => return Err(e),
+        },
+        (None, None) => None,
+    };
+
+    let (book_id, isbn, title, author) = match book {
+        Some(book) => (Some(book.id), book.isbn, book.title, book.author),
+        None => match (line.title.as_deref().map(str::trim), line.author.as_deref().map(str::trim)) {
+            (Some(title), Some(author)) if !title.is_empty() && !author.is_empty() => (None, isbn, title.to_string(), author.to_string()),
+            _ => return Ok(Err("lines need a book_id, or a title and an author".to_string())),
+        },
+    };
+    Ok(Ok(NewOrderLine {
+        book_id,
+        isbn,
+        title,
+        author,
+        quantity: line.quantity,
+        unit_price_cents: line.unit_price_cents,
+    }))
+}
+
+fn order_response(repos: &AppState, order: PurchaseOrder) -> diesel::QueryResult<OrderResponse> {
+    with_lines(&mut *repos.acquisition_repo.lock().unwrap(), order)
+}
+
+fn with_lines(acquisition_repo: &mut (impl AcquisitionRepositoryTrait + ?Sized), order: PurchaseOrder) -> diesel::QueryResult<OrderResponse> {
+    let lines = acquisition_repo.get_order_lines(&order.id)?;
+    let total_cents = lines.iter().map(OrderLine::total_cents).sum();
+    Ok(OrderResponse { order, total_cents, lines })
+}
+
+// Handlers
+#[utoipa::path(
+    post,
+    path = "/vendors",
+    tag = "acquisitions",
+    request_body = VendorRequest,
+    responses((status = 201, description = "Created; Location points at the new vendor", body = Vendor), (status = 422, description = "Invalid vendor"), (status = 401, description = "Missing or invalid credentials"), (status = 403, description = "Missing permission")),
+)]
+pub async fn create_vendor(
+    req: HttpRequest,
+    repos: web::Data<AppState>,
+    user: AuthenticatedUser,
+    form: web::Json<VendorRequest>,
+) -> impl Responder {
+    if let Err(e) = user.require_anywhere(Permission::ManageLibrary) {
+        return e.error_response();
+    }
+    if let Err(message) = form.validate() {
+        return HttpResponse::UnprocessableEntity().body(message);
+    }
+
+    let now = chrono::offset::Utc::now().naive_utc().to_string();
+    let new_vendor = NewVendor {
+        name: form.name.trim(),
+        email: form.email.as_deref(),
+        phone: form.phone.as_deref(),
+        created_at: &now,
+        updated_at: &now,
+    };
+    let created = audited(&repos, &req, &user, |tx| {
+        let vendor = tx.acquisitions().create_vendor(&new_vendor)?;
+        tx.record(AuditEvent {
+            action: "create",
+            entity: "vendor",
+            entity_id: vendor.id.to_string(),
+            before: None,
+            after: snapshot(&vendor),
+        })?;
+        Ok(vendor)
+    });
+    match created {
+        Ok(vendor) => HttpResponse::Created()
+            .insert_header(("Location", format!("{}/vendors/{}", CURRENT_VERSION, vendor.id)))
+            .json(vendor),
+        Err(e) => {
+            error!(error = ?e, "Failed to create vendor"); // Hata mesajını logla
+            HttpResponse::InternalServerError().body(format!("Failed to create vendor: {}", e))
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/vendors",
+    tag = "acquisitions",
+    responses((status = 200, description = "All vendors by name", body = Vec<Vendor>), (status = 401, description = "Missing or invalid credentials"), (status = 403, description = "Missing permission")),
+)]
+pub async fn get_vendors(repos: web::Data<AppState>, user: AuthenticatedUser) -> impl Responder {
+    if let Err(e) = user.require_anywhere(Permission::ManageHoldings) {
+        return e.error_response();
+    }
+
+    let vendors = repos.acquisition_repo.lock().unwrap().get_vendors();
+    match vendors {
+        Ok(vendors) => HttpResponse::Ok().json(vendors),
+        Err(e) => {
+            error!(error = ?e, "Failed to get vendors"); // Hata mesajını logla
+            HttpResponse::InternalServerError().body(format!("Failed to get vendors: {}", e))
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/vendors/{id}",
+    tag = "acquisitions",
+    params(("id" = i32, Path, description = "Vendor id")),
+    responses((status = 200, description = "The vendor", body = Vendor), (status = 404, description = "No such vendor"), (status = 401, description = "Missing or invalid credentials"), (status = 403, description = "Missing permission")),
+)]
+pub async fn get_vendor(repos: web::Data<AppState>, user: AuthenticatedUser, id: web::Path<i32>) -> impl Responder {
+    if let Err(e) = user.require_anywhere(Permission::ManageHoldings) {
+        return e.error_response();
+    }
+
+    let vendor = repos.acquisition_repo.lock().unwrap().get_vendor(&id);
+    match vendor {
+        Ok(vendor) => HttpResponse::Ok().json(vendor),
+        Err(diesel::result::Error::NotFound) => HttpResponse::NotFound().finish(),
+        Err(e) => {
+            error!(error = ?e, "Failed to get vendor"); // Hata mesajını logla
+            HttpResponse::InternalServerError().body(format!("Failed to get vendor: {}", e))
+        }
+    }
+}
+
+#[utoipa::path(
+    put,
+    path = "/vendors/{id}",
+    tag = "acquisitions",
+    params(("id" = i32, Path, description = "Vendor id")),
+    request_body = VendorRequest,
+    responses((status = 200, description = "Updated", body = Vendor), (status = 404, description = "No such vendor"), (status = 422, description = "Invalid vendor"), (status = 401, description = "Missing or invalid credentials"), (status = 403, description = "Missing permission")),
+)]
+pub async fn update_vendor(
+    req: HttpRequest,
+    repos: web::Data<AppState>,
+    user: AuthenticatedUser,
+    id: web::Path<i32>,
+    form: web::Json<VendorRequest>,
+) -> impl Responder {
+    if let Err(e) = user.require_anywhere(Permission::ManageLibrary) {
+        return e.error_response();
+    }
+    if let Err(message) = form.validate() {
+        return HttpResponse::UnprocessableEntity().body(message);
+    }
+
+    let mut acquisition_repo = repos.acquisition_repo.lock().unwrap();
+    let before = match acquisition_repo.get_vendor(&id) {
+        Ok(vendor) => vendor,
+        Err(diesel::result::Error::NotFound) => return HttpResponse::NotFound().finish(),
+        Err(e) => {
+            error!(error = ?e, "Failed to update vendor"); // Hata mesajını logla
+            return HttpResponse::InternalServerError().body(format!("Failed to update vendor: {}", e));
+        }
+    };
+
+    drop(acquisition_repo);
+    let updated = audited(&repos, &req, &user, |tx| {
+        tx.acquisitions().update_vendor(&id, form.name.trim(), form.email.as_deref(), form.phone.as_deref())?;
+        let after = tx.acquisitions().get_vendor(&id)?;
+        tx.record(AuditEvent {
+            action: "update",
+            entity: "vendor",
+            entity_id: id.to_string(),
+            before: snapshot(&before),
+            after: snapshot(&after),
+        })?;
+        Ok(after)
+    });
+    match updated {
+        Ok(after) => HttpResponse::Ok().json(after),
+        Err(e) => {
+            error!(error = ?e, "Failed to update vendor"); // Hata mesajını logla
+            HttpResponse::InternalServerError().body(format!("Failed to update vendor: {}", e))
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/libraries/{id}/budgets",
+    tag = "acquisitions",
+    params(("id" = i32, Path, description = "Library id")),
+    responses((status = 200, description = "The library's budgets with what is committed against them, newest year first", body = Vec<BudgetResponse>), (status = 401, description = "Missing or invalid credentials"), (status = 403, description = "Missing permission")),
+)]
+pub async fn get_budgets(repos: web::Data<AppState>, user: AuthenticatedUser, id: web::Path<i32>) -> impl Responder {
+    if let Err(e) = user.require(Permission::ViewReports, Some(*id)) {
+        return e.error_response();
+    }
+
+    let mut acquisition_repo = repos.acquisition_repo.lock().unwrap();
+    let budgets = acquisition_repo.get_budgets(&id).and_then(|budgets| {
+        budgets
+            .into_iter()
+            .map(|budget| {
+                let committed_cents = acquisition_repo.committed_cents(&budget.library_id, &budget.fiscal_year)?;
+                let remaining_cents = budget.amount_cents - committed_cents;
+                Ok(BudgetResponse { budget, committed_cents, remaining_cents })
+            })
+            .collect::<diesel::QueryResult<Vec<_>>>()
+    });
+    match budgets {
+        Ok(budgets) => HttpResponse::Ok().json(budgets),
+        Err(e) => {
+            error!(error = ?e, "Failed to get budgets"); // Hata mesajını logla
+            HttpResponse::InternalServerError().body(format!("Failed to get budgets: {}", e))
+        }
+    }
+}
+
+// Budgets are allocated centrally, so setting one takes a system-wide grant.
+#[utoipa::path(
+    put,
+    path = "/libraries/{id}/budgets/{fiscal_year}",
+    tag = "acquisitions",
+    params(BudgetPath),
+    request_body = BudgetRequest,
+    responses((status = 200, description = "Budget created or replaced", body = Budget), (status = 404, description = "No such library"), (status = 422, description = "Negative amount"), (status = 401, description = "Missing or invalid credentials"), (status = 403, description = "Missing permission")),
+)]
+pub async fn set_budget(
+    req: HttpRequest,
+    repos: web::Data<AppState>,
+    user: AuthenticatedUser,
+    path: web::Path<BudgetPath>,
+    form: web::Json<BudgetRequest>,
+) -> impl Responder {
+    if let Err(e) = user.require(Permission::ManageSystem, None) {
+        return e.error_response();
+    }
+    if form.amount_cents < 0 {
+        return HttpResponse::UnprocessableEntity().body("amount_cents must not be negative");
+    }
+    match repos.lib_repo.lock().unwrap().get_library_by_id(&path.id) {
+        Ok(_) => {}
+        Err(diesel::result::Error::NotFound) => return HttpResponse::NotFound().finish(),
+        Err(e) => {
+            error!(error = ?e, "Failed to set budget"); // Hata mesajını logla
+            return HttpResponse::InternalServerError().body(format!("Failed to set budget: {}", e));
+        }
+    }
+
+    let updated = audited(&repos, &req, &user, |tx| {
+        let before = match tx.acquisitions().get_budget(&path.id, &path.fiscal_year) {
+            Ok(budget) => Some(budget),
+            Err(diesel::result::Error::NotFound) => None,
+            Err(e) => return Err(e),
+        };
+        let after = tx.acquisitions().set_budget(&path.id, &path.fiscal_year, &form.amount_cents)?;
+        tx.record(AuditEvent {
+            action: if before.is_some() { "update" } else { "create" },
+            entity: "budget",
+            entity_id: after.id.to_string(),
+            before: before.as_ref().and_then(snapshot),
+            after: snapshot(&after),
+        })?;
+        Ok(after)
+    });
+    match updated {
+        Ok(after) => HttpResponse::Ok().json(after),
+        Err(e) => {
+            error!(error = ?e, "Failed to set budget"); // Hata mesajını logla
+            HttpResponse::InternalServerError().body(format!("Failed to set budget: {}", e))
+        }
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/purchase-orders",
+    tag = "acquisitions",
+    request_body = CreateOrderRequest,
+    responses((status = 201, description = "Draft created; Location points at the new order", body = OrderResponse), (status = 422, description = "Invalid order, unknown vendor or book"), (status = 401, description = "Missing or invalid credentials"), (status = 403, description = "Missing permission")),
+)]
+pub async fn create_order(
+    req: HttpRequest,
+    repos: web::Data<AppState>,
+    user: AuthenticatedUser,
+    form: web::Json<CreateOrderRequest>,
+) -> impl Responder {
+    if let Err(e) = user.require(Permission::ManageHoldings, Some(form.library_id)) {
+        return e.error_response();
+    }
+    if form.lines.is_empty() {
+        return HttpResponse::UnprocessableEntity().body("lines must not be empty");
+    }
+    let fiscal_year = form.fiscal_year.unwrap_or_else(current_year);
+    if !(1..=9999).contains(&fiscal_year) {
+        return HttpResponse::UnprocessableEntity().body("fiscal_year must be a year");
+    }
+    // SQLite does not enforce the foreign keys, so the library and vendor are looked up first.
+    match repos.lib_repo.lock().unwrap().get_library_by_id(&form.library_id) {
+        Ok(_) => {}
+        Err(diesel::result::Error::NotFound) => return HttpResponse::UnprocessableEntity().body(format!("No library {}", form.library_id)),
+        Err(e) => {
+            error!(error = ?e, "Failed to create purchase order"); // Hata mesajını logla
+            return HttpResponse::InternalServerError().body(format!("Failed to create purchase order: {}", e));
+        }
+    }
+    match repos.acquisition_repo.lock().unwrap().get_vendor(&form.vendor_id) {
+        Ok(_) => {}
+        Err(diesel::result::Error::NotFound) => return HttpResponse::UnprocessableEntity().body(format!("No vendor {}", form.vendor_id)),
+        Err(e) => {
+            error!(error = ?e, "Failed to create purchase order"); // Hata mesajını logla
+            return HttpResponse::InternalServerError().body(format!("Failed to create purchase order: {}", e));
+        }
+    }
+
+    let mut lines = Vec::with_capacity(form.lines.len());
+    for line in &form.lines {
+        match resolve_line(&repos, line) {
+            Ok(Ok(line)) => lines.push(line),
+            Ok(Err(message)) => return HttpResponse::UnprocessableEntity().body(message),
+            Err(e) => {
+                error!(error = ?e, "Failed to create purchase order"); // Hata mesajını logla
+                return HttpResponse::InternalServerError().body(format!("Failed to create purchase order: {}", e));
+            }
+        }
+    }
+
+    let now = chrono::offset::Utc::now().naive_utc().to_string();
+    let new_order = NewPurchaseOrder {
+        library_id: &form.library_id,
+        vendor_id: &form.vendor_id,
+        fiscal_year: &fiscal_year,
+        status: ORDER_DRAFT,
+        note: form.note.as_deref(),
+        created_by: Some(&user.id),
+        created_at: &now,
+    };
+    let created = audited(&repos, &req, &user, |tx| {
+        let order = tx.acquisitions().create_order(&new_order, &lines)?;
+        let created = with_lines(&mut tx.acquisitions(), order)?;
+        tx.record(AuditEvent {
+            action: "create",
+            entity: "purchase_order",
+            entity_id: created.order.id.to_string(),
+            before: None,
+            after: snapshot(&created),
+        })?;
+        Ok(created)
+    });
+    match created {
+        Ok(created) => HttpResponse::Created()
+            .insert_header(("Location", format!("{}/purchase-orders/{}", CURRENT_VERSION, created.order.id)))
+            .json(created),
+        Err(e) => {
+            error!(error = ?e, "Failed to create purchase order"); // Hata mesajını logla
+            HttpResponse::InternalServerError().body(format!("Failed to create purchase order: {}", e))
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/purchase-orders",
+    tag = "acquisitions",
+    params(OrderFilter),
+    responses((status = 200, description = "Purchase orders, newest first", body = Vec<PurchaseOrder>), (status = 422, description = "Unknown status"), (status = 401, description = "Missing or invalid credentials"), (status = 403, description = "Missing permission")),
+)]
+pub async fn get_orders(repos: web::Data<AppState>, user: AuthenticatedUser, filter: web::Query<OrderFilter>) -> impl Responder {
+    if let Err(e) = user.require(Permission::ManageHoldings, filter.library_id) {
+        return e.error_response();
+    }
+    if let Some(status) = &filter.status {
+        if !ORDER_STATUSES.contains(&status.as_str()) {
+            return HttpResponse::UnprocessableEntity().body(format!("status must be one of {}", ORDER_STATUSES.join(", ")));
+        }
+    }
+
+    let filter = OrderFilter { limit: filter.limit.or(Some(100)), ..filter.into_inner() };
+    let orders = repos.acquisition_repo.lock().unwrap().get_orders(&filter);
+    match orders {
+        Ok(orders) => HttpResponse::Ok().json(orders),
+        Err(e) => {
+            error!(error = ?e, "Failed to get purchase orders"); // Hata mesajını logla
+            HttpResponse::InternalServerError().body(format!("Failed to get purchase orders: {}", e))
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/purchase-orders/{id}",
+    tag = "acquisitions",
+    params(OrderPath),
+    responses((status = 200, description = "The order with its lines", body = OrderResponse), (status = 404, description = "No such order"), (status = 401, description = "Missing or invalid credentials"), (status = 403, description = "Missing permission")),
+)]
+pub async fn get_order(repos: web::Data<AppState>, user: AuthenticatedUser, path: web::Path<OrderPath>) -> impl Responder {
+    let order = repos.acquisition_repo.lock().unwrap().get_order(&path.id);
+    let order = match order {
+        Ok(order) => order,
+        Err(diesel::result::Error::NotFound) => return HttpResponse::NotFound().finish(),
+        Err(e) => {
+            error!(error = ?e, "Failed to get purchase order"); // Hata mesajını logla
+            return HttpResponse::InternalServerError().body(format!("Failed to get purchase order: {}", e));
+        }
+    };
+    if let Err(e) = user.require(Permission::ManageHoldings, Some(order.library_id)) {
+        return e.error_response();
+    }
+
+    match order_response(&repos, order) {
+        Ok(order) => HttpResponse::Ok().json(order),
+        Err(e) => {
+            error!(error = ?e, "Failed to get purchase order"); // Hata mesajını logla
+            HttpResponse::InternalServerError().body(format!("Failed to get purchase order: {}", e))
+        }
+    }
+}
+
+// Placing an order commits money, so it takes the library's management rather than holdings rights.
+#[utoipa::path(
+    post,
+    path = "/purchase-orders/{id}/submit",
+    tag = "acquisitions",
+    params(OrderPath),
+    responses((status = 200, description = "Ordered from the vendor and charged to the budget", body = OrderResponse), (status = 404, description = "No such order"), (status = 409, description = "The order is not a draft, or the library has no budget for the year or not enough of it left"), (status = 401, description = "Missing or invalid credentials"), (status = 403, description = "Missing permission")),
+)]
+pub async fn submit_order(req: HttpRequest, repos: web::Data<AppState>, user: AuthenticatedUser, path: web::Path<OrderPath>) -> impl Responder {
+    let before = repos.acquisition_repo.lock().unwrap().get_order(&path.id);
+    let before = match before.and_then(|order| order_response(&repos, order)) {
+        Ok(order) => order,
+        Err(diesel::result::Error::NotFound) => return HttpResponse::NotFound().finish(),
+        Err(e) => {
+            error!(error = ?e, "Failed to submit purchase order"); // Hata mesajını logla
+            return HttpResponse::InternalServerError().body(format!("Failed to submit purchase order: {}", e));
+        }
+    };
+    if let Err(e) = user.require(Permission::ManageLibrary, Some(before.order.library_id)) {
+        return e.error_response();
+    }
+    if before.order.status != ORDER_DRAFT {
+        return HttpResponse::Conflict().body(format!("Order is {}", before.order.status));
+    }
+
+    let mut acquisition_repo = repos.acquisition_repo.lock().unwrap();
+    let (library_id, fiscal_year) = (before.order.library_id, before.order.fiscal_year);
+    let remaining = acquisition_repo
+        .get_budget(&library_id, &fiscal_year)
+        .and_then(|budget| Ok(budget.amount_cents - acquisition_repo.committed_cents(&library_id, &fiscal_year)?));
+    match remaining {
+        Ok(remaining) if remaining < before.total_cents => {
+            return HttpResponse::Conflict()
+                .body(format!("Order total of {} cents exceeds the {} cents left in the {} budget", before.total_cents, remaining, fiscal_year))
+        }
+        Ok(_) => {}
+        Err(diesel::result::Error::NotFound) => {
+            return HttpResponse::Conflict().body(format!("Library {} has no budget for {}", library_id, fiscal_year))
+        }
+        Err(e) => {
+            error!(error = ?e, "Failed to submit purchase order"); // Hata mesajını logla
+            return HttpResponse::InternalServerError().body(format!("Failed to submit purchase order: {}", e));
+        }
+    }
+
+    drop(acquisition_repo);
+    let at: String = chrono::offset::Utc::now().naive_utc().to_string();
+    let submitted = step(&repos, &req, &user, "submit", &before, |tx| tx.acquisitions().advance_order(&path.id, ORDER_DRAFT, ORDER_ORDERED, &at));
+    match submitted {
+        Ok((0, after)) if after.order.status != ORDER_DRAFT => HttpResponse::Conflict().body(format!("Order is {}", after.order.status)),
+        Ok((0, _)) => HttpResponse::Conflict().body(format!("Not enough of the {} budget left", fiscal_year)),
+        Ok((_, after)) => HttpResponse::Ok().json(after),
+        Err(e) => {
+            error!(error = ?e, "Failed to submit purchase order"); // Hata mesajını logla
+            HttpResponse::InternalServerError().body(format!("Failed to submit purchase order: {}", e))
+        }
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/purchase-orders/{id}/receive",
+    tag = "acquisitions",
+    params(OrderPath),
+    request_body = ReceiveRequest,
+    responses((status = 200, description = "Delivered copies added to the library's stock", body = OrderResponse), (status = 404, description = "No such order"), (status = 409, description = "The order is not awaiting delivery, or more copies than outstanding"), (status = 422, description = "Unknown line or invalid quantity"), (status = 401, description = "Missing or invalid credentials"), (status = 403, description = "Missing permission")),
+)]
+pub async fn receive_order(
+    req: HttpRequest,
+    repos: web::Data<AppState>,
+    user: AuthenticatedUser,
+    path: web::Path<OrderPath>,
+    form: web::Json<ReceiveRequest>,
+) -> impl Responder {
+    let before = repos.acquisition_repo.lock().unwrap().get_order(&path.id);
+    let before = match before.and_then(|order| order_response(&repos, order)) {
+        Ok(order) => order,
+        Err(diesel::result::Error::NotFound) => return HttpResponse::NotFound().finish(),
+        Err(e) => {
+            error!(error = ?e, "Failed to receive purchase order"); // Hata mesajını logla
+            return HttpResponse::InternalServerError().body(format!("Failed to receive purchase order: {}", e));
+        }
+    };
+    if let Err(e) = user.require(Permission::ManageHoldings, Some(before.order.library_id)) {
+        return e.error_response();
+    }
+    if before.order.status != ORDER_ORDERED && before.order.status != ORDER_PARTIALLY_RECEIVED {
+        return HttpResponse::Conflict().body(format!("Order is {}", before.order.status));
+    }
+    // Deleting a library keeps its orders; their copies have nowhere to go.
+    match repos.lib_repo.lock().unwrap().get_library_by_id(&before.order.library_id) {
+        Ok(_) => {}
+        Err(diesel::result::Error::NotFound) => {
+            return HttpResponse::Conflict().body(format!("Library {} no longer exists", before.order.library_id))
+        }
+        Err(e) => {
+            error!(error = ?e, "Failed to receive purchase order"); // Hata mesajını logla
+            return HttpResponse::InternalServerError().body(format!("Failed to receive purchase order: {}", e));
+        }
+    }
+
+    let receipts: Vec<(i32, i32)> = match &form.lines {
+        Some(lines) => lines.iter().map(|line| (line.line_id, line.quantity)).collect(),
+        None => before.lines.iter().filter(|line| line.outstanding() > 0).map(|line| (line.id, line.outstanding())).collect(),
+    };
+    if receipts.is_empty() {
+        return HttpResponse::UnprocessableEntity().body("lines must not be empty");
+    }
+    let mut seen = HashSet::new();
+    for (line_id, quantity) in &receipts {
+        let line = match before.lines.iter().find(|line| line.id == *line_id) {
+            Some(line) => line,
+            None => return HttpResponse::UnprocessableEntity().body(format!("No line {} on this order", line_id)),
+        };
+        if !seen.insert(line_id) {
+            return HttpResponse::UnprocessableEntity().body(format!("Line {} is listed twice", line_id));
+        }
+        if *quantity < 1 {
+            return HttpResponse::UnprocessableEntity().body("quantity must be at least 1");
+        }
+        if *quantity > line.outstanding() {
+            return HttpResponse::Conflict().body(format!("Line {} has {} copies outstanding", line_id, line.outstanding()));
+        }
+    }
+
+    let at: String = chrono::offset::Utc::now().naive_utc().to_string();
+    let received = step(&repos, &req, &user, "receive", &before, |tx| tx.acquisitions().receive_order(&path.id, &receipts, &at));
+    match received {
+        Ok((0, after)) if after.order.status != before.order.status => HttpResponse::Conflict().body(format!("Order is {}", after.order.status)),
+        Ok((0, _)) => HttpResponse::Conflict().body("Order was received meanwhile; reload it and try again"),
+        Ok((_, after)) => HttpResponse::Ok().json(after),
+        Err(e) => {
+            error!(error = ?e, "Failed to receive purchase order"); // Hata mesajını logla
+            HttpResponse::InternalServerError().body(format!("Failed to receive purchase order: {}", e))
+        }
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/purchase-orders/{id}/cancel",
+    tag = "acquisitions",
+    params(OrderPath),
+    responses((status = 200, description = "Cancelled; a placed order no longer counts against the budget", body = OrderResponse), (status = 404, description = "No such order"), (status = 409, description = "Copies were already received, or the order is closed"), (status = 401, description = "Missing or invalid credentials"), (status = 403, description = "Missing permission")),
+)]
+pub async fn cancel_order(req: HttpRequest, repos: web::Data<AppState>, user: AuthenticatedUser, path: web::Path<OrderPath>) -> impl Responder {
+    let before = repos.acquisition_repo.lock().unwrap().get_order(&path.id);
+    let before = match before.and_then(|order| order_response(&repos, order)) {
+        Ok(order) => order,
+        Err(diesel::result::Error::NotFound) => return HttpResponse::NotFound().finish(),
+        Err(e) => {
+            error!(error = ?e, "Failed to cancel purchase order"); // Hata mesajını logla
+            return HttpResponse::InternalServerError().body(format!("Failed to cancel purchase order: {}", e));
+        }
+    };
+    if let Err(e) = user.require(Permission::ManageLibrary, Some(before.order.library_id)) {
+        return e.error_response();
+    }
+    if before.order.status != ORDER_DRAFT && before.order.status != ORDER_ORDERED {
+        return HttpResponse::Conflict().body(format!("Order is {}", before.order.status));
+    }
+
+    let at: String = chrono::offset::Utc::now().naive_utc().to_string();
+    let cancelled = step(&repos, &req, &user, "cancel", &before, |tx| tx.acquisitions().advance_order(&path.id, &before.order.status, ORDER_CANCELLED, &at));
+    match cancelled {
+        Ok((0, after)) => HttpResponse::Conflict().body(format!("Order is {}", after.order.status)),
+        Ok((_, after)) => HttpResponse::Ok().json(after),
+        Err(e) => {
+            error!(error = ?e, "Failed to cancel purchase order"); // Hata mesajını logla
+            HttpResponse::InternalServerError().body(format!("Failed to cancel purchase order: {}", e))
+        }
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/purchase-orders/{id}/close",
+    tag = "acquisitions",
+    params(OrderPath),
+    responses((status = 200, description = "Closed as received; the copies still outstanding no longer count against the budget", body = OrderResponse), (status = 404, description = "No such order"), (status = 409, description = "The order is not partially received"), (status = 401, description = "Missing or invalid credentials"), (status = 403, description = "Missing permission")),
+)]
+pub async fn close_order(req: HttpRequest, repos: web::Data<AppState>, user: AuthenticatedUser, path: web::Path<OrderPath>) -> impl Responder {
+    let before = repos.acquisition_repo.lock().unwrap().get_order(&path.id);
+    let before = match before.and_then(|order| order_response(&repos, order)) {
+        Ok(order) => order,
+        Err(diesel::result::Error::NotFound) => return HttpResponse::NotFound().finish(),
+        Err(e) => {
+            error!(error = ?e, "Failed to close purchase order"); // Hata mesajını logla
+            return HttpResponse::InternalServerError().body(format!("Failed to close purchase order: {}", e));
+        }
+    };
+    if let Err(e) = user.require(Permission::ManageLibrary, Some(before.order.library_id)) {
+        return e.error_response();
+    }
+    if before.order.status != ORDER_PARTIALLY_RECEIVED {
+        return HttpResponse::Conflict().body(format!("Order is {}", before.order.status));
+    }
+
+    let at: String = chrono::offset::Utc::now().naive_utc().to_string();
+    let closed = step(&repos, &req, &user, "close", &before, |tx| tx.acquisitions().advance_order(&path.id, ORDER_PARTIALLY_RECEIVED, ORDER_RECEIVED, &at));
+    match closed {
+        Ok((0, after)) => HttpResponse::Conflict().body(format!("Order is {}", after.order.status)),
+        Ok((_, after)) => HttpResponse::Ok().json(after),
+        Err(e) => {
+            error!(error = ?e, "Failed to close purchase order"); // Hata mesajını logla
+            HttpResponse::InternalServerError().body(format!("Failed to close purchase order: {}", e))
+        }
+    }
+}
+
+// Makes a status change of the order and audits it when it took effect; returns the rows `change` updated and the
+// order as it is now.
+fn step(
+    repos: &AppState,
+    req: &HttpRequest,
+    user: &AuthenticatedUser,
+    action: &str,
+    before: &OrderResponse,
+    change: impl FnOnce(&mut Tx) -> diesel::QueryResult<usize>,
+) -> diesel::QueryResult<(usize, OrderResponse)> {
+    audited(repos, req, user, |tx| {
+        let updated = change(tx)?;
+        let order = tx.acquisitions().get_order(&before.order.id)?;
+        let after = with_lines(&mut tx.acquisitions(), order)?;
+        if updated > 0 {
+            tx.record(AuditEvent {
+                action,
+                entity: "purchase_order",
+                entity_id: after.order.id.to_string(),
+                before: snapshot(before),
+                after: snapshot(&after),
+            })?;
+        }
+        Ok((updated, after))
+    })
+}
+
+// Routes configuration
+pub fn acquisition_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::resource("/vendors")
+            .route(web::post().to(create_vendor))
+            .route(web::get().to(get_vendors))
+    )
+    .service(
+        web::resource("/vendors/{id}")
+            .route(web::get().to(get_vendor))
+            .route(web::put().to(update_vendor))
+    )
+    .service(
+        web::resource("/libraries/{id}/budgets")
+            .route(web::get().to(get_budgets))
+    )
+    .service(
+        web::resource("/libraries/{id}/budgets/{fiscal_year}")
+            .route(web::put().to(set_budget))
+    )
+    .service(
+        web::resource("/purchase-orders")
+            .route(web::post().to(create_order))
+            .route(web::get().to(get_orders))
+    )
+    .service(
+        web::resource("/purchase-orders/{id}")
+            .route(web::get().to(get_order))
+    )
+    .service(
+        web::resource("/purchase-orders/{id}/submit")
+            .route(web::post().to(submit_order))
+    )
+    .service(
+        web::resource("/purchase-orders/{id}/receive")
+            .route(web::post().to(receive_order))
+    )
+    .service(
+        web::resource("/purchase-orders/{id}/cancel")
+            .route(web::post().to(cancel_order))
+    )
+    .service(
+        web::resource("/purchase-orders/{id}/close")
+            .route(web::post().to(close_order))
+    );
+}
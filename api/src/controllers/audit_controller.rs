@@ -0,0 +1,93 @@
+use actix_web::{web, HttpResponse, Responder, ResponseError};
+use tracing::error;
+
+use domain::models::audit::{AuditEntry, AuditFilter};
+use domain::models::role::Permission;
+
+use crate::auth::AuthenticatedUser;
+use crate::AppState;
+
+// Handlers
+#[utoipa::path(
+    get,
+    path = "/audit",
+    tag = "audit",
+    params(AuditFilter),
+    responses((status = 200, description = "Matching audit entries, oldest first", body = Vec<AuditEntry>), (status = 401, description = "Missing or invalid credentials"), (status = 403, description = "Missing permission")),
+)]
+pub async fn get_audit_entries(
+    repos: web::Data<AppState>,
+    user: AuthenticatedUser,
+    filter: web::Query<AuditFilter>,
+) -> impl Responder {
+    if let Err(e) = user.require(Permission::ManageSystem, None) {
+        return e.error_response();
+    }
+
+    let mut audit_repo = repos.audit_repo.lock().unwrap();
+    match audit_repo.get_entries(&filter) {
+        Ok(entries) => HttpResponse::Ok().json(entries),
+        Err(e) => {
+            error!(error = ?e, "Failed to get audit entries"); // Hata mesajını logla
+            HttpResponse::InternalServerError().body(format!("Failed to get audit entries: {}", e))
+        }
+    }
+}
+
+// JSON Lines export; unlike the listing it is not limited unless a limit is given.
+#[utoipa::path(
+    get,
+    path = "/audit/export",
+    tag = "audit",
+    params(AuditFilter),
+    responses((status = 200, description = "Matching audit entries as JSON Lines", content_type = "application/x-ndjson", body = String), (status = 401, description = "Missing or invalid credentials"), (status = 403, description = "Missing permission")),
+)]
+pub async fn export_audit_entries(
+    repos: web::Data<AppState>,
+    user: AuthenticatedUser,
+    filter: web::Query<AuditFilter>,
+) -> impl Responder {
+    if let Err(e) = user.require(Permission::ManageSystem, None) {
+        return e.error_response();
+    }
+
+    let mut filter = filter.into_inner();
+    filter.limit = filter.limit.or(Some(-1));
+
+    let mut audit_repo = repos.audit_repo.lock().unwrap();
+    match audit_repo.get_entries(&filter) {
+        Ok(entries) => {
+            let mut body = String::new();
+            for entry in &entries {
+                match serde_json::to_string(entry) {
+                    Ok(line) => {
+                        body.push_str(&line);
+                        body.push('\n');
+                    }
+                    Err(e) => return HttpResponse::InternalServerError().body(format!("Failed to export audit entries: {}", e)),
+                }
+            }
+
+            HttpResponse::Ok()
+                .content_type("application/x-ndjson")
+                .insert_header(("Content-Disposition", "attachment; filename=\"audit.jsonl\""))
+                .body(body)
+        }
+        Err(e) => {
+            error!(error = ?e, "Failed to export audit entries"); // Hata mesajını logla
+            HttpResponse::InternalServerError().body(format!("Failed to export audit entries: {}", e))
+        }
+    }
+}
+
+// Routes configuration
+pub fn audit_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::resource("/audit")
+            .route(web::get().to(get_audit_entries))
+    )
+    .service(
+        web::resource("/audit/export")
+            .route(web::get().to(export_audit_entries))
+    );
+}
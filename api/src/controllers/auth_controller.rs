@@ -0,0 +1,558 @@
+use actix_web::{web, HttpRequest, HttpResponse, Responder, ResponseError};
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use tracing::error;
+use utoipa::{IntoParams, ToSchema};
+
+use domain::models::role::{Permission, Role, UserRole};
+use domain::models::user::{ApiKey, User, ACCOUNT_MEMBER, ACCOUNT_STAFF};
+use domain::traits::UserRepositoryTrait;
+use infrastructure::security::{generate_token, hash_password, hash_token, verify_password, API_KEY_PREFIX_LEN};
+
+use crate::audit::{audited, snapshot, AuditEvent};
+use crate::auth::{issue_access_token, issue_refresh_token, AuthError, AuthenticatedUser};
+use crate::AppState;
+
+// Request yapılandırmaları
+#[derive(Deserialize, ToSchema)]
+pub struct LoginRequest {
+    username: String,
+    password: String,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct RefreshRequest {
+    refresh_token: String,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct CreateUserRequest {
+    username: String,
+    password: String,
+    account_type: String,
+    member_id: Option<i32>,
+}
+
+impl CreateUserRequest {
+    fn validate(&self) -> Result<(), String> {
+        if self.username.trim().is_empty() {
+            return Err("username must not be empty".to_string());
+        }
+        if self.password.len() < 8 {
+            return Err("password must be at least 8 characters".to_string());
+        }
+        if self.account_type != ACCOUNT_STAFF && self.account_type != ACCOUNT_MEMBER {
+            return Err(format!("account_type must be '{}' or '{}'", ACCOUNT_STAFF, ACCOUNT_MEMBER));
+        }
+        if self.account_type == ACCOUNT_MEMBER && self.member_id.is_none() {
+            return Err("member accounts must reference a member_id".to_string());
+        }
+        Ok(())
+    }
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct GrantRoleRequest {
+    role: String,
+    library_id: Option<i32>,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Path)]
+pub struct UserRolePath {
+    id: i32,
+    role_id: i32,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct CreateApiKeyRequest {
+    name: String,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct TokenResponse {
+    access_token: String,
+    token_type: &'static str,
+    expires_in: i64,
+    refresh_token: String,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct CreatedApiKeyResponse {
+    name: String,
+    prefix: String,
+    key: String,
+}
+
+fn token_response(repos: &AppState, user: &User) -> HttpResponse {
+    let access_token = match issue_access_token(&repos.auth, user) {
+        Ok(token) => token,
+        Err(e) => return e.error_response(),
+    };
+    let refresh_token = match issue_refresh_token(repos, user.id) {
+        Ok(token) => token,
+        Err(e) => return e.error_response(),
+    };
+
+    HttpResponse::Ok().json(TokenResponse {
+        access_token,
+        token_type: "Bearer",
+        expires_in: repos.auth.access_token_ttl_seconds,
+        refresh_token,
+    })
+}
+
+// Handlers
+#[utoipa::path(
+    post,
+    path = "/auth/login",
+    tag = "auth",
+    request_body = LoginRequest,
+    responses((status = 200, description = "Token pair", body = TokenResponse), (status = 401, description = "Unknown user or wrong password")),
+    security(()),
+)]
+pub async fn login(
+    repos: web::Data<AppState>,
+    form: web::Json<LoginRequest>,
+) -> impl Responder {
+    let result = repos.user_repo.lock().unwrap().get_user_by_username(&form.username);
+
+    match result {
+        Ok(user) if verify_password(&form.password, &user.password_hash) => token_response(&repos, &user),
+        Ok(_) | Err(diesel::result::Error::NotFound) => AuthError::InvalidCredentials.error_response(),
+        Err(e) => {
+            error!(error = ?e, "Failed to log in"); // Hata mesajını logla
+            HttpResponse::InternalServerError().body(format!("Failed to log in: {}", e))
+        }
+    }
+}
+
+// Refresh tokens are single use: the presented token is revoked and a new pair is issued.
+#[utoipa::path(
+    post,
+    path = "/auth/refresh",
+    tag = "auth",
+    request_body = RefreshRequest,
+    responses((status = 200, description = "New token pair; the presented refresh token is revoked", body = TokenResponse), (status = 401, description = "Unknown, revoked or expired refresh token")),
+    security(()),
+)]
+pub async fn refresh(
+    repos: web::Data<AppState>,
+    form: web::Json<RefreshRequest>,
+) -> impl Responder {
+    let token_hash = hash_token(&form.refresh_token);
+    let mut user_repo = repos.user_repo.lock().unwrap();
+
+    let stored = match user_repo.get_refresh_token(&token_hash) {
+        Ok(stored) => stored,
+        Err(diesel::result::Error::NotFound) => return AuthError::InvalidCredentials.error_response(),
+        Err(e) => {
+            error!(error = ?e, "Failed to refresh token"); // Hata mesajını logla
+            return HttpResponse::InternalServerError().body(format!("Failed to refresh token: {}", e));
+        }
+    };
+    let expired = NaiveDateTime::parse_from_str(&stored.expires_at, "%Y-%m-%d %H:%M:%S%.f")
+        .map(|expires_at| expires_at <= chrono::offset::Utc::now().naive_utc())
+        .unwrap_or(true);
+    if stored.revoked_at.is_some() || expired {
+        return AuthError::InvalidCredentials.error_response();
+    }
+
+    let user = match user_repo.revoke_refresh_token(&token_hash).and_then(|_| user_repo.get_user_by_id(&stored.user_id)) {
+        Ok(user) => user,
+        Err(e) => {
+            error!(error = ?e, "Failed to refresh token"); // Hata mesajını logla
+            return HttpResponse::InternalServerError().body(format!("Failed to refresh token: {}", e));
+        }
+    };
+    drop(user_repo);
+
+    token_response(&repos, &user)
+}
+
+#[utoipa::path(
+    post,
+    path = "/auth/logout",
+    tag = "auth",
+    request_body = RefreshRequest,
+    responses((status = 204, description = "Refresh token revoked")),
+    security(()),
+)]
+pub async fn logout(
+    repos: web::Data<AppState>,
+    form: web::Json<RefreshRequest>,
+) -> impl Responder {
+    let mut user_repo = repos.user_repo.lock().unwrap();
+    match user_repo.revoke_refresh_token(&hash_token(&form.refresh_token)) {
+        Ok(_) => HttpResponse::NoContent().finish(),
+        Err(e) => {
+            error!(error = ?e, "Failed to log out"); // Hata mesajını logla
+            HttpResponse::InternalServerError().body(format!("Failed to log out: {}", e))
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/auth/me",
+    tag = "auth",
+    responses((status = 200, description = "The caller and the roles it holds", body = AuthenticatedUser), (status = 401, description = "Missing or invalid credentials")),
+)]
+pub async fn get_current_user(
+    user: AuthenticatedUser,
+) -> impl Responder {
+    HttpResponse::Ok().json(user)
+}
+
+#[utoipa::path(
+    post,
+    path = "/users",
+    tag = "users",
+    request_body = CreateUserRequest,
+    responses((status = 201, description = "User created"), (status = 409, description = "Username is already taken"), (status = 422, description = "Invalid user"), (status = 401, description = "Missing or invalid credentials"), (status = 403, description = "Missing permission")),
+)]
+pub async fn create_user(
+    req: HttpRequest,
+    repos: web::Data<AppState>,
+    user: AuthenticatedUser,
+    form: web::Json<CreateUserRequest>,
+) -> impl Responder {
+    if let Err(e) = user.require(Permission::ManageSystem, None) {
+        return e.error_response();
+    }
+    if let Err(message) = form.validate() {
+        return HttpResponse::UnprocessableEntity().body(message);
+    }
+
+    let password_hash = match hash_password(&form.password) {
+        Ok(hash) => hash,
+        Err(e) => return HttpResponse::InternalServerError().body(format!("Failed to create user: {}", e)),
+    };
+
+    let created = audited(&repos, &req, &user, |tx| {
+        let created = tx.users().create_user(form.username.trim(), &password_hash, &form.account_type, form.member_id.as_ref())?;
+        tx.record(AuditEvent {
+            action: "create",
+            entity: "user",
+            entity_id: created.id.to_string(),
+            before: None,
+            after: snapshot(&created),
+        })
+    });
+    match created {
+        Ok(()) => HttpResponse::Created().finish(),
+        Err(diesel::result::Error::DatabaseError(diesel::result::DatabaseErrorKind::UniqueViolation, _)) => {
+            HttpResponse::Conflict().body("Username is already taken")
+        }
+        Err(e) => {
+            error!(error = ?e, "Failed to create user"); // Hata mesajını logla
+            HttpResponse::InternalServerError().body(format!("Failed to create user: {}", e))
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/users",
+    tag = "users",
+    responses((status = 200, description = "All user accounts", body = Vec<User>), (status = 401, description = "Missing or invalid credentials"), (status = 403, description = "Missing permission")),
+)]
+pub async fn get_users(
+    repos: web::Data<AppState>,
+    user: AuthenticatedUser,
+) -> impl Responder {
+    if let Err(e) = user.require(Permission::ManageSystem, None) {
+        return e.error_response();
+    }
+
+    let mut user_repo = repos.user_repo.lock().unwrap();
+    match user_repo.get_users() {
+        Ok(users) => HttpResponse::Ok().json(users),
+        Err(e) => {
+            error!(error = ?e, "Failed to get users"); // Hata mesajını logla
+            HttpResponse::InternalServerError().body(format!("Failed to get users: {}", e))
+        }
+    }
+}
+
+// Library managers hand out librarian and member roles for their own library; everything else needs an admin.
+fn require_role_admin(user: &AuthenticatedUser, role: Role, library_id: Option<i32>) -> Result<(), AuthError> {
+    match (role, library_id) {
+        (Role::Librarian | Role::Member, Some(library_id)) => user.require(Permission::ManageStaff, Some(library_id)),
+        _ => user.require(Permission::ManageSystem, None),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/users/{id}/roles",
+    tag = "users",
+    responses((status = 200, description = "Explicit role grants of the user", body = Vec<UserRole>), (status = 401, description = "Missing or invalid credentials"), (status = 403, description = "Missing permission")),
+)]
+pub async fn get_user_roles(
+    repos: web::Data<AppState>,
+    user: AuthenticatedUser,
+    id: web::Path<i32>,
+) -> impl Responder {
+    if user.id != *id {
+        if let Err(e) = user.require_anywhere(Permission::ManageStaff) {
+            return e.error_response();
+        }
+    }
+
+    let mut user_repo = repos.user_repo.lock().unwrap();
+    match user_repo.get_user_roles(&id) {
+        Ok(roles) => HttpResponse::Ok().json(roles),
+        Err(e) => {
+            error!(error = ?e, "Failed to get user roles"); // Hata mesajını logla
+            HttpResponse::InternalServerError().body(format!("Failed to get user roles: {}", e))
+        }
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/users/{id}/roles",
+    tag = "users",
+    request_body = GrantRoleRequest,
+    responses((status = 201, description = "Role granted"), (status = 409, description = "User already has this role"), (status = 422, description = "Unknown role or invalid scope"), (status = 401, description = "Missing or invalid credentials"), (status = 403, description = "Missing permission")),
+)]
+pub async fn grant_role(
+    req: HttpRequest,
+    repos: web::Data<AppState>,
+    user: AuthenticatedUser,
+    id: web::Path<i32>,
+    form: web::Json<GrantRoleRequest>,
+) -> impl Responder {
+    let role = match Role::parse(&form.role) {
+        Some(role) => role,
+        None => return HttpResponse::UnprocessableEntity().body(format!("Unknown role '{}'", form.role)),
+    };
+    if role == Role::SystemAdmin && form.library_id.is_some() {
+        return HttpResponse::UnprocessableEntity().body("system_admin cannot be scoped to a library");
+    }
+    if let Err(e) = require_role_admin(&user, role, form.library_id) {
+        return e.error_response();
+    }
+
+    let granted = audited(&repos, &req, &user, |tx| {
+        let granted = tx.users().grant_role(&id, role.as_str(), form.library_id.as_ref())?;
+        tx.record(AuditEvent {
+            action: "create",
+            entity: "user_role",
+            entity_id: granted.id.to_string(),
+            before: None,
+            after: snapshot(&granted),
+        })
+    });
+    match granted {
+        Ok(()) => HttpResponse::Created().finish(),
+        Err(diesel::result::Error::DatabaseError(diesel::result::DatabaseErrorKind::UniqueViolation, _)) => {
+            HttpResponse::Conflict().body("User already has this role")
+        }
+        Err(e) => {
+            error!(error = ?e, "Failed to grant role"); // Hata mesajını logla
+            HttpResponse::InternalServerError().body(format!("Failed to grant role: {}", e))
+        }
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = "/users/{id}/roles/{role_id}",
+    tag = "users",
+    params(UserRolePath),
+    responses((status = 204, description = "Role revoked"), (status = 404, description = "No such role grant"), (status = 401, description = "Missing or invalid credentials"), (status = 403, description = "Missing permission")),
+)]
+pub async fn revoke_role(
+    req: HttpRequest,
+    repos: web::Data<AppState>,
+    user: AuthenticatedUser,
+    path: web::Path<UserRolePath>,
+) -> impl Responder {
+    let mut user_repo = repos.user_repo.lock().unwrap();
+
+    let roles = match user_repo.get_user_roles(&path.id) {
+        Ok(roles) => roles,
+        Err(e) => {
+            error!(error = ?e, "Failed to revoke role"); // Hata mesajını logla
+            return HttpResponse::InternalServerError().body(format!("Failed to revoke role: {}", e));
+        }
+    };
+    let Some(existing) = roles.iter().find(|role| role.id == path.role_id) else {
+        return HttpResponse::NotFound().finish();
+    };
+    let role = Role::parse(&existing.role).unwrap_or(Role::SystemAdmin);
+    if let Err(e) = require_role_admin(&user, role, existing.library_id) {
+        return e.error_response();
+    }
+
+    drop(user_repo);
+    let revoked = audited(&repos, &req, &user, |tx| {
+        let revoked = tx.users().revoke_role(&path.id, &path.role_id)?;
+        if revoked == 1 {
+            tx.record(AuditEvent {
+                action: "delete",
+                entity: "user_role",
+                entity_id: path.role_id.to_string(),
+                before: snapshot(existing),
+                after: None,
+            })?;
+        }
+        Ok(revoked)
+    });
+    match revoked {
+        Ok(0) => HttpResponse::NotFound().finish(),
+        Ok(_) => HttpResponse::NoContent().finish(),
+        Err(e) => {
+            error!(error = ?e, "Failed to revoke role"); // Hata mesajını logla
+            HttpResponse::InternalServerError().body(format!("Failed to revoke role: {}", e))
+        }
+    }
+}
+
+// The plaintext key is only ever returned here.
+#[utoipa::path(
+    post,
+    path = "/api-keys",
+    tag = "api-keys",
+    request_body = CreateApiKeyRequest,
+    responses((status = 201, description = "API key created; the key is only shown here", body = CreatedApiKeyResponse), (status = 422, description = "Missing name"), (status = 401, description = "Missing or invalid credentials"), (status = 403, description = "Missing permission")),
+)]
+pub async fn create_api_key(
+    req: HttpRequest,
+    repos: web::Data<AppState>,
+    user: AuthenticatedUser,
+    form: web::Json<CreateApiKeyRequest>,
+) -> impl Responder {
+    if !repos.features.api_keys {
+        return HttpResponse::Forbidden().body("API keys are disabled on this server");
+    }
+    if form.name.trim().is_empty() {
+        return HttpResponse::UnprocessableEntity().body("name must not be empty");
+    }
+
+    let key = format!("lib_{}", generate_token());
+    let prefix = key[..API_KEY_PREFIX_LEN].to_string();
+
+    let created = audited(&repos, &req, &user, |tx| {
+        let created = tx.users().create_api_key(&user.id, form.name.trim(), &prefix, &hash_token(&key))?;
+        tx.record(AuditEvent {
+            action: "create",
+            entity: "api_key",
+            entity_id: created.id.to_string(),
+            before: None,
+            after: snapshot(&created),
+        })?;
+        Ok(created)
+    });
+    match created {
+        Ok(created) => HttpResponse::Created().json(CreatedApiKeyResponse {
+            name: created.name,
+            prefix,
+            key,
+        }),
+        Err(e) => {
+            error!(error = ?e, "Failed to create api key"); // Hata mesajını logla
+            HttpResponse::InternalServerError().body(format!("Failed to create api key: {}", e))
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api-keys",
+    tag = "api-keys",
+    responses((status = 200, description = "The caller's API keys", body = Vec<ApiKey>), (status = 401, description = "Missing or invalid credentials")),
+)]
+pub async fn get_api_keys(
+    repos: web::Data<AppState>,
+    user: AuthenticatedUser,
+) -> impl Responder {
+    let mut user_repo = repos.user_repo.lock().unwrap();
+    match user_repo.get_api_keys(&user.id) {
+        Ok(keys) => HttpResponse::Ok().json(keys),
+        Err(e) => {
+            error!(error = ?e, "Failed to get api keys"); // Hata mesajını logla
+            HttpResponse::InternalServerError().body(format!("Failed to get api keys: {}", e))
+        }
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api-keys/{id}",
+    tag = "api-keys",
+    responses((status = 204, description = "API key revoked"), (status = 404, description = "No such API key"), (status = 401, description = "Missing or invalid credentials")),
+)]
+pub async fn revoke_api_key(
+    req: HttpRequest,
+    repos: web::Data<AppState>,
+    user: AuthenticatedUser,
+    id: web::Path<i32>,
+) -> impl Responder {
+    let revoked = audited(&repos, &req, &user, |tx| {
+        let revoked = tx.users().revoke_api_key(&user.id, &id)?;
+        if revoked == 1 {
+            tx.record(AuditEvent {
+                action: "revoke",
+                entity: "api_key",
+                entity_id: id.to_string(),
+                before: None,
+                after: None,
+            })?;
+        }
+        Ok(revoked)
+    });
+    match revoked {
+        Ok(0) => HttpResponse::NotFound().finish(),
+        Ok(_) => HttpResponse::NoContent().finish(),
+        Err(e) => {
+            error!(error = ?e, "Failed to revoke api key"); // Hata mesajını logla
+            HttpResponse::InternalServerError().body(format!("Failed to revoke api key: {}", e))
+        }
+    }
+}
+
+// Routes configuration
+pub fn auth_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::resource("/auth/login")
+            .route(web::post().to(login))
+    )
+    .service(
+        web::resource("/auth/refresh")
+            .route(web::post().to(refresh))
+    )
+    .service(
+        web::resource("/auth/logout")
+            .route(web::post().to(logout))
+    )
+    .service(
+        web::resource("/auth/me")
+            .route(web::get().to(get_current_user))
+    )
+    .service(
+        web::resource("/users")
+            .route(web::post().to(create_user))
+            .route(web::get().to(get_users))
+    )
+    .service(
+        web::resource("/users/{id}/roles")
+            .route(web::get().to(get_user_roles))
+            .route(web::post().to(grant_role))
+    )
+    .service(
+        web::resource("/users/{id}/roles/{role_id}")
+            .route(web::delete().to(revoke_role))
+    )
+    .service(
+        web::resource("/api-keys")
+            .route(web::post().to(create_api_key))
+            .route(web::get().to(get_api_keys))
+    )
+    .service(
+        web::resource("/api-keys/{id}")
+            .route(web::delete().to(revoke_api_key))
+    );
+}
@@ -1,89 +1,357 @@
-use actix_web::{web, HttpResponse, Responder};
-use serde::Deserialize;
+use actix_web::http::header::ETag;
+use actix_web::{web, HttpRequest, HttpResponse, Responder, ResponseError};
+use serde::{Deserialize, Serialize};
+use tracing::error;
+use utoipa::ToSchema;
 
+use domain::models::book::{normalize_isbn, Book, BookChanges};
+use domain::models::role::Permission;
+use domain::traits::BookRepositoryTrait;
+
+use crate::audit::{audited, snapshot, AuditEvent};
+use crate::auth::AuthenticatedUser;
+use crate::etag::{check_if_match, entity_tag, not_modified, precondition_failed};
+use crate::patch::{apply_patch, changed};
+use crate::versioning::CURRENT_VERSION;
 use crate::AppState;
 
 // Request yapılandırması
-#[derive(Deserialize)]
+#[derive(Deserialize, ToSchema)]
 pub struct CreateBookRequest {
     title: String,
     author: String,
     library_id: i32,
+    // ISBN-10 or ISBN-13; hyphens and spaces are dropped.
+    isbn: Option<String>,
 }
 
-#[derive(Deserialize)]
+#[derive(Serialize, Deserialize, ToSchema)]
 pub struct UpdateBookRequest {
     title: String,
     author: String,
+    // Unchanged when omitted or null on PUT; PATCH it to null to clear it.
+    #[serde(default)]
+    isbn: Option<String>,
+}
+
+impl UpdateBookRequest {
+    fn validate(&self) -> Result<(), String> {
+        if self.title.trim().is_empty() {
+            return Err("title must not be empty".to_string());
+        }
+        if self.author.trim().is_empty() {
+            return Err("author must not be empty".to_string());
+        }
+        parse_isbn(self.isbn.as_deref())?;
+        Ok(())
+    }
+}
+
+pub(crate) fn parse_isbn(isbn: Option<&str>) -> Result<Option<String>, String> {
+    isbn.map(|isbn| normalize_isbn(isbn).ok_or_else(|| format!("{} is not a valid ISBN", isbn))).transpose()
+}
+
+fn isbn_conflict() -> HttpResponse {
+    HttpResponse::Conflict().body("A book with this ISBN already exists")
+}
+
+// A catalogue edit shows in every library holding the book, so it needs holdings rights in each of them; a
+// book no library holds yet only needs them somewhere.
+fn check_book_holdings(repos: &AppState, user: &AuthenticatedUser, book_id: &i32) -> Option<HttpResponse> {
+    let stock = match repos.lib_repo.lock().unwrap().get_book_stock(book_id) {
+        Ok(stock) => stock,
+        Err(e) => {
+            error!(error = ?e, "Failed to get book holdings"); // Hata mesajını logla
+            return Some(HttpResponse::InternalServerError().body(format!("Failed to get book holdings: {}", e)));
+        }
+    };
+
+    let allowed = if stock.is_empty() {
+        user.require_anywhere(Permission::ManageHoldings)
+    } else {
+        stock.iter().try_for_each(|holding| user.require(Permission::ManageHoldings, Some(holding.library_id)))
+    };
+    allowed.err().map(|e| e.error_response())
 }
 
+#[utoipa::path(
+    post,
+    path = "/books",
+    tag = "books",
+    request_body = CreateBookRequest,
+    responses((status = 201, description = "Created; Location points at the new book", headers(("ETag" = String, description = "Current version of the resource"))), (status = 409, description = "A book with this ISBN already exists"), (status = 422, description = "Invalid ISBN"), (status = 401, description = "Missing or invalid credentials"), (status = 403, description = "Missing permission")),
+)]
 pub async fn create_book(
+    req: HttpRequest,
     repos: web::Data<AppState>,
+    user: AuthenticatedUser,
     form: web::Json<CreateBookRequest>,
 ) -> impl Responder {
-    let book_repo = repos.book_repo.clone();
-    let result = book_repo.lock().unwrap().create_book(&form.title, &form.author, &form.library_id);
+    if let Err(e) = user.require(Permission::ManageHoldings, Some(form.library_id)) {
+        return e.error_response();
+    }
+
+    let isbn = match parse_isbn(form.isbn.as_deref()) {
+        Ok(isbn) => isbn,
+        Err(message) => return HttpResponse::UnprocessableEntity().body(message),
+    };
+
+    let result = audited(&repos, &req, &user, |tx| {
+        let created = tx.books().create_book(&form.title, &form.author, isbn.as_deref(), &form.library_id)?;
+        tx.record(AuditEvent {
+            action: "create",
+            entity: "book",
+            entity_id: created.id.to_string(),
+            before: None,
+            after: snapshot(&created),
+        })?;
+        Ok(created)
+    });
 
     match result {
-        Ok(_) => HttpResponse::Created().finish(),
+        Ok(created) => HttpResponse::Created()
+            .insert_header(("Location", format!("{}/books/{}", CURRENT_VERSION, created.id)))
+            .insert_header(ETag(entity_tag(created.version)))
+            .finish(),
+        Err(diesel::result::Error::DatabaseError(diesel::result::DatabaseErrorKind::UniqueViolation, _)) => isbn_conflict(),
         Err(e) => {
-            println!("Failed to create book: {:?}", e); // Hata mesajını logla
+            error!(error = ?e, "Failed to create book"); // Hata mesajını logla
             HttpResponse::InternalServerError().body(format!("Failed to create book: {}", e))
         }
     }
 }
 
+#[utoipa::path(
+    put,
+    path = "/books/{id}",
+    tag = "books",
+    params(("If-Match" = String, Header, description = "ETag from the last GET, or `*`")),
+    request_body = UpdateBookRequest,
+    responses((status = 200, description = "Updated", headers(("ETag" = String, description = "Current version of the resource"))), (status = 404, description = "No such book"), (status = 409, description = "A book with this ISBN already exists"), (status = 422, description = "Invalid book"), (status = 412, description = "If-Match does not match the current version"), (status = 428, description = "If-Match header is required"), (status = 401, description = "Missing or invalid credentials"), (status = 403, description = "Missing permission")),
+)]
 pub async fn update_book(
+    req: HttpRequest,
     repos: web::Data<AppState>,
+    user: AuthenticatedUser,
     id: web::Path<i32>,
     form: web::Json<UpdateBookRequest>,
 ) -> impl Responder {
-    let book_repo = repos.book_repo.clone();
-    let result = book_repo.lock().unwrap().update_book(&id, &form.title, &form.author);
+    if let Some(response) = check_book_holdings(&repos, &user, &id) {
+        return response;
+    }
+
+    if let Err(message) = form.validate() {
+        return HttpResponse::UnprocessableEntity().body(message);
+    }
+
+    let mut book_repo = repos.book_repo.lock().unwrap();
 
+    let current = match book_repo.get_book_by_id(&id) {
+        Ok(book) => book,
+        Err(diesel::result::Error::NotFound) => return HttpResponse::NotFound().finish(),
+        Err(e) => {
+            error!(error = ?e, "Failed to update book"); // Hata mesajını logla
+            return HttpResponse::InternalServerError().body(format!("Failed to update book: {}", e));
+        }
+    };
+    if let Some(response) = check_if_match(&req, current.version) {
+        return response;
+    }
+
+    let isbn = parse_isbn(form.isbn.as_deref()).ok().flatten().or_else(|| current.isbn.clone());
+    let result = audited(&repos, &req, &user, |tx| {
+        let updated = tx.books().update_book(&id, &form.title, &form.author, isbn.as_deref(), &current.version)?;
+        if updated == 1 {
+            let after = tx.books().get_book_by_id(&id)?;
+            tx.record(AuditEvent {
+                action: "update",
+                entity: "book",
+                entity_id: id.to_string(),
+                before: snapshot(&current),
+                after: snapshot(&after),
+            })?;
+        }
+        Ok(updated)
+    });
     match result {
-        Ok(_) => HttpResponse::Ok().finish(),
+        Ok(0) => precondition_failed(),
+        Ok(_) => HttpResponse::Ok().insert_header(ETag(entity_tag(current.version + 1))).finish(),
+        Err(diesel::result::Error::DatabaseError(diesel::result::DatabaseErrorKind::UniqueViolation, _)) => isbn_conflict(),
         Err(e) => {
-            println!("Failed to update book: {:?}", e); // Hata mesajını logla
+            error!(error = ?e, "Failed to update book"); // Hata mesajını logla
             HttpResponse::InternalServerError().body(format!("Failed to update book: {}", e))
         }
     }
 }
 
+#[utoipa::path(
+    patch,
+    path = "/books/{id}",
+    tag = "books",
+    params(("If-Match" = String, Header, description = "ETag from the last GET, or `*`")),
+    request_body(content((UpdateBookRequest = "application/merge-patch+json"), (UpdateBookRequest = "application/json-patch+json"))),
+    responses((status = 200, description = "Patched", headers(("ETag" = String, description = "Current version of the resource"))), (status = 400, description = "Malformed patch"), (status = 404, description = "No such book"), (status = 409, description = "JSON Patch test or path failed, or a book with this ISBN already exists"), (status = 415, description = "Unsupported patch media type"), (status = 422, description = "Patched book is invalid"), (status = 412, description = "If-Match does not match the current version"), (status = 428, description = "If-Match header is required"), (status = 401, description = "Missing or invalid credentials"), (status = 403, description = "Missing permission")),
+)]
+pub async fn patch_book(
+    req: HttpRequest,
+    repos: web::Data<AppState>,
+    user: AuthenticatedUser,
+    id: web::Path<i32>,
+    body: web::Bytes,
+) -> impl Responder {
+    if let Some(response) = check_book_holdings(&repos, &user, &id) {
+        return response;
+    }
+
+    let mut book_repo = repos.book_repo.lock().unwrap();
+
+    let current = match book_repo.get_book_by_id(&id) {
+        Ok(book) => book,
+        Err(diesel::result::Error::NotFound) => return HttpResponse::NotFound().finish(),
+        Err(e) => {
+            error!(error = ?e, "Failed to patch book"); // Hata mesajını logla
+            return HttpResponse::InternalServerError().body(format!("Failed to patch book: {}", e));
+        }
+    };
+    if let Some(response) = check_if_match(&req, current.version) {
+        return response;
+    }
+
+    let existing = UpdateBookRequest { title: current.title.clone(), author: current.author.clone(), isbn: current.isbn.clone() };
+    let patched = match apply_patch(&req, &body, &existing) {
+        Ok(patched) => patched,
+        Err(e) => return e.error_response(),
+    };
+    if let Err(message) = patched.validate() {
+        return HttpResponse::UnprocessableEntity().body(message);
+    }
+
+    let isbn = parse_isbn(patched.isbn.as_deref()).ok().flatten();
+    let changes = BookChanges {
+        title: changed(existing.title.as_str(), patched.title.as_str()),
+        author: changed(existing.author.as_str(), patched.author.as_str()),
+        isbn: (isbn != current.isbn).then_some(isbn.as_deref()),
+    };
+    if changes.is_empty() {
+        return HttpResponse::Ok().insert_header(ETag(entity_tag(current.version))).finish();
+    }
+
+    let result = audited(&repos, &req, &user, |tx| {
+        let patched = tx.books().patch_book(&id, &changes, &current.version)?;
+        if patched == 1 {
+            let after = tx.books().get_book_by_id(&id)?;
+            tx.record(AuditEvent {
+                action: "update",
+                entity: "book",
+                entity_id: id.to_string(),
+                before: snapshot(&current),
+                after: snapshot(&after),
+            })?;
+        }
+        Ok(patched)
+    });
+    match result {
+        Ok(0) => precondition_failed(),
+        Ok(_) => HttpResponse::Ok().insert_header(ETag(entity_tag(current.version + 1))).finish(),
+        Err(diesel::result::Error::DatabaseError(diesel::result::DatabaseErrorKind::UniqueViolation, _)) => isbn_conflict(),
+        Err(e) => {
+            error!(error = ?e, "Failed to patch book"); // Hata mesajını logla
+            HttpResponse::InternalServerError().body(format!("Failed to patch book: {}", e))
+        }
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = "/books/{id}",
+    tag = "books",
+    params(("If-Match" = String, Header, description = "ETag from the last GET, or `*`")),
+    responses((status = 204, description = "Deleted"), (status = 404, description = "No such book"), (status = 412, description = "If-Match does not match the current version"), (status = 428, description = "If-Match header is required"), (status = 401, description = "Missing or invalid credentials"), (status = 403, description = "Missing permission")),
+)]
 pub async fn delete_book(
+    req: HttpRequest,
     repos: web::Data<AppState>,
+    user: AuthenticatedUser,
     id: web::Path<i32>,
 ) -> impl Responder {
-    let book_repo = repos.book_repo.clone();
-    let result = book_repo.lock().unwrap().delete_book(&id);
+    // Deleting a book removes it from every library's catalogue and shelves.
+    if let Err(e) = user.require(Permission::ManageSystem, None) {
+        return e.error_response();
+    }
+
+    let mut book_repo = repos.book_repo.lock().unwrap();
 
+    let current = match book_repo.get_book_by_id(&id) {
+        Ok(book) => book,
+        Err(diesel::result::Error::NotFound) => return HttpResponse::NotFound().finish(),
+        Err(e) => {
+            error!(error = ?e, "Failed to delete book"); // Hata mesajını logla
+            return HttpResponse::InternalServerError().body(format!("Failed to delete book: {}", e));
+        }
+    };
+    if let Some(response) = check_if_match(&req, current.version) {
+        return response;
+    }
+
+    let result = audited(&repos, &req, &user, |tx| {
+        let deleted = tx.books().delete_book(&id, &current.version)?;
+        if deleted == 1 {
+            tx.record(AuditEvent {
+                action: "delete",
+                entity: "book",
+                entity_id: id.to_string(),
+                before: snapshot(&current),
+                after: None,
+            })?;
+        }
+        Ok(deleted)
+    });
     match result {
+        Ok(0) => precondition_failed(),
         Ok(_) => HttpResponse::NoContent().finish(),
         Err(e) => {
-            println!("Failed to delete book: {:?}", e); // Hata mesajını logla
+            error!(error = ?e, "Failed to delete book"); // Hata mesajını logla
             HttpResponse::InternalServerError().body(format!("Failed to delete book: {}", e))
         }
     }
 }
 
+#[utoipa::path(
+    get,
+    path = "/books/{id}",
+    tag = "books",
+    params(("If-None-Match" = Option<String>, Header, description = "Answer 304 when the ETag still matches")),
+    responses((status = 200, description = "The book", body = Book, headers(("ETag" = String, description = "Current version of the resource"))), (status = 304, description = "Not modified"), (status = 404, description = "No such book"), (status = 401, description = "Missing or invalid credentials"), (status = 403, description = "Missing permission")),
+)]
 pub async fn get_book(
+    req: HttpRequest,
     repos: web::Data<AppState>,
+    _user: AuthenticatedUser,
     id: web::Path<i32>,
 ) -> impl Responder {
     let book_repo = repos.book_repo.clone();
     let result = book_repo.lock().unwrap().get_book_by_id(&id);
 
     match result {
-        Ok(book) => HttpResponse::Ok().json(book),
+        Ok(book) => not_modified(&req, book.version)
+            .unwrap_or_else(|| HttpResponse::Ok().insert_header(ETag(entity_tag(book.version))).json(book)),
+        Err(diesel::result::Error::NotFound) => HttpResponse::NotFound().finish(),
         Err(e) => {
-            println!("Failed to get book: {:?}", e); // Hata mesajını logla
+            error!(error = ?e, "Failed to get book"); // Hata mesajını logla
             HttpResponse::InternalServerError().body(format!("Failed to get book: {}", e))
         }
     }
 }
 
+#[utoipa::path(
+    get,
+    path = "/books",
+    tag = "books",
+    responses((status = 200, description = "All books", body = Vec<Book>), (status = 401, description = "Missing or invalid credentials"), (status = 403, description = "Missing permission")),
+)]
 pub async fn get_books(
     repos: web::Data<AppState>,
+    _user: AuthenticatedUser,
 ) -> impl Responder {
     let book_repo = repos.book_repo.clone();
     let result = book_repo.lock().unwrap().get_books();
@@ -91,14 +359,21 @@ pub async fn get_books(
     match result {
         Ok(books) => HttpResponse::Ok().json(books),
         Err(e) => {
-            println!("Failed to get books: {:?}", e); // Hata mesajını logla
+            error!(error = ?e, "Failed to get books"); // Hata mesajını logla
             HttpResponse::InternalServerError().body(format!("Failed to get books: {}", e))
         }
     }
 }
 
+#[utoipa::path(
+    get,
+    path = "/libraries/{library_id}/books",
+    tag = "books",
+    responses((status = 200, description = "Books held by the library", body = Vec<Book>), (status = 401, description = "Missing or invalid credentials"), (status = 403, description = "Missing permission")),
+)]
 pub async fn get_books_by_library_id(
     repos: web::Data<AppState>,
+    _user: AuthenticatedUser,
     library_id: web::Path<i32>,
 ) -> impl Responder {
     let book_repo = repos.book_repo.clone();
@@ -107,7 +382,7 @@ pub async fn get_books_by_library_id(
     match result {
         Ok(books) => HttpResponse::Ok().json(books),
         Err(e) => {
-            println!("Failed to get books by library id: {:?}", e); // Hata mesajını logla
+            error!(error = ?e, "Failed to get books by library id"); // Hata mesajını logla
             HttpResponse::InternalServerError().body(format!("Failed to get books by library id: {}", e))
         }
     }
@@ -124,6 +399,7 @@ pub fn book_routes(cfg: &mut web::ServiceConfig) {
         web::resource("/books/{id}")
             .route(web::get().to(get_book))
             .route(web::put().to(update_book))
+            .route(web::patch().to(patch_book))
             .route(web::delete().to(delete_book))
     )
     .service(
@@ -0,0 +1,431 @@
+use actix_web::{web, HttpRequest, HttpResponse, Responder, ResponseError};
+use chrono::{NaiveDate, NaiveTime};
+use serde::{Deserialize, Serialize};
+use tracing::error;
+use utoipa::{IntoParams, ToSchema};
+
+use domain::models::calendar::{Closure, NewClosure, OpeningHours};
+use domain::models::role::Permission;
+use domain::traits::CalendarRepositoryTrait;
+
+use crate::audit::{audited, snapshot, AuditEvent};
+use crate::auth::AuthenticatedUser;
+use crate::calendar::{load_calendar, to_ics};
+use crate::AppState;
+
+// Longest range one calendar request may cover.
+const MAX_RANGE_DAYS: i64 = 366;
+
+// Request yapılandırmaları
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct CalendarQuery {
+    // First day (YYYY-MM-DD); today when omitted.
+    from: Option<String>,
+    // Day after the last one (YYYY-MM-DD); 30 days after `from` for JSON and a year for iCalendar when omitted.
+    to: Option<String>,
+}
+
+impl CalendarQuery {
+    fn range(&self, default_days: i64) -> Result<(NaiveDate, NaiveDate), String> {
+        let parse = |name: &str, value: &str| {
+            NaiveDate::parse_from_str(value, "%Y-%m-%d").map_err(|_| format!("{} must be a date (YYYY-MM-DD)", name))
+        };
+        let from = match &self.from {
+            Some(from) => parse("from", from)?,
+            None => chrono::offset::Utc::now().date_naive(),
+        };
+        let to = match &self.to {
+            Some(to) => parse("to", to)?,
+            None => from + chrono::Duration::days(default_days),
+        };
+        if to <= from {
+            return Err("to must be after from".to_string());
+        }
+        if (to - from).num_days() > MAX_RANGE_DAYS {
+            return Err(format!("A calendar covers at most {} days", MAX_RANGE_DAYS));
+        }
+        Ok((from, to))
+    }
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct HoursEntry {
+    // 1 (Monday) to 7 (Sunday).
+    weekday: i32,
+    // HH:MM
+    opens_at: String,
+    closes_at: String,
+}
+
+// Times are stored zero-padded, e.g. "9:00" as "09:00", so they compare and format consistently.
+fn padded_time(value: &str) -> String {
+    NaiveTime::parse_from_str(value, "%H:%M").map(|time| time.format("%H:%M").to_string()).unwrap_or_else(|_| value.to_string())
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct SetHoursRequest {
+    // One entry per open weekday; days left out are closed. An empty list opens the library every day.
+    hours: Vec<HoursEntry>,
+}
+
+impl SetHoursRequest {
+    fn validate(&self) -> Result<(), String> {
+        let parse = |value: &str| NaiveTime::parse_from_str(value, "%H:%M").map_err(|_| format!("{} is not a time (HH:MM)", value));
+        for (i, entry) in self.hours.iter().enumerate() {
+            if !(1..=7).contains(&entry.weekday) {
+                return Err("weekday must be between 1 (Monday) and 7 (Sunday)".to_string());
+            }
+            if self.hours[..i].iter().any(|other| other.weekday == entry.weekday) {
+                return Err(format!("weekday {} is listed twice", entry.weekday));
+            }
+            if parse(&entry.opens_at)? >= parse(&entry.closes_at)? {
+                return Err(format!("weekday {} must open before it closes", entry.weekday));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct CreateClosureRequest {
+    // YYYY-MM-DD
+    date: String,
+    // Shown in the calendar, e.g. "New Year's Day".
+    reason: String,
+}
+
+impl CreateClosureRequest {
+    fn validate(&self) -> Result<(), String> {
+        if NaiveDate::parse_from_str(&self.date, "%Y-%m-%d").is_err() {
+            return Err("date must be a date (YYYY-MM-DD)".to_string());
+        }
+        if self.reason.trim().is_empty() {
+            return Err("reason must not be empty".to_string());
+        }
+        Ok(())
+    }
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Path)]
+pub struct ClosurePath {
+    id: i32,
+    closure_id: i32,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct CalendarDay {
+    date: String,
+    open: bool,
+    opens_at: Option<String>,
+    closes_at: Option<String>,
+    // Why the library is closed on a day it would otherwise open.
+    closure: Option<String>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct CalendarResponse {
+    library_id: i32,
+    hours: Vec<OpeningHours>,
+    days: Vec<CalendarDay>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ClosureResponse {
+    closure: Closure,
+    // Open loans that were due that day and now fall due on the next open day.
+    moved_loans: usize,
+}
+
+// Handlers
+#[utoipa::path(
+    get,
+    path = "/libraries/{id}/calendar",
+    tag = "calendar",
+    params(("id" = i32, Path, description = "Library id"), CalendarQuery),
+    responses((status = 200, description = "Opening hours and each day of the range, open or closed", body = CalendarResponse), (status = 404, description = "No such library"), (status = 422, description = "Invalid range"), (status = 401, description = "Missing or invalid credentials"), (status = 403, description = "Missing permission")),
+)]
+pub async fn get_calendar(
+    repos: web::Data<AppState>,
+    _user: AuthenticatedUser,
+    id: web::Path<i32>,
+    query: web::Query<CalendarQuery>,
+) -> impl Responder {
+    let (from, to) = match query.range(30) {
+        Ok(range) => range,
+        Err(message) => return HttpResponse::UnprocessableEntity().body(message),
+    };
+    match repos.lib_repo.lock().unwrap().get_library_by_id(&id) {
+        Ok(_) => {}
+        Err(diesel::result::Error::NotFound) => return HttpResponse::NotFound().finish(),
+        Err(e) => {
+            error!(error = ?e, "Failed to get calendar"); // Hata mesajını logla
+            return HttpResponse::InternalServerError().body(format!("Failed to get calendar: {}", e));
+        }
+    }
+
+    let calendar = match load_calendar(&repos, *id, Some(&from.to_string())) {
+        Ok(calendar) => calendar,
+        Err(e) => {
+            error!(error = ?e, "Failed to get calendar"); // Hata mesajını logla
+            return HttpResponse::InternalServerError().body(format!("Failed to get calendar: {}", e));
+        }
+    };
+    let days = from
+        .iter_days()
+        .take_while(|date| *date < to)
+        .map(|date| {
+            let open = calendar.is_open(date);
+            let hours = calendar.hours_on(date).filter(|_| open);
+            CalendarDay {
+                date: date.to_string(),
+                open,
+                opens_at: hours.map(|hours| hours.opens_at.clone()),
+                closes_at: hours.map(|hours| hours.closes_at.clone()),
+                closure: calendar.closure_on(date).map(|closure| closure.reason.clone()),
+            }
+        })
+        .collect();
+
+    HttpResponse::Ok().json(CalendarResponse { library_id: *id, hours: calendar.hours, days })
+}
+
+#[utoipa::path(
+    get,
+    path = "/libraries/{id}/calendar.ics",
+    tag = "calendar",
+    params(("id" = i32, Path, description = "Library id"), CalendarQuery),
+    responses((status = 200, description = "The calendar as iCalendar (text/calendar)", body = String), (status = 404, description = "No such library"), (status = 422, description = "Invalid range"), (status = 401, description = "Missing or invalid credentials"), (status = 403, description = "Missing permission")),
+)]
+pub async fn get_calendar_ics(
+    repos: web::Data<AppState>,
+    _user: AuthenticatedUser,
+    id: web::Path<i32>,
+    query: web::Query<CalendarQuery>,
+) -> impl Responder {
+    let (from, to) = match query.range(365) {
+        Ok(range) => range,
+        Err(message) => return HttpResponse::UnprocessableEntity().body(message),
+    };
+    let library = match repos.lib_repo.lock().unwrap().get_library_by_id(&id) {
+        Ok(library) => library,
+        Err(diesel::result::Error::NotFound) => return HttpResponse::NotFound().finish(),
+        Err(e) => {
+            error!(error = ?e, "Failed to export calendar"); // Hata mesajını logla
+            return HttpResponse::InternalServerError().body(format!("Failed to export calendar: {}", e));
+        }
+    };
+
+    match load_calendar(&repos, library.id, Some(&from.to_string())) {
+        Ok(calendar) => HttpResponse::Ok()
+            .content_type("text/calendar; charset=utf-8")
+            .insert_header(("Content-Disposition", format!("attachment; filename=\"library-{}.ics\"", library.id)))
+            .body(to_ics(&library, &calendar, from, to, chrono::offset::Utc::now().naive_utc())),
+        Err(e) => {
+            error!(error = ?e, "Failed to export calendar"); // Hata mesajını logla
+            HttpResponse::InternalServerError().body(format!("Failed to export calendar: {}", e))
+        }
+    }
+}
+
+#[utoipa::path(
+    put,
+    path = "/libraries/{id}/hours",
+    tag = "calendar",
+    params(("id" = i32, Path, description = "Library id")),
+    request_body = SetHoursRequest,
+    responses((status = 200, description = "Opening hours replaced", body = Vec<OpeningHours>), (status = 404, description = "No such library"), (status = 422, description = "Invalid hours"), (status = 401, description = "Missing or invalid credentials"), (status = 403, description = "Missing permission")),
+)]
+pub async fn set_hours(
+    req: HttpRequest,
+    repos: web::Data<AppState>,
+    user: AuthenticatedUser,
+    id: web::Path<i32>,
+    form: web::Json<SetHoursRequest>,
+) -> impl Responder {
+    if let Err(e) = user.require(Permission::ManageLibrary, Some(*id)) {
+        return e.error_response();
+    }
+    if let Err(message) = form.validate() {
+        return HttpResponse::UnprocessableEntity().body(message);
+    }
+    match repos.lib_repo.lock().unwrap().get_library_by_id(&id) {
+        Ok(_) => {}
+        Err(diesel::result::Error::NotFound) => return HttpResponse::NotFound().finish(),
+        Err(e) => {
+            error!(error = ?e, "Failed to set opening hours"); // Hata mesajını logla
+            return HttpResponse::InternalServerError().body(format!("Failed to set opening hours: {}", e));
+        }
+    }
+
+    let mut hours: Vec<OpeningHours> = form
+        .hours
+        .iter()
+        .map(|entry| OpeningHours {
+            library_id: *id,
+            weekday: entry.weekday,
+            opens_at: padded_time(&entry.opens_at),
+            closes_at: padded_time(&entry.closes_at),
+        })
+        .collect();
+    hours.sort_by_key(|hours| hours.weekday);
+
+    let result = audited(&repos, &req, &user, |tx| {
+        let before = tx.calendar().get_hours(&id)?;
+        tx.calendar().set_hours(&id, &hours)?;
+        tx.record(AuditEvent {
+            action: "update",
+            entity: "library_hours",
+            entity_id: id.to_string(),
+            before: snapshot(&before),
+            after: snapshot(&hours),
+        })
+    });
+    match result {
+        Ok(()) => HttpResponse::Ok().json(hours),
+        Err(e) => {
+            error!(error = ?e, "Failed to set opening hours"); // Hata mesajını logla
+            HttpResponse::InternalServerError().body(format!("Failed to set opening hours: {}", e))
+        }
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/libraries/{id}/closures",
+    tag = "calendar",
+    params(("id" = i32, Path, description = "Library id")),
+    request_body = CreateClosureRequest,
+    responses((status = 201, description = "Closure added; open loans due that day moved to the next open day", body = ClosureResponse), (status = 404, description = "No such library"), (status = 409, description = "The library is already closed that day"), (status = 422, description = "Invalid closure"), (status = 401, description = "Missing or invalid credentials"), (status = 403, description = "Missing permission")),
+)]
+pub async fn add_closure(
+    req: HttpRequest,
+    repos: web::Data<AppState>,
+    user: AuthenticatedUser,
+    id: web::Path<i32>,
+    form: web::Json<CreateClosureRequest>,
+) -> impl Responder {
+    if let Err(e) = user.require(Permission::ManageLibrary, Some(*id)) {
+        return e.error_response();
+    }
+    if let Err(message) = form.validate() {
+        return HttpResponse::UnprocessableEntity().body(message);
+    }
+    match repos.lib_repo.lock().unwrap().get_library_by_id(&id) {
+        Ok(_) => {}
+        Err(diesel::result::Error::NotFound) => return HttpResponse::NotFound().finish(),
+        Err(e) => {
+            error!(error = ?e, "Failed to add closure"); // Hata mesajını logla
+            return HttpResponse::InternalServerError().body(format!("Failed to add closure: {}", e));
+        }
+    }
+
+    // "2026-1-5" is accepted and stored as "2026-01-05", the form closures are compared in.
+    let date = NaiveDate::parse_from_str(&form.date, "%Y-%m-%d").map(|date| date.to_string()).unwrap_or_default();
+    let new_closure = NewClosure {
+        library_id: &id,
+        date: &date,
+        reason: form.reason.trim(),
+    };
+    let created = audited(&repos, &req, &user, |tx| {
+        let closure = tx.calendar().add_closure(&new_closure)?;
+        tx.record(AuditEvent {
+            action: "create",
+            entity: "library_closure",
+            entity_id: closure.id.to_string(),
+            before: None,
+            after: snapshot(&closure),
+        })?;
+        Ok(closure)
+    });
+    let closure = match created {
+        Ok(closure) => closure,
+        Err(diesel::result::Error::DatabaseError(diesel::result::DatabaseErrorKind::UniqueViolation, _)) => {
+            return HttpResponse::Conflict().body(format!("The library is already closed on {}", date));
+        }
+        Err(e) => {
+            error!(error = ?e, "Failed to add closure"); // Hata mesajını logla
+            return HttpResponse::InternalServerError().body(format!("Failed to add closure: {}", e));
+        }
+    };
+
+    let moved = load_calendar(&repos, *id, Some(&closure.date)).and_then(|calendar| {
+        let date = NaiveDate::parse_from_str(&closure.date, "%Y-%m-%d").unwrap_or_default();
+        let next = calendar.next_open_day(date).to_string();
+        repos.calendar_repo.lock().unwrap().postpone_due_dates(&id, &closure.date, &next)
+    });
+    let moved_loans = match moved {
+        Ok(moved) => moved,
+        Err(e) => {
+            error!(error = ?e, "Failed to move due dates"); // Hata mesajını logla
+            0
+        }
+    };
+
+    HttpResponse::Created().json(ClosureResponse { closure, moved_loans })
+}
+
+#[utoipa::path(
+    delete,
+    path = "/libraries/{id}/closures/{closure_id}",
+    tag = "calendar",
+    params(ClosurePath),
+    responses((status = 204, description = "Closure removed; due dates already moved stay"), (status = 404, description = "No such closure"), (status = 401, description = "Missing or invalid credentials"), (status = 403, description = "Missing permission")),
+)]
+pub async fn delete_closure(
+    req: HttpRequest,
+    repos: web::Data<AppState>,
+    user: AuthenticatedUser,
+    path: web::Path<ClosurePath>,
+) -> impl Responder {
+    if let Err(e) = user.require(Permission::ManageLibrary, Some(path.id)) {
+        return e.error_response();
+    }
+
+    let deleted = audited(&repos, &req, &user, |tx| {
+        let deleted = tx.calendar().delete_closure(&path.id, &path.closure_id)?;
+        if deleted == 1 {
+            tx.record(AuditEvent {
+                action: "delete",
+                entity: "library_closure",
+                entity_id: path.closure_id.to_string(),
+                before: None,
+                after: None,
+            })?;
+        }
+        Ok(deleted)
+    });
+    match deleted {
+        Ok(0) => HttpResponse::NotFound().finish(),
+        Ok(_) => HttpResponse::NoContent().finish(),
+        Err(e) => {
+            error!(error = ?e, "Failed to delete closure"); // Hata mesajını logla
+            HttpResponse::InternalServerError().body(format!("Failed to delete closure: {}", e))
+        }
+    }
+}
+
+// Routes configuration
+pub fn calendar_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::resource("/libraries/{id}/calendar")
+            .route(web::get().to(get_calendar))
+    )
+    .service(
+        web::resource("/libraries/{id}/calendar.ics")
+            .route(web::get().to(get_calendar_ics))
+    )
+    .service(
+        web::resource("/libraries/{id}/hours")
+            .route(web::put().to(set_hours))
+    )
+    .service(
+        web::resource("/libraries/{id}/closures")
+            .route(web::post().to(add_closure))
+    )
+    .service(
+        web::resource("/libraries/{id}/closures/{closure_id}")
+            .route(web::delete().to(delete_closure))
+    );
+}
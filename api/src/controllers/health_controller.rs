@@ -0,0 +1,100 @@
+use std::time::Duration;
+
+use actix_web::{web, HttpResponse, Responder};
+use diesel::RunQueryDsl;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use infrastructure::migrations::{applied_version, latest_version};
+
+use crate::AppState;
+
+#[derive(Serialize, ToSchema)]
+pub struct Check {
+    ok: bool,
+    detail: String,
+}
+
+impl Check {
+    fn pass(detail: String) -> Check {
+        Check { ok: true, detail }
+    }
+
+    fn fail(detail: String) -> Check {
+        Check { ok: false, detail }
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct Readiness {
+    ready: bool,
+    database: Check,
+    migrations: Check,
+    pool: Check,
+}
+
+// Liveness: the process is up and serving requests. Deliberately touches nothing else.
+#[utoipa::path(
+    get,
+    path = "/healthz",
+    tag = "operations",
+    responses((status = 200, description = "The process is serving requests")),
+    security(()),
+)]
+pub async fn healthz() -> impl Responder {
+    HttpResponse::Ok().json(serde_json::json!({ "status": "ok" }))
+}
+
+// Readiness: the database answers, its schema is at the version this build expects and the pool has room.
+#[utoipa::path(
+    get,
+    path = "/readyz",
+    tag = "operations",
+    responses((status = 200, description = "Ready to serve traffic", body = Readiness), (status = 503, description = "A dependency is not ready", body = Readiness)),
+    security(()),
+)]
+pub async fn readyz(repos: web::Data<AppState>) -> impl Responder {
+    let state = repos.pool.state();
+    let max_size = repos.pool.max_size();
+    let pool = if state.connections >= max_size && state.idle_connections == 0 {
+        Check::fail(format!("all {} connections in use", max_size))
+    } else {
+        Check::pass(format!("{} of {} connections in use", state.connections - state.idle_connections, max_size))
+    };
+
+    let (database, migrations) = match repos.pool.get_timeout(Duration::from_secs(1)) {
+        Ok(mut conn) => {
+            let database = match diesel::sql_query("SELECT 1").execute(&mut conn) {
+                Ok(_) => Check::pass("reachable".to_string()),
+                Err(e) => Check::fail(e.to_string()),
+            };
+            let migrations = match applied_version(&mut conn) {
+                Ok(version) if version == latest_version() => Check::pass(format!("at version {}", version)),
+                Ok(version) => Check::fail(format!("at version {}, expected {}", version, latest_version())),
+                Err(e) => Check::fail(e.to_string()),
+            };
+            (database, migrations)
+        }
+        Err(e) => (Check::fail(e.to_string()), Check::fail("database unavailable".to_string())),
+    };
+
+    let ready = database.ok && migrations.ok && pool.ok && !repos.background.is_stopping();
+    let report = Readiness { ready, database, migrations, pool };
+    if ready {
+        HttpResponse::Ok().json(report)
+    } else {
+        HttpResponse::ServiceUnavailable().json(report)
+    }
+}
+
+// Routes configuration
+pub fn health_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::resource("/healthz")
+            .route(web::get().to(healthz))
+    )
+    .service(
+        web::resource("/readyz")
+            .route(web::get().to(readyz))
+    );
+}
@@ -0,0 +1,183 @@
+use actix_web::{web, HttpResponse, Responder, ResponseError};
+use serde::{Deserialize, Serialize};
+use tracing::error;
+use utoipa::{IntoParams, ToSchema};
+
+use domain::models::job::{JobRun, TRIGGER_MANUAL};
+use domain::models::role::Permission;
+use infrastructure::config::JOB_SCHEDULE_OFF;
+
+use crate::auth::AuthenticatedUser;
+use crate::jobs::{find_job, job_schedule, run_job, JobOutcome, JOBS};
+use crate::AppState;
+
+#[derive(Serialize, ToSchema)]
+pub struct JobResponse {
+    name: String,
+    description: String,
+    // Cron expression with seconds (sec min hour day-of-month month day-of-week), or "off".
+    schedule: String,
+    enabled: bool,
+    next_run_at: Option<String>,
+    last_run: Option<JobRun>,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Path)]
+pub struct JobPath {
+    name: String,
+}
+
+#[derive(Deserialize, IntoParams)]
+pub struct JobRunsQuery {
+    // Newest runs first; defaults to 50.
+    limit: Option<i64>,
+}
+
+// Handlers
+#[utoipa::path(
+    get,
+    path = "/admin/jobs",
+    tag = "jobs",
+    responses((status = 200, description = "Every job with its schedule and latest run", body = Vec<JobResponse>), (status = 401, description = "Missing or invalid credentials"), (status = 403, description = "Missing permission")),
+)]
+pub async fn get_jobs(repos: web::Data<AppState>, user: AuthenticatedUser) -> impl Responder {
+    if let Err(e) = user.require(Permission::ManageSystem, None) {
+        return e.error_response();
+    }
+
+    let mut job_repo = repos.job_repo.lock().unwrap();
+    let latest = match job_repo.get_latest_runs() {
+        Ok(runs) => runs,
+        Err(e) => {
+            error!(error = ?e, "Failed to get jobs"); // Hata mesajını logla
+            return HttpResponse::InternalServerError().body(format!("Failed to get jobs: {}", e));
+        }
+    };
+
+    let enabled = repos.features.jobs;
+    let jobs: Vec<JobResponse> = JOBS
+        .iter()
+        .map(|job| {
+            let schedule = job_schedule(&repos.jobs, job.name);
+            JobResponse {
+                name: job.name.to_string(),
+                description: job.description.to_string(),
+                schedule: repos.jobs.schedule(job.name).unwrap_or(JOB_SCHEDULE_OFF).to_string(),
+                enabled: enabled && schedule.is_some(),
+                next_run_at: schedule
+                    .filter(|_| enabled)
+                    .and_then(|schedule| schedule.upcoming(chrono::Utc).next())
+                    .map(|at| at.naive_utc().to_string()),
+                last_run: latest.iter().find(|run| run.job == job.name).cloned(),
+            }
+        })
+        .collect();
+
+    HttpResponse::Ok().json(jobs)
+}
+
+// Runs the job now and answers once it has finished; the run is recorded like a scheduled one.
+#[utoipa::path(
+    post,
+    path = "/admin/jobs/{name}/run",
+    tag = "jobs",
+    params(JobPath),
+    responses((status = 200, description = "The finished run; check its status", body = JobRun), (status = 401, description = "Missing or invalid credentials"), (status = 403, description = "Missing permission"), (status = 404, description = "No such job"), (status = 409, description = "The job is already running")),
+)]
+pub async fn run_job_now(repos: web::Data<AppState>, user: AuthenticatedUser, path: web::Path<JobPath>) -> impl Responder {
+    if let Err(e) = user.require(Permission::ManageSystem, None) {
+        return e.error_response();
+    }
+
+    let Some(job) = find_job(&path.name) else {
+        return HttpResponse::NotFound().finish();
+    };
+
+    let state = repos.clone();
+    match web::block(move || run_job(&state, job, TRIGGER_MANUAL, None)).await {
+        Ok(Ok(JobOutcome::Finished(run))) => HttpResponse::Ok().json(run),
+        Ok(Ok(_)) => HttpResponse::Conflict().body("Job is already running"),
+        Ok(Err(e)) => {
+            error!(error = %e, "Failed to run job"); // Hata mesajını logla
+            HttpResponse::InternalServerError().body(format!("Failed to run job: {}", e))
+        }
+        Err(e) => {
+            error!(error = %e, "Failed to run job"); // Hata mesajını logla
+            HttpResponse::InternalServerError().body(format!("Failed to run job: {}", e))
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/admin/jobs/{name}/runs",
+    tag = "jobs",
+    params(JobPath, JobRunsQuery),
+    responses((status = 200, description = "Runs of the job, newest first", body = Vec<JobRun>), (status = 401, description = "Missing or invalid credentials"), (status = 403, description = "Missing permission"), (status = 404, description = "No such job")),
+)]
+pub async fn get_job_runs(
+    repos: web::Data<AppState>,
+    user: AuthenticatedUser,
+    path: web::Path<JobPath>,
+    query: web::Query<JobRunsQuery>,
+) -> impl Responder {
+    if let Err(e) = user.require(Permission::ManageSystem, None) {
+        return e.error_response();
+    }
+
+    if find_job(&path.name).is_none() {
+        return HttpResponse::NotFound().finish();
+    }
+
+    let mut job_repo = repos.job_repo.lock().unwrap();
+    match job_repo.get_runs(&path.name, query.limit.unwrap_or(50)) {
+        Ok(runs) => HttpResponse::Ok().json(runs),
+        Err(e) => {
+            error!(error = ?e, "Failed to get job runs"); // Hata mesajını logla
+            HttpResponse::InternalServerError().body(format!("Failed to get job runs: {}", e))
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/admin/job-runs/{id}",
+    tag = "jobs",
+    responses((status = 200, description = "The job run", body = JobRun), (status = 401, description = "Missing or invalid credentials"), (status = 403, description = "Missing permission"), (status = 404, description = "No such run")),
+)]
+pub async fn get_job_run(repos: web::Data<AppState>, user: AuthenticatedUser, id: web::Path<i32>) -> impl Responder {
+    if let Err(e) = user.require(Permission::ManageSystem, None) {
+        return e.error_response();
+    }
+
+    let mut job_repo = repos.job_repo.lock().unwrap();
+    match job_repo.get_run(&id) {
+        Ok(run) => HttpResponse::Ok().json(run),
+        Err(diesel::result::Error::NotFound) => HttpResponse::NotFound().finish(),
+        Err(e) => {
+            error!(error = ?e, "Failed to get job run"); // Hata mesajını logla
+            HttpResponse::InternalServerError().body(format!("Failed to get job run: {}", e))
+        }
+    }
+}
+
+// Routes configuration
+pub fn job_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::resource("/admin/jobs")
+            .route(web::get().to(get_jobs))
+    )
+    .service(
+        web::resource("/admin/jobs/{name}/run")
+            .route(web::post().to(run_job_now))
+    )
+    .service(
+        web::resource("/admin/jobs/{name}/runs")
+            .route(web::get().to(get_job_runs))
+    )
+    .service(
+        web::resource("/admin/job-runs/{id}")
+            .route(web::get().to(get_job_run))
+    );
+}
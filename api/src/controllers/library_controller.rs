@@ -1,98 +1,650 @@
-use actix_web::{web, HttpResponse, Responder};
-use serde::Deserialize;
+use actix_web::http::header::ETag;
+use actix_web::{web, HttpRequest, HttpResponse, Responder, ResponseError};
+use serde::{Deserialize, Serialize};
+use tracing::error;
+use utoipa::{IntoParams, ToSchema};
+use domain::models::library::{distance_km, Library, LibraryChanges, LibraryDetails, LibraryStock};
+use domain::models::role::Permission;
+use domain::traits::{LibraryRepositoryTrait, StaffRepositoryTrait};
+use crate::audit::{audited, snapshot, AuditEvent};
+use crate::auth::AuthenticatedUser;
+use crate::controllers::staff_controller::check_staff_member;
+use crate::etag::{check_if_match, entity_tag, not_modified, precondition_failed};
+use crate::patch::{apply_patch, changed};
+use crate::versioning::CURRENT_VERSION;
 use crate::AppState;
 
+// Search radius of `/libraries/nearby` when none is given, and the largest one accepted, in kilometres.
+const DEFAULT_RADIUS_KM: f64 = 10.0;
+const MAX_RADIUS_KM: f64 = 500.0;
+
 // Request yapılandırmaları
-#[derive(Deserialize)]
+#[derive(Deserialize, ToSchema)]
 pub struct CreateLibraryRequest {
     name: String,
     address: String,
     manager_id: i32,
+    #[serde(flatten)]
+    details: LibraryDetails,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Path)]
+pub struct LibraryBookPath {
+    library_id: i32,
+    book_id: i32,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct SetQuantityRequest {
+    quantity: i32,
 }
 
-#[derive(Deserialize)]
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct LoanRetentionRequest {
+    // Days after return before loans booked here are unlinked from members; 0 keeps them linked and null
+    // falls back to `loans.history_retention_days`.
+    days: Option<i32>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
 pub struct UpdateLibraryRequest {
     name: String,
     address: String,
     manager_id: i32,
+    // Contact and location fields left out are cleared.
+    #[serde(flatten)]
+    details: LibraryDetails,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct NearbyQuery {
+    // Decimal degrees.
+    lat: f64,
+    lon: f64,
+    // Kilometres; 10 when omitted, at most 500.
+    radius: Option<f64>,
+    // Only libraries with a copy of this book on the shelf.
+    book_id: Option<i32>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct NearbyLibrary {
+    library: Library,
+    distance_km: f64,
+    // Copies of `book_id` on the shelf; absent when no book was asked for.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    available: Option<i64>,
+}
+
+fn validate_position(latitude: f64, longitude: f64) -> Result<(), String> {
+    if !(-90.0..=90.0).contains(&latitude) {
+        return Err("latitude must be between -90 and 90".to_string());
+    }
+    if !(-180.0..=180.0).contains(&longitude) {
+        return Err("longitude must be between -180 and 180".to_string());
+    }
+    Ok(())
+}
+
+// Located libraries within `radius` km of `position`, nearest first. With the stock of a book, only those with a
+// copy of it on the shelf.
+fn nearby_libraries(libraries: Vec<Library>, stock: Option<&[LibraryStock]>, position: (f64, f64), radius: f64) -> Vec<NearbyLibrary> {
+    let mut nearby: Vec<NearbyLibrary> = libraries
+        .into_iter()
+        .filter_map(|library| {
+            let distance = distance_km(position, library.location()?);
+            let available = stock.map(|stock| stock.iter().find(|held| held.library_id == library.id).map_or(0, |held| held.available));
+            (distance <= radius && available.is_none_or(|available| available > 0)).then(|| NearbyLibrary {
+                library,
+                distance_km: (distance * 100.0).round() / 100.0,
+                available,
+            })
+        })
+        .collect();
+    nearby.sort_by(|a, b| a.distance_km.total_cmp(&b.distance_km));
+    nearby
+}
+
+fn validate_details(details: &LibraryDetails) -> Result<(), String> {
+    match (details.latitude, details.longitude) {
+        (Some(latitude), Some(longitude)) => validate_position(latitude, longitude)?,
+        (None, None) => {}
+        _ => return Err("latitude and longitude must be given together".to_string()),
+    }
+    if let Some(phone) = &details.phone {
+        if !phone.chars().any(|c| c.is_ascii_digit()) || !phone.chars().all(|c| c.is_ascii_digit() || " +-().".contains(c)) {
+            return Err("phone must be a phone number".to_string());
+        }
+    }
+    if details.email.as_ref().is_some_and(|email| !email.contains('@')) {
+        return Err("email must be a valid email address".to_string());
+    }
+    if details.website.as_ref().is_some_and(|website| !website.starts_with("https://") && !website.starts_with("http://")) {
+        return Err("website must be an http(s) URL".to_string());
+    }
+    Ok(())
+}
+
+impl UpdateLibraryRequest {
+    fn validate(&self) -> Result<(), String> {
+        if self.name.trim().is_empty() {
+            return Err("name must not be empty".to_string());
+        }
+        if self.address.trim().is_empty() {
+            return Err("address must not be empty".to_string());
+        }
+        if self.manager_id <= 0 {
+            return Err("manager_id must be a valid member id".to_string());
+        }
+        validate_details(&self.details)
+    }
 }
 
 // Handlers
+#[utoipa::path(
+    post,
+    path = "/libraries",
+    tag = "libraries",
+    request_body = CreateLibraryRequest,
+    responses((status = 201, description = "Created; Location points at the new library", headers(("ETag" = String, description = "Current version of the resource"))), (status = 422, description = "Invalid contact details or position, or a manager who may not join the staff"), (status = 401, description = "Missing or invalid credentials"), (status = 403, description = "Missing permission")),
+)]
 pub async fn create_library(
+    req: HttpRequest,
     repos: web::Data<AppState>,
+    user: AuthenticatedUser,
     form: web::Json<CreateLibraryRequest>,
 ) -> impl Responder {
-    let mut lib_repo = repos.lib_repo.lock().unwrap();
-    
-    match lib_repo.create_library(&form.name, &form.address, &form.manager_id) {
-        Ok(_) => HttpResponse::Created().finish(),
+    if let Err(e) = user.require(Permission::ManageSystem, None) {
+        return e.error_response();
+    }
+    if let Err(message) = validate_details(&form.details) {
+        return HttpResponse::UnprocessableEntity().body(message);
+    }
+    if let Some(response) = check_staff_member(&repos, &form.manager_id) {
+        return response;
+    }
+
+    let result = audited(&repos, &req, &user, |tx| {
+        let created = tx.libraries().create_library(&form.name, &form.address, &form.manager_id, &form.details)?;
+        tx.record(AuditEvent {
+            action: "create",
+            entity: "library",
+            entity_id: created.id.to_string(),
+            before: None,
+            after: snapshot(&created),
+        })?;
+        // The manager's assignment is created with the library.
+        let staff = tx.staff().get_staff(&created.id, None)?;
+        for assignment in staff {
+            tx.record(AuditEvent {
+                action: "create",
+                entity: "staff_assignment",
+                entity_id: assignment.id.to_string(),
+                before: None,
+                after: snapshot(&assignment),
+            })?;
+        }
+        Ok(created)
+    });
+
+    match result {
+        Ok(created) => HttpResponse::Created()
+            .insert_header(("Location", format!("{}/libraries/{}", CURRENT_VERSION, created.id)))
+            .insert_header(ETag(entity_tag(created.version)))
+            .finish(),
         Err(e) => {
-            println!("Failed to create library: {:?}", e); // Hata mesajını logla
+            error!(error = ?e, "Failed to create library"); // Hata mesajını logla
             HttpResponse::InternalServerError().body(format!("Failed to create library: {}", e))
         }
     }
 }
 
+#[utoipa::path(
+    put,
+    path = "/libraries/{id}",
+    tag = "libraries",
+    params(("If-Match" = String, Header, description = "ETag from the last GET, or `*`")),
+    request_body = UpdateLibraryRequest,
+    responses((status = 200, description = "Updated", headers(("ETag" = String, description = "Current version of the resource"))), (status = 404, description = "No such library"), (status = 422, description = "Invalid library"), (status = 412, description = "If-Match does not match the current version"), (status = 428, description = "If-Match header is required"), (status = 401, description = "Missing or invalid credentials"), (status = 403, description = "Missing permission")),
+)]
 pub async fn update_library(
+    req: HttpRequest,
     repos: web::Data<AppState>,
+    user: AuthenticatedUser,
     id: web::Path<i32>,
     form: web::Json<UpdateLibraryRequest>,
 ) -> impl Responder {
+    if let Err(e) = user.require(Permission::ManageLibrary, Some(*id)) {
+        return e.error_response();
+    }
+
+    if let Err(message) = form.validate() {
+        return HttpResponse::UnprocessableEntity().body(message);
+    }
+
     let mut lib_repo = repos.lib_repo.lock().unwrap();
-    
-    match lib_repo.update_library(&id, &form.name, &form.address, &form.manager_id) {
-        Ok(_) => HttpResponse::Ok().finish(),
+
+    let current = match lib_repo.get_library_by_id(&id) {
+        Ok(library) => library,
+        Err(diesel::result::Error::NotFound) => return HttpResponse::NotFound().finish(),
+        Err(e) => {
+            error!(error = ?e, "Failed to update library"); // Hata mesajını logla
+            return HttpResponse::InternalServerError().body(format!("Failed to update library: {}", e));
+        }
+    };
+    if let Some(response) = check_if_match(&req, current.version) {
+        return response;
+    }
+    // Reassigning the manager changes who holds manager rights, so only admins may do it. The library is
+    // handed over to the new manager, which the staff history records.
+    if form.manager_id != current.manager_id {
+        if let Err(e) = user.require(Permission::ManageSystem, None) {
+            return e.error_response();
+        }
+        if let Some(response) = check_staff_member(&repos, &form.manager_id) {
+            return response;
+        }
+    }
+
+    let result = audited(&repos, &req, &user, |tx| {
+        let updated = tx.libraries().update_library(&id, &form.name, &form.address, &form.manager_id, &form.details, &current.version)?;
+        if updated == 1 {
+            let after = tx.libraries().get_library_by_id(&id)?;
+            tx.record(AuditEvent {
+                action: "update",
+                entity: "library",
+                entity_id: id.to_string(),
+                before: snapshot(&current),
+                after: snapshot(&after),
+            })?;
+        }
+        Ok(updated)
+    });
+    match result {
+        Ok(0) => precondition_failed(),
+        Ok(_) => HttpResponse::Ok().insert_header(ETag(entity_tag(current.version + 1))).finish(),
         Err(e) => {
-            println!("Failed to update library: {:?}", e); // Hata mesajını logla
+            error!(error = ?e, "Failed to update library"); // Hata mesajını logla
             HttpResponse::InternalServerError().body(format!("Failed to update library: {}", e))
         }
     }
 }
 
+#[utoipa::path(
+    patch,
+    path = "/libraries/{id}",
+    tag = "libraries",
+    params(("If-Match" = String, Header, description = "ETag from the last GET, or `*`")),
+    request_body(content((UpdateLibraryRequest = "application/merge-patch+json"), (UpdateLibraryRequest = "application/json-patch+json"))),
+    responses((status = 200, description = "Patched", headers(("ETag" = String, description = "Current version of the resource"))), (status = 400, description = "Malformed patch"), (status = 404, description = "No such library"), (status = 409, description = "JSON Patch test or path failed"), (status = 415, description = "Unsupported patch media type"), (status = 422, description = "Patched library is invalid"), (status = 412, description = "If-Match does not match the current version"), (status = 428, description = "If-Match header is required"), (status = 401, description = "Missing or invalid credentials"), (status = 403, description = "Missing permission")),
+)]
+pub async fn patch_library(
+    req: HttpRequest,
+    repos: web::Data<AppState>,
+    user: AuthenticatedUser,
+    id: web::Path<i32>,
+    body: web::Bytes,
+) -> impl Responder {
+    if let Err(e) = user.require(Permission::ManageLibrary, Some(*id)) {
+        return e.error_response();
+    }
+
+    let mut lib_repo = repos.lib_repo.lock().unwrap();
+
+    let current = match lib_repo.get_library_by_id(&id) {
+        Ok(library) => library,
+        Err(diesel::result::Error::NotFound) => return HttpResponse::NotFound().finish(),
+        Err(e) => {
+            error!(error = ?e, "Failed to patch library"); // Hata mesajını logla
+            return HttpResponse::InternalServerError().body(format!("Failed to patch library: {}", e));
+        }
+    };
+    if let Some(response) = check_if_match(&req, current.version) {
+        return response;
+    }
+
+    let existing = UpdateLibraryRequest {
+        name: current.name.clone(),
+        address: current.address.clone(),
+        manager_id: current.manager_id,
+        details: current.details(),
+    };
+    let patched = match apply_patch(&req, &body, &existing) {
+        Ok(patched) => patched,
+        Err(e) => return e.error_response(),
+    };
+    if let Err(message) = patched.validate() {
+        return HttpResponse::UnprocessableEntity().body(message);
+    }
+
+    let changes = LibraryChanges {
+        name: changed(existing.name.as_str(), patched.name.as_str()),
+        address: changed(existing.address.as_str(), patched.address.as_str()),
+        manager_id: changed(&existing.manager_id, &patched.manager_id),
+        details: changed(&existing.details, &patched.details).cloned(),
+        ..LibraryChanges::default()
+    };
+    if let Some(manager_id) = changes.manager_id {
+        if let Err(e) = user.require(Permission::ManageSystem, None) {
+            return e.error_response();
+        }
+        if let Some(response) = check_staff_member(&repos, manager_id) {
+            return response;
+        }
+    }
+    if changes.is_empty() {
+        return HttpResponse::Ok().insert_header(ETag(entity_tag(current.version))).finish();
+    }
+
+    let result = audited(&repos, &req, &user, |tx| {
+        let patched = tx.libraries().patch_library(&id, &changes, &current.version)?;
+        if patched == 1 {
+            let after = tx.libraries().get_library_by_id(&id)?;
+            tx.record(AuditEvent {
+                action: "update",
+                entity: "library",
+                entity_id: id.to_string(),
+                before: snapshot(&current),
+                after: snapshot(&after),
+            })?;
+        }
+        Ok(patched)
+    });
+    match result {
+        Ok(0) => precondition_failed(),
+        Ok(_) => HttpResponse::Ok().insert_header(ETag(entity_tag(current.version + 1))).finish(),
+        Err(e) => {
+            error!(error = ?e, "Failed to patch library"); // Hata mesajını logla
+            HttpResponse::InternalServerError().body(format!("Failed to patch library: {}", e))
+        }
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = "/libraries/{id}",
+    tag = "libraries",
+    params(("If-Match" = String, Header, description = "ETag from the last GET, or `*`")),
+    responses((status = 204, description = "Deleted"), (status = 404, description = "No such library"), (status = 412, description = "If-Match does not match the current version"), (status = 428, description = "If-Match header is required"), (status = 401, description = "Missing or invalid credentials"), (status = 403, description = "Missing permission")),
+)]
 pub async fn delete_library(
+    req: HttpRequest,
     repos: web::Data<AppState>,
+    user: AuthenticatedUser,
     id: web::Path<i32>,
 ) -> impl Responder {
+    if let Err(e) = user.require(Permission::ManageSystem, None) {
+        return e.error_response();
+    }
+
     let mut lib_repo = repos.lib_repo.lock().unwrap();
-    
-    match lib_repo.delete_library(&id) {
+
+    let current = match lib_repo.get_library_by_id(&id) {
+        Ok(library) => library,
+        Err(diesel::result::Error::NotFound) => return HttpResponse::NotFound().finish(),
+        Err(e) => {
+            error!(error = ?e, "Failed to delete library"); // Hata mesajını logla
+            return HttpResponse::InternalServerError().body(format!("Failed to delete library: {}", e));
+        }
+    };
+    if let Some(response) = check_if_match(&req, current.version) {
+        return response;
+    }
+
+    let result = audited(&repos, &req, &user, |tx| {
+        let deleted = tx.libraries().delete_library(&id, &current.version)?;
+        if deleted == 1 {
+            tx.record(AuditEvent {
+                action: "delete",
+                entity: "library",
+                entity_id: id.to_string(),
+                before: snapshot(&current),
+                after: None,
+            })?;
+        }
+        Ok(deleted)
+    });
+    match result {
+        Ok(0) => precondition_failed(),
         Ok(_) => HttpResponse::NoContent().finish(),
         Err(e) => {
-            println!("Failed to delete library: {:?}", e); // Hata mesajını logla
+            error!(error = ?e, "Failed to delete library"); // Hata mesajını logla
             HttpResponse::InternalServerError().body(format!("Failed to delete library: {}", e))
         }
     }
 }
 
+#[utoipa::path(
+    get,
+    path = "/libraries/{id}",
+    tag = "libraries",
+    params(("If-None-Match" = Option<String>, Header, description = "Answer 304 when the ETag still matches")),
+    responses((status = 200, description = "The library", body = Library, headers(("ETag" = String, description = "Current version of the resource"))), (status = 304, description = "Not modified"), (status = 404, description = "No such library"), (status = 401, description = "Missing or invalid credentials"), (status = 403, description = "Missing permission")),
+)]
 pub async fn get_library(
+    req: HttpRequest,
     repos: web::Data<AppState>,
+    _user: AuthenticatedUser,
     id: web::Path<i32>,
 ) -> impl Responder {
     let mut lib_repo = repos.lib_repo.lock().unwrap();
-    
     match lib_repo.get_library_by_id(&id) {
-        Ok(library) => HttpResponse::Ok().json(library),
+        Ok(library) => not_modified(&req, library.version)
+            .unwrap_or_else(|| HttpResponse::Ok().insert_header(ETag(entity_tag(library.version))).json(library)),
+        Err(diesel::result::Error::NotFound) => HttpResponse::NotFound().finish(),
         Err(e) => {
-            println!("Failed to get library: {:?}", e); // Hata mesajını logla
+            error!(error = ?e, "Failed to get library"); // Hata mesajını logla
             HttpResponse::InternalServerError().body(format!("Failed to get library: {}", e))
         }
     }
 }
 
+#[utoipa::path(
+    get,
+    path = "/libraries",
+    tag = "libraries",
+    responses((status = 200, description = "All libraries", body = Vec<Library>), (status = 401, description = "Missing or invalid credentials"), (status = 403, description = "Missing permission")),
+)]
 pub async fn get_libraries(
     repos: web::Data<AppState>,
+    _user: AuthenticatedUser,
 ) -> impl Responder {
     let mut lib_repo = repos.lib_repo.lock().unwrap();
     
     match lib_repo.get_libraries() {
         Ok(libraries) => HttpResponse::Ok().json(libraries),
         Err(e) => {
-            println!("Failed to get libraries: {:?}", e); // Hata mesajını logla
+            error!(error = ?e, "Failed to get libraries"); // Hata mesajını logla
             HttpResponse::InternalServerError().body(format!("Failed to get libraries: {}", e))
         }
     }
 }
 
+#[utoipa::path(
+    get,
+    path = "/libraries/nearby",
+    tag = "libraries",
+    params(NearbyQuery),
+    responses((status = 200, description = "Libraries within the radius, nearest first; with `book_id`, only those with a copy on the shelf", body = Vec<NearbyLibrary>), (status = 422, description = "Invalid position or radius"), (status = 401, description = "Missing or invalid credentials"), (status = 403, description = "Missing permission")),
+)]
+pub async fn get_nearby_libraries(
+    repos: web::Data<AppState>,
+    _user: AuthenticatedUser,
+    query: web::Query<NearbyQuery>,
+) -> impl Responder {
+    if let Err(message) = validate_position(query.lat, query.lon) {
+        return HttpResponse::UnprocessableEntity().body(message);
+    }
+    let radius = query.radius.unwrap_or(DEFAULT_RADIUS_KM);
+    if !(radius > 0.0 && radius <= MAX_RADIUS_KM) {
+        return HttpResponse::UnprocessableEntity().body(format!("radius must be above 0 and at most {} km", MAX_RADIUS_KM));
+    }
+
+    let mut lib_repo = repos.lib_repo.lock().unwrap();
+    let found = lib_repo.get_located_libraries().and_then(|libraries| {
+        let stock = match query.book_id {
+            Some(book_id) => Some(lib_repo.get_book_stock(&book_id)?),
+            None => None,
+        };
+        Ok((libraries, stock))
+    });
+    let (libraries, stock) = match found {
+        Ok(found) => found,
+        Err(e) => {
+            error!(error = ?e, "Failed to find nearby libraries"); // Hata mesajını logla
+            return HttpResponse::InternalServerError().body(format!("Failed to find nearby libraries: {}", e));
+        }
+    };
+
+    HttpResponse::Ok().json(nearby_libraries(libraries, stock.as_deref(), (query.lat, query.lon), radius))
+}
+
+#[utoipa::path(
+    post,
+    path = "/libraries/{library_id}/books/{book_id}",
+    tag = "libraries",
+    params(LibraryBookPath),
+    responses((status = 201, description = "Book added to the library with quantity 1"), (status = 409, description = "Book is already held by this library"), (status = 401, description = "Missing or invalid credentials"), (status = 403, description = "Missing permission")),
+)]
+pub async fn add_library_book(
+    req: HttpRequest,
+    repos: web::Data<AppState>,
+    user: AuthenticatedUser,
+    path: web::Path<LibraryBookPath>,
+) -> impl Responder {
+    if let Err(e) = user.require(Permission::ManageHoldings, Some(path.library_id)) {
+        return e.error_response();
+    }
+
+    let result = audited(&repos, &req, &user, |tx| {
+        tx.libraries().add_book(&path.library_id, &path.book_id)?;
+        let after = tx.libraries().get_library_book(&path.library_id, &path.book_id)?;
+        tx.record(AuditEvent {
+            action: "create",
+            entity: "library_book",
+            entity_id: format!("{}:{}", path.library_id, path.book_id),
+            before: None,
+            after: snapshot(&after),
+        })
+    });
+    match result {
+        Ok(()) => HttpResponse::Created().finish(),
+        Err(diesel::result::Error::DatabaseError(diesel::result::DatabaseErrorKind::UniqueViolation, _)) => {
+            HttpResponse::Conflict().body("Book is already held by this library")
+        }
+        Err(e) => {
+            error!(error = ?e, "Failed to add book to library"); // Hata mesajını logla
+            HttpResponse::InternalServerError().body(format!("Failed to add book to library: {}", e))
+        }
+    }
+}
+
+#[utoipa::path(
+    put,
+    path = "/libraries/{library_id}/books/{book_id}",
+    tag = "libraries",
+    params(LibraryBookPath),
+    request_body = SetQuantityRequest,
+    responses((status = 200, description = "Quantity updated"), (status = 404, description = "Book is not held by this library"), (status = 422, description = "Negative quantity"), (status = 401, description = "Missing or invalid credentials"), (status = 403, description = "Missing permission")),
+)]
+pub async fn set_library_book_quantity(
+    req: HttpRequest,
+    repos: web::Data<AppState>,
+    user: AuthenticatedUser,
+    path: web::Path<LibraryBookPath>,
+    form: web::Json<SetQuantityRequest>,
+) -> impl Responder {
+    if let Err(e) = user.require(Permission::ManageHoldings, Some(path.library_id)) {
+        return e.error_response();
+    }
+    if form.quantity < 0 {
+        return HttpResponse::UnprocessableEntity().body("quantity must not be negative");
+    }
+
+    let result = audited(&repos, &req, &user, |tx| {
+        let before = tx.libraries().get_library_book(&path.library_id, &path.book_id)?;
+        tx.libraries().add_book_quantity(&path.library_id, &path.book_id, &form.quantity)?;
+        let after = tx.libraries().get_library_book(&path.library_id, &path.book_id)?;
+        tx.record(AuditEvent {
+            action: "update",
+            entity: "library_book",
+            entity_id: format!("{}:{}", path.library_id, path.book_id),
+            before: snapshot(&before),
+            after: snapshot(&after),
+        })
+    });
+    match result {
+        Ok(()) => HttpResponse::Ok().finish(),
+        Err(diesel::result::Error::NotFound) => HttpResponse::NotFound().finish(),
+        Err(e) => {
+            error!(error = ?e, "Failed to update book quantity"); // Hata mesajını logla
+            HttpResponse::InternalServerError().body(format!("Failed to update book quantity: {}", e))
+        }
+    }
+}
+
+#[utoipa::path(
+    put,
+    path = "/libraries/{id}/loan-retention",
+    tag = "libraries",
+    params(("id" = i32, Path, description = "Library id")),
+    request_body = LoanRetentionRequest,
+    responses((status = 200, description = "Retention period saved; the updated library", body = Library, headers(("ETag" = String, description = "Current version of the resource"))), (status = 404, description = "No such library"), (status = 409, description = "The library changed meanwhile; retry"), (status = 422, description = "Negative period"), (status = 401, description = "Missing or invalid credentials"), (status = 403, description = "Missing permission")),
+)]
+pub async fn set_loan_retention(
+    req: HttpRequest,
+    repos: web::Data<AppState>,
+    user: AuthenticatedUser,
+    id: web::Path<i32>,
+    form: web::Json<LoanRetentionRequest>,
+) -> impl Responder {
+    if let Err(e) = user.require(Permission::ManageLibrary, Some(*id)) {
+        return e.error_response();
+    }
+    if form.days.is_some_and(|days| days < 0) {
+        return HttpResponse::UnprocessableEntity().body("days must not be negative");
+    }
+
+    let mut lib_repo = repos.lib_repo.lock().unwrap();
+    let current = match lib_repo.get_library_by_id(&id) {
+        Ok(library) => library,
+        Err(diesel::result::Error::NotFound) => return HttpResponse::NotFound().finish(),
+        Err(e) => {
+            error!(error = ?e, "Failed to set loan retention"); // Hata mesajını logla
+            return HttpResponse::InternalServerError().body(format!("Failed to set loan retention: {}", e));
+        }
+    };
+
+    let changes = LibraryChanges {
+        loan_retention_days: Some(form.days),
+        ..LibraryChanges::default()
+    };
+    let updated = audited(&repos, &req, &user, |tx| {
+        let updated = tx.libraries().patch_library(&id, &changes, &current.version)?;
+        let library = tx.libraries().get_library_by_id(&id)?;
+        if updated == 1 {
+            tx.record(AuditEvent {
+                action: "update",
+                entity: "library",
+                entity_id: library.id.to_string(),
+                before: snapshot(&current),
+                after: snapshot(&library),
+            })?;
+        }
+        Ok((updated, library))
+    });
+    match updated {
+        Ok((0, _)) => HttpResponse::Conflict().body("The library changed meanwhile; retry"),
+        Ok((_, library)) => HttpResponse::Ok().insert_header(ETag(entity_tag(library.version))).json(library),
+        Err(e) => {
+            error!(error = ?e, "Failed to set loan retention"); // Hata mesajını logla
+            HttpResponse::InternalServerError().body(format!("Failed to set loan retention: {}", e))
+        }
+    }
+}
+
 // Routes configuration
 pub fn library_routes(cfg: &mut web::ServiceConfig) {
     cfg.service(
@@ -100,10 +652,87 @@ pub fn library_routes(cfg: &mut web::ServiceConfig) {
             .route(web::post().to(create_library))
             .route(web::get().to(get_libraries))
     )
+    .service(
+        web::resource("/libraries/nearby")
+            .route(web::get().to(get_nearby_libraries))
+    )
     .service(
         web::resource("/libraries/{id}")
             .route(web::get().to(get_library))
             .route(web::put().to(update_library))
+            .route(web::patch().to(patch_library))
             .route(web::delete().to(delete_library))
+    )
+    .service(
+        web::resource("/libraries/{id}/loan-retention")
+            .route(web::put().to(set_loan_retention))
+    )
+    .service(
+        web::resource("/libraries/{library_id}/books/{book_id}")
+            .route(web::post().to(add_library_book))
+            .route(web::put().to(set_library_book_quantity))
     );
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn library(id: i32, location: Option<(f64, f64)>) -> Library {
+        Library {
+            id,
+            name: format!("Library {}", id),
+            address: "Main St 1".to_string(),
+            created_at: "2026-01-01".to_string(),
+            updated_at: "2026-01-01".to_string(),
+            manager_id: 1,
+            version: 1,
+            loan_retention_days: None,
+            street: None,
+            city: None,
+            postal_code: None,
+            country: None,
+            phone: None,
+            email: None,
+            website: None,
+            latitude: location.map(|(latitude, _)| latitude),
+            longitude: location.map(|(_, longitude)| longitude),
+        }
+    }
+
+    fn held(library_id: i32, available: i64) -> LibraryStock {
+        LibraryStock { library_id, available }
+    }
+
+    const PARIS: (f64, f64) = (48.8566, 2.3522);
+    const LONDON: (f64, f64) = (51.5074, -0.1278);
+
+    #[test]
+    fn distance_is_measured_along_the_great_circle() {
+        assert_eq!(distance_km(PARIS, PARIS), 0.0);
+        assert!((distance_km(PARIS, LONDON) - 343.5).abs() < 1.0);
+        assert_eq!(distance_km(PARIS, LONDON), distance_km(LONDON, PARIS));
+        // Half the circumference between antipodes.
+        assert!((distance_km((0.0, 0.0), (0.0, 180.0)) - 20015.1).abs() < 1.0);
+    }
+
+    #[test]
+    fn nearby_keeps_located_libraries_within_the_radius_nearest_first() {
+        // Library 1 is about 4 km from the centre of Paris, library 3 about 1 km; library 4 has no position.
+        let libraries = vec![library(1, Some((48.8924, 2.3522))), library(2, Some(LONDON)), library(3, Some((48.8656, 2.3522))), library(4, None)];
+
+        let nearby = nearby_libraries(libraries, None, PARIS, 10.0);
+        let found: Vec<(i32, f64, Option<i64>)> = nearby.iter().map(|near| (near.library.id, near.distance_km, near.available)).collect();
+        assert_eq!(found, vec![(3, 1.0, None), (1, 3.98, None)]);
+    }
+
+    #[test]
+    fn nearby_with_a_book_skips_libraries_without_a_copy_on_the_shelf() {
+        let libraries = vec![library(1, Some(PARIS)), library(2, Some(PARIS)), library(3, Some(PARIS))];
+        let stock = [held(1, 2), held(2, 0)];
+
+        let nearby = nearby_libraries(libraries, Some(&stock), PARIS, 10.0);
+        let found: Vec<(i32, Option<i64>)> = nearby.iter().map(|near| (near.library.id, near.available)).collect();
+        assert_eq!(found, vec![(1, Some(2))]);
+    }
+}
@@ -0,0 +1,664 @@
+use std::collections::HashMap;
+
+use actix_web::{web, HttpRequest, HttpResponse, Responder, ResponseError};
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tracing::error;
+use utoipa::{IntoParams, ToSchema};
+
+use domain::models::loan::{Hold, Loan, NewHold};
+use domain::models::member::{Member, MemberChanges};
+use domain::models::role::Permission;
+use domain::traits::MemberRepositoryTrait;
+
+use crate::audit::{audited, snapshot, AuditEvent};
+use crate::auth::{AuthError, AuthenticatedUser};
+use crate::calendar::{due_on_open_day, loan_calendars, LibraryCalendar};
+use crate::controllers::member_controller::{export_response, validate_language};
+use crate::AppState;
+
+// Request yapılandırmaları
+#[derive(Deserialize, ToSchema)]
+pub struct PlaceHoldRequest {
+    book_id: i32,
+    // Pick the book up at this library; any library when omitted.
+    #[serde(default)]
+    library_id: Option<i32>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct PreferencesRequest {
+    // Language tag for notices, e.g. `en`; null falls back to the server default.
+    language: Option<String>,
+    // Whether to get a reminder before a loan is due.
+    notify_reminders: bool,
+    // Keep returned loans in the reading history past the libraries' retention periods; unchanged when omitted.
+    #[serde(default)]
+    keep_loan_history: Option<bool>,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Path)]
+pub struct LoanPath {
+    id: i32,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Path)]
+pub struct HoldPath {
+    id: i32,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct Fine {
+    loan_id: i32,
+    book_id: i32,
+    title: String,
+    due_at: String,
+    returned_at: Option<String>,
+    // Started days past the due date, up to the return or now, on which the library was open.
+    days_overdue: i64,
+    amount_cents: i64,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct FinesResponse {
+    fine_per_day_cents: i64,
+    total_cents: i64,
+    fines: Vec<Fine>,
+}
+
+const TIMESTAMP_FORMAT: &str = "%Y-%m-%d %H:%M:%S%.f";
+
+// The member behind the caller's account; accounts without a member are refused.
+fn caller_member(user: &AuthenticatedUser) -> Result<i32, AuthError> {
+    user.require(Permission::ViewOwnLoans, None)?;
+    user.member_id
+        .ok_or_else(|| AuthError::Forbidden("The account is not linked to a member".to_string()))
+}
+
+fn days_overdue(loan: &Loan, now: &NaiveDateTime) -> i64 {
+    let due = match loan.due_at.as_deref().and_then(|due| NaiveDateTime::parse_from_str(due, TIMESTAMP_FORMAT).ok()) {
+        Some(due) => due,
+        None => return 0,
+    };
+    let end = loan
+        .returned_at
+        .as_deref()
+        .and_then(|returned| NaiveDateTime::parse_from_str(returned, TIMESTAMP_FORMAT).ok())
+        .unwrap_or(*now);
+    let late = (end - due).num_seconds();
+    if late <= 0 {
+        return 0;
+    }
+    (late + 86_399) / 86_400
+}
+
+// Days overdue that are charged: those the library the loan is booked to was open.
+fn chargeable_days(loan: &Loan, now: &NaiveDateTime, calendars: &HashMap<i32, LibraryCalendar>) -> i64 {
+    let days = days_overdue(loan, now);
+    let calendar = loan.library_id.and_then(|library_id| calendars.get(&library_id));
+    let due = loan.due_at.as_deref().and_then(|due| NaiveDateTime::parse_from_str(due, TIMESTAMP_FORMAT).ok());
+    match (calendar, due) {
+        (Some(calendar), Some(due)) if days > 0 => days - calendar.closed_days(due.date() + chrono::Duration::days(1), days),
+        _ => days,
+    }
+}
+
+// Fines for the overdue ones among `loans`, counted up to `now` for loans still out. Days the library was
+// closed are not charged.
+pub(crate) fn fines_for(loans: &[Loan], per_day: i64, now: &NaiveDateTime, calendars: &HashMap<i32, LibraryCalendar>) -> FinesResponse {
+    let fines: Vec<Fine> = loans
+        .iter()
+        .filter_map(|loan| {
+            let days = chargeable_days(loan, now, calendars);
+            (days > 0).then(|| Fine {
+                loan_id: loan.id,
+                book_id: loan.book_id,
+                title: loan.title.clone(),
+                due_at: loan.due_at.clone().unwrap_or_default(),
+                returned_at: loan.returned_at.clone(),
+                days_overdue: days,
+                amount_cents: days * per_day,
+            })
+        })
+        .collect();
+
+    FinesResponse {
+        fine_per_day_cents: per_day,
+        total_cents: fines.iter().map(|fine| fine.amount_cents).sum(),
+        fines,
+    }
+}
+
+fn list_loans(repos: &AppState, member_id: i32, open: bool) -> HttpResponse {
+    let mut member_repo = repos.member_repo.lock().unwrap();
+    match member_repo.get_loans(&member_id, open) {
+        Ok(loans) => HttpResponse::Ok().json(loans),
+        Err(e) => {
+            error!(error = ?e, "Failed to get loans"); // Hata mesajını logla
+            HttpResponse::InternalServerError().body(format!("Failed to get loans: {}", e))
+        }
+    }
+}
+
+// Handlers
+#[utoipa::path(
+    get,
+    path = "/me",
+    tag = "me",
+    responses((status = 200, description = "The caller's member profile", body = Member), (status = 401, description = "Missing or invalid credentials"), (status = 403, description = "Missing permission")),
+)]
+pub async fn get_profile(repos: web::Data<AppState>, user: AuthenticatedUser) -> impl Responder {
+    let member_id = match caller_member(&user) {
+        Ok(member_id) => member_id,
+        Err(e) => return e.error_response(),
+    };
+
+    let mut member_repo = repos.member_repo.lock().unwrap();
+    match member_repo.get_member_by_id(&member_id) {
+        Ok(member) => HttpResponse::Ok().json(member),
+        Err(diesel::result::Error::NotFound) => HttpResponse::NotFound().finish(),
+        Err(e) => {
+            error!(error = ?e, "Failed to get member"); // Hata mesajını logla
+            HttpResponse::InternalServerError().body(format!("Failed to get member: {}", e))
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/me/loans",
+    tag = "me",
+    responses((status = 200, description = "The caller's open loans with their due dates, newest first", body = Vec<Loan>), (status = 401, description = "Missing or invalid credentials"), (status = 403, description = "Missing permission")),
+)]
+pub async fn get_loans(repos: web::Data<AppState>, user: AuthenticatedUser) -> impl Responder {
+    match caller_member(&user) {
+        Ok(member_id) => list_loans(&repos, member_id, true),
+        Err(e) => e.error_response(),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/me/loans/history",
+    tag = "me",
+    responses((status = 200, description = "The caller's returned loans, newest first", body = Vec<Loan>), (status = 401, description = "Missing or invalid credentials"), (status = 403, description = "Missing permission")),
+)]
+pub async fn get_loan_history(repos: web::Data<AppState>, user: AuthenticatedUser) -> impl Responder {
+    match caller_member(&user) {
+        Ok(member_id) => list_loans(&repos, member_id, false),
+        Err(e) => e.error_response(),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/me/loans/{id}/renew",
+    tag = "me",
+    params(LoanPath),
+    responses((status = 200, description = "Renewed; the loan with its new due date", body = Loan), (status = 404, description = "No such open loan"), (status = 409, description = "Overdue, renewed too often or held by another member"), (status = 401, description = "Missing or invalid credentials"), (status = 403, description = "Missing permission")),
+)]
+pub async fn renew_loan(req: HttpRequest, repos: web::Data<AppState>, user: AuthenticatedUser, path: web::Path<LoanPath>) -> impl Responder {
+    let member_id = match caller_member(&user) {
+        Ok(member_id) => member_id,
+        Err(e) => return e.error_response(),
+    };
+
+    let now = chrono::offset::Utc::now().naive_utc();
+    let mut member_repo = repos.member_repo.lock().unwrap();
+    let loan = match member_repo.get_loans(&member_id, true) {
+        Ok(loans) => match loans.into_iter().find(|loan| loan.id == path.id) {
+            Some(loan) => loan,
+            None => return HttpResponse::NotFound().finish(),
+        },
+        Err(e) => {
+            error!(error = ?e, "Failed to get loans"); // Hata mesajını logla
+            return HttpResponse::InternalServerError().body(format!("Failed to get loans: {}", e));
+        }
+    };
+    if days_overdue(&loan, &now) > 0 {
+        return HttpResponse::Conflict().body("Overdue loans cannot be renewed");
+    }
+    if loan.renewals >= repos.loans.max_renewals {
+        return HttpResponse::Conflict().body(format!("The loan has been renewed {} times already", loan.renewals));
+    }
+    match member_repo.count_waiting_holds(&loan.book_id, &member_id) {
+        Ok(0) => {}
+        Ok(_) => return HttpResponse::Conflict().body("Another member is waiting for this book"),
+        Err(e) => {
+            error!(error = ?e, "Failed to renew loan"); // Hata mesajını logla
+            return HttpResponse::InternalServerError().body(format!("Failed to renew loan: {}", e));
+        }
+    }
+
+    let due_at = match due_on_open_day(&repos, loan.library_id, now + chrono::Duration::days(repos.loans.loan_period_days)) {
+        Ok(due) => due.to_string(),
+        Err(e) => {
+            error!(error = ?e, "Failed to renew loan"); // Hata mesajını logla
+            return HttpResponse::InternalServerError().body(format!("Failed to renew loan: {}", e));
+        }
+    };
+    drop(member_repo);
+    let renewed = audited(&repos, &req, &user, |tx| {
+        if tx.members().renew_loan(&member_id, &loan.id, &due_at, &repos.loans.max_renewals)? == 0 {
+            return Ok(None);
+        }
+        let loans = tx.members().get_loans(&member_id, true)?;
+        tx.record(AuditEvent {
+            action: "renew",
+            entity: "loan",
+            entity_id: format!("{}:{}", member_id, loan.book_id),
+            before: Some(json!({ "due_at": loan.due_at, "renewals": loan.renewals })),
+            after: Some(json!({ "due_at": due_at, "renewals": loan.renewals + 1 })),
+        })?;
+        Ok(Some(loans.into_iter().find(|renewed| renewed.id == loan.id)))
+    });
+    match renewed {
+        Ok(None) => HttpResponse::Conflict().body("The loan can no longer be renewed"),
+        Ok(Some(after)) => HttpResponse::Ok().json(after),
+        Err(e) => {
+            error!(error = ?e, "Failed to renew loan"); // Hata mesajını logla
+            HttpResponse::InternalServerError().body(format!("Failed to renew loan: {}", e))
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/me/holds",
+    tag = "me",
+    responses((status = 200, description = "The caller's active holds, oldest first", body = Vec<Hold>), (status = 401, description = "Missing or invalid credentials"), (status = 403, description = "Missing permission")),
+)]
+pub async fn get_holds(repos: web::Data<AppState>, user: AuthenticatedUser) -> impl Responder {
+    let member_id = match caller_member(&user) {
+        Ok(member_id) => member_id,
+        Err(e) => return e.error_response(),
+    };
+
+    let mut member_repo = repos.member_repo.lock().unwrap();
+    match member_repo.get_holds(&member_id) {
+        Ok(holds) => HttpResponse::Ok().json(holds),
+        Err(e) => {
+            error!(error = ?e, "Failed to get holds"); // Hata mesajını logla
+            HttpResponse::InternalServerError().body(format!("Failed to get holds: {}", e))
+        }
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/me/holds",
+    tag = "me",
+    request_body = PlaceHoldRequest,
+    responses((status = 201, description = "Hold placed", body = Hold), (status = 409, description = "The caller already holds or borrows the book, or may not borrow"), (status = 422, description = "No such book"), (status = 401, description = "Missing or invalid credentials"), (status = 403, description = "Missing permission")),
+)]
+pub async fn place_hold(req: HttpRequest, repos: web::Data<AppState>, user: AuthenticatedUser, form: web::Json<PlaceHoldRequest>) -> impl Responder {
+    let member_id = match caller_member(&user) {
+        Ok(member_id) => member_id,
+        Err(e) => return e.error_response(),
+    };
+
+    let book = repos.book_repo.lock().unwrap().get_book_by_id(&form.book_id);
+    match book {
+        Ok(_) => {}
+        Err(diesel::result::Error::NotFound) => return HttpResponse::UnprocessableEntity().body(format!("Book {} does not exist", form.book_id)),
+        Err(e) => {
+            error!(error = ?e, "Failed to place hold"); // Hata mesajını logla
+            return HttpResponse::InternalServerError().body(format!("Failed to place hold: {}", e));
+        }
+    }
+
+    // SQLite does not enforce the foreign key, so the library is looked up first.
+    if let Some(library_id) = form.library_id {
+        match repos.lib_repo.lock().unwrap().get_library_by_id(&library_id) {
+            Ok(_) => {}
+            Err(diesel::result::Error::NotFound) => {
+                return HttpResponse::UnprocessableEntity().body("library_id must name an existing library")
+            }
+            Err(e) => {
+                error!(error = ?e, "Failed to place hold"); // Hata mesajını logla
+                return HttpResponse::InternalServerError().body(format!("Failed to place hold: {}", e));
+            }
+        }
+    }
+
+    let mut member_repo = repos.member_repo.lock().unwrap();
+    match member_repo.get_member_by_id(&member_id) {
+        Ok(member) => {
+            if let Some(block) = member.circulation_block(&chrono::offset::Utc::now().date_naive().to_string()) {
+                return HttpResponse::Conflict().body(block);
+            }
+        }
+        Err(e) => {
+            error!(error = ?e, "Failed to place hold"); // Hata mesajını logla
+            return HttpResponse::InternalServerError().body(format!("Failed to place hold: {}", e));
+        }
+    }
+    match member_repo.get_loans(&member_id, true) {
+        Ok(loans) if loans.iter().any(|loan| loan.book_id == form.book_id) => {
+            return HttpResponse::Conflict().body("You already have this book on loan");
+        }
+        Ok(_) => {}
+        Err(e) => {
+            error!(error = ?e, "Failed to place hold"); // Hata mesajını logla
+            return HttpResponse::InternalServerError().body(format!("Failed to place hold: {}", e));
+        }
+    }
+
+    let placed_at: String = chrono::offset::Utc::now().naive_utc().to_string();
+    let new_hold = NewHold {
+        member_id: &member_id,
+        book_id: &form.book_id,
+        library_id: form.library_id.as_ref(),
+        placed_at: placed_at.as_str(),
+    };
+    drop(member_repo);
+    let placed = audited(&repos, &req, &user, |tx| {
+        let hold = tx.members().place_hold(&new_hold)?;
+        tx.record(AuditEvent {
+            action: "create",
+            entity: "hold",
+            entity_id: hold.id.to_string(),
+            before: None,
+            after: snapshot(&hold),
+        })?;
+        Ok(hold)
+    });
+    match placed {
+        Ok(hold) => HttpResponse::Created().json(hold),
+        Err(diesel::result::Error::DatabaseError(diesel::result::DatabaseErrorKind::UniqueViolation, _)) => {
+            HttpResponse::Conflict().body("You already hold this book")
+        }
+        Err(e) => {
+            error!(error = ?e, "Failed to place hold"); // Hata mesajını logla
+            HttpResponse::InternalServerError().body(format!("Failed to place hold: {}", e))
+        }
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = "/me/holds/{id}",
+    tag = "me",
+    params(HoldPath),
+    responses((status = 200, description = "Hold cancelled"), (status = 404, description = "No such active hold"), (status = 401, description = "Missing or invalid credentials"), (status = 403, description = "Missing permission")),
+)]
+pub async fn cancel_hold(req: HttpRequest, repos: web::Data<AppState>, user: AuthenticatedUser, path: web::Path<HoldPath>) -> impl Responder {
+    let member_id = match caller_member(&user) {
+        Ok(member_id) => member_id,
+        Err(e) => return e.error_response(),
+    };
+
+    let cancelled = audited(&repos, &req, &user, |tx| {
+        let cancelled = tx.members().cancel_hold(&member_id, &path.id)?;
+        if cancelled == 1 {
+            tx.record(AuditEvent {
+                action: "cancel",
+                entity: "hold",
+                entity_id: path.id.to_string(),
+                before: None,
+                after: None,
+            })?;
+        }
+        Ok(cancelled)
+    });
+    match cancelled {
+        Ok(0) => HttpResponse::NotFound().finish(),
+        Ok(_) => HttpResponse::Ok().finish(),
+        Err(e) => {
+            error!(error = ?e, "Failed to cancel hold"); // Hata mesajını logla
+            HttpResponse::InternalServerError().body(format!("Failed to cancel hold: {}", e))
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/me/fines",
+    tag = "me",
+    responses((status = 200, description = "Fines for the caller's overdue loans, open and returned", body = FinesResponse), (status = 401, description = "Missing or invalid credentials"), (status = 403, description = "Missing permission")),
+)]
+pub async fn get_fines(repos: web::Data<AppState>, user: AuthenticatedUser) -> impl Responder {
+    let member_id = match caller_member(&user) {
+        Ok(member_id) => member_id,
+        Err(e) => return e.error_response(),
+    };
+
+    let mut member_repo = repos.member_repo.lock().unwrap();
+    let loans = member_repo
+        .get_loans(&member_id, true)
+        .and_then(|mut open| member_repo.get_loans(&member_id, false).map(|returned| {
+            open.extend(returned);
+            open
+        }));
+    let loans = match loans {
+        Ok(loans) => loans,
+        Err(e) => {
+            error!(error = ?e, "Failed to get fines"); // Hata mesajını logla
+            return HttpResponse::InternalServerError().body(format!("Failed to get fines: {}", e));
+        }
+    };
+
+    let calendars = match loan_calendars(&repos, &loans) {
+        Ok(calendars) => calendars,
+        Err(e) => {
+            error!(error = ?e, "Failed to get fines"); // Hata mesajını logla
+            return HttpResponse::InternalServerError().body(format!("Failed to get fines: {}", e));
+        }
+    };
+
+    let now = chrono::offset::Utc::now().naive_utc();
+    HttpResponse::Ok().json(fines_for(&loans, repos.loans.fine_per_day_cents, &now, &calendars))
+}
+
+#[utoipa::path(
+    get,
+    path = "/me/export",
+    tag = "me",
+    responses((status = 200, description = "All data held about the caller, as a JSON download; same as `GET /members/{id}/export`"), (status = 401, description = "Missing or invalid credentials"), (status = 403, description = "Missing permission")),
+)]
+pub async fn export_data(repos: web::Data<AppState>, user: AuthenticatedUser) -> impl Responder {
+    match caller_member(&user) {
+        Ok(member_id) => export_response(&repos, member_id),
+        Err(e) => e.error_response(),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/me/preferences",
+    tag = "me",
+    responses((status = 200, description = "The caller's notification preferences", body = PreferencesRequest), (status = 401, description = "Missing or invalid credentials"), (status = 403, description = "Missing permission")),
+)]
+pub async fn get_preferences(repos: web::Data<AppState>, user: AuthenticatedUser) -> impl Responder {
+    let member_id = match caller_member(&user) {
+        Ok(member_id) => member_id,
+        Err(e) => return e.error_response(),
+    };
+
+    let mut member_repo = repos.member_repo.lock().unwrap();
+    match member_repo.get_member_by_id(&member_id) {
+        Ok(member) => HttpResponse::Ok().json(PreferencesRequest {
+            language: member.language,
+            notify_reminders: member.notify_reminders,
+            keep_loan_history: Some(member.keep_loan_history),
+        }),
+        Err(diesel::result::Error::NotFound) => HttpResponse::NotFound().finish(),
+        Err(e) => {
+            error!(error = ?e, "Failed to get preferences"); // Hata mesajını logla
+            HttpResponse::InternalServerError().body(format!("Failed to get preferences: {}", e))
+        }
+    }
+}
+
+#[utoipa::path(
+    put,
+    path = "/me/preferences",
+    tag = "me",
+    request_body = PreferencesRequest,
+    responses((status = 200, description = "Preferences saved", body = PreferencesRequest), (status = 409, description = "The profile changed meanwhile; retry"), (status = 422, description = "Invalid language"), (status = 401, description = "Missing or invalid credentials"), (status = 403, description = "Missing permission")),
+)]
+pub async fn update_preferences(
+    req: HttpRequest,
+    repos: web::Data<AppState>,
+    user: AuthenticatedUser,
+    form: web::Json<PreferencesRequest>,
+) -> impl Responder {
+    let member_id = match caller_member(&user) {
+        Ok(member_id) => member_id,
+        Err(e) => return e.error_response(),
+    };
+    if let Err(message) = validate_language(form.language.as_deref()) {
+        return HttpResponse::UnprocessableEntity().body(message);
+    }
+
+    let mut member_repo = repos.member_repo.lock().unwrap();
+    let current = match member_repo.get_member_by_id(&member_id) {
+        Ok(member) => member,
+        Err(diesel::result::Error::NotFound) => return HttpResponse::NotFound().finish(),
+        Err(e) => {
+            error!(error = ?e, "Failed to update preferences"); // Hata mesajını logla
+            return HttpResponse::InternalServerError().body(format!("Failed to update preferences: {}", e));
+        }
+    };
+
+    drop(member_repo);
+    let mut form = form.into_inner();
+    form.keep_loan_history = Some(form.keep_loan_history.unwrap_or(current.keep_loan_history));
+    let changes = MemberChanges {
+        language: Some(form.language.as_deref()),
+        notify_reminders: Some(form.notify_reminders),
+        keep_loan_history: form.keep_loan_history,
+        ..MemberChanges::default()
+    };
+    let updated = audited(&repos, &req, &user, |tx| {
+        let updated = tx.members().patch_member(&member_id, &changes, &current.version)?;
+        if updated == 1 {
+            tx.record(AuditEvent {
+                action: "update",
+                entity: "member",
+                entity_id: member_id.to_string(),
+                before: Some(json!({
+                    "language": current.language,
+                    "notify_reminders": current.notify_reminders,
+                    "keep_loan_history": current.keep_loan_history,
+                })),
+                after: snapshot(&form),
+            })?;
+        }
+        Ok(updated)
+    });
+    match updated {
+        Ok(0) => HttpResponse::Conflict().body("The profile changed meanwhile; retry"),
+        Ok(_) => HttpResponse::Ok().json(form),
+        Err(e) => {
+            error!(error = ?e, "Failed to update preferences"); // Hata mesajını logla
+            HttpResponse::InternalServerError().body(format!("Failed to update preferences: {}", e))
+        }
+    }
+}
+
+// Routes configuration
+pub fn me_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::resource("/me")
+            .route(web::get().to(get_profile))
+    )
+    .service(
+        web::resource("/me/loans")
+            .route(web::get().to(get_loans))
+    )
+    .service(
+        web::resource("/me/loans/history")
+            .route(web::get().to(get_loan_history))
+    )
+    .service(
+        web::resource("/me/loans/{id}/renew")
+            .route(web::post().to(renew_loan))
+    )
+    .service(
+        web::resource("/me/holds")
+            .route(web::get().to(get_holds))
+            .route(web::post().to(place_hold))
+    )
+    .service(
+        web::resource("/me/holds/{id}")
+            .route(web::delete().to(cancel_hold))
+    )
+    .service(
+        web::resource("/me/fines")
+            .route(web::get().to(get_fines))
+    )
+    .service(
+        web::resource("/me/export")
+            .route(web::get().to(export_data))
+    )
+    .service(
+        web::resource("/me/preferences")
+            .route(web::get().to(get_preferences))
+            .route(web::put().to(update_preferences))
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use domain::models::calendar::{Closure, OpeningHours};
+
+    fn at(value: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(value, TIMESTAMP_FORMAT).unwrap()
+    }
+
+    fn loan(id: i32, library_id: Option<i32>, due_at: Option<&str>, returned_at: Option<&str>) -> Loan {
+        Loan {
+            id,
+            book_id: id,
+            title: format!("Book {}", id),
+            author: "Author".to_string(),
+            library_id,
+            library_name: None,
+            borrowed_at: "2025-12-20 12:00:00".to_string(),
+            due_at: due_at.map(str::to_string),
+            returned_at: returned_at.map(str::to_string),
+            renewals: 0,
+        }
+    }
+
+    // Library 1 opens Monday to Friday and is closed on Friday 2026-01-09.
+    fn calendars() -> HashMap<i32, LibraryCalendar> {
+        let hours = (1..=5)
+            .map(|weekday| OpeningHours { library_id: 1, weekday, opens_at: "09:00".to_string(), closes_at: "17:00".to_string() })
+            .collect();
+        let closures = vec![Closure { id: 1, library_id: 1, date: "2026-01-09".to_string(), reason: "Holiday".to_string() }];
+        HashMap::from([(1, LibraryCalendar { hours, closures })])
+    }
+
+    #[test]
+    fn chargeable_days_leave_out_closed_days() {
+        let now = at("2026-01-12 13:00:00");
+        let due = Some("2026-01-05 12:00:00");
+        // Eight started days late: the 6th to the 13th, of which the 9th, 10th and 11th were closed.
+        assert_eq!(chargeable_days(&loan(1, Some(1), due, None), &now, &calendars()), 5);
+        assert_eq!(chargeable_days(&loan(1, Some(2), due, None), &now, &calendars()), 8);
+        assert_eq!(chargeable_days(&loan(1, None, due, None), &now, &calendars()), 8);
+        assert_eq!(chargeable_days(&loan(1, Some(1), due, Some("2026-01-06 11:00:00")), &now, &calendars()), 1);
+        assert_eq!(chargeable_days(&loan(1, Some(1), due, Some("2026-01-05 11:00:00")), &now, &calendars()), 0);
+        assert_eq!(chargeable_days(&loan(1, Some(1), None, None), &now, &calendars()), 0);
+    }
+
+    #[test]
+    fn fines_cover_overdue_loans_only() {
+        let loans = vec![
+            loan(1, Some(1), Some("2026-01-05 12:00:00"), None),
+            loan(2, None, Some("2026-01-11 12:00:00"), None),
+            loan(3, None, Some("2026-02-01 12:00:00"), None),
+        ];
+        let fines = fines_for(&loans, 25, &at("2026-01-12 13:00:00"), &calendars());
+
+        assert_eq!(fines.fines.iter().map(|fine| (fine.loan_id, fine.days_overdue, fine.amount_cents)).collect::<Vec<_>>(), vec![(1, 5, 125), (2, 2, 50)]);
+        assert_eq!(fines.total_cents, 175);
+        assert_eq!(fines.fine_per_day_cents, 25);
+    }
+}
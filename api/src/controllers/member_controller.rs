@@ -1,26 +1,116 @@
-use actix_web::{web, HttpResponse, Responder};
-use serde::Deserialize;
+use actix_web::http::header::ETag;
+use actix_web::{web, HttpRequest, HttpResponse, Responder, ResponseError};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tracing::error;
+use utoipa::{IntoParams, ToSchema};
+use domain::models::audit::{AuditEntry, AuditFilter};
+use domain::models::book::Book;
+use domain::models::loan::{Hold, Loan};
+use domain::models::notice::{LoanNotice, NoticeFilter};
+use domain::models::user::User;
+use domain::models::member::{duplicate_candidates, DuplicateCandidate, Member, MemberChanges, MergeSummary, MEMBER_ACTIVE, MEMBER_BANNED, MEMBER_SUSPENDED};
+use domain::models::role::Permission;
+use domain::traits::MemberRepositoryTrait;
+use crate::audit::{audited, snapshot, AuditEvent};
+use crate::auth::AuthenticatedUser;
+use crate::calendar::{due_on_open_day, loan_calendars};
+use crate::controllers::me_controller::{fines_for, FinesResponse};
+use crate::etag::{check_if_match, entity_tag, not_modified, precondition_failed};
+use crate::patch::{apply_patch, changed};
+use crate::versioning::CURRENT_VERSION;
 use crate::AppState;
 
 // Request yapılandırmaları
-#[derive(Deserialize)]
+#[derive(Deserialize, ToSchema)]
 pub struct CreateMemberRequest {
     name: String,
     email: String,
+    #[serde(default)]
+    language: Option<String>,
 }
 
-#[derive(Deserialize)]
+#[derive(Serialize, Deserialize, ToSchema)]
 pub struct UpdateMemberRequest {
     name: String,
     email: String,
+    #[serde(default)]
+    language: Option<String>,
 }
 
-#[derive(Deserialize)]
+impl UpdateMemberRequest {
+    fn validate(&self) -> Result<(), String> {
+        if self.name.trim().is_empty() {
+            return Err("name must not be empty".to_string());
+        }
+        if !self.email.contains('@') {
+            return Err("email must be a valid email address".to_string());
+        }
+        validate_language(self.language.as_deref())
+    }
+}
+
+impl CreateMemberRequest {
+    fn validate(&self) -> Result<(), String> {
+        validate_language(self.language.as_deref())
+    }
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Path)]
 pub struct BorrowReturnRequest {
     member_id: i32,
     book_id: i32,
 }
 
+#[derive(Deserialize, ToSchema)]
+pub struct MemberStatusRequest {
+    // active, suspended or banned; memberships expire on their own.
+    status: String,
+    // Required when suspending or banning; shown to the member when a loan or hold is refused.
+    #[serde(default)]
+    reason: Option<String>,
+    // Last day of a suspension (YYYY-MM-DD); indefinite when omitted.
+    #[serde(default)]
+    until: Option<String>,
+    // New last day of the membership (YYYY-MM-DD), e.g. when renewing it; unchanged when omitted.
+    #[serde(default)]
+    expires_at: Option<String>,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct MergeMembersRequest {
+    // The duplicate record; it is deleted once everything has moved to the member in the path.
+    from_member_id: i32,
+}
+
+impl MemberStatusRequest {
+    fn validate(&self) -> Result<(), String> {
+        if ![MEMBER_ACTIVE, MEMBER_SUSPENDED, MEMBER_BANNED].contains(&self.status.as_str()) {
+            return Err(format!("status must be one of {}, {} or {}", MEMBER_ACTIVE, MEMBER_SUSPENDED, MEMBER_BANNED));
+        }
+        if self.status != MEMBER_ACTIVE && self.reason.as_deref().is_none_or(|reason| reason.trim().is_empty()) {
+            return Err(format!("reason is required when the status is {}", self.status));
+        }
+        if self.until.is_some() && self.status != MEMBER_SUSPENDED {
+            return Err("until only applies to suspensions".to_string());
+        }
+        for (name, value) in [("until", &self.until), ("expires_at", &self.expires_at)] {
+            if let Some(value) = value {
+                NaiveDate::parse_from_str(value, "%Y-%m-%d").map_err(|_| format!("{} must be a date (YYYY-MM-DD)", name))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Deserialize, IntoParams)]
+pub struct BorrowQuery {
+    // Library the loan is booked to; defaults to the member's lowest-numbered library stocking the book.
+    library_id: Option<i32>,
+}
+
 #[derive(Deserialize)]
 pub struct GetBorrowedBooksRequest {
     member_id: i32,
@@ -28,139 +118,879 @@ pub struct GetBorrowedBooksRequest {
     library_id: i32,
 }
 
+// Language tags like `en` or `pt-BR`; notice templates are looked up by this value.
+pub(crate) fn validate_language(language: Option<&str>) -> Result<(), String> {
+    match language {
+        Some(tag) if tag.is_empty() || tag.len() > 16 || !tag.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') => {
+            Err("language must be a language tag such as `en`".to_string())
+        }
+        _ => Ok(()),
+    }
+}
+
+// Everything held about a member, as returned by the export endpoints.
+#[derive(Serialize, ToSchema)]
+pub struct MemberExport {
+    exported_at: String,
+    member: Member,
+    library_ids: Vec<i32>,
+    accounts: Vec<User>,
+    // Open and returned loans.
+    loans: Vec<Loan>,
+    holds: Vec<Hold>,
+    fines: FinesResponse,
+    notices: Vec<LoanNotice>,
+    // Changes to the member and its accounts, and everything done through those accounts.
+    audit: Vec<AuditEntry>,
+}
+
+fn collect_export(repos: &AppState, member_id: i32) -> diesel::QueryResult<MemberExport> {
+    let (member, library_ids, loans, holds) = {
+        let mut member_repo = repos.member_repo.lock().unwrap();
+        let member = member_repo.get_member_by_id(&member_id)?;
+        let library_ids = member_repo.get_member_library_ids(&member_id)?;
+        let mut loans = member_repo.get_loans(&member_id, true)?;
+        loans.extend(member_repo.get_loans(&member_id, false)?);
+        let holds = member_repo.get_hold_history(&member_id)?;
+        (member, library_ids, loans, holds)
+    };
+    let accounts: Vec<User> = repos.user_repo.lock().unwrap()
+        .get_users()?
+        .into_iter()
+        .filter(|account| account.member_id == Some(member_id))
+        .collect();
+    let notices = repos.notice_repo.lock().unwrap().get_notices(&NoticeFilter {
+        member_id: Some(member_id),
+        kind: None,
+        limit: Some(i64::MAX),
+    })?;
+
+    let mut filters = vec![AuditFilter {
+        entity: Some("member".to_string()),
+        id: Some(member_id.to_string()),
+        limit: Some(i64::MAX),
+        ..AuditFilter::default()
+    }];
+    for account in &accounts {
+        filters.push(AuditFilter {
+            entity: Some("user".to_string()),
+            id: Some(account.id.to_string()),
+            limit: Some(i64::MAX),
+            ..AuditFilter::default()
+        });
+        filters.push(AuditFilter {
+            actor_user_id: Some(account.id),
+            limit: Some(i64::MAX),
+            ..AuditFilter::default()
+        });
+    }
+    let mut audit = Vec::new();
+    {
+        let mut audit_repo = repos.audit_repo.lock().unwrap();
+        for filter in &filters {
+            audit.extend(audit_repo.get_entries(filter)?);
+        }
+    }
+    audit.sort_by_key(|entry| entry.id);
+    audit.dedup_by_key(|entry| entry.id);
+
+    let now = chrono::offset::Utc::now().naive_utc();
+    Ok(MemberExport {
+        exported_at: now.to_string(),
+        fines: fines_for(&loans, repos.loans.fine_per_day_cents, &now, &loan_calendars(repos, &loans)?),
+        member,
+        library_ids,
+        accounts,
+        loans,
+        holds,
+        notices,
+        audit,
+    })
+}
+
+// The export as a JSON download; shared by `GET /members/{id}/export` and `GET /me/export`.
+pub(crate) fn export_response(repos: &AppState, member_id: i32) -> HttpResponse {
+    match collect_export(repos, member_id) {
+        Ok(export) => HttpResponse::Ok()
+            .insert_header(("Content-Disposition", format!("attachment; filename=\"member-{}.json\"", member_id)))
+            .json(export),
+        Err(diesel::result::Error::NotFound) => HttpResponse::NotFound().finish(),
+        Err(e) => {
+            error!(error = ?e, "Failed to export member"); // Hata mesajını logla
+            HttpResponse::InternalServerError().body(format!("Failed to export member: {}", e))
+        }
+    }
+}
+
 fn default_library_id() -> i32 {
     -1
 }
 
+#[derive(Serialize, ToSchema)]
+pub struct LoanResponse {
+    due_at: String,
+}
+
 // Handlers
+#[utoipa::path(
+    post,
+    path = "/members",
+    tag = "members",
+    request_body = CreateMemberRequest,
+    responses((status = 201, description = "Created; Location points at the new member", headers(("ETag" = String, description = "Current version of the resource"))), (status = 409, description = "A member with this email already exists"), (status = 401, description = "Missing or invalid credentials"), (status = 403, description = "Missing permission")),
+)]
 pub async fn create_member(
+    req: HttpRequest,
     repos: web::Data<AppState>,
+    user: AuthenticatedUser,
     form: web::Json<CreateMemberRequest>,
 ) -> impl Responder {
-    let mut member_repo = repos.member_repo.lock().unwrap();
-    match member_repo.create_member(&form.name, &form.email) {
-        Ok(_) => HttpResponse::Created().finish(),
+    if let Err(e) = user.require_anywhere(Permission::ManageMembers) {
+        return e.error_response();
+    }
+
+    if let Err(message) = form.validate() {
+        return HttpResponse::UnprocessableEntity().body(message);
+    }
+
+    let expires_at = (repos.loans.membership_days > 0)
+        .then(|| (chrono::offset::Utc::now().date_naive() + chrono::Duration::days(repos.loans.membership_days)).to_string());
+    let result = audited(&repos, &req, &user, |tx| {
+        let created = tx.members().create_member(&form.name, &form.email, form.language.as_deref(), expires_at.as_deref())?;
+        tx.record(AuditEvent {
+            action: "create",
+            entity: "member",
+            entity_id: created.id.to_string(),
+            before: None,
+            after: snapshot(&created),
+        })?;
+        Ok(created)
+    });
+    match result {
+        Ok(created) => HttpResponse::Created()
+            .insert_header(("Location", format!("{}/members/{}", CURRENT_VERSION, created.id)))
+            .insert_header(ETag(entity_tag(created.version)))
+            .finish(),
+        Err(diesel::result::Error::DatabaseError(diesel::result::DatabaseErrorKind::UniqueViolation, _)) => {
+            HttpResponse::Conflict().body("A member with this email already exists")
+        }
         Err(e) => {
-            println!("Failed to create member: {:?}", e); // Hata mesajını logla
+            error!(error = ?e, "Failed to create member"); // Hata mesajını logla
             HttpResponse::InternalServerError().body(format!("Failed to create member: {}", e))
         }
     }
 }
 
+#[utoipa::path(
+    put,
+    path = "/members/{id}",
+    tag = "members",
+    params(("If-Match" = String, Header, description = "ETag from the last GET, or `*`")),
+    request_body = UpdateMemberRequest,
+    responses((status = 200, description = "Updated", headers(("ETag" = String, description = "Current version of the resource"))), (status = 404, description = "No such member"), (status = 409, description = "A member with this email already exists"), (status = 422, description = "Invalid member"), (status = 412, description = "If-Match does not match the current version"), (status = 428, description = "If-Match header is required"), (status = 401, description = "Missing or invalid credentials"), (status = 403, description = "Missing permission")),
+)]
 pub async fn update_member(
+    req: HttpRequest,
     repos: web::Data<AppState>,
+    user: AuthenticatedUser,
     id: web::Path<i32>,
     form: web::Json<UpdateMemberRequest>,
 ) -> impl Responder {
+    if let Err(e) = user.require_anywhere(Permission::ManageMembers) {
+        return e.error_response();
+    }
+
+    if let Err(message) = form.validate() {
+        return HttpResponse::UnprocessableEntity().body(message);
+    }
+
     let mut member_repo = repos.member_repo.lock().unwrap();
-    match member_repo.update_member(&id, &form.name, &form.email) {
-        Ok(_) => HttpResponse::Ok().finish(),
+
+    let current = match member_repo.get_member_by_id(&id) {
+        Ok(member) => member,
+        Err(diesel::result::Error::NotFound) => return HttpResponse::NotFound().finish(),
         Err(e) => {
-            println!("Failed to update member: {:?}", e); // Hata mesajını logla
+            error!(error = ?e, "Failed to update member"); // Hata mesajını logla
+            return HttpResponse::InternalServerError().body(format!("Failed to update member: {}", e));
+        }
+    };
+    if let Some(response) = check_if_match(&req, current.version) {
+        return response;
+    }
+
+    let result = audited(&repos, &req, &user, |tx| {
+        let updated = tx.members().update_member(&id, &form.name, &form.email, form.language.as_deref(), &current.version)?;
+        if updated == 1 {
+            let after = tx.members().get_member_by_id(&id)?;
+            tx.record(AuditEvent {
+                action: "update",
+                entity: "member",
+                entity_id: id.to_string(),
+                before: snapshot(&current),
+                after: snapshot(&after),
+            })?;
+        }
+        Ok(updated)
+    });
+    match result {
+        Ok(0) => precondition_failed(),
+        Ok(_) => HttpResponse::Ok().insert_header(ETag(entity_tag(current.version + 1))).finish(),
+        Err(diesel::result::Error::DatabaseError(diesel::result::DatabaseErrorKind::UniqueViolation, _)) => {
+            HttpResponse::Conflict().body("A member with this email already exists")
+        }
+        Err(e) => {
+            error!(error = ?e, "Failed to update member"); // Hata mesajını logla
             HttpResponse::InternalServerError().body(format!("Failed to update member: {}", e))
         }
     }
 }
 
+#[utoipa::path(
+    patch,
+    path = "/members/{id}",
+    tag = "members",
+    params(("If-Match" = String, Header, description = "ETag from the last GET, or `*`")),
+    request_body(content((UpdateMemberRequest = "application/merge-patch+json"), (UpdateMemberRequest = "application/json-patch+json"))),
+    responses((status = 200, description = "Patched", headers(("ETag" = String, description = "Current version of the resource"))), (status = 400, description = "Malformed patch"), (status = 404, description = "No such member"), (status = 409, description = "JSON Patch test or path failed, or a member with this email already exists"), (status = 415, description = "Unsupported patch media type"), (status = 422, description = "Patched member is invalid"), (status = 412, description = "If-Match does not match the current version"), (status = 428, description = "If-Match header is required"), (status = 401, description = "Missing or invalid credentials"), (status = 403, description = "Missing permission")),
+)]
+pub async fn patch_member(
+    req: HttpRequest,
+    repos: web::Data<AppState>,
+    user: AuthenticatedUser,
+    id: web::Path<i32>,
+    body: web::Bytes,
+) -> impl Responder {
+    if let Err(e) = user.require_anywhere(Permission::ManageMembers) {
+        return e.error_response();
+    }
+
+    let mut member_repo = repos.member_repo.lock().unwrap();
+
+    let current = match member_repo.get_member_by_id(&id) {
+        Ok(member) => member,
+        Err(diesel::result::Error::NotFound) => return HttpResponse::NotFound().finish(),
+        Err(e) => {
+            error!(error = ?e, "Failed to patch member"); // Hata mesajını logla
+            return HttpResponse::InternalServerError().body(format!("Failed to patch member: {}", e));
+        }
+    };
+    if let Some(response) = check_if_match(&req, current.version) {
+        return response;
+    }
+
+    let existing = UpdateMemberRequest {
+        name: current.name.clone(),
+        email: current.email.clone(),
+        language: current.language.clone(),
+    };
+    let patched = match apply_patch(&req, &body, &existing) {
+        Ok(patched) => patched,
+        Err(e) => return e.error_response(),
+    };
+    if let Err(message) = patched.validate() {
+        return HttpResponse::UnprocessableEntity().body(message);
+    }
+
+    let changes = MemberChanges {
+        name: changed(existing.name.as_str(), patched.name.as_str()),
+        email: changed(existing.email.as_str(), patched.email.as_str()),
+        language: (existing.language != patched.language).then_some(patched.language.as_deref()),
+        ..MemberChanges::default()
+    };
+    if changes.is_empty() {
+        return HttpResponse::Ok().insert_header(ETag(entity_tag(current.version))).finish();
+    }
+
+    let result = audited(&repos, &req, &user, |tx| {
+        let patched = tx.members().patch_member(&id, &changes, &current.version)?;
+        if patched == 1 {
+            let after = tx.members().get_member_by_id(&id)?;
+            tx.record(AuditEvent {
+                action: "update",
+                entity: "member",
+                entity_id: id.to_string(),
+                before: snapshot(&current),
+                after: snapshot(&after),
+            })?;
+        }
+        Ok(patched)
+    });
+    match result {
+        Ok(0) => precondition_failed(),
+        Ok(_) => HttpResponse::Ok().insert_header(ETag(entity_tag(current.version + 1))).finish(),
+        Err(diesel::result::Error::DatabaseError(diesel::result::DatabaseErrorKind::UniqueViolation, _)) => {
+            HttpResponse::Conflict().body("A member with this email already exists")
+        }
+        Err(e) => {
+            error!(error = ?e, "Failed to patch member"); // Hata mesajını logla
+            HttpResponse::InternalServerError().body(format!("Failed to patch member: {}", e))
+        }
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = "/members/{id}",
+    tag = "members",
+    params(("If-Match" = String, Header, description = "ETag from the last GET, or `*`")),
+    responses((status = 204, description = "Deleted"), (status = 404, description = "No such member"), (status = 412, description = "If-Match does not match the current version"), (status = 428, description = "If-Match header is required"), (status = 401, description = "Missing or invalid credentials"), (status = 403, description = "Missing permission")),
+)]
 pub async fn delete_member(
+    req: HttpRequest,
     repos: web::Data<AppState>,
+    user: AuthenticatedUser,
     id: web::Path<i32>,
 ) -> impl Responder {
+    if let Err(e) = user.require_anywhere(Permission::ManageMembers) {
+        return e.error_response();
+    }
+
     let mut member_repo = repos.member_repo.lock().unwrap();
-    match member_repo.delete_member(&id) {
+
+    let current = match member_repo.get_member_by_id(&id) {
+        Ok(member) => member,
+        Err(diesel::result::Error::NotFound) => return HttpResponse::NotFound().finish(),
+        Err(e) => {
+            error!(error = ?e, "Failed to delete member"); // Hata mesajını logla
+            return HttpResponse::InternalServerError().body(format!("Failed to delete member: {}", e));
+        }
+    };
+    if let Some(response) = check_if_match(&req, current.version) {
+        return response;
+    }
+
+    let result = audited(&repos, &req, &user, |tx| {
+        let deleted = tx.members().delete_member(&id, &current.version)?;
+        if deleted == 1 {
+            tx.record(AuditEvent {
+                action: "delete",
+                entity: "member",
+                entity_id: id.to_string(),
+                before: snapshot(&current),
+                after: None,
+            })?;
+        }
+        Ok(deleted)
+    });
+    match result {
+        Ok(0) => precondition_failed(),
         Ok(_) => HttpResponse::NoContent().finish(),
         Err(e) => {
-            println!("Failed to delete member: {:?}", e); // Hata mesajını logla
+            error!(error = ?e, "Failed to delete member"); // Hata mesajını logla
             HttpResponse::InternalServerError().body(format!("Failed to delete member: {}", e))
         }
     }
 }
 
+#[utoipa::path(
+    get,
+    path = "/members/{id}",
+    tag = "members",
+    params(("If-None-Match" = Option<String>, Header, description = "Answer 304 when the ETag still matches")),
+    responses((status = 200, description = "The member", body = Member, headers(("ETag" = String, description = "Current version of the resource"))), (status = 304, description = "Not modified"), (status = 404, description = "No such member"), (status = 401, description = "Missing or invalid credentials"), (status = 403, description = "Missing permission")),
+)]
 pub async fn get_member(
+    req: HttpRequest,
     repos: web::Data<AppState>,
+    user: AuthenticatedUser,
     id: web::Path<i32>,
 ) -> impl Responder {
+    if !user.is_member(*id) {
+        if let Err(e) = user.require_anywhere(Permission::ManageMembers) {
+            return e.error_response();
+        }
+    }
+
     let mut member_repo = repos.member_repo.lock().unwrap();
     match member_repo.get_member_by_id(&id) {
-        Ok(member) => HttpResponse::Ok().json(member),
+        Ok(member) => not_modified(&req, member.version)
+            .unwrap_or_else(|| HttpResponse::Ok().insert_header(ETag(entity_tag(member.version))).json(member)),
+        Err(diesel::result::Error::NotFound) => HttpResponse::NotFound().finish(),
         Err(e) => {
-            println!("Failed to get member: {:?}", e); // Hata mesajını logla
+            error!(error = ?e, "Failed to get member"); // Hata mesajını logla
             HttpResponse::InternalServerError().body(format!("Failed to get member: {}", e))
         }
     }
 }
 
+#[utoipa::path(
+    get,
+    path = "/members",
+    tag = "members",
+    responses((status = 200, description = "All members", body = Vec<Member>), (status = 401, description = "Missing or invalid credentials"), (status = 403, description = "Missing permission")),
+)]
 pub async fn get_members(
     repos: web::Data<AppState>,
+    user: AuthenticatedUser,
 ) -> impl Responder {
+    if let Err(e) = user.require_anywhere(Permission::ManageMembers) {
+        return e.error_response();
+    }
+
     let mut member_repo = repos.member_repo.lock().unwrap();
     match member_repo.get_members() {
         Ok(members) => HttpResponse::Ok().json(members),
         Err(e) => {
-            println!("Failed to get members: {:?}", e); // Hata mesajını logla
+            error!(error = ?e, "Failed to get members"); // Hata mesajını logla
             HttpResponse::InternalServerError().body(format!("Failed to get members: {}", e))
         }
     }
 }
 
+#[utoipa::path(
+    get,
+    path = "/libraries/{library_id}/members",
+    tag = "members",
+    responses((status = 200, description = "Members of the library", body = Vec<Member>), (status = 401, description = "Missing or invalid credentials"), (status = 403, description = "Missing permission")),
+)]
 pub async fn get_members_by_library_id(
     repos: web::Data<AppState>,
+    user: AuthenticatedUser,
     library_id: web::Path<i32>,
 ) -> impl Responder {
+    if let Err(e) = user.require(Permission::ManageMembers, Some(*library_id)) {
+        return e.error_response();
+    }
+
     let mut member_repo = repos.member_repo.lock().unwrap();
     match member_repo.get_members_by_library_id(&library_id) {
         Ok(members) => HttpResponse::Ok().json(members),
         Err(e) => {
-            println!("Failed to get members by library id: {:?}", e); // Hata mesajını logla
+            error!(error = ?e, "Failed to get members by library id"); // Hata mesajını logla
             HttpResponse::InternalServerError().body(format!("Failed to get members by library id: {}", e))
         }
     }
 }
 
+#[utoipa::path(
+    post,
+    path = "/members/{member_id}/books/{book_id}",
+    tag = "loans",
+    params(BorrowReturnRequest, BorrowQuery),
+    responses((status = 200, description = "Loan created", body = LoanResponse), (status = 401, description = "Missing or invalid credentials"), (status = 403, description = "Missing permission"), (status = 404, description = "No such member"), (status = 409, description = "The member is suspended, banned or expired, or no copy is on the shelf"), (status = 422, description = "The library does not hold the book")),
+)]
 pub async fn borrow_book(
+    req: HttpRequest,
     repos: web::Data<AppState>,
+    user: AuthenticatedUser,
     path: web::Path<BorrowReturnRequest>,
+    query: web::Query<BorrowQuery>,
 ) -> impl Responder {
     let BorrowReturnRequest { member_id, book_id } = path.into_inner();
-    let mut member_repo = repos.member_repo.lock().unwrap();
-    match member_repo.borrow_book(&member_id, &book_id) {
-        Ok(_) => HttpResponse::Ok().finish(),
+
+    // The loan is checked against the library it is booked to; one booked to no library takes a global grant.
+    let library_id = match query.library_id {
+        Some(library_id) => Some(library_id),
+        None => match repos.member_repo.lock().unwrap().loan_library(&member_id, &book_id) {
+            Ok(library_id) => library_id,
+            Err(e) => {
+                error!(error = ?e, "Failed to borrow book"); // Hata mesajını logla
+                return HttpResponse::InternalServerError().body(format!("Failed to borrow book: {}", e));
+            }
+        },
+    };
+    if let Err(e) = user.require(Permission::ManageLoans, library_id) {
+        return e.error_response();
+    }
+
+    let member = repos.member_repo.lock().unwrap().get_member_by_id(&member_id);
+    match member {
+        Ok(member) => {
+            if let Some(block) = member.circulation_block(&chrono::offset::Utc::now().date_naive().to_string()) {
+                return HttpResponse::Conflict().body(block);
+            }
+        }
+        Err(diesel::result::Error::NotFound) => return HttpResponse::NotFound().finish(),
         Err(e) => {
-            println!("Failed to borrow book: {:?}", e); // Hata mesajını logla
+            error!(error = ?e, "Failed to get member"); // Hata mesajını logla
+            return HttpResponse::InternalServerError().body(format!("Failed to get member: {}", e));
+        }
+    }
+    if let Some(library_id) = query.library_id {
+        let held = repos.book_repo.lock().unwrap().get_books_by_library_id(&library_id);
+        match held {
+            Ok(books) if books.iter().any(|book| book.id == book_id) => {}
+            Ok(_) => return HttpResponse::UnprocessableEntity().body(format!("Library {} does not hold book {}", library_id, book_id)),
+            Err(e) => {
+                error!(error = ?e, "Failed to get library books"); // Hata mesajını logla
+                return HttpResponse::InternalServerError().body(format!("Failed to get library books: {}", e));
+            }
+        }
+    }
+
+    // Due dates fall on a day the library the loan is booked to is open.
+    let due = chrono::offset::Utc::now().naive_utc() + chrono::Duration::days(repos.loans.loan_period_days);
+    let due_at = match due_on_open_day(&repos, library_id, due) {
+        Ok(due) => due.to_string(),
+        Err(e) => {
+            error!(error = ?e, "Failed to borrow book"); // Hata mesajını logla
+            return HttpResponse::InternalServerError().body(format!("Failed to borrow book: {}", e));
+        }
+    };
+    let result = audited(&repos, &req, &user, |tx| {
+        let borrowed = tx.members().borrow_book(&member_id, &book_id, library_id.as_ref(), &due_at)?;
+        if borrowed > 0 {
+            tx.record(AuditEvent {
+                action: "borrow",
+                entity: "loan",
+                entity_id: format!("{}:{}", member_id, book_id),
+                before: None,
+                after: Some(json!({ "member_id": member_id, "book_id": book_id, "library_id": library_id, "due_at": due_at })),
+            })?;
+        }
+        Ok(borrowed)
+    });
+    match result {
+        Ok(0) => HttpResponse::Conflict().body(format!("No copy of book {} is on the shelf", book_id)),
+        Ok(_) => HttpResponse::Ok().json(LoanResponse { due_at }),
+        Err(e) => {
+            error!(error = ?e, "Failed to borrow book"); // Hata mesajını logla
             HttpResponse::InternalServerError().body(format!("Failed to borrow book: {}", e))
         }
     }
 }
 
+#[utoipa::path(
+    delete,
+    path = "/members/{member_id}/books/{book_id}",
+    tag = "loans",
+    params(BorrowReturnRequest),
+    responses((status = 200, description = "Book returned"), (status = 404, description = "The member has no open loan of the book"), (status = 401, description = "Missing or invalid credentials"), (status = 403, description = "Missing permission")),
+)]
 pub async fn return_book(
+    req: HttpRequest,
     repos: web::Data<AppState>,
+    user: AuthenticatedUser,
     path: web::Path<BorrowReturnRequest>,
 ) -> impl Responder {
     let BorrowReturnRequest { member_id, book_id } = path.into_inner();
     let mut member_repo = repos.member_repo.lock().unwrap();
-    match member_repo.return_book(&member_id, &book_id) {
+
+    // The return is checked against the library the loan was booked to; one booked to no library takes a global grant.
+    let loan = match member_repo.get_loans(&member_id, true) {
+        Ok(loans) => loans.into_iter().find(|loan| loan.book_id == book_id),
+        Err(e) => {
+            error!(error = ?e, "Failed to return book"); // Hata mesajını logla
+            return HttpResponse::InternalServerError().body(format!("Failed to return book: {}", e));
+        }
+    };
+    let Some(loan) = loan else {
+        return HttpResponse::NotFound().finish();
+    };
+    if let Err(e) = user.require(Permission::ManageLoans, loan.library_id) {
+        return e.error_response();
+    }
+    drop(member_repo);
+    let result = audited(&repos, &req, &user, |tx| {
+        let returned = tx.members().return_book(&member_id, &book_id)?;
+        if returned > 0 {
+            tx.record(AuditEvent {
+                action: "return",
+                entity: "loan",
+                entity_id: format!("{}:{}", member_id, book_id),
+                before: Some(json!({ "member_id": member_id, "book_id": book_id })),
+                after: None,
+            })?;
+        }
+        Ok(returned)
+    });
+    match result {
+        Ok(0) => HttpResponse::NotFound().finish(),
         Ok(_) => HttpResponse::Ok().finish(),
         Err(e) => {
-            println!("Failed to return book: {:?}", e); // Hata mesajını logla
+            error!(error = ?e, "Failed to return book"); // Hata mesajını logla
             HttpResponse::InternalServerError().body(format!("Failed to return book: {}", e))
         }
     }
 }
 
+#[utoipa::path(
+    get,
+    path = "/members/{member_id}/borrowed_books",
+    tag = "loans",
+    params(("member_id" = i32, Path, description = "Member id")),
+    responses((status = 200, description = "Books the member has on loan", body = Vec<Book>), (status = 401, description = "Missing or invalid credentials"), (status = 403, description = "Missing permission")),
+)]
 pub async fn get_borrowed_books(
     repos: web::Data<AppState>,
+    user: AuthenticatedUser,
     path: web::Path<GetBorrowedBooksRequest>,
 ) -> impl Responder {
+    // Members see their own loans; staff need loan rights in the library asked for, or anywhere when no library is given.
+    if !user.is_member(path.member_id) {
+        let allowed = if path.library_id == default_library_id() {
+            user.require_anywhere(Permission::ManageLoans)
+        } else {
+            user.require(Permission::ManageLoans, Some(path.library_id))
+        };
+        if let Err(e) = allowed {
+            return e.error_response();
+        }
+    }
+
     let mut member_repo = repos.member_repo.lock().unwrap();
     match member_repo.get_borrowed_books(&path.member_id, &path.library_id) {
         Ok(books) => HttpResponse::Ok().json(books),
         Err(e) => {
-            println!("Failed to get borrowed books: {:?}", e); // Hata mesajını logla
+            error!(error = ?e, "Failed to get borrowed books"); // Hata mesajını logla
             HttpResponse::InternalServerError().body(format!("Failed to get borrowed books: {}", e))
         }
     }
 }
 
+// Same as `get_borrowed_books`, narrowed to one library; kept separate so each path is documented on its own.
+#[utoipa::path(
+    get,
+    path = "/members/{member_id}/borrowed_books/{library_id}",
+    tag = "loans",
+    params(("member_id" = i32, Path, description = "Member id"), ("library_id" = i32, Path, description = "Only loans of books held by this library")),
+    responses((status = 200, description = "Books the member has on loan from the library", body = Vec<Book>), (status = 401, description = "Missing or invalid credentials"), (status = 403, description = "Missing permission")),
+)]
+pub async fn get_library_borrowed_books(
+    repos: web::Data<AppState>,
+    user: AuthenticatedUser,
+    path: web::Path<GetBorrowedBooksRequest>,
+) -> impl Responder {
+    get_borrowed_books(repos, user, path).await
+}
+
+#[utoipa::path(
+    post,
+    path = "/members/{id}/status",
+    tag = "members",
+    params(("id" = i32, Path, description = "Member id")),
+    request_body = MemberStatusRequest,
+    responses((status = 200, description = "Status changed; the updated member", body = Member, headers(("ETag" = String, description = "Current version of the resource"))), (status = 404, description = "No such member"), (status = 409, description = "The member changed meanwhile; retry"), (status = 422, description = "Invalid status change"), (status = 401, description = "Missing or invalid credentials"), (status = 403, description = "Missing permission")),
+)]
+pub async fn set_member_status(
+    req: HttpRequest,
+    repos: web::Data<AppState>,
+    user: AuthenticatedUser,
+    id: web::Path<i32>,
+    form: web::Json<MemberStatusRequest>,
+) -> impl Responder {
+    if let Err(e) = user.require_anywhere(Permission::ManageMembers) {
+        return e.error_response();
+    }
+    if let Err(message) = form.validate() {
+        return HttpResponse::UnprocessableEntity().body(message);
+    }
+
+    let mut member_repo = repos.member_repo.lock().unwrap();
+    let current = match member_repo.get_member_by_id(&id) {
+        Ok(member) => member,
+        Err(diesel::result::Error::NotFound) => return HttpResponse::NotFound().finish(),
+        Err(e) => {
+            error!(error = ?e, "Failed to set member status"); // Hata mesajını logla
+            return HttpResponse::InternalServerError().body(format!("Failed to set member status: {}", e));
+        }
+    };
+
+    let today = chrono::offset::Utc::now().date_naive().to_string();
+    let reason = form.reason.as_deref().map(str::trim).filter(|reason| !reason.is_empty());
+    let changes = MemberChanges {
+        status: Some(form.status.as_str()),
+        status_reason: Some(reason),
+        status_since: Some(Some(today.as_str())),
+        status_until: Some(form.until.as_deref()),
+        expires_at: form.expires_at.as_deref().map(Some),
+        ..MemberChanges::default()
+    };
+    drop(member_repo);
+    let result = audited(&repos, &req, &user, |tx| {
+        if tx.members().patch_member(&id, &changes, &current.version)? == 0 {
+            return Ok(None);
+        }
+        let member = tx.members().get_member_by_id(&id)?;
+        tx.record(AuditEvent {
+            action: "set_status",
+            entity: "member",
+            entity_id: member.id.to_string(),
+            before: snapshot(&current),
+            after: snapshot(&member),
+        })?;
+        Ok(Some(member))
+    });
+    match result {
+        Ok(None) => HttpResponse::Conflict().body("The member changed meanwhile; retry"),
+        Ok(Some(member)) => HttpResponse::Ok().insert_header(ETag(entity_tag(member.version))).json(member),
+        Err(e) => {
+            error!(error = ?e, "Failed to set member status"); // Hata mesajını logla
+            HttpResponse::InternalServerError().body(format!("Failed to set member status: {}", e))
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/members/duplicates",
+    tag = "members",
+    responses((status = 200, description = "Pairs of member records that probably describe the same person", body = [DuplicateCandidate]), (status = 401, description = "Missing or invalid credentials"), (status = 403, description = "Missing permission")),
+)]
+pub async fn get_duplicate_members(repos: web::Data<AppState>, user: AuthenticatedUser) -> impl Responder {
+    if let Err(e) = user.require_anywhere(Permission::ManageMembers) {
+        return e.error_response();
+    }
+
+    let mut member_repo = repos.member_repo.lock().unwrap();
+    match member_repo.get_members() {
+        Ok(members) => HttpResponse::Ok().json(duplicate_candidates(&members)),
+        Err(e) => {
+            error!(error = ?e, "Failed to find duplicate members"); // Hata mesajını logla
+            HttpResponse::InternalServerError().body(format!("Failed to find duplicate members: {}", e))
+        }
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/members/{id}/merge",
+    tag = "members",
+    params(("id" = i32, Path, description = "Member to keep")),
+    request_body = MergeMembersRequest,
+    responses((status = 200, description = "Merged; what moved over from the duplicate", body = MergeSummary), (status = 404, description = "No such member"), (status = 409, description = "Both records have the same book out"), (status = 422, description = "A member cannot be merged into itself"), (status = 401, description = "Missing or invalid credentials"), (status = 403, description = "Missing permission")),
+)]
+pub async fn merge_members(
+    req: HttpRequest,
+    repos: web::Data<AppState>,
+    user: AuthenticatedUser,
+    id: web::Path<i32>,
+    form: web::Json<MergeMembersRequest>,
+) -> impl Responder {
+    if let Err(e) = user.require_anywhere(Permission::ManageMembers) {
+        return e.error_response();
+    }
+    if *id == form.from_member_id {
+        return HttpResponse::UnprocessableEntity().body("A member cannot be merged into itself");
+    }
+
+    let mut member_repo = repos.member_repo.lock().unwrap();
+    let (into, from) = match (member_repo.get_member_by_id(&id), member_repo.get_member_by_id(&form.from_member_id)) {
+        (Ok(into), Ok(from)) => (into, from),
+        (Err(diesel::result::Error::NotFound), _) | (_, Err(diesel::result::Error::NotFound)) => return HttpResponse::NotFound().finish(),
+        (Err(e), _) | (_, Err(e)) => {
+            error!(error = ?e, "Failed to merge members"); // Hata mesajını logla
+            return HttpResponse::InternalServerError().body(format!("Failed to merge members: {}", e));
+        }
+    };
+
+    drop(member_repo);
+    let result = audited(&repos, &req, &user, |tx| {
+        let summary = tx.members().merge_members(&into.id, &from.id)?;
+        tx.record(AuditEvent {
+            action: "merge",
+            entity: "member",
+            entity_id: into.id.to_string(),
+            before: Some(json!({ "into": into, "from": from })),
+            after: snapshot(&summary),
+        })?;
+        Ok(summary)
+    });
+    match result {
+        Ok(summary) => HttpResponse::Ok().json(summary),
+        Err(diesel::result::Error::DatabaseError(diesel::result::DatabaseErrorKind::UniqueViolation, _)) => {
+            HttpResponse::Conflict().body("Both records have the same book out; return one of the loans first")
+        }
+        Err(e) => {
+            error!(error = ?e, "Failed to merge members"); // Hata mesajını logla
+            HttpResponse::InternalServerError().body(format!("Failed to merge members: {}", e))
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/members/{id}/export",
+    tag = "members",
+    params(("id" = i32, Path, description = "Member id")),
+    responses((status = 200, description = "All data held about the member, as a JSON download", body = MemberExport), (status = 404, description = "No such member"), (status = 401, description = "Missing or invalid credentials"), (status = 403, description = "Missing permission")),
+)]
+pub async fn export_member(
+    req: HttpRequest,
+    repos: web::Data<AppState>,
+    user: AuthenticatedUser,
+    id: web::Path<i32>,
+) -> impl Responder {
+    if let Err(e) = user.require_anywhere(Permission::ManageMembers) {
+        return e.error_response();
+    }
+
+    let response = export_response(&repos, *id);
+    if response.status().is_success() {
+        let recorded = audited(&repos, &req, &user, |tx| {
+            tx.record(AuditEvent {
+                action: "export",
+                entity: "member",
+                entity_id: id.to_string(),
+                before: None,
+                after: None,
+            })
+        });
+        if let Err(e) = recorded {
+            error!(error = ?e, "Failed to export member"); // Hata mesajını logla
+            return HttpResponse::InternalServerError().body(format!("Failed to export member: {}", e));
+        }
+    }
+    response
+}
+
+#[utoipa::path(
+    post,
+    path = "/members/{id}/erase",
+    tag = "members",
+    params(("id" = i32, Path, description = "Member id")),
+    responses((status = 200, description = "Erased; the anonymized member", body = Member), (status = 404, description = "No such member"), (status = 409, description = "The member has open loans or was already erased"), (status = 401, description = "Missing or invalid credentials"), (status = 403, description = "Missing permission")),
+)]
+pub async fn erase_member(
+    req: HttpRequest,
+    repos: web::Data<AppState>,
+    user: AuthenticatedUser,
+    id: web::Path<i32>,
+) -> impl Responder {
+    if let Err(e) = user.require_anywhere(Permission::ManageMembers) {
+        return e.error_response();
+    }
+
+    let mut member_repo = repos.member_repo.lock().unwrap();
+    let current = match member_repo.get_member_by_id(&id) {
+        Ok(member) => member,
+        Err(diesel::result::Error::NotFound) => return HttpResponse::NotFound().finish(),
+        Err(e) => {
+            error!(error = ?e, "Failed to erase member"); // Hata mesajını logla
+            return HttpResponse::InternalServerError().body(format!("Failed to erase member: {}", e));
+        }
+    };
+    if let Some(erased_at) = &current.erased_at {
+        return HttpResponse::Conflict().body(format!("Member {} was already erased on {}", current.id, erased_at));
+    }
+
+    let date: String = chrono::offset::Utc::now().naive_utc().to_string();
+    drop(member_repo);
+    let result = audited(&repos, &req, &user, |tx| {
+        if tx.members().erase_member(&id, &date)? == 0 {
+            return Ok(None);
+        }
+        let member = tx.members().get_member_by_id(&id)?;
+        // The entry itself must not bring back what was just erased.
+        tx.record(AuditEvent {
+            action: "erase",
+            entity: "member",
+            entity_id: member.id.to_string(),
+            before: None,
+            after: None,
+        })?;
+        Ok(Some(member))
+    });
+    match result {
+        Ok(None) => HttpResponse::Conflict().body(format!("Member {} still has books out; they must be returned first", current.id)),
+        Ok(Some(member)) => HttpResponse::Ok().json(member),
+        Err(e) => {
+            error!(error = ?e, "Failed to erase member"); // Hata mesajını logla
+            HttpResponse::InternalServerError().body(format!("Failed to erase member: {}", e))
+        }
+    }
+}
+
 // Routes configuration
 pub fn member_routes(cfg: &mut web::ServiceConfig) {
     cfg.service(
@@ -168,19 +998,41 @@ pub fn member_routes(cfg: &mut web::ServiceConfig) {
             .route(web::post().to(create_member))
             .route(web::get().to(get_members))
     )
+    // Registered before `/members/{id}` so "duplicates" is not taken for an id.
+    .service(
+        web::resource("/members/duplicates")
+            .route(web::get().to(get_duplicate_members))
+    )
     .service(
         web::resource("/members/{id}")
             .route(web::get().to(get_member))
             .route(web::put().to(update_member))
+            .route(web::patch().to(patch_member))
             .route(web::delete().to(delete_member))
     )
+    .service(
+        web::resource("/members/{id}/status")
+            .route(web::post().to(set_member_status))
+    )
+    .service(
+        web::resource("/members/{id}/merge")
+            .route(web::post().to(merge_members))
+    )
+    .service(
+        web::resource("/members/{id}/export")
+            .route(web::get().to(export_member))
+    )
+    .service(
+        web::resource("/members/{id}/erase")
+            .route(web::post().to(erase_member))
+    )
     .service(
         web::resource("/members/{member_id}/borrowed_books")
             .route(web::get().to(get_borrowed_books))
     )
     .service(
         web::resource("/members/{member_id}/borrowed_books/{library_id}")
-            .route(web::get().to(get_borrowed_books))
+            .route(web::get().to(get_library_borrowed_books))
     )
     .service(
         web::resource("/members/{member_id}/books/{book_id}")
@@ -0,0 +1,36 @@
+use actix_web::{web, HttpResponse, Responder};
+use tracing::error;
+
+use crate::AppState;
+
+// Unauthenticated so Prometheus can scrape it; expose it on an internal address only, or turn the metrics feature off.
+#[utoipa::path(
+    get,
+    path = "/metrics",
+    tag = "operations",
+    responses((status = 200, description = "Prometheus text exposition format", content_type = "text/plain", body = String), (status = 404, description = "The metrics feature is disabled")),
+    security(()),
+)]
+pub async fn get_metrics(repos: web::Data<AppState>) -> impl Responder {
+    if !repos.features.metrics {
+        return HttpResponse::NotFound().finish();
+    }
+
+    match repos.metrics.render(&repos) {
+        Ok(body) => HttpResponse::Ok()
+            .content_type("text/plain; version=0.0.4; charset=utf-8")
+            .body(body),
+        Err(e) => {
+            error!(error = %e, "Failed to render metrics"); // Hata mesajını logla
+            HttpResponse::InternalServerError().body(format!("Failed to render metrics: {}", e))
+        }
+    }
+}
+
+// Routes configuration
+pub fn metrics_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::resource("/metrics")
+            .route(web::get().to(get_metrics))
+    );
+}
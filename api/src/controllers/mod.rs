@@ -1,3 +1,15 @@
+pub mod acquisition_controller;
+pub mod audit_controller;
+pub mod auth_controller;
 pub mod book_controller;
+pub mod calendar_controller;
+pub mod health_controller;
+pub mod job_controller;
 pub mod library_controller;
-pub mod member_controller;
\ No newline at end of file
+pub mod me_controller;
+pub mod member_controller;
+pub mod metrics_controller;
+pub mod notice_controller;
+pub mod report_controller;
+pub mod staff_controller;
+pub mod transfer_controller;
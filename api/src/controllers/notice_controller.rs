@@ -0,0 +1,79 @@
+use actix_web::{web, HttpResponse, Responder, ResponseError};
+use tracing::error;
+
+use domain::models::notice::{LoanNotice, NoticeFilter};
+use domain::models::role::Permission;
+
+use crate::auth::AuthenticatedUser;
+use crate::notices::{send_due_notices, NoticeRun};
+use crate::AppState;
+
+// Handlers
+#[utoipa::path(
+    get,
+    path = "/notices",
+    tag = "loans",
+    params(NoticeFilter),
+    responses((status = 200, description = "Sent loan notices, newest first", body = Vec<LoanNotice>), (status = 401, description = "Missing or invalid credentials"), (status = 403, description = "Missing permission")),
+)]
+pub async fn get_notices(
+    repos: web::Data<AppState>,
+    user: AuthenticatedUser,
+    filter: web::Query<NoticeFilter>,
+) -> impl Responder {
+    // Members may list the notices sent to them; anything wider needs loan rights.
+    let own = filter.member_id.is_some_and(|member_id| user.is_member(member_id));
+    if !own {
+        if let Err(e) = user.require_anywhere(Permission::ManageLoans) {
+            return e.error_response();
+        }
+    }
+
+    let mut notice_repo = repos.notice_repo.lock().unwrap();
+    match notice_repo.get_notices(&filter) {
+        Ok(notices) => HttpResponse::Ok().json(notices),
+        Err(e) => {
+            error!(error = ?e, "Failed to get notices"); // Hata mesajını logla
+            HttpResponse::InternalServerError().body(format!("Failed to get notices: {}", e))
+        }
+    }
+}
+
+// Runs a scan now instead of waiting for the scheduler, e.g. after changing templates.
+#[utoipa::path(
+    post,
+    path = "/notices/run",
+    tag = "loans",
+    responses((status = 200, description = "Notices sent and failed by this scan", body = NoticeRun), (status = 401, description = "Missing or invalid credentials"), (status = 403, description = "Missing permission")),
+)]
+pub async fn run_notices(repos: web::Data<AppState>, user: AuthenticatedUser) -> impl Responder {
+    if let Err(e) = user.require(Permission::ManageSystem, None) {
+        return e.error_response();
+    }
+
+    let state = repos.clone();
+    let now = chrono::offset::Utc::now().naive_utc();
+    match web::block(move || send_due_notices(&state, now)).await {
+        Ok(Ok(run)) => HttpResponse::Ok().json(run),
+        Ok(Err(e)) => {
+            error!(error = %e, "Failed to send notices"); // Hata mesajını logla
+            HttpResponse::InternalServerError().body(format!("Failed to send notices: {}", e))
+        }
+        Err(e) => {
+            error!(error = %e, "Failed to send notices"); // Hata mesajını logla
+            HttpResponse::InternalServerError().body(format!("Failed to send notices: {}", e))
+        }
+    }
+}
+
+// Routes configuration
+pub fn notice_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::resource("/notices")
+            .route(web::get().to(get_notices))
+    )
+    .service(
+        web::resource("/notices/run")
+            .route(web::post().to(run_notices))
+    );
+}
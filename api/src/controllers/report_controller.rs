@@ -0,0 +1,215 @@
+use actix_web::{web, HttpResponse, Responder, ResponseError};
+use chrono::NaiveDate;
+use diesel::QueryResult;
+use serde::Serialize;
+use tracing::error;
+
+use domain::models::report::{
+    Acquisition, AuthorLoans, BudgetSpending, LibraryOverdue, LibraryUtilization, MemberActivity, ReportFilter, ReportScope, TitleLoans,
+    FORMAT_CSV, FORMAT_JSON,
+};
+use domain::models::role::Permission;
+use domain::traits::ReportRepositoryTrait;
+
+use crate::auth::AuthenticatedUser;
+use crate::AppState;
+
+const DEFAULT_LIMIT: i64 = 100;
+const MAX_LIMIT: i64 = 10_000;
+
+// Turns the inclusive day range of a filter into timestamp bounds; an open end covers all history.
+fn report_scope(filter: &ReportFilter) -> Result<ReportScope, String> {
+    let parse = |name: &str, value: &str| {
+        NaiveDate::parse_from_str(value, "%Y-%m-%d").map_err(|_| format!("{} must be a date (YYYY-MM-DD)", name))
+    };
+    let from = filter.from.as_deref().map(|from| parse("from", from)).transpose()?;
+    let to = filter.to.as_deref().map(|to| parse("to", to)).transpose()?;
+    if let (Some(from), Some(to)) = (from, to) {
+        if from > to {
+            return Err("from must not be after to".to_string());
+        }
+    }
+    let limit = filter.limit.unwrap_or(DEFAULT_LIMIT);
+    if !(1..=MAX_LIMIT).contains(&limit) {
+        return Err(format!("limit must be between 1 and {}", MAX_LIMIT));
+    }
+    if let Some(format) = &filter.format {
+        if format != FORMAT_JSON && format != FORMAT_CSV {
+            return Err(format!("format must be '{}' or '{}'", FORMAT_JSON, FORMAT_CSV));
+        }
+    }
+
+    Ok(ReportScope {
+        from: from.map(|from| from.and_hms_opt(0, 0, 0).unwrap().to_string()).unwrap_or_default(),
+        until: to
+            .and_then(|to| to.succ_opt())
+            .map(|until| until.and_hms_opt(0, 0, 0).unwrap().to_string())
+            .unwrap_or_else(|| "9999-12-31 23:59:59".to_string()),
+        library_id: filter.library_id,
+        limit,
+    })
+}
+
+fn to_csv<T: Serialize>(rows: &[T]) -> Result<String, String> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    for row in rows {
+        writer.serialize(row).map_err(|e| e.to_string())?;
+    }
+    let bytes = writer.into_inner().map_err(|e| e.to_string())?;
+    String::from_utf8(bytes).map_err(|e| e.to_string())
+}
+
+// Shared by every report: checks the caller may see the requested library (or all of them), runs the query
+// and answers with JSON or, for `format=csv`, a CSV download named after the report.
+fn run_report<T, F>(repos: &AppState, user: &AuthenticatedUser, filter: &ReportFilter, name: &str, query: F) -> HttpResponse
+where
+    T: Serialize,
+    F: FnOnce(&mut dyn ReportRepositoryTrait, &ReportScope, &str) -> QueryResult<Vec<T>>,
+{
+    if let Err(e) = user.require(Permission::ViewReports, filter.library_id) {
+        return e.error_response();
+    }
+
+    let scope = match report_scope(filter) {
+        Ok(scope) => scope,
+        Err(e) => return HttpResponse::UnprocessableEntity().body(e),
+    };
+
+    let now = chrono::offset::Utc::now().naive_utc().to_string();
+    let rows = {
+        let mut report_repo = repos.report_repo.lock().unwrap();
+        match query(&mut *report_repo, &scope, &now) {
+            Ok(rows) => rows,
+            Err(e) => {
+                error!(error = ?e, report = name, "Failed to build report"); // Hata mesajını logla
+                return HttpResponse::InternalServerError().body(format!("Failed to build report: {}", e));
+            }
+        }
+    };
+
+    if filter.format.as_deref() != Some(FORMAT_CSV) {
+        return HttpResponse::Ok().json(rows);
+    }
+    match to_csv(&rows) {
+        Ok(body) => HttpResponse::Ok()
+            .content_type("text/csv; charset=utf-8")
+            .insert_header(("Content-Disposition", format!("attachment; filename=\"{}.csv\"", name)))
+            .body(body),
+        Err(e) => {
+            error!(error = %e, report = name, "Failed to export report"); // Hata mesajını logla
+            HttpResponse::InternalServerError().body(format!("Failed to export report: {}", e))
+        }
+    }
+}
+
+// Handlers
+#[utoipa::path(
+    get,
+    path = "/reports/top-titles",
+    tag = "reports",
+    params(ReportFilter),
+    responses((status = 200, description = "Most borrowed titles in the period", content((Vec<TitleLoans> = "application/json"), (String = "text/csv"))), (status = 401, description = "Missing or invalid credentials"), (status = 403, description = "Missing permission"), (status = 422, description = "Invalid filter")),
+)]
+pub async fn get_top_titles(repos: web::Data<AppState>, user: AuthenticatedUser, filter: web::Query<ReportFilter>) -> impl Responder {
+    run_report(&repos, &user, &filter, "top-titles", |repo, scope, _| repo.top_titles(scope))
+}
+
+#[utoipa::path(
+    get,
+    path = "/reports/top-authors",
+    tag = "reports",
+    params(ReportFilter),
+    responses((status = 200, description = "Most borrowed authors in the period", content((Vec<AuthorLoans> = "application/json"), (String = "text/csv"))), (status = 401, description = "Missing or invalid credentials"), (status = 403, description = "Missing permission"), (status = 422, description = "Invalid filter")),
+)]
+pub async fn get_top_authors(repos: web::Data<AppState>, user: AuthenticatedUser, filter: web::Query<ReportFilter>) -> impl Responder {
+    run_report(&repos, &user, &filter, "top-authors", |repo, scope, _| repo.top_authors(scope))
+}
+
+// The period applies to due dates here, not to borrow dates.
+#[utoipa::path(
+    get,
+    path = "/reports/overdue",
+    tag = "reports",
+    params(ReportFilter),
+    responses((status = 200, description = "Overdue and late-returned loans per library", content((Vec<LibraryOverdue> = "application/json"), (String = "text/csv"))), (status = 401, description = "Missing or invalid credentials"), (status = 403, description = "Missing permission"), (status = 422, description = "Invalid filter")),
+)]
+pub async fn get_overdue_report(repos: web::Data<AppState>, user: AuthenticatedUser, filter: web::Query<ReportFilter>) -> impl Responder {
+    run_report(&repos, &user, &filter, "overdue", |repo, scope, now| repo.overdue_by_library(scope, now))
+}
+
+#[utoipa::path(
+    get,
+    path = "/reports/utilization",
+    tag = "reports",
+    params(ReportFilter),
+    responses((status = 200, description = "Loans per copy for each library", content((Vec<LibraryUtilization> = "application/json"), (String = "text/csv"))), (status = 401, description = "Missing or invalid credentials"), (status = 403, description = "Missing permission"), (status = 422, description = "Invalid filter")),
+)]
+pub async fn get_utilization_report(repos: web::Data<AppState>, user: AuthenticatedUser, filter: web::Query<ReportFilter>) -> impl Responder {
+    run_report(&repos, &user, &filter, "utilization", |repo, scope, _| repo.utilization(scope))
+}
+
+#[utoipa::path(
+    get,
+    path = "/reports/member-activity",
+    tag = "reports",
+    params(ReportFilter),
+    responses((status = 200, description = "Loans per member in the period", content((Vec<MemberActivity> = "application/json"), (String = "text/csv"))), (status = 401, description = "Missing or invalid credentials"), (status = 403, description = "Missing permission"), (status = 422, description = "Invalid filter")),
+)]
+pub async fn get_member_activity_report(repos: web::Data<AppState>, user: AuthenticatedUser, filter: web::Query<ReportFilter>) -> impl Responder {
+    run_report(&repos, &user, &filter, "member-activity", |repo, scope, now| repo.member_activity(scope, now))
+}
+
+#[utoipa::path(
+    get,
+    path = "/reports/acquisitions",
+    tag = "reports",
+    params(ReportFilter),
+    responses((status = 200, description = "Titles added to libraries in the period", content((Vec<Acquisition> = "application/json"), (String = "text/csv"))), (status = 401, description = "Missing or invalid credentials"), (status = 403, description = "Missing permission"), (status = 422, description = "Invalid filter")),
+)]
+pub async fn get_acquisitions_report(repos: web::Data<AppState>, user: AuthenticatedUser, filter: web::Query<ReportFilter>) -> impl Responder {
+    run_report(&repos, &user, &filter, "acquisitions", |repo, scope, _| repo.acquisitions(scope))
+}
+
+// The period applies to when orders were placed; budgets are listed even without orders.
+#[utoipa::path(
+    get,
+    path = "/reports/budget-spending",
+    tag = "reports",
+    params(ReportFilter),
+    responses((status = 200, description = "Budgets against what was ordered and received per library and fiscal year", content((Vec<BudgetSpending> = "application/json"), (String = "text/csv"))), (status = 401, description = "Missing or invalid credentials"), (status = 403, description = "Missing permission"), (status = 422, description = "Invalid filter")),
+)]
+pub async fn get_budget_spending_report(repos: web::Data<AppState>, user: AuthenticatedUser, filter: web::Query<ReportFilter>) -> impl Responder {
+    run_report(&repos, &user, &filter, "budget-spending", |repo, scope, _| repo.budget_spending(scope))
+}
+
+// Routes configuration
+pub fn report_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::resource("/reports/top-titles")
+            .route(web::get().to(get_top_titles))
+    )
+    .service(
+        web::resource("/reports/top-authors")
+            .route(web::get().to(get_top_authors))
+    )
+    .service(
+        web::resource("/reports/overdue")
+            .route(web::get().to(get_overdue_report))
+    )
+    .service(
+        web::resource("/reports/utilization")
+            .route(web::get().to(get_utilization_report))
+    )
+    .service(
+        web::resource("/reports/member-activity")
+            .route(web::get().to(get_member_activity_report))
+    )
+    .service(
+        web::resource("/reports/acquisitions")
+            .route(web::get().to(get_acquisitions_report))
+    )
+    .service(
+        web::resource("/reports/budget-spending")
+            .route(web::get().to(get_budget_spending_report))
+    );
+}
@@ -0,0 +1,383 @@
+use actix_web::{web, HttpRequest, HttpResponse, Responder, ResponseError};
+use chrono::NaiveDate;
+use serde::Deserialize;
+use tracing::error;
+use utoipa::{IntoParams, ToSchema};
+
+use domain::models::role::{Permission, Role};
+use domain::models::staff::{Handover, NewStaffAssignment, StaffAssignment, STAFF_ROLES};
+use domain::traits::StaffRepositoryTrait;
+
+use crate::audit::{audited, snapshot, AuditEvent};
+use crate::auth::{AuthError, AuthenticatedUser};
+use crate::AppState;
+
+// Request yapılandırmaları
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct StaffQuery {
+    // Lists ended and future assignments too.
+    #[serde(default)]
+    history: bool,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct CreateAssignmentRequest {
+    member_id: i32,
+    // library_manager or librarian
+    role: String,
+    // YYYY-MM-DD; today when omitted.
+    #[serde(default)]
+    started_at: Option<String>,
+    // First day no longer worked (YYYY-MM-DD); open-ended when omitted.
+    #[serde(default)]
+    ended_at: Option<String>,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct UpdateAssignmentRequest {
+    started_at: String,
+    #[serde(default)]
+    ended_at: Option<String>,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Path)]
+pub struct AssignmentPath {
+    id: i32,
+    assignment_id: i32,
+}
+
+fn today() -> String {
+    chrono::offset::Utc::now().date_naive().to_string()
+}
+
+fn validate_period(started_at: &str, ended_at: Option<&str>) -> Result<(), String> {
+    let parse = |name: &str, value: &str| {
+        NaiveDate::parse_from_str(value, "%Y-%m-%d").map_err(|_| format!("{} must be a date (YYYY-MM-DD)", name))
+    };
+    let started = parse("started_at", started_at)?;
+    if let Some(ended_at) = ended_at {
+        if parse("ended_at", ended_at)? <= started {
+            return Err("ended_at must be after started_at".to_string());
+        }
+    }
+    Ok(())
+}
+
+// Librarians are assigned by the library's managers; manager assignments by administrators, as with role grants.
+fn require_staff_admin(user: &AuthenticatedUser, role: &str, library_id: i32) -> Result<(), AuthError> {
+    match Role::parse(role) {
+        Some(Role::Librarian) => user.require(Permission::ManageStaff, Some(library_id)),
+        _ => user.require(Permission::ManageSystem, None),
+    }
+}
+
+// The member has to exist and may not be erased or banned to join a library's staff, managers included.
+pub(crate) fn check_staff_member(repos: &AppState, member_id: &i32) -> Option<HttpResponse> {
+    match repos.member_repo.lock().unwrap().get_member_by_id(member_id) {
+        Ok(member) => member.staff_block().map(|block| HttpResponse::UnprocessableEntity().body(block)),
+        Err(diesel::result::Error::NotFound) => Some(HttpResponse::UnprocessableEntity().body(format!("No member {}", member_id))),
+        Err(e) => {
+            error!(error = ?e, "Failed to check staff member"); // Hata mesajını logla
+            Some(HttpResponse::InternalServerError().body(format!("Failed to check staff member: {}", e)))
+        }
+    }
+}
+
+// Another assignment of the same member and role sharing a day with the period, if any.
+fn find_overlap(repos: &AppState, assignment: (&i32, &i32, &str), started_at: &str, ended_at: Option<&str>, except: Option<i32>) -> diesel::QueryResult<Option<StaffAssignment>> {
+    let (library_id, member_id, role) = assignment;
+    let staff = repos.staff_repo.lock().unwrap().get_staff(library_id, None)?;
+    Ok(staff.into_iter().find(|other| {
+        Some(other.id) != except && other.member_id == *member_id && other.role == role && other.overlaps(started_at, ended_at)
+    }))
+}
+
+// The library's manager keeps a manager assignment until the library is handed over.
+fn is_manager_assignment(repos: &AppState, assignment: &StaffAssignment) -> diesel::QueryResult<bool> {
+    if assignment.role != Role::LibraryManager.as_str() {
+        return Ok(false);
+    }
+    let library = repos.lib_repo.lock().unwrap().get_library_by_id(&assignment.library_id)?;
+    Ok(library.manager_id == assignment.member_id && assignment.is_active(&today()))
+}
+
+// Handlers
+#[utoipa::path(
+    get,
+    path = "/libraries/{id}/staff",
+    tag = "staff",
+    params(("id" = i32, Path, description = "Library id"), StaffQuery),
+    responses((status = 200, description = "Staff assignments active today, or all of them with `history`; newest first", body = Vec<StaffAssignment>), (status = 401, description = "Missing or invalid credentials"), (status = 403, description = "Missing permission")),
+)]
+pub async fn get_staff(
+    repos: web::Data<AppState>,
+    user: AuthenticatedUser,
+    id: web::Path<i32>,
+    query: web::Query<StaffQuery>,
+) -> impl Responder {
+    if let Err(e) = user.require(Permission::ManageStaff, Some(*id)) {
+        return e.error_response();
+    }
+
+    let today = today();
+    let day = (!query.history).then_some(today.as_str());
+    match repos.staff_repo.lock().unwrap().get_staff(&id, day) {
+        Ok(staff) => HttpResponse::Ok().json(staff),
+        Err(e) => {
+            error!(error = ?e, "Failed to get staff"); // Hata mesajını logla
+            HttpResponse::InternalServerError().body(format!("Failed to get staff: {}", e))
+        }
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/libraries/{id}/staff",
+    tag = "staff",
+    params(("id" = i32, Path, description = "Library id")),
+    request_body = CreateAssignmentRequest,
+    responses((status = 201, description = "Member assigned", body = StaffAssignment), (status = 404, description = "No such library"), (status = 409, description = "The member already has this role at the library in that period"), (status = 422, description = "Invalid role, period or member"), (status = 401, description = "Missing or invalid credentials"), (status = 403, description = "Missing permission")),
+)]
+pub async fn add_staff(
+    req: HttpRequest,
+    repos: web::Data<AppState>,
+    user: AuthenticatedUser,
+    id: web::Path<i32>,
+    form: web::Json<CreateAssignmentRequest>,
+) -> impl Responder {
+    if !STAFF_ROLES.iter().any(|role| role.as_str() == form.role) {
+        return HttpResponse::UnprocessableEntity().body("role must be library_manager or librarian");
+    }
+    if let Err(e) = require_staff_admin(&user, &form.role, *id) {
+        return e.error_response();
+    }
+    let started_at = form.started_at.clone().unwrap_or_else(today);
+    if let Err(message) = validate_period(&started_at, form.ended_at.as_deref()) {
+        return HttpResponse::UnprocessableEntity().body(message);
+    }
+    match repos.lib_repo.lock().unwrap().get_library_by_id(&id) {
+        Ok(_) => {}
+        Err(diesel::result::Error::NotFound) => return HttpResponse::NotFound().finish(),
+        Err(e) => {
+            error!(error = ?e, "Failed to assign staff"); // Hata mesajını logla
+            return HttpResponse::InternalServerError().body(format!("Failed to assign staff: {}", e));
+        }
+    }
+    if let Some(response) = check_staff_member(&repos, &form.member_id) {
+        return response;
+    }
+
+    let created = find_overlap(&repos, (&id, &form.member_id, &form.role), &started_at, form.ended_at.as_deref(), None).and_then(|overlap| {
+        if overlap.is_some() {
+            return Ok(None);
+        }
+        let now = chrono::offset::Utc::now().naive_utc().to_string();
+        let new_assignment = NewStaffAssignment {
+            library_id: &id,
+            member_id: &form.member_id,
+            role: &form.role,
+            started_at: &started_at,
+            ended_at: form.ended_at.as_deref(),
+            created_at: &now,
+        };
+        audited(&repos, &req, &user, |tx| {
+            let assignment = tx.staff().add_assignment(&new_assignment)?;
+            tx.record(AuditEvent {
+                action: "create",
+                entity: "staff_assignment",
+                entity_id: assignment.id.to_string(),
+                before: None,
+                after: snapshot(&assignment),
+            })?;
+            Ok(Some(assignment))
+        })
+    });
+    match created {
+        Ok(None) => HttpResponse::Conflict().body(format!("Member {} is already {} at this library in that period", form.member_id, form.role)),
+        Ok(Some(assignment)) => HttpResponse::Created().json(assignment),
+        Err(e) => {
+            error!(error = ?e, "Failed to assign staff"); // Hata mesajını logla
+            HttpResponse::InternalServerError().body(format!("Failed to assign staff: {}", e))
+        }
+    }
+}
+
+#[utoipa::path(
+    put,
+    path = "/libraries/{id}/staff/{assignment_id}",
+    tag = "staff",
+    params(AssignmentPath),
+    request_body = UpdateAssignmentRequest,
+    responses((status = 200, description = "Period changed; the updated assignment", body = StaffAssignment), (status = 404, description = "No such assignment"), (status = 409, description = "Overlaps another assignment, or would end the current manager's"), (status = 422, description = "Invalid period"), (status = 401, description = "Missing or invalid credentials"), (status = 403, description = "Missing permission")),
+)]
+pub async fn update_staff(
+    req: HttpRequest,
+    repos: web::Data<AppState>,
+    user: AuthenticatedUser,
+    path: web::Path<AssignmentPath>,
+    form: web::Json<UpdateAssignmentRequest>,
+) -> impl Responder {
+    let current = match repos.staff_repo.lock().unwrap().get_assignment(&path.id, &path.assignment_id) {
+        Ok(assignment) => assignment,
+        Err(diesel::result::Error::NotFound) => return HttpResponse::NotFound().finish(),
+        Err(e) => {
+            error!(error = ?e, "Failed to update staff assignment"); // Hata mesajını logla
+            return HttpResponse::InternalServerError().body(format!("Failed to update staff assignment: {}", e));
+        }
+    };
+    if let Err(e) = require_staff_admin(&user, &current.role, path.id) {
+        return e.error_response();
+    }
+    if let Err(message) = validate_period(&form.started_at, form.ended_at.as_deref()) {
+        return HttpResponse::UnprocessableEntity().body(message);
+    }
+
+    let checked = is_manager_assignment(&repos, &current).and_then(|manager| {
+        let overlap = find_overlap(&repos, (&current.library_id, &current.member_id, &current.role), &form.started_at, form.ended_at.as_deref(), Some(current.id))?;
+        Ok((manager, overlap))
+    });
+    match checked {
+        Ok((true, _)) if form.ended_at.is_some() => {
+            return HttpResponse::Conflict().body(format!("Member {} manages this library; hand it over to another manager first", current.member_id));
+        }
+        Ok((_, Some(overlap))) => {
+            return HttpResponse::Conflict().body(format!("Overlaps assignment {} of the same member and role", overlap.id));
+        }
+        Ok(_) => {}
+        Err(e) => {
+            error!(error = ?e, "Failed to update staff assignment"); // Hata mesajını logla
+            return HttpResponse::InternalServerError().body(format!("Failed to update staff assignment: {}", e));
+        }
+    }
+
+    let updated = audited(&repos, &req, &user, |tx| {
+        tx.staff().set_assignment_dates(&path.id, &path.assignment_id, &form.started_at, form.ended_at.as_deref())?;
+        let assignment = tx.staff().get_assignment(&path.id, &path.assignment_id)?;
+        tx.record(AuditEvent {
+            action: "update",
+            entity: "staff_assignment",
+            entity_id: assignment.id.to_string(),
+            before: snapshot(&current),
+            after: snapshot(&assignment),
+        })?;
+        Ok(assignment)
+    });
+    match updated {
+        Ok(assignment) => HttpResponse::Ok().json(assignment),
+        Err(e) => {
+            error!(error = ?e, "Failed to update staff assignment"); // Hata mesajını logla
+            HttpResponse::InternalServerError().body(format!("Failed to update staff assignment: {}", e))
+        }
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = "/libraries/{id}/staff/{assignment_id}",
+    tag = "staff",
+    params(AssignmentPath),
+    responses((status = 204, description = "Assignment ended today; it stays in the history"), (status = 404, description = "No such assignment"), (status = 409, description = "Already ended, or the current manager's assignment"), (status = 401, description = "Missing or invalid credentials"), (status = 403, description = "Missing permission")),
+)]
+pub async fn end_staff(
+    req: HttpRequest,
+    repos: web::Data<AppState>,
+    user: AuthenticatedUser,
+    path: web::Path<AssignmentPath>,
+) -> impl Responder {
+    let current = match repos.staff_repo.lock().unwrap().get_assignment(&path.id, &path.assignment_id) {
+        Ok(assignment) => assignment,
+        Err(diesel::result::Error::NotFound) => return HttpResponse::NotFound().finish(),
+        Err(e) => {
+            error!(error = ?e, "Failed to end staff assignment"); // Hata mesajını logla
+            return HttpResponse::InternalServerError().body(format!("Failed to end staff assignment: {}", e));
+        }
+    };
+    if let Err(e) = require_staff_admin(&user, &current.role, path.id) {
+        return e.error_response();
+    }
+
+    let today = today();
+    if current.ended_at.as_deref().is_some_and(|ended| ended <= today.as_str()) {
+        return HttpResponse::Conflict().body("The assignment has already ended");
+    }
+    match is_manager_assignment(&repos, &current) {
+        Ok(true) => {
+            return HttpResponse::Conflict().body(format!("Member {} manages this library; hand it over to another manager first", current.member_id));
+        }
+        Ok(false) => {}
+        Err(e) => {
+            error!(error = ?e, "Failed to end staff assignment"); // Hata mesajını logla
+            return HttpResponse::InternalServerError().body(format!("Failed to end staff assignment: {}", e));
+        }
+    }
+
+    // An assignment that has not started yet ends before it begins.
+    let ended_at = today.max(current.started_at.clone());
+    let ended = audited(&repos, &req, &user, |tx| {
+        let ended = tx.staff().set_assignment_dates(&path.id, &path.assignment_id, &current.started_at, Some(&ended_at))?;
+        if ended == 1 {
+            let after = StaffAssignment { ended_at: Some(ended_at.clone()), ..current.clone() };
+            tx.record(AuditEvent {
+                action: "update",
+                entity: "staff_assignment",
+                entity_id: current.id.to_string(),
+                before: snapshot(&current),
+                after: snapshot(&after),
+            })?;
+        }
+        Ok(ended)
+    });
+    match ended {
+        Ok(0) => HttpResponse::NotFound().finish(),
+        Ok(_) => HttpResponse::NoContent().finish(),
+        Err(e) => {
+            error!(error = ?e, "Failed to end staff assignment"); // Hata mesajını logla
+            HttpResponse::InternalServerError().body(format!("Failed to end staff assignment: {}", e))
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/libraries/{id}/handovers",
+    tag = "staff",
+    params(("id" = i32, Path, description = "Library id")),
+    responses((status = 200, description = "Changes of the library's manager, newest first", body = Vec<Handover>), (status = 401, description = "Missing or invalid credentials"), (status = 403, description = "Missing permission")),
+)]
+pub async fn get_handovers(
+    repos: web::Data<AppState>,
+    user: AuthenticatedUser,
+    id: web::Path<i32>,
+) -> impl Responder {
+    if let Err(e) = user.require(Permission::ManageStaff, Some(*id)) {
+        return e.error_response();
+    }
+
+    match repos.staff_repo.lock().unwrap().get_handovers(&id) {
+        Ok(handovers) => HttpResponse::Ok().json(handovers),
+        Err(e) => {
+            error!(error = ?e, "Failed to get handovers"); // Hata mesajını logla
+            HttpResponse::InternalServerError().body(format!("Failed to get handovers: {}", e))
+        }
+    }
+}
+
+// Routes configuration
+pub fn staff_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::resource("/libraries/{id}/staff")
+            .route(web::get().to(get_staff))
+            .route(web::post().to(add_staff))
+    )
+    .service(
+        web::resource("/libraries/{id}/staff/{assignment_id}")
+            .route(web::put().to(update_staff))
+            .route(web::delete().to(end_staff))
+    )
+    .service(
+        web::resource("/libraries/{id}/handovers")
+            .route(web::get().to(get_handovers))
+    );
+}
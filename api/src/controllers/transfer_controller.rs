@@ -0,0 +1,379 @@
+use actix_web::{web, HttpRequest, HttpResponse, Responder, ResponseError};
+use serde::{Deserialize, Serialize};
+use tracing::error;
+use utoipa::{IntoParams, ToSchema};
+
+use domain::models::role::Permission;
+use domain::models::transfer::{
+    can_transition, NewTransfer, Transfer, TransferFilter, TRANSFER_APPROVED, TRANSFER_CANCELLED, TRANSFER_IN_TRANSIT,
+    TRANSFER_RECEIVED, TRANSFER_REQUESTED, TRANSFER_RETURNED, TRANSFER_STATUSES,
+};
+use domain::traits::TransferRepositoryTrait;
+
+use crate::audit::{audited, snapshot, AuditEvent};
+use crate::auth::{AuthError, AuthenticatedUser};
+use crate::versioning::CURRENT_VERSION;
+use crate::AppState;
+
+// Request yapılandırmaları
+#[derive(Deserialize, ToSchema)]
+pub struct CreateTransferRequest {
+    book_id: i32,
+    // The lending library.
+    from_library_id: i32,
+    // The borrowing library; the caller needs holdings rights here.
+    to_library_id: i32,
+    #[serde(default = "default_quantity")]
+    quantity: i32,
+    // A member waiting for the copies, if any.
+    #[serde(default)]
+    member_id: Option<i32>,
+    #[serde(default)]
+    note: Option<String>,
+}
+
+impl CreateTransferRequest {
+    fn validate(&self) -> Result<(), String> {
+        if self.quantity < 1 {
+            return Err("quantity must be at least 1".to_string());
+        }
+        if self.from_library_id == self.to_library_id {
+            return Err("from_library_id and to_library_id must differ".to_string());
+        }
+        Ok(())
+    }
+}
+
+fn default_quantity() -> i32 {
+    1
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Path)]
+pub struct TransferPath {
+    id: i32,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Path)]
+pub struct InTransitPath {
+    library_id: i32,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct InTransitResponse {
+    // Shipped to this library and not received yet.
+    incoming: Vec<Transfer>,
+    // Shipped from this library and not received yet.
+    outgoing: Vec<Transfer>,
+}
+
+// Which library's staff may take a step.
+enum Side {
+    From,
+    To,
+    Either,
+}
+
+fn allowed(user: &AuthenticatedUser, transfer: &Transfer, side: Side) -> Result<(), AuthError> {
+    let from = || user.require(Permission::ManageHoldings, Some(transfer.from_library_id));
+    let to = || user.require(Permission::ManageHoldings, Some(transfer.to_library_id));
+    match side {
+        Side::From => from(),
+        Side::To => to(),
+        Side::Either => from().or_else(|_| to()),
+    }
+}
+
+// Moves a transfer to `status` when the caller may and the current status allows it.
+async fn advance(
+    req: HttpRequest,
+    repos: web::Data<AppState>,
+    user: AuthenticatedUser,
+    id: i32,
+    status: &str,
+    side: Side,
+) -> HttpResponse {
+    let mut transfer_repo = repos.transfer_repo.lock().unwrap();
+    let before = match transfer_repo.get_transfer(&id) {
+        Ok(transfer) => transfer,
+        Err(diesel::result::Error::NotFound) => return HttpResponse::NotFound().finish(),
+        Err(e) => {
+            error!(error = ?e, "Failed to get transfer"); // Hata mesajını logla
+            return HttpResponse::InternalServerError().body(format!("Failed to get transfer: {}", e));
+        }
+    };
+    if let Err(e) = allowed(&user, &before, side) {
+        return e.error_response();
+    }
+    if !can_transition(&before.status, status) {
+        return HttpResponse::Conflict().body(format!("Transfer is {}", before.status));
+    }
+
+    let at: String = chrono::offset::Utc::now().naive_utc().to_string();
+    drop(transfer_repo);
+    let advanced = audited(&repos, &req, &user, |tx| {
+        let updated = tx.transfers().advance_transfer(&id, &before.status, status, &at)?;
+        let after = tx.transfers().get_transfer(&id)?;
+        if updated == 1 {
+            tx.record(AuditEvent {
+                action: status,
+                entity: "transfer",
+                entity_id: id.to_string(),
+                before: snapshot(&before),
+                after: snapshot(&after),
+            })?;
+        }
+        Ok((updated, after))
+    });
+    match advanced {
+        Ok((0, after)) if after.status != before.status => HttpResponse::Conflict().body(format!("Transfer is {}", after.status)),
+        Ok((0, _)) => HttpResponse::Conflict().body("Not enough copies available"),
+        Ok((_, after)) => HttpResponse::Ok().json(after),
+        Err(e) => {
+            error!(error = ?e, "Failed to update transfer"); // Hata mesajını logla
+            HttpResponse::InternalServerError().body(format!("Failed to update transfer: {}", e))
+        }
+    }
+}
+
+// Handlers
+#[utoipa::path(
+    post,
+    path = "/transfers",
+    tag = "transfers",
+    request_body = CreateTransferRequest,
+    responses((status = 201, description = "Requested; Location points at the new transfer", body = Transfer), (status = 422, description = "Invalid transfer or the lending library does not hold the book"), (status = 401, description = "Missing or invalid credentials"), (status = 403, description = "Missing permission")),
+)]
+pub async fn create_transfer(
+    req: HttpRequest,
+    repos: web::Data<AppState>,
+    user: AuthenticatedUser,
+    form: web::Json<CreateTransferRequest>,
+) -> impl Responder {
+    if let Err(e) = user.require(Permission::ManageHoldings, Some(form.to_library_id)) {
+        return e.error_response();
+    }
+    if let Err(message) = form.validate() {
+        return HttpResponse::UnprocessableEntity().body(message);
+    }
+
+    let held = repos.book_repo.lock().unwrap().get_books_by_library_id(&form.from_library_id);
+    match held {
+        Ok(books) if books.iter().any(|book| book.id == form.book_id) => {}
+        Ok(_) => {
+            return HttpResponse::UnprocessableEntity()
+                .body(format!("Library {} does not hold book {}", form.from_library_id, form.book_id))
+        }
+        Err(e) => {
+            error!(error = ?e, "Failed to get library books"); // Hata mesajını logla
+            return HttpResponse::InternalServerError().body(format!("Failed to get library books: {}", e));
+        }
+    }
+
+    let requested_at: String = chrono::offset::Utc::now().naive_utc().to_string();
+    let new_transfer = NewTransfer {
+        book_id: &form.book_id,
+        from_library_id: &form.from_library_id,
+        to_library_id: &form.to_library_id,
+        member_id: form.member_id.as_ref(),
+        quantity: &form.quantity,
+        status: TRANSFER_REQUESTED,
+        note: form.note.as_deref(),
+        requested_by: Some(&user.id),
+        requested_at: requested_at.as_str(),
+    };
+
+    let result = audited(&repos, &req, &user, |tx| {
+        let created = tx.transfers().create_transfer(&new_transfer)?;
+        tx.record(AuditEvent {
+            action: "create",
+            entity: "transfer",
+            entity_id: created.id.to_string(),
+            before: None,
+            after: snapshot(&created),
+        })?;
+        Ok(created)
+    });
+    match result {
+        Ok(created) => HttpResponse::Created()
+            .insert_header(("Location", format!("{}/transfers/{}", CURRENT_VERSION, created.id)))
+            .json(created),
+        Err(e) => {
+            error!(error = ?e, "Failed to create transfer"); // Hata mesajını logla
+            HttpResponse::InternalServerError().body(format!("Failed to create transfer: {}", e))
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/transfers",
+    tag = "transfers",
+    params(TransferFilter),
+    responses((status = 200, description = "Transfers, newest first", body = Vec<Transfer>), (status = 422, description = "Unknown status"), (status = 401, description = "Missing or invalid credentials"), (status = 403, description = "Missing permission")),
+)]
+pub async fn get_transfers(repos: web::Data<AppState>, user: AuthenticatedUser, filter: web::Query<TransferFilter>) -> impl Responder {
+    if let Err(e) = user.require(Permission::ManageHoldings, filter.library_id) {
+        return e.error_response();
+    }
+    if let Some(status) = &filter.status {
+        if !TRANSFER_STATUSES.contains(&status.as_str()) {
+            return HttpResponse::UnprocessableEntity().body(format!("status must be one of {}", TRANSFER_STATUSES.join(", ")));
+        }
+    }
+
+    let filter = TransferFilter { limit: filter.limit.or(Some(100)), ..filter.into_inner() };
+    let mut transfer_repo = repos.transfer_repo.lock().unwrap();
+    match transfer_repo.get_transfers(&filter) {
+        Ok(transfers) => HttpResponse::Ok().json(transfers),
+        Err(e) => {
+            error!(error = ?e, "Failed to get transfers"); // Hata mesajını logla
+            HttpResponse::InternalServerError().body(format!("Failed to get transfers: {}", e))
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/transfers/{id}",
+    tag = "transfers",
+    params(TransferPath),
+    responses((status = 200, description = "The transfer", body = Transfer), (status = 404, description = "No such transfer"), (status = 401, description = "Missing or invalid credentials"), (status = 403, description = "Missing permission")),
+)]
+pub async fn get_transfer(repos: web::Data<AppState>, user: AuthenticatedUser, path: web::Path<TransferPath>) -> impl Responder {
+    let mut transfer_repo = repos.transfer_repo.lock().unwrap();
+    match transfer_repo.get_transfer(&path.id) {
+        Ok(transfer) => match allowed(&user, &transfer, Side::Either) {
+            Ok(()) => HttpResponse::Ok().json(transfer),
+            Err(e) => e.error_response(),
+        },
+        Err(diesel::result::Error::NotFound) => HttpResponse::NotFound().finish(),
+        Err(e) => {
+            error!(error = ?e, "Failed to get transfer"); // Hata mesajını logla
+            HttpResponse::InternalServerError().body(format!("Failed to get transfer: {}", e))
+        }
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/transfers/{id}/approve",
+    tag = "transfers",
+    params(TransferPath),
+    responses((status = 200, description = "Approved by the lending library", body = Transfer), (status = 404, description = "No such transfer"), (status = 409, description = "The transfer is not requested"), (status = 401, description = "Missing or invalid credentials"), (status = 403, description = "Missing permission")),
+)]
+pub async fn approve_transfer(req: HttpRequest, repos: web::Data<AppState>, user: AuthenticatedUser, path: web::Path<TransferPath>) -> impl Responder {
+    advance(req, repos, user, path.id, TRANSFER_APPROVED, Side::From).await
+}
+
+#[utoipa::path(
+    post,
+    path = "/transfers/{id}/ship",
+    tag = "transfers",
+    params(TransferPath),
+    responses((status = 200, description = "Shipped; the copies leave the lending library's stock", body = Transfer), (status = 404, description = "No such transfer"), (status = 409, description = "The transfer is not approved or too few copies are available"), (status = 401, description = "Missing or invalid credentials"), (status = 403, description = "Missing permission")),
+)]
+pub async fn ship_transfer(req: HttpRequest, repos: web::Data<AppState>, user: AuthenticatedUser, path: web::Path<TransferPath>) -> impl Responder {
+    advance(req, repos, user, path.id, TRANSFER_IN_TRANSIT, Side::From).await
+}
+
+#[utoipa::path(
+    post,
+    path = "/transfers/{id}/receive",
+    tag = "transfers",
+    params(TransferPath),
+    responses((status = 200, description = "Received; the copies join the borrowing library's stock", body = Transfer), (status = 404, description = "No such transfer"), (status = 409, description = "The transfer is not in transit"), (status = 401, description = "Missing or invalid credentials"), (status = 403, description = "Missing permission")),
+)]
+pub async fn receive_transfer(req: HttpRequest, repos: web::Data<AppState>, user: AuthenticatedUser, path: web::Path<TransferPath>) -> impl Responder {
+    advance(req, repos, user, path.id, TRANSFER_RECEIVED, Side::To).await
+}
+
+#[utoipa::path(
+    post,
+    path = "/transfers/{id}/return",
+    tag = "transfers",
+    params(TransferPath),
+    responses((status = 200, description = "Returned; the copies go back to the lending library's stock", body = Transfer), (status = 404, description = "No such transfer"), (status = 409, description = "The transfer is not received or its copies are on loan"), (status = 401, description = "Missing or invalid credentials"), (status = 403, description = "Missing permission")),
+)]
+pub async fn return_transfer(req: HttpRequest, repos: web::Data<AppState>, user: AuthenticatedUser, path: web::Path<TransferPath>) -> impl Responder {
+    advance(req, repos, user, path.id, TRANSFER_RETURNED, Side::To).await
+}
+
+#[utoipa::path(
+    post,
+    path = "/transfers/{id}/cancel",
+    tag = "transfers",
+    params(TransferPath),
+    responses((status = 200, description = "Cancelled before shipping", body = Transfer), (status = 404, description = "No such transfer"), (status = 409, description = "The transfer has already shipped"), (status = 401, description = "Missing or invalid credentials"), (status = 403, description = "Missing permission")),
+)]
+pub async fn cancel_transfer(req: HttpRequest, repos: web::Data<AppState>, user: AuthenticatedUser, path: web::Path<TransferPath>) -> impl Responder {
+    advance(req, repos, user, path.id, TRANSFER_CANCELLED, Side::Either).await
+}
+
+#[utoipa::path(
+    get,
+    path = "/libraries/{library_id}/in-transit",
+    tag = "transfers",
+    params(InTransitPath),
+    responses((status = 200, description = "Copies shipped to or from the library and not received yet", body = InTransitResponse), (status = 401, description = "Missing or invalid credentials"), (status = 403, description = "Missing permission")),
+)]
+pub async fn get_in_transit(repos: web::Data<AppState>, user: AuthenticatedUser, path: web::Path<InTransitPath>) -> impl Responder {
+    if let Err(e) = user.require(Permission::ManageHoldings, Some(path.library_id)) {
+        return e.error_response();
+    }
+
+    let filter = TransferFilter {
+        library_id: Some(path.library_id),
+        status: Some(TRANSFER_IN_TRANSIT.to_string()),
+        ..TransferFilter::default()
+    };
+    let mut transfer_repo = repos.transfer_repo.lock().unwrap();
+    match transfer_repo.get_transfers(&filter) {
+        Ok(transfers) => {
+            let (incoming, outgoing) = transfers.into_iter().partition(|transfer| transfer.to_library_id == path.library_id);
+            HttpResponse::Ok().json(InTransitResponse { incoming, outgoing })
+        }
+        Err(e) => {
+            error!(error = ?e, "Failed to get transfers in transit"); // Hata mesajını logla
+            HttpResponse::InternalServerError().body(format!("Failed to get transfers in transit: {}", e))
+        }
+    }
+}
+
+// Routes configuration
+pub fn transfer_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::resource("/transfers")
+            .route(web::post().to(create_transfer))
+            .route(web::get().to(get_transfers))
+    )
+    .service(
+        web::resource("/transfers/{id}")
+            .route(web::get().to(get_transfer))
+    )
+    .service(
+        web::resource("/transfers/{id}/approve")
+            .route(web::post().to(approve_transfer))
+    )
+    .service(
+        web::resource("/transfers/{id}/ship")
+            .route(web::post().to(ship_transfer))
+    )
+    .service(
+        web::resource("/transfers/{id}/receive")
+            .route(web::post().to(receive_transfer))
+    )
+    .service(
+        web::resource("/transfers/{id}/return")
+            .route(web::post().to(return_transfer))
+    )
+    .service(
+        web::resource("/transfers/{id}/cancel")
+            .route(web::post().to(cancel_transfer))
+    )
+    .service(
+        web::resource("/libraries/{library_id}/in-transit")
+            .route(web::get().to(get_in_transit))
+    );
+}
@@ -0,0 +1,51 @@
+use actix_web::http::header::{self, EntityTag, ETag, Header, IfMatch, IfNoneMatch};
+use actix_web::{HttpRequest, HttpResponse};
+
+// Entity tags are the row version, so any successful write changes them.
+pub fn entity_tag(version: i32) -> EntityTag {
+    EntityTag::new_strong(version.to_string())
+}
+
+// Returns the response to send instead when the request's If-Match does not name the current version;
+// a missing header is rejected and `*` accepts whatever is current.
+pub fn check_if_match(req: &HttpRequest, current_version: i32) -> Option<HttpResponse> {
+    if !req.headers().contains_key(header::IF_MATCH) {
+        return Some(HttpResponse::PreconditionRequired().body("If-Match header is required"));
+    }
+
+    match IfMatch::parse(req) {
+        Ok(IfMatch::Any) => None,
+        Ok(IfMatch::Items(tags)) => {
+            let current = entity_tag(current_version);
+            if tags.iter().any(|tag| tag.strong_eq(&current)) {
+                None
+            } else {
+                Some(HttpResponse::PreconditionFailed()
+                    .insert_header(ETag(current))
+                    .body("Resource has been modified; fetch it again and retry with the new ETag"))
+            }
+        }
+        Err(_) => Some(HttpResponse::BadRequest().body("Malformed If-Match header")),
+    }
+}
+
+// Used when the versioned write itself matched no row because another writer got there first.
+pub fn precondition_failed() -> HttpResponse {
+    HttpResponse::PreconditionFailed().body("Resource has been modified; fetch it again and retry with the new ETag")
+}
+
+// Returns a 304 response when the client already holds the current version.
+pub fn not_modified(req: &HttpRequest, current_version: i32) -> Option<HttpResponse> {
+    let current = entity_tag(current_version);
+    let matches = match IfNoneMatch::parse(req).ok() {
+        Some(IfNoneMatch::Any) => true,
+        Some(IfNoneMatch::Items(tags)) => tags.iter().any(|tag| tag.weak_eq(&current)),
+        None => false,
+    };
+
+    if matches {
+        Some(HttpResponse::NotModified().insert_header(ETag(current)).finish())
+    } else {
+        None
+    }
+}
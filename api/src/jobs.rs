@@ -0,0 +1,292 @@
+use std::collections::BTreeMap;
+use std::str::FromStr;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::time::Duration;
+
+use actix_web::web;
+use chrono::{DateTime, NaiveDateTime, Utc};
+use cron::Schedule;
+
+use domain::models::job::{JobRun, NewJobRun, STATUS_FAILED, STATUS_RUNNING, STATUS_SUCCEEDED, TRIGGER_SCHEDULE};
+use domain::traits::MemberRepositoryTrait;
+use infrastructure::checkpoint_wal;
+use infrastructure::config::{JobsConfig, JOB_SCHEDULE_OFF};
+use infrastructure::security::generate_token;
+
+use crate::audit::{audited_by_system, snapshot, AuditEvent};
+use crate::notices::send_due_notices;
+use crate::AppState;
+
+// A unit of periodic work. `run` blocks and returns a one-line summary for the run record.
+pub struct Job {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub run: fn(&AppState, NaiveDateTime) -> Result<String, String>,
+}
+
+// Every job the scheduler knows; default schedules live in `infrastructure::config::JOBS` under the same names.
+pub const JOBS: &[Job] = &[
+    Job {
+        name: "loan_notices",
+        description: "Send due-date reminders and overdue notices",
+        run: run_loan_notices,
+    },
+    Job {
+        name: "member_status",
+        description: "Lift suspensions that ended and expire memberships past their last day",
+        run: run_member_status,
+    },
+    Job {
+        name: "loan_history",
+        description: "Unlink returned loans from their members once the retention period has passed",
+        run: run_loan_history,
+    },
+    Job {
+        name: "purge_refresh_tokens",
+        description: "Delete expired and revoked refresh tokens",
+        run: run_purge_refresh_tokens,
+    },
+    Job {
+        name: "purge_job_runs",
+        description: "Delete job runs older than jobs.keep_runs_days",
+        run: run_purge_job_runs,
+    },
+    Job {
+        name: "wal_checkpoint",
+        description: "Fold the SQLite write-ahead log back into the database file",
+        run: run_wal_checkpoint,
+    },
+];
+
+pub fn find_job(name: &str) -> Option<&'static Job> {
+    JOBS.iter().find(|job| job.name == name)
+}
+
+fn run_loan_notices(state: &AppState, now: NaiveDateTime) -> Result<String, String> {
+    if !state.features.notifications {
+        return Ok("skipped: notifications are disabled".to_string());
+    }
+
+    let run = send_due_notices(state, now)?;
+    Ok(format!("{} sent, {} failed", run.sent, run.failed))
+}
+
+fn run_member_status(state: &AppState, now: NaiveDateTime) -> Result<String, String> {
+    let today = now.date().to_string();
+    let refreshed = audited_by_system(state, "member_status", |tx| {
+        // The members whose stored status is about to catch up with the effective one.
+        let due: Vec<_> = tx.members().get_members()?
+            .into_iter()
+            .filter(|member| member.effective_status(&today) != member.status)
+            .collect();
+        let refreshed = tx.members().refresh_member_statuses(&today)?;
+        for before in due {
+            let after = tx.members().get_member_by_id(&before.id)?;
+            tx.record(AuditEvent {
+                action: "set_status",
+                entity: "member",
+                entity_id: before.id.to_string(),
+                before: snapshot(&before),
+                after: snapshot(&after),
+            })?;
+        }
+        Ok(refreshed)
+    });
+    let (lifted, expired) = refreshed.map_err(|e| e.to_string())?;
+    Ok(format!("{} suspensions lifted, {} memberships expired", lifted, expired))
+}
+
+fn run_loan_history(state: &AppState, now: NaiveDateTime) -> Result<String, String> {
+    let anonymized = audited_by_system(state, "loan_history", |tx| {
+        let loan_ids = tx.members().anonymize_loans(&now.format("%Y-%m-%d %H:%M:%S").to_string(), &state.loans.history_retention_days)?;
+        // The entries name the loan only; recording who had it would undo the anonymization.
+        for loan_id in &loan_ids {
+            tx.record(AuditEvent {
+                action: "anonymize",
+                entity: "loan",
+                entity_id: loan_id.to_string(),
+                before: None,
+                after: None,
+            })?;
+        }
+        Ok(loan_ids.len())
+    });
+    Ok(format!("{} loans anonymized", anonymized.map_err(|e| e.to_string())?))
+}
+
+fn run_purge_refresh_tokens(state: &AppState, now: NaiveDateTime) -> Result<String, String> {
+    let removed = state.user_repo.lock().unwrap().purge_refresh_tokens(&now.to_string()).map_err(|e| e.to_string())?;
+    Ok(format!("{} removed", removed))
+}
+
+fn run_purge_job_runs(state: &AppState, now: NaiveDateTime) -> Result<String, String> {
+    let before = (now - chrono::Duration::days(state.jobs.keep_runs_days)).to_string();
+    let removed = state.job_repo.lock().unwrap().purge_runs(&before).map_err(|e| e.to_string())?;
+    Ok(format!("{} removed", removed))
+}
+
+fn run_wal_checkpoint(state: &AppState, _now: NaiveDateTime) -> Result<String, String> {
+    checkpoint_wal(&state.pool)?;
+    Ok("checkpointed".to_string())
+}
+
+// Identifies this process in leases and run records: host, pid and a random suffix against pid reuse.
+pub fn instance_id() -> String {
+    let host = std::env::var("HOSTNAME").unwrap_or_else(|_| "localhost".to_string());
+    format!("{}-{}-{}", host, std::process::id(), &generate_token()[..8])
+}
+
+// The parsed schedule of `job`, or None when it is turned off. Schedules are checked at startup.
+pub fn job_schedule(config: &JobsConfig, job: &str) -> Option<Schedule> {
+    config
+        .schedule(job)
+        .filter(|schedule| *schedule != JOB_SCHEDULE_OFF)
+        .and_then(|schedule| Schedule::from_str(schedule).ok())
+}
+
+pub enum JobOutcome {
+    Finished(Box<JobRun>),
+    // Another run of the job holds the lease.
+    Busy,
+    // Another instance already ran this scheduled time.
+    AlreadyRan,
+}
+
+// Runs `job` once under its lease and records the run. Only one run of a job holds the lease at a time, on any
+// instance; the lease is renewed while the job runs, and one left by a crashed instance expires after
+// jobs.lease_seconds. Blocking: run it off the async executor.
+pub fn run_job(state: &AppState, job: &Job, trigger: &str, scheduled_for: Option<&str>) -> Result<JobOutcome, String> {
+    let now = Utc::now().naive_utc();
+    // Each run holds the lease under its own name, so it can only ever renew or release its own lease.
+    let holder = format!("{}/{}", state.instance, &generate_token()[..8]);
+    let acquired = state
+        .job_repo
+        .lock()
+        .unwrap()
+        .acquire_lease(job.name, &holder, &now.to_string(), &lease_until(state, now))
+        .map_err(|e| e.to_string())?;
+    if !acquired {
+        return Ok(JobOutcome::Busy);
+    }
+
+    let (stop, stopped) = mpsc::channel::<()>();
+    let outcome = std::thread::scope(|scope| {
+        scope.spawn(|| keep_lease(state, job, &holder, stopped));
+        let outcome = run_leased(state, job, trigger, scheduled_for, now);
+        drop(stop);
+        outcome
+    });
+    if let Err(e) = state.job_repo.lock().unwrap().release_lease(job.name, &holder) {
+        tracing::error!(error = ?e, job = job.name, "Failed to release job lease");
+    }
+    outcome
+}
+
+fn lease_until(state: &AppState, now: NaiveDateTime) -> String {
+    (now + chrono::Duration::seconds(state.jobs.lease_seconds as i64)).to_string()
+}
+
+// Extends the lease every third of jobs.lease_seconds until `stop` hangs up, so runs longer than the lease
+// keep it.
+fn keep_lease(state: &AppState, job: &Job, holder: &str, stop: Receiver<()>) {
+    let interval = Duration::from_secs((state.jobs.lease_seconds / 3).max(1));
+    while let Err(RecvTimeoutError::Timeout) = stop.recv_timeout(interval) {
+        let until = lease_until(state, Utc::now().naive_utc());
+        match state.job_repo.lock().unwrap().renew_lease(job.name, holder, &until) {
+            Ok(true) => {}
+            Ok(false) => {
+                tracing::warn!(job = job.name, "Job lease was taken over; the job may now run twice");
+                return;
+            }
+            Err(e) => tracing::error!(error = ?e, job = job.name, "Failed to renew job lease"),
+        }
+    }
+}
+
+fn run_leased(
+    state: &AppState,
+    job: &Job,
+    trigger: &str,
+    scheduled_for: Option<&str>,
+    now: NaiveDateTime,
+) -> Result<JobOutcome, String> {
+    let started_at = now.to_string();
+    let new_run = NewJobRun {
+        job: job.name,
+        trigger,
+        scheduled_for,
+        instance: &state.instance,
+        status: STATUS_RUNNING,
+        started_at: &started_at,
+    };
+    let Some(run) = state.job_repo.lock().unwrap().start_run(&new_run).map_err(|e| e.to_string())? else {
+        return Ok(JobOutcome::AlreadyRan);
+    };
+
+    let result = (job.run)(state, now);
+    let (status, output, error) = match &result {
+        Ok(output) => (STATUS_SUCCEEDED, Some(output.as_str()), None),
+        Err(e) => (STATUS_FAILED, None, Some(e.as_str())),
+    };
+    match &result {
+        Ok(output) => tracing::info!(job = job.name, trigger, output = %output, "Job finished"),
+        Err(e) => tracing::error!(job = job.name, trigger, error = %e, "Job failed"),
+    }
+
+    let mut job_repo = state.job_repo.lock().unwrap();
+    job_repo.finish_run(&run.id, status, output, error).map_err(|e| e.to_string())?;
+    job_repo.get_run(&run.id).map(|run| JobOutcome::Finished(Box::new(run))).map_err(|e| e.to_string())
+}
+
+// Wakes at the next scheduled time of any enabled job and runs the jobs due, until shutdown. Every instance
+// runs the scheduler; the unique (job, scheduled_for) run record makes sure only one of them runs each slot.
+pub fn start_job_scheduler(state: web::Data<AppState>) {
+    if !state.features.jobs {
+        return;
+    }
+
+    let schedules: BTreeMap<&'static str, Schedule> =
+        JOBS.iter().filter_map(|job| job_schedule(&state.jobs, job.name).map(|schedule| (job.name, schedule))).collect();
+    if schedules.is_empty() {
+        return;
+    }
+
+    let mut stop = state.background.stop_signal();
+    let task_state = state.clone();
+    state.background.spawn("jobs", async move {
+        let mut next: BTreeMap<&'static str, DateTime<Utc>> = schedules
+            .iter()
+            .filter_map(|(name, schedule)| schedule.upcoming(Utc).next().map(|at| (*name, at)))
+            .collect();
+
+        while let Some(wake_at) = next.values().min().copied() {
+            let wait = (wake_at - Utc::now()).to_std().unwrap_or(Duration::ZERO);
+            tokio::select! {
+                _ = tokio::time::sleep(wait) => {}
+                _ = stop.changed() => break,
+            }
+
+            let now = Utc::now();
+            let due: Vec<(&'static str, DateTime<Utc>)> =
+                next.iter().filter(|(_, at)| **at <= now).map(|(name, at)| (*name, *at)).collect();
+            for (name, at) in due {
+                // Slots missed while a long job ran are skipped rather than run back to back.
+                match schedules[name].after(&now).next() {
+                    Some(following) => next.insert(name, following),
+                    None => next.remove(name),
+                };
+
+                let job_state = task_state.clone();
+                let scheduled_for = at.naive_utc().to_string();
+                let job = find_job(name).expect("scheduled job is in JOBS");
+                let run = tokio::task::spawn_blocking(move || run_job(&job_state, job, TRIGGER_SCHEDULE, Some(&scheduled_for)));
+                match run.await {
+                    Ok(Ok(JobOutcome::Busy)) => tracing::debug!(job = name, "Job is running elsewhere; skipped"),
+                    Ok(Ok(_)) => {}
+                    Ok(Err(e)) => tracing::error!(job = name, error = %e, "Failed to run job"),
+                    Err(e) => tracing::error!(job = name, error = %e, "Job panicked"),
+                }
+            }
+        }
+    });
+}
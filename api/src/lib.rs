@@ -1,32 +1,168 @@
 use std::sync::{Arc, Mutex};
-use domain::traits::{BookRepositoryTrait, LibraryRepositoryTrait, MemberRepositoryTrait};
+use actix_web::middleware::from_fn;
+use actix_web::web;
+use auth::AuthSettings;
+use domain::models::role::Role;
+use domain::models::user::ACCOUNT_STAFF;
+use diesel::r2d2::{ConnectionManager, Pool};
+use diesel::sqlite::SqliteConnection;
+use domain::traits::{AcquisitionRepositoryTrait, AuditRepositoryTrait, BookRepositoryTrait, CalendarRepositoryTrait, JobRepositoryTrait, LibraryRepositoryTrait, MemberRepositoryTrait, NoticeRepositoryTrait, Notifier, ReportRepositoryTrait, StaffRepositoryTrait, StatsRepositoryTrait, TransferRepositoryTrait, UserRepositoryTrait};
+use infrastructure::repositories::acquisition_repository::AcquisitionRepository;
+use infrastructure::repositories::audit_repository::AuditRepository;
 use infrastructure::repositories::book_repository::BookRepository;
+use infrastructure::config::{ApiConfig, AuthConfig, Config, FeaturesConfig, JobsConfig, LoansConfig, NotificationsConfig};
 use infrastructure::establish_connection;
-use infrastructure::repositories::create_tables;
+use infrastructure::migrations::run_migrations;
+use infrastructure::notifications::build_notifier;
+use infrastructure::repositories::job_repository::JobRepository;
 use infrastructure::repositories::library_repository::LibraryRepository;
 use infrastructure::repositories::member_repository::MemberRepository;
+use infrastructure::repositories::notice_repository::NoticeRepository;
+use infrastructure::repositories::report_repository::ReportRepository;
+use infrastructure::repositories::staff_repository::StaffRepository;
+use infrastructure::repositories::stats_repository::StatsRepository;
+use infrastructure::repositories::transfer_repository::TransferRepository;
+use infrastructure::repositories::calendar_repository::CalendarRepository;
+use infrastructure::repositories::user_repository::UserRepository;
+use infrastructure::security::{generate_token, hash_password};
+use lifecycle::BackgroundTasks;
+use metrics::Metrics;
 
+pub mod audit;
+pub mod auth;
+pub mod calendar;
 pub mod controllers;
+pub mod etag;
+pub mod jobs;
+pub mod lifecycle;
+pub mod metrics;
+pub mod notices;
+pub mod openapi;
+pub mod patch;
+pub mod request_id;
+pub mod versioning;
+
+// Every route the service answers, shared by the server binary and the tests.
+pub fn routes(cfg: &mut web::ServiceConfig, api: &ApiConfig) {
+    use controllers::{health_controller::health_routes, metrics_controller::metrics_routes};
+    use versioning::{deprecated_alias, v1_routes, v2_routes};
+
+    cfg.configure(health_routes)
+        .configure(metrics_routes)
+        .configure(openapi::openapi_routes)
+        .service(web::scope("/v1").configure(v1_routes))
+        .service(web::scope("/v2").configure(v2_routes).configure(v1_routes));
+
+    // Registered last: the empty prefix matches every path.
+    if api.unversioned_routes {
+        cfg.service(web::scope("").wrap(from_fn(deprecated_alias)).configure(v1_routes));
+    }
+}
 
 pub struct AppState {
-    pub book_repo: Arc<Mutex<dyn BookRepositoryTrait + Send + Sync>>,
-    pub lib_repo : Arc<Mutex<dyn LibraryRepositoryTrait + Send + Sync>>,
-    pub member_repo : Arc<Mutex<dyn MemberRepositoryTrait + Send + Sync>>,
+    pub book_repo: Arc<Mutex<dyn BookRepositoryTrait + Send>>,
+    pub lib_repo : Arc<Mutex<dyn LibraryRepositoryTrait + Send>>,
+    pub member_repo : Arc<Mutex<dyn MemberRepositoryTrait + Send>>,
+    pub user_repo : Arc<Mutex<dyn UserRepositoryTrait + Send>>,
+    pub audit_repo : Arc<Mutex<dyn AuditRepositoryTrait + Send>>,
+    pub stats_repo : Arc<Mutex<dyn StatsRepositoryTrait + Send>>,
+    pub notice_repo : Arc<Mutex<dyn NoticeRepositoryTrait + Send>>,
+    pub job_repo : Arc<Mutex<dyn JobRepositoryTrait + Send>>,
+    pub report_repo : Arc<Mutex<dyn ReportRepositoryTrait + Send>>,
+    pub transfer_repo : Arc<Mutex<dyn TransferRepositoryTrait + Send>>,
+    pub calendar_repo : Arc<Mutex<dyn CalendarRepositoryTrait + Send>>,
+    pub staff_repo : Arc<Mutex<dyn StaffRepositoryTrait + Send>>,
+    pub acquisition_repo : Arc<Mutex<dyn AcquisitionRepositoryTrait + Send>>,
+    pub notifier: Arc<dyn Notifier + Send + Sync>,
+    pub pool: Arc<Pool<ConnectionManager<SqliteConnection>>>,
+    pub metrics: Arc<Metrics>,
+    pub background: BackgroundTasks,
+    pub auth: AuthSettings,
+    pub loans: LoansConfig,
+    pub notifications: NotificationsConfig,
+    pub jobs: JobsConfig,
+    // This process in job leases and run records.
+    pub instance: String,
+    pub api: ApiConfig,
+    pub features: FeaturesConfig,
+}
+
+fn auth_settings(config: &AuthConfig) -> AuthSettings {
+    let jwt_secret = config.jwt_secret.clone().unwrap_or_else(|| {
+        tracing::warn!("auth.jwt_secret is not set; using a random secret, issued tokens will not survive a restart");
+        generate_token()
+    });
+
+    AuthSettings {
+        jwt_secret,
+        access_token_ttl_seconds: config.access_token_ttl_seconds,
+        refresh_token_ttl_seconds: config.refresh_token_ttl_seconds,
+    }
 }
 
+// Creates the first staff account from auth.bootstrap_admin_username/password on an empty database.
+fn bootstrap_admin(user_repo: &mut dyn UserRepositoryTrait, config: &AuthConfig) {
+    let (Some(username), Some(password)) = (&config.bootstrap_admin_username, &config.bootstrap_admin_password) else {
+        return;
+    };
+    if user_repo.count_users().expect("Failed to count users") > 0 {
+        return;
+    }
+
+    let password_hash = hash_password(password).expect("Failed to hash bootstrap admin password");
+    let admin = user_repo
+        .create_user(username, &password_hash, ACCOUNT_STAFF, None)
+        .expect("Failed to create bootstrap admin");
+    user_repo
+        .grant_role(&admin.id, Role::SystemAdmin.as_str(), None)
+        .expect("Failed to grant bootstrap admin role");
+    tracing::info!(username = %username, "Created bootstrap admin account");
+}
 
-pub fn create_app_state() -> AppState {
-    let pool = establish_connection();
-    let arc_pool = Arc::new(pool);
-    let book_repo = Arc::new(Mutex::new(BookRepository::new(arc_pool.clone())));
-    let lib_repo = Arc::new(Mutex::new(LibraryRepository::new(arc_pool.clone())));
-    let member_repo = Arc::new(Mutex::new(MemberRepository::new(arc_pool.clone())));
+pub fn create_app_state(config: &Config, metrics: Arc<Metrics>) -> AppState {
+    let pool = establish_connection(&config.database, metrics.pool_events());
+    let book_repo = Arc::new(Mutex::new(BookRepository::new(pool.clone())));
+    let lib_repo = Arc::new(Mutex::new(LibraryRepository::new(pool.clone())));
+    let member_repo = Arc::new(Mutex::new(MemberRepository::new(pool.clone())));
+    let user_repo = Arc::new(Mutex::new(UserRepository::new(pool.clone())));
+    let audit_repo = Arc::new(Mutex::new(AuditRepository::new(pool.clone())));
+    let stats_repo = Arc::new(Mutex::new(StatsRepository::new(pool.clone())));
+    let notice_repo = Arc::new(Mutex::new(NoticeRepository::new(pool.clone())));
+    let job_repo = Arc::new(Mutex::new(JobRepository::new(pool.clone())));
+    let report_repo = Arc::new(Mutex::new(ReportRepository::new(pool.clone())));
+    let transfer_repo = Arc::new(Mutex::new(TransferRepository::new(pool.clone())));
+    let calendar_repo = Arc::new(Mutex::new(CalendarRepository::new(pool.clone())));
+    let staff_repo = Arc::new(Mutex::new(StaffRepository::new(pool.clone())));
+    let acquisition_repo = Arc::new(Mutex::new(AcquisitionRepository::new(pool.clone())));
+    let notifier = Arc::from(build_notifier(&config.notifications).expect("Failed to set up the notifier"));
 
-    create_tables(&arc_pool);
+    run_migrations(&pool);
+    bootstrap_admin(&mut *user_repo.lock().unwrap(), &config.auth);
 
     AppState {
         book_repo,
         lib_repo,
         member_repo,
+        user_repo,
+        audit_repo,
+        stats_repo,
+        notice_repo,
+        job_repo,
+        report_repo,
+        transfer_repo,
+        calendar_repo,
+        staff_repo,
+        acquisition_repo,
+        notifier,
+        pool,
+        metrics,
+        background: BackgroundTasks::new(),
+        auth: auth_settings(&config.auth),
+        loans: config.loans.clone(),
+        notifications: config.notifications.clone(),
+        jobs: config.jobs.clone(),
+        instance: jobs::instance_id(),
+        api: config.api.clone(),
+        features: config.features.clone(),
     }
 }
@@ -0,0 +1,61 @@
+use std::future::Future;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+
+// Long-running tasks started next to the HTTP server. Each one watches the stop signal and is awaited,
+// up to a deadline, once the server has drained its in-flight requests.
+pub struct BackgroundTasks {
+    stop: watch::Sender<bool>,
+    handles: Mutex<Vec<(String, JoinHandle<()>)>>,
+}
+
+impl BackgroundTasks {
+    pub fn new() -> BackgroundTasks {
+        let (stop, _) = watch::channel(false);
+        BackgroundTasks { stop, handles: Mutex::new(Vec::new()) }
+    }
+
+    // Resolves `changed()` once shutdown starts; tasks should finish their current unit of work and return.
+    pub fn stop_signal(&self) -> watch::Receiver<bool> {
+        self.stop.subscribe()
+    }
+
+    pub fn is_stopping(&self) -> bool {
+        *self.stop.borrow()
+    }
+
+    pub fn spawn<F>(&self, name: &str, task: F)
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        let handle = tokio::spawn(task);
+        self.handles.lock().unwrap().push((name.to_string(), handle));
+    }
+
+    // Signals every task to stop and waits for them; tasks still running at the deadline are aborted.
+    pub async fn shutdown(&self, timeout: Duration) {
+        self.stop.send_replace(true);
+
+        let handles: Vec<(String, JoinHandle<()>)> = self.handles.lock().unwrap().drain(..).collect();
+        let deadline = tokio::time::Instant::now() + timeout;
+        for (name, mut handle) in handles {
+            match tokio::time::timeout_at(deadline, &mut handle).await {
+                Ok(Ok(())) => tracing::info!(task = %name, "Background task stopped"),
+                Ok(Err(e)) => tracing::error!(task = %name, error = %e, "Background task failed"),
+                Err(_) => {
+                    handle.abort();
+                    tracing::warn!(task = %name, "Background task did not stop in time; aborted");
+                }
+            }
+        }
+    }
+}
+
+impl Default for BackgroundTasks {
+    fn default() -> Self {
+        BackgroundTasks::new()
+    }
+}
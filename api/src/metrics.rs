@@ -0,0 +1,221 @@
+use std::time::Instant;
+
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::middleware::Next;
+use actix_web::{web, Error};
+use diesel::r2d2::event::{CheckoutEvent, TimeoutEvent};
+use diesel::r2d2::HandleEvent;
+use prometheus::{
+    Encoder, Histogram, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, IntGauge, IntGaugeVec, Opts, Registry,
+    TextEncoder,
+};
+use tracing::span::{Attributes, Id};
+use tracing::Subscriber;
+use tracing_subscriber::layer::Context;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::Layer;
+
+use crate::AppState;
+
+const LATENCY_BUCKETS: [f64; 12] = [0.0005, 0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5];
+
+// Every Prometheus series the service exports. The domain and pool gauges are refreshed when /metrics is scraped.
+pub struct Metrics {
+    registry: Registry,
+    http_requests: IntCounterVec,
+    http_request_duration: HistogramVec,
+    pool_connections: IntGaugeVec,
+    pool_max_size: IntGauge,
+    pool_wait: Histogram,
+    pool_timeouts: IntCounter,
+    repository_calls: HistogramVec,
+    active_loans: IntGauge,
+    overdue_loans: IntGauge,
+    available_copies: IntGaugeVec,
+}
+
+impl Metrics {
+    pub fn new() -> Metrics {
+        let http_requests = IntCounterVec::new(
+            Opts::new("http_requests_total", "HTTP requests by method, route pattern and status"),
+            &["method", "route", "status"],
+        ).unwrap();
+        let http_request_duration = HistogramVec::new(
+            HistogramOpts::new("http_request_duration_seconds", "HTTP request latency by method and route pattern")
+                .buckets(LATENCY_BUCKETS.to_vec()),
+            &["method", "route"],
+        ).unwrap();
+        let pool_connections = IntGaugeVec::new(
+            Opts::new("db_pool_connections", "Pooled database connections by state"),
+            &["state"],
+        ).unwrap();
+        let pool_max_size = IntGauge::new("db_pool_max_size", "Maximum number of pooled database connections").unwrap();
+        let pool_wait = Histogram::with_opts(
+            HistogramOpts::new("db_pool_wait_seconds", "Time spent waiting to check a connection out of the pool")
+                .buckets(LATENCY_BUCKETS.to_vec()),
+        ).unwrap();
+        let pool_timeouts = IntCounter::new("db_pool_timeouts_total", "Connection checkouts that timed out").unwrap();
+        let repository_calls = HistogramVec::new(
+            HistogramOpts::new("repository_call_duration_seconds", "Repository call latency by repository and method")
+                .buckets(LATENCY_BUCKETS.to_vec()),
+            &["repository", "method"],
+        ).unwrap();
+        let active_loans = IntGauge::new("library_active_loans", "Books currently on loan").unwrap();
+        let overdue_loans = IntGauge::new("library_overdue_loans", "Loans past their due date").unwrap();
+        let available_copies = IntGaugeVec::new(
+            Opts::new("library_available_copies", "Copies on the shelf per library"),
+            &["library_id"],
+        ).unwrap();
+
+        let registry = Registry::new();
+        registry.register(Box::new(http_requests.clone())).unwrap();
+        registry.register(Box::new(http_request_duration.clone())).unwrap();
+        registry.register(Box::new(pool_connections.clone())).unwrap();
+        registry.register(Box::new(pool_max_size.clone())).unwrap();
+        registry.register(Box::new(pool_wait.clone())).unwrap();
+        registry.register(Box::new(pool_timeouts.clone())).unwrap();
+        registry.register(Box::new(repository_calls.clone())).unwrap();
+        registry.register(Box::new(active_loans.clone())).unwrap();
+        registry.register(Box::new(overdue_loans.clone())).unwrap();
+        registry.register(Box::new(available_copies.clone())).unwrap();
+
+        Metrics {
+            registry,
+            http_requests,
+            http_request_duration,
+            pool_connections,
+            pool_max_size,
+            pool_wait,
+            pool_timeouts,
+            repository_calls,
+            active_loans,
+            overdue_loans,
+            available_copies,
+        }
+    }
+
+    pub fn pool_events(&self) -> Box<dyn HandleEvent> {
+        Box::new(PoolEvents { wait: self.pool_wait.clone(), timeouts: self.pool_timeouts.clone() })
+    }
+
+    pub fn repository_layer(&self) -> RepositoryTimingLayer {
+        RepositoryTimingLayer { calls: self.repository_calls.clone() }
+    }
+
+    fn refresh(&self, state: &AppState) {
+        let pool = state.pool.state();
+        let idle = i64::from(pool.idle_connections);
+        self.pool_connections.with_label_values(&["idle"]).set(idle);
+        self.pool_connections.with_label_values(&["in_use"]).set(i64::from(pool.connections) - idle);
+        self.pool_max_size.set(i64::from(state.pool.max_size()));
+
+        let mut stats_repo = state.stats_repo.lock().unwrap();
+        let now: String = chrono::offset::Utc::now().naive_utc().to_string();
+        match stats_repo.get_loan_counts(&now) {
+            Ok(counts) => {
+                self.active_loans.set(counts.active);
+                self.overdue_loans.set(counts.overdue);
+            }
+            Err(e) => tracing::error!(error = ?e, "Failed to collect loan metrics"),
+        }
+        match stats_repo.get_available_copies() {
+            Ok(libraries) => {
+                // Drop series of libraries that no longer stock anything.
+                self.available_copies.reset();
+                for library in libraries {
+                    self.available_copies.with_label_values(&[&library.library_id.to_string()]).set(library.available);
+                }
+            }
+            Err(e) => tracing::error!(error = ?e, "Failed to collect availability metrics"),
+        }
+    }
+
+    // Prometheus text exposition format.
+    pub fn render(&self, state: &AppState) -> Result<String, String> {
+        self.refresh(state);
+
+        let mut buffer = Vec::new();
+        TextEncoder::new().encode(&self.registry.gather(), &mut buffer).map_err(|e| e.to_string())?;
+        String::from_utf8(buffer).map_err(|e| e.to_string())
+    }
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Metrics::new()
+    }
+}
+
+// Counts and times every request. Unrouted paths share one `unmatched` label to keep cardinality bounded.
+pub async fn track_requests(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    let state = req.app_data::<web::Data<AppState>>().cloned();
+    let method = req.method().to_string();
+    let route = req.match_pattern().unwrap_or_else(|| "unmatched".to_string());
+
+    let started = Instant::now();
+    let result = next.call(req).await;
+
+    if let Some(state) = state {
+        let status = match &result {
+            Ok(res) => res.status(),
+            Err(e) => e.as_response_error().status_code(),
+        };
+        let metrics = &state.metrics;
+        metrics.http_requests.with_label_values(&[&method, &route, status.as_str()]).inc();
+        metrics.http_request_duration.with_label_values(&[&method, &route]).observe(started.elapsed().as_secs_f64());
+    }
+
+    result
+}
+
+#[derive(Debug)]
+struct PoolEvents {
+    wait: Histogram,
+    timeouts: IntCounter,
+}
+
+impl HandleEvent for PoolEvents {
+    fn handle_checkout(&self, event: CheckoutEvent) {
+        self.wait.observe(event.duration().as_secs_f64());
+    }
+
+    fn handle_timeout(&self, _event: TimeoutEvent) {
+        self.timeouts.inc();
+    }
+}
+
+struct CallStarted(Instant);
+
+// Times the `#[instrument]` spans around repository methods. It must be registered with a filter that
+// enables `infrastructure::repositories` at debug level regardless of the log level.
+pub struct RepositoryTimingLayer {
+    calls: HistogramVec,
+}
+
+impl<S> Layer<S> for RepositoryTimingLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, _attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        if let Some(span) = ctx.span(id) {
+            span.extensions_mut().insert(CallStarted(Instant::now()));
+        }
+    }
+
+    fn on_close(&self, id: Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(&id) else {
+            return;
+        };
+        let extensions = span.extensions();
+        if let Some(CallStarted(started)) = extensions.get::<CallStarted>() {
+            let repository = span.metadata().target().rsplit("::").next().unwrap_or_default();
+            self.calls
+                .with_label_values(&[repository, span.name()])
+                .observe(started.elapsed().as_secs_f64());
+        }
+    }
+}
@@ -0,0 +1,58 @@
+use chrono::NaiveDateTime;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use domain::models::notice::{NewLoanNotice, NOTICE_OVERDUE, NOTICE_REMINDER};
+use infrastructure::notifications::{notice_days, render_notice};
+
+use crate::AppState;
+
+#[derive(Debug, Default, Serialize, ToSchema)]
+pub struct NoticeRun {
+    pub sent: usize,
+    pub failed: usize,
+}
+
+// Sends every reminder and overdue notice that is due and not sent yet. A notice is recorded only after the
+// notifier accepted it, so failed sends are retried on the next scan. Blocking: run it off the async executor.
+pub fn send_due_notices(state: &AppState, now: NaiveDateTime) -> Result<NoticeRun, String> {
+    let config = &state.notifications;
+    let now_text = now.to_string();
+    let mut scans = vec![(NOTICE_OVERDUE, String::new(), now_text.clone())];
+    if config.reminder_days_before > 0 {
+        let until = (now + chrono::Duration::days(config.reminder_days_before)).to_string();
+        scans.push((NOTICE_REMINDER, now_text.clone(), until));
+    }
+
+    let mut run = NoticeRun::default();
+    for (kind, from, until) in scans {
+        let loans = state.notice_repo.lock().unwrap().find_due_loans(kind, &from, &until).map_err(|e| e.to_string())?;
+
+        for loan in loans {
+            let notice = render_notice(config, kind, &loan, notice_days(kind, &loan.due_at, &now));
+            if let Err(e) = state.notifier.send(&notice) {
+                tracing::warn!(error = %e, kind, member_id = loan.member_id, book_id = loan.book_id, "Failed to send loan notice");
+                run.failed += 1;
+                continue;
+            }
+
+            let sent_at = chrono::offset::Utc::now().naive_utc().to_string();
+            let record = NewLoanNotice {
+                member_id: &loan.member_id,
+                book_id: &loan.book_id,
+                borrowed_at: &loan.borrowed_at,
+                kind,
+                channel: state.notifier.channel(),
+                recipient: &loan.member_email,
+                sent_at: &sent_at,
+                due_at: Some(&loan.due_at),
+            };
+            if let Err(e) = state.notice_repo.lock().unwrap().record_notice(&record) {
+                tracing::error!(error = ?e, kind, member_id = loan.member_id, book_id = loan.book_id, "Failed to record loan notice");
+            }
+            run.sent += 1;
+        }
+    }
+
+    Ok(run)
+}
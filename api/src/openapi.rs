@@ -0,0 +1,172 @@
+use actix_web::web;
+use utoipa::openapi::security::{ApiKey, ApiKeyValue, HttpAuthScheme, HttpBuilder, SecurityScheme};
+use utoipa::{Modify, OpenApi};
+use utoipa_swagger_ui::SwaggerUi;
+
+use crate::controllers::{
+    acquisition_controller, audit_controller, auth_controller, book_controller, calendar_controller, health_controller, job_controller, library_controller, me_controller,
+    member_controller, metrics_controller, notice_controller, report_controller, staff_controller, transfer_controller,
+};
+
+// The spec is generated from the `#[utoipa::path]` attributes on the handlers; a path added to a controller
+// has to be listed here too, which `tests/openapi.rs` checks. Resource paths are documented under `/v1`.
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "Library Automation API", description = "Books, libraries, members and loans."),
+    paths(
+        health_controller::healthz,
+        health_controller::readyz,
+        metrics_controller::get_metrics,
+    ),
+    nest((path = "/v1", api = V1Api)),
+    modifiers(&SecurityAddon),
+    security(("bearer_auth" = []), ("api_key" = [])),
+    tags(
+        (name = "auth", description = "Login and token refresh"),
+        (name = "users", description = "User accounts and role grants"),
+        (name = "api-keys", description = "Long-lived keys for integrations"),
+        (name = "audit", description = "Append-only audit trail"),
+        (name = "books", description = "Book catalogue"),
+        (name = "libraries", description = "Libraries and their holdings"),
+        (name = "calendar", description = "Opening hours and closures of libraries"),
+        (name = "staff", description = "Staff assignments of libraries and manager handovers"),
+        (name = "members", description = "Library members"),
+        (name = "loans", description = "Borrowing and returning books"),
+        (name = "me", description = "Member self-service: loans, renewals, holds, fines and preferences"),
+        (name = "transfers", description = "Copies lent between libraries"),
+        (name = "acquisitions", description = "Vendors, library budgets and purchase orders"),
+        (name = "reports", description = "Circulation reports as JSON or CSV"),
+        (name = "jobs", description = "Scheduled background jobs and their runs"),
+        (name = "operations", description = "Health probes and metrics"),
+    )
+)]
+pub struct ApiDoc;
+
+#[derive(OpenApi)]
+#[openapi(paths(
+    auth_controller::login,
+    auth_controller::refresh,
+    auth_controller::logout,
+    auth_controller::get_current_user,
+    auth_controller::create_user,
+    auth_controller::get_users,
+    auth_controller::get_user_roles,
+    auth_controller::grant_role,
+    auth_controller::revoke_role,
+    auth_controller::create_api_key,
+    auth_controller::get_api_keys,
+    auth_controller::revoke_api_key,
+    audit_controller::get_audit_entries,
+    audit_controller::export_audit_entries,
+    book_controller::create_book,
+    book_controller::get_books,
+    book_controller::get_book,
+    book_controller::update_book,
+    book_controller::patch_book,
+    book_controller::delete_book,
+    book_controller::get_books_by_library_id,
+    job_controller::get_jobs,
+    job_controller::run_job_now,
+    job_controller::get_job_runs,
+    job_controller::get_job_run,
+    library_controller::create_library,
+    library_controller::get_libraries,
+    library_controller::get_library,
+    library_controller::get_nearby_libraries,
+    library_controller::update_library,
+    library_controller::patch_library,
+    library_controller::delete_library,
+    library_controller::add_library_book,
+    library_controller::set_library_book_quantity,
+    library_controller::set_loan_retention,
+    calendar_controller::get_calendar,
+    calendar_controller::get_calendar_ics,
+    calendar_controller::set_hours,
+    calendar_controller::add_closure,
+    calendar_controller::delete_closure,
+    staff_controller::get_staff,
+    staff_controller::add_staff,
+    staff_controller::update_staff,
+    staff_controller::end_staff,
+    staff_controller::get_handovers,
+    me_controller::get_profile,
+    me_controller::get_loans,
+    me_controller::get_loan_history,
+    me_controller::renew_loan,
+    me_controller::get_holds,
+    me_controller::place_hold,
+    me_controller::cancel_hold,
+    me_controller::get_fines,
+    me_controller::export_data,
+    me_controller::get_preferences,
+    me_controller::update_preferences,
+    member_controller::create_member,
+    member_controller::get_members,
+    member_controller::get_member,
+    member_controller::update_member,
+    member_controller::patch_member,
+    member_controller::delete_member,
+    member_controller::set_member_status,
+    member_controller::get_duplicate_members,
+    member_controller::merge_members,
+    member_controller::export_member,
+    member_controller::erase_member,
+    member_controller::get_members_by_library_id,
+    member_controller::borrow_book,
+    member_controller::return_book,
+    member_controller::get_borrowed_books,
+    member_controller::get_library_borrowed_books,
+    notice_controller::get_notices,
+    notice_controller::run_notices,
+    report_controller::get_top_titles,
+    report_controller::get_top_authors,
+    report_controller::get_overdue_report,
+    report_controller::get_utilization_report,
+    report_controller::get_member_activity_report,
+    report_controller::get_acquisitions_report,
+    report_controller::get_budget_spending_report,
+    transfer_controller::create_transfer,
+    transfer_controller::get_transfers,
+    transfer_controller::get_transfer,
+    transfer_controller::approve_transfer,
+    transfer_controller::ship_transfer,
+    transfer_controller::receive_transfer,
+    transfer_controller::return_transfer,
+    transfer_controller::cancel_transfer,
+    transfer_controller::get_in_transit,
+    acquisition_controller::create_vendor,
+    acquisition_controller::get_vendors,
+    acquisition_controller::get_vendor,
+    acquisition_controller::update_vendor,
+    acquisition_controller::get_budgets,
+    acquisition_controller::set_budget,
+    acquisition_controller::create_order,
+    acquisition_controller::get_orders,
+    acquisition_controller::get_order,
+    acquisition_controller::submit_order,
+    acquisition_controller::receive_order,
+    acquisition_controller::cancel_order,
+    acquisition_controller::close_order,
+))]
+struct V1Api;
+
+struct SecurityAddon;
+
+impl Modify for SecurityAddon {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "bearer_auth",
+            SecurityScheme::Http(HttpBuilder::new().scheme(HttpAuthScheme::Bearer).bearer_format("JWT").build()),
+        );
+        components.add_security_scheme(
+            "api_key",
+            SecurityScheme::ApiKey(ApiKey::Header(ApiKeyValue::new("X-Api-Key"))),
+        );
+    }
+}
+
+// Routes configuration
+pub fn openapi_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(SwaggerUi::new("/swagger-ui/{_:.*}").url("/openapi.json", ApiDoc::openapi()));
+}
@@ -0,0 +1,82 @@
+use std::fmt;
+
+use actix_web::http::header::{ContentType, Header};
+use actix_web::http::StatusCode;
+use actix_web::{HttpRequest, HttpResponse, ResponseError};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+
+pub const MERGE_PATCH: &str = "application/merge-patch+json";
+pub const JSON_PATCH: &str = "application/json-patch+json";
+
+#[derive(Debug)]
+pub enum PatchError {
+    UnsupportedMediaType,
+    Malformed(String),
+    Conflict(String),
+    Invalid(String),
+}
+
+impl fmt::Display for PatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PatchError::UnsupportedMediaType => write!(f, "PATCH requires Content-Type {} or {}", MERGE_PATCH, JSON_PATCH),
+            PatchError::Malformed(message) => write!(f, "Malformed patch: {}", message),
+            PatchError::Conflict(message) => write!(f, "Failed to apply patch: {}", message),
+            PatchError::Invalid(message) => write!(f, "Patched resource is invalid: {}", message),
+        }
+    }
+}
+
+impl ResponseError for PatchError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            PatchError::UnsupportedMediaType => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            PatchError::Malformed(_) => StatusCode::BAD_REQUEST,
+            PatchError::Conflict(_) => StatusCode::CONFLICT,
+            PatchError::Invalid(_) => StatusCode::UNPROCESSABLE_ENTITY,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        let mut response = HttpResponse::build(self.status_code());
+        if let PatchError::UnsupportedMediaType = self {
+            response.insert_header(("Accept-Patch", format!("{}, {}", MERGE_PATCH, JSON_PATCH)));
+        }
+        response.body(self.to_string())
+    }
+}
+
+// Applies a JSON Merge Patch (RFC 7396) or JSON Patch (RFC 6902) body to `current`, picked by Content-Type.
+// The merged document must still deserialize into `T` and may not introduce fields `T` does not have.
+pub fn apply_patch<T: Serialize + DeserializeOwned>(req: &HttpRequest, body: &[u8], current: &T) -> Result<T, PatchError> {
+    let original = serde_json::to_value(current).map_err(|e| PatchError::Invalid(e.to_string()))?;
+    let mut document = original.clone();
+
+    let media_type = ContentType::parse(req).ok().map(|content_type| content_type.0.essence_str().to_string());
+    match media_type.as_deref() {
+        Some(MERGE_PATCH) => {
+            let patch: Value = serde_json::from_slice(body).map_err(|e| PatchError::Malformed(e.to_string()))?;
+            json_patch::merge(&mut document, &patch);
+        }
+        Some(JSON_PATCH) => {
+            let patch: json_patch::Patch = serde_json::from_slice(body).map_err(|e| PatchError::Malformed(e.to_string()))?;
+            json_patch::patch(&mut document, &patch).map_err(|e| PatchError::Conflict(e.to_string()))?;
+        }
+        _ => return Err(PatchError::UnsupportedMediaType),
+    }
+
+    if let (Value::Object(before), Value::Object(after)) = (&original, &document) {
+        if let Some(field) = after.keys().find(|key| !before.contains_key(*key)) {
+            return Err(PatchError::Invalid(format!("unknown field `{}`", field)));
+        }
+    }
+
+    serde_json::from_value(document).map_err(|e| PatchError::Invalid(e.to_string()))
+}
+
+// Only columns whose value actually changed end up in the repository changeset.
+pub fn changed<'a, V: PartialEq + ?Sized>(before: &V, after: &'a V) -> Option<&'a V> {
+    if before == after { None } else { Some(after) }
+}
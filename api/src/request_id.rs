@@ -0,0 +1,72 @@
+use std::time::Instant;
+
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header::{HeaderName, HeaderValue};
+use actix_web::middleware::Next;
+use actix_web::{Error, HttpMessage, HttpRequest};
+use tracing::{field, info_span, Instrument};
+
+use infrastructure::security::generate_token;
+
+pub const REQUEST_ID_HEADER: &str = "X-Request-Id";
+
+// Correlation id of the current request, stored in the request extensions by `request_tracing`.
+#[derive(Debug, Clone)]
+pub struct RequestId(pub String);
+
+// A client supplied id is kept when it is short printable ASCII so it can be logged and echoed safely.
+fn incoming_request_id(req: &ServiceRequest) -> Option<String> {
+    let value = req.headers().get(REQUEST_ID_HEADER)?.to_str().ok()?.trim();
+    let valid = !value.is_empty() && value.len() <= 128 && value.chars().all(|c| c.is_ascii_graphic());
+    valid.then(|| value.to_string())
+}
+
+pub fn request_id(req: &HttpRequest) -> String {
+    req.extensions()
+        .get::<RequestId>()
+        .map(|id| id.0.clone())
+        .unwrap_or_else(|| generate_token()[..16].to_string())
+}
+
+// Wraps every request in an `http_request` span carrying the request id and echoes the id back in
+// X-Request-Id. The span's close event, with status and latency recorded, serves as the access log.
+pub async fn request_tracing(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    let id = incoming_request_id(&req).unwrap_or_else(|| generate_token()[..16].to_string());
+    req.extensions_mut().insert(RequestId(id.clone()));
+
+    let span = info_span!(
+        "http_request",
+        request_id = %id,
+        method = %req.method(),
+        route = %req.match_pattern().unwrap_or_else(|| "unmatched".to_string()),
+        status = field::Empty,
+        latency_ms = field::Empty,
+    );
+    let started = Instant::now();
+    let result = next.call(req).instrument(span.clone()).await;
+    let latency_ms = started.elapsed().as_secs_f64() * 1000.0;
+    span.record("latency_ms", latency_ms);
+
+    let mut res = match result {
+        Ok(res) => res,
+        Err(e) => {
+            span.in_scope(|| tracing::error!(error = %e, "request failed"));
+            return Err(e);
+        }
+    };
+
+    let status = res.status();
+    span.record("status", status.as_u16());
+    if status.is_server_error() {
+        span.in_scope(|| tracing::error!(status = status.as_u16(), "request failed"));
+    }
+
+    if let Ok(value) = HeaderValue::from_str(&id) {
+        res.headers_mut().insert(HeaderName::from_static("x-request-id"), value);
+    }
+    Ok(res)
+}
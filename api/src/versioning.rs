@@ -0,0 +1,76 @@
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header::{HeaderName, HeaderValue, LINK};
+use actix_web::middleware::Next;
+use actix_web::{web, Error};
+use chrono::NaiveDate;
+
+use crate::controllers::{
+    acquisition_controller::acquisition_routes, audit_controller::audit_routes, auth_controller::auth_routes, book_controller::book_routes, calendar_controller::calendar_routes,
+    job_controller::job_routes, library_controller::library_routes, me_controller::me_routes, member_controller::member_routes,
+    notice_controller::notice_routes, report_controller::report_routes, staff_controller::staff_routes,
+    transfer_controller::transfer_routes,
+};
+use crate::AppState;
+
+// Prefix of the current API version; unversioned paths point their successor link here.
+pub const CURRENT_VERSION: &str = "/v1";
+
+// When the unversioned paths were deprecated (2026-10-19 UTC), sent as the RFC 9745 `Deprecation` date.
+const UNVERSIONED_DEPRECATED_AT: i64 = 1_792_368_000;
+
+// The resource API as of v1. Health probes, metrics and the spec stay unversioned.
+pub fn v1_routes(cfg: &mut web::ServiceConfig) {
+    cfg.configure(auth_routes)
+        .configure(acquisition_routes)
+        .configure(audit_routes)
+        .configure(book_routes)
+        .configure(calendar_routes)
+        .configure(job_routes)
+        .configure(library_routes)
+        .configure(me_routes)
+        .configure(member_routes)
+        .configure(notice_routes)
+        .configure(report_routes)
+        .configure(staff_routes)
+        .configure(transfer_routes);
+}
+
+// Handlers whose request or response shape changed in v2 go here. They are registered ahead of the v1
+// routes in the `/v2` scope, so every endpoint not overridden keeps its v1 behaviour.
+pub fn v2_routes(_cfg: &mut web::ServiceConfig) {}
+
+// Marks responses served from the unversioned aliases as deprecated and links to the `/v1` equivalent.
+pub async fn deprecated_alias(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    let successor = match req.query_string() {
+        "" => format!("{}{}", CURRENT_VERSION, req.path()),
+        query => format!("{}{}?{}", CURRENT_VERSION, req.path(), query),
+    };
+    let sunset = req
+        .app_data::<web::Data<AppState>>()
+        .and_then(|state| NaiveDate::parse_from_str(&state.api.unversioned_sunset, "%Y-%m-%d").ok())
+        .map(|date| date.format("%a, %d %b %Y 00:00:00 GMT").to_string());
+
+    let mut res = next.call(req).await?;
+    // The empty scope also sees paths that exist in no version; leave those 404s alone.
+    if res.request().match_pattern().is_none() {
+        return Ok(res);
+    }
+
+    let headers = res.headers_mut();
+    headers.insert(
+        HeaderName::from_static("deprecation"),
+        HeaderValue::from_str(&format!("@{}", UNVERSIONED_DEPRECATED_AT)).unwrap(),
+    );
+    if let Some(sunset) = sunset.and_then(|value| HeaderValue::from_str(&value).ok()) {
+        headers.insert(HeaderName::from_static("sunset"), sunset);
+    }
+    if let Ok(link) = HeaderValue::from_str(&format!("<{}>; rel=\"successor-version\"", successor)) {
+        headers.append(LINK, link);
+    }
+
+    Ok(res)
+}
@@ -0,0 +1,107 @@
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+
+use actix_web::http::{Method, StatusCode};
+use actix_web::test::{call_service, init_service, TestRequest};
+use actix_web::{web, App, HttpResponse};
+use utoipa::OpenApi;
+
+use api::metrics::Metrics;
+use api::openapi::ApiDoc;
+use api::versioning::CURRENT_VERSION;
+use api::{create_app_state, routes};
+use infrastructure::config::Config;
+
+// (METHOD, path template) pairs documented in the generated spec.
+fn documented() -> BTreeSet<(String, String)> {
+    let spec = serde_json::to_value(ApiDoc::openapi()).unwrap();
+    let mut operations = BTreeSet::new();
+    for (path, item) in spec["paths"].as_object().unwrap() {
+        for method in item.as_object().unwrap().keys() {
+            if ["get", "post", "put", "patch", "delete"].contains(&method.as_str()) {
+                operations.insert((method.to_uppercase(), path.clone()));
+            }
+        }
+    }
+    operations
+}
+
+// (METHOD, path template) pairs registered by the `*_routes` functions, read from the controller sources.
+// Everything but the operational endpoints is mounted under the current version.
+fn registered() -> BTreeSet<(String, String)> {
+    let unversioned = ["health_controller.rs", "metrics_controller.rs"];
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("src/controllers");
+    let mut operations = BTreeSet::new();
+    for entry in fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        let prefix = if unversioned.iter().any(|name| path.ends_with(name)) { "" } else { CURRENT_VERSION };
+        let source = fs::read_to_string(path).unwrap();
+        let mut resource = None;
+        for line in source.lines().map(str::trim) {
+            if let Some(rest) = line.strip_prefix("web::resource(\"") {
+                resource = rest.split('"').next().map(str::to_string);
+            } else if let (Some(path), Some(rest)) = (&resource, line.strip_prefix(".route(web::")) {
+                let method = rest.split('(').next().unwrap().to_uppercase();
+                operations.insert((method, format!("{}{}", prefix, path)));
+            }
+        }
+    }
+    operations
+}
+
+#[test]
+fn every_route_is_documented() {
+    let documented = documented();
+    let registered = registered();
+    assert!(!registered.is_empty(), "no routes found in the controller sources");
+
+    let undocumented: Vec<_> = registered.difference(&documented).collect();
+    assert!(undocumented.is_empty(), "routes missing from the OpenAPI spec: {:?}", undocumented);
+    let unregistered: Vec<_> = documented.difference(&registered).collect();
+    assert!(unregistered.is_empty(), "documented operations without a route: {:?}", unregistered);
+}
+
+#[actix_web::test]
+async fn every_documented_operation_is_served() {
+    let database = std::env::temp_dir().join(format!("library-openapi-{}.db", std::process::id()));
+    let mut config = Config::default();
+    config.database.url = database.to_string_lossy().into_owned();
+    let state = web::Data::new(create_app_state(&config, Arc::new(Metrics::new())));
+
+    let app = init_service(
+        App::new()
+            .app_data(state)
+            .configure(|cfg| routes(cfg, &config.api))
+            .default_service(web::to(|| async { HttpResponse::NotImplemented().finish() })),
+    )
+    .await;
+
+    for (method, template) in documented() {
+        let uri = template
+            .split('/')
+            .map(|segment| if segment.starts_with('{') { "1" } else { segment })
+            .collect::<Vec<_>>()
+            .join("/");
+        let req = TestRequest::default()
+            .method(Method::from_bytes(method.as_bytes()).unwrap())
+            .uri(&uri)
+            .to_request();
+        let status = call_service(&app, req).await.status();
+        assert!(
+            status != StatusCode::NOT_IMPLEMENTED && status != StatusCode::METHOD_NOT_ALLOWED,
+            "{} {} is documented but not served ({})",
+            method,
+            template,
+            status
+        );
+    }
+
+    let spec = call_service(&app, TestRequest::get().uri("/openapi.json").to_request()).await;
+    assert_eq!(spec.status(), StatusCode::OK);
+
+    for suffix in ["", "-shm", "-wal"] {
+        let _ = fs::remove_file(format!("{}{}", database.display(), suffix));
+    }
+}
@@ -0,0 +1,135 @@
+use diesel::{Insertable, Queryable};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+use crate::schema::purchase_order_lines as purchase_order_lines_schema;
+use crate::schema::purchase_orders as purchase_orders_schema;
+use crate::schema::vendors as vendors_schema;
+
+pub const ORDER_DRAFT: &str = "draft";
+pub const ORDER_ORDERED: &str = "ordered";
+pub const ORDER_PARTIALLY_RECEIVED: &str = "partially_received";
+pub const ORDER_RECEIVED: &str = "received";
+pub const ORDER_CANCELLED: &str = "cancelled";
+
+pub const ORDER_STATUSES: [&str; 5] = [
+    ORDER_DRAFT,
+    ORDER_ORDERED,
+    ORDER_PARTIALLY_RECEIVED,
+    ORDER_RECEIVED,
+    ORDER_CANCELLED,
+];
+
+// Where books are bought from.
+#[derive(Debug, Clone, Queryable)]
+#[derive(Serialize, Deserialize, ToSchema)]
+#[diesel(table_name = vendors_schema)]
+pub struct Vendor {
+    pub id: i32,
+    pub name: String,
+    pub email: Option<String>,
+    pub phone: Option<String>,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = vendors_schema)]
+pub struct NewVendor<'a> {
+    pub name: &'a str,
+    pub email: Option<&'a str>,
+    pub phone: Option<&'a str>,
+    pub created_at: &'a str,
+    pub updated_at: &'a str,
+}
+
+// What a library may spend on purchase orders in a fiscal year.
+#[derive(Debug, Clone, Queryable)]
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct Budget {
+    pub id: i32,
+    pub library_id: i32,
+    pub fiscal_year: i32,
+    pub amount_cents: i64,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+// Books bought from a vendor for a library and charged to its budget for `fiscal_year`. A draft is ordered,
+// received in one or more deliveries and closed when the last copy arrives, or closed short by hand; drafts and
+// orders with nothing received yet can be cancelled.
+#[derive(Debug, Clone, Queryable)]
+#[derive(Serialize, Deserialize, ToSchema)]
+#[diesel(table_name = purchase_orders_schema)]
+pub struct PurchaseOrder {
+    pub id: i32,
+    pub library_id: i32,
+    pub vendor_id: i32,
+    pub fiscal_year: i32,
+    pub status: String,
+    pub note: Option<String>,
+    pub created_by: Option<i32>,
+    pub created_at: String,
+    pub ordered_at: Option<String>,
+    // When the order was received, in full or closed short, or cancelled.
+    pub closed_at: Option<String>,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = purchase_orders_schema)]
+pub struct NewPurchaseOrder<'a> {
+    pub library_id: &'a i32,
+    pub vendor_id: &'a i32,
+    pub fiscal_year: &'a i32,
+    pub status: &'a str,
+    pub note: Option<&'a str>,
+    pub created_by: Option<&'a i32>,
+    pub created_at: &'a str,
+}
+
+// Copies of one title on an order. Lines for books not yet in the catalogue carry a title, author and ISBN;
+// the book is created, or found by its ISBN, when the first copy is received.
+#[derive(Debug, Clone, Queryable)]
+#[derive(Serialize, Deserialize, ToSchema)]
+#[diesel(table_name = purchase_order_lines_schema)]
+pub struct OrderLine {
+    pub id: i32,
+    pub order_id: i32,
+    pub book_id: Option<i32>,
+    pub isbn: Option<String>,
+    pub title: String,
+    pub author: String,
+    pub quantity: i32,
+    pub unit_price_cents: i64,
+    pub received_quantity: i32,
+}
+
+impl OrderLine {
+    pub fn total_cents(&self) -> i64 {
+        self.quantity as i64 * self.unit_price_cents
+    }
+
+    pub fn outstanding(&self) -> i32 {
+        self.quantity - self.received_quantity
+    }
+}
+
+#[derive(Debug, Clone, Insertable)]
+#[diesel(table_name = purchase_order_lines_schema)]
+pub struct NewOrderLine {
+    pub book_id: Option<i32>,
+    pub isbn: Option<String>,
+    pub title: String,
+    pub author: String,
+    pub quantity: i32,
+    pub unit_price_cents: i64,
+}
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct OrderFilter {
+    pub library_id: Option<i32>,
+    pub vendor_id: Option<i32>,
+    pub fiscal_year: Option<i32>,
+    pub status: Option<String>,
+    pub limit: Option<i64>,
+}
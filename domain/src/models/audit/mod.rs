@@ -0,0 +1,54 @@
+#![allow(unused_imports)]
+#![allow(unused_braces)]
+
+use diesel::{Queryable, Insertable};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+use crate::schema::audit_log as audit_log_schema;
+
+// Actor name of changes the service makes on its own, from scheduled jobs and library-admin commands.
+pub const SYSTEM_ACTOR: &str = "system";
+
+// One immutable record per mutating operation. Snapshots and diff are stored as JSON text.
+#[derive(Debug, Queryable)]
+#[derive(Serialize, Deserialize, ToSchema)]
+#[diesel(table_name = audit_log_schema)]
+pub struct AuditEntry {
+    pub id: i32,
+    pub occurred_at: String,
+    pub request_id: String,
+    pub actor_user_id: Option<i32>,
+    pub actor_username: Option<String>,
+    pub action: String,
+    pub entity: String,
+    pub entity_id: String,
+    pub before_json: Option<String>,
+    pub after_json: Option<String>,
+    pub diff_json: Option<String>,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = audit_log_schema)]
+pub struct NewAuditEntry<'a> {
+    pub occurred_at: &'a str,
+    pub request_id: &'a str,
+    pub actor_user_id: Option<&'a i32>,
+    pub actor_username: Option<&'a str>,
+    pub action: &'a str,
+    pub entity: &'a str,
+    pub entity_id: &'a str,
+    pub before_json: Option<&'a str>,
+    pub after_json: Option<&'a str>,
+    pub diff_json: Option<&'a str>,
+}
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct AuditFilter {
+    pub entity: Option<String>,
+    pub id: Option<String>,
+    pub actor_user_id: Option<i32>,
+    pub from: Option<String>,
+    pub to: Option<String>,
+    pub limit: Option<i64>,
+}
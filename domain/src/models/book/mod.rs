@@ -1,15 +1,16 @@
 #![allow(unused_imports)]
 #![allow(unused_braces)]
 
-use diesel::{Queryable, Insertable};
+use diesel::{AsChangeset, Queryable, Insertable};
 use diesel::sql_types::{Integer, Text, Timestamp};
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 use crate::schema::books as book_schema;
 use crate::schema::borrowed_books as borrowed_books_schema;
 use crate::schema::library_books as library_books_schema;
 
 #[derive(Debug, Queryable)]
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, ToSchema)]
 #[diesel(table_name = book_schema)]
 pub struct Book {
     pub id: i32,
@@ -17,6 +18,9 @@ pub struct Book {
     pub author:  String,
     pub created_at: String,
     pub updated_at: String,
+    pub version: i32,
+    // ISBN-10 or ISBN-13 without hyphens or spaces.
+    pub isbn: Option<String>,
 }
 
 #[derive(Debug, Insertable)]
@@ -24,6 +28,7 @@ pub struct Book {
 pub struct NewBook<'a> {
     pub title: &'a str,
     pub author: &'a str,
+    pub isbn: Option<&'a str>,
     pub created_at: &'a str,
     pub updated_at: &'a str,
 }
@@ -34,7 +39,20 @@ pub struct NewBook<'a> {
 pub struct NewBorrowedBook<'a> {
     pub member_id: &'a i32,
     pub book_id: &'a i32,
+    pub library_id: Option<i32>,
     pub borrowed_at: &'a str,
+    pub due_at: &'a str,
+}
+
+// A library's holding of a book.
+#[derive(Debug, Queryable)]
+#[derive(Serialize, Deserialize, ToSchema)]
+#[diesel(table_name = library_books_schema)]
+pub struct LibraryBook {
+    pub library_id: i32,
+    pub book_id: i32,
+    pub quantity: i32,
+    pub added_at: Option<String>,
 }
 
 #[derive(Insertable)]
@@ -43,4 +61,38 @@ pub struct NewLibraryBook<'a> {
     pub library_id: &'a i32,
     pub book_id: &'a i32,
     pub quantity: &'a i32,
-}
\ No newline at end of file
+    pub added_at: &'a str,
+}
+
+// Partial update; columns left as None are not touched.
+#[derive(Debug, Default, AsChangeset)]
+#[diesel(table_name = book_schema)]
+pub struct BookChanges<'a> {
+    pub title: Option<&'a str>,
+    pub author: Option<&'a str>,
+    pub isbn: Option<Option<&'a str>>,
+}
+
+impl BookChanges<'_> {
+    pub fn is_empty(&self) -> bool {
+        self.title.is_none() && self.author.is_none() && self.isbn.is_none()
+    }
+}
+
+// Strips hyphens and spaces from an ISBN and checks its length and check digit; None when it is not a valid
+// ISBN-10 or ISBN-13.
+pub fn normalize_isbn(value: &str) -> Option<String> {
+    let isbn: String = value.chars().filter(|c| *c != '-' && *c != ' ').collect::<String>().to_uppercase();
+    let digit = |c: char| c.to_digit(10);
+    let valid = match isbn.len() {
+        10 => isbn.chars().enumerate().try_fold(0, |sum, (i, c)| {
+            let value = if i == 9 && c == 'X' { Some(10) } else { digit(c) };
+            value.map(|value| sum + value * (10 - i as u32))
+        }).is_some_and(|sum| sum % 11 == 0),
+        13 => isbn.chars().enumerate().try_fold(0, |sum, (i, c)| {
+            digit(c).map(|value| sum + value * if i % 2 == 0 { 1 } else { 3 })
+        }).is_some_and(|sum| sum % 10 == 0),
+        _ => false,
+    };
+    valid.then_some(isbn)
+}
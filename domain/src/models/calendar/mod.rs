@@ -0,0 +1,37 @@
+use diesel::{Insertable, Queryable};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use crate::schema::library_closures as library_closures_schema;
+use crate::schema::library_hours as library_hours_schema;
+
+// When a library is open on one day of the week; 1 is Monday and 7 is Sunday.
+#[derive(Debug, Clone, Queryable, Insertable)]
+#[derive(Serialize, Deserialize, ToSchema)]
+#[diesel(table_name = library_hours_schema)]
+pub struct OpeningHours {
+    pub library_id: i32,
+    pub weekday: i32,
+    // HH:MM, local to the library.
+    pub opens_at: String,
+    pub closes_at: String,
+}
+
+// A day the library stays closed regardless of its opening hours, e.g. a public holiday.
+#[derive(Debug, Clone, Queryable)]
+#[derive(Serialize, Deserialize, ToSchema)]
+#[diesel(table_name = library_closures_schema)]
+pub struct Closure {
+    pub id: i32,
+    pub library_id: i32,
+    // YYYY-MM-DD
+    pub date: String,
+    pub reason: String,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = library_closures_schema)]
+pub struct NewClosure<'a> {
+    pub library_id: &'a i32,
+    pub date: &'a str,
+    pub reason: &'a str,
+}
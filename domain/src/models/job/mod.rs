@@ -0,0 +1,39 @@
+use diesel::{Insertable, Queryable, QueryableByName};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use crate::schema::job_runs as job_runs_schema;
+
+pub const TRIGGER_SCHEDULE: &str = "schedule";
+pub const TRIGGER_MANUAL: &str = "manual";
+
+pub const STATUS_RUNNING: &str = "running";
+pub const STATUS_SUCCEEDED: &str = "succeeded";
+pub const STATUS_FAILED: &str = "failed";
+
+// One execution of a background job. `scheduled_for` is set for scheduled runs only.
+#[derive(Debug, Clone, Queryable, QueryableByName)]
+#[derive(Serialize, Deserialize, ToSchema)]
+#[diesel(table_name = job_runs_schema)]
+pub struct JobRun {
+    pub id: i32,
+    pub job: String,
+    pub trigger: String,
+    pub scheduled_for: Option<String>,
+    pub instance: String,
+    pub status: String,
+    pub started_at: String,
+    pub finished_at: Option<String>,
+    pub output: Option<String>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = job_runs_schema)]
+pub struct NewJobRun<'a> {
+    pub job: &'a str,
+    pub trigger: &'a str,
+    pub scheduled_for: Option<&'a str>,
+    pub instance: &'a str,
+    pub status: &'a str,
+    pub started_at: &'a str,
+}
@@ -1,13 +1,14 @@
 #![allow(unused_imports)]
 #![allow(unused_braces)]
 
-use diesel::{Queryable, Insertable};
-use diesel::sql_types::{Integer, Text, Timestamp};
+use diesel::{AsChangeset, Queryable, QueryableByName, Insertable};
+use diesel::sql_types::{BigInt, Integer, Text, Timestamp};
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 use crate::schema::library as library_schema;
 
 #[derive(Debug, Queryable)]
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, ToSchema)]
 #[diesel(table_name = library_schema)]
 pub struct Library {
     pub id: i32,
@@ -16,6 +17,76 @@ pub struct Library {
     pub created_at: String,
     pub updated_at: String,
     pub manager_id: i32,
+    pub version: i32,
+    // Days after return before loans booked here are unlinked from members; None uses
+    // `loans.history_retention_days`, 0 keeps them linked.
+    pub loan_retention_days: Option<i32>,
+    pub street: Option<String>,
+    pub city: Option<String>,
+    pub postal_code: Option<String>,
+    pub country: Option<String>,
+    pub phone: Option<String>,
+    pub email: Option<String>,
+    pub website: Option<String>,
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+}
+
+impl Library {
+    // (latitude, longitude) when both are known.
+    pub fn location(&self) -> Option<(f64, f64)> {
+        self.latitude.zip(self.longitude)
+    }
+
+    pub fn details(&self) -> LibraryDetails {
+        LibraryDetails {
+            street: self.street.clone(),
+            city: self.city.clone(),
+            postal_code: self.postal_code.clone(),
+            country: self.country.clone(),
+            phone: self.phone.clone(),
+            email: self.email.clone(),
+            website: self.website.clone(),
+            latitude: self.latitude,
+            longitude: self.longitude,
+        }
+    }
+}
+
+// Structured address, contact details and position of a library; all optional. Written as a whole, so fields
+// left as None are cleared.
+#[derive(Debug, Clone, Default, PartialEq, Insertable, AsChangeset)]
+#[derive(Serialize, Deserialize, ToSchema)]
+#[diesel(table_name = library_schema, treat_none_as_null = true)]
+pub struct LibraryDetails {
+    pub street: Option<String>,
+    pub city: Option<String>,
+    pub postal_code: Option<String>,
+    pub country: Option<String>,
+    pub phone: Option<String>,
+    pub email: Option<String>,
+    pub website: Option<String>,
+    // Decimal degrees (WGS 84).
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+}
+
+// Great-circle distance in kilometres between two (latitude, longitude) points.
+pub fn distance_km(from: (f64, f64), to: (f64, f64)) -> f64 {
+    const EARTH_RADIUS_KM: f64 = 6371.0088;
+    let (lat1, lon1) = (from.0.to_radians(), from.1.to_radians());
+    let (lat2, lon2) = (to.0.to_radians(), to.1.to_radians());
+    let a = ((lat2 - lat1) / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * ((lon2 - lon1) / 2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS_KM * a.sqrt().min(1.0).asin()
+}
+
+// How many copies of a book a library has on the shelf.
+#[derive(Debug, QueryableByName)]
+pub struct LibraryStock {
+    #[diesel(sql_type = Integer)]
+    pub library_id: i32,
+    #[diesel(sql_type = BigInt)]
+    pub available: i64,
 }
 
 #[derive(Debug, Insertable)]
@@ -26,4 +97,30 @@ pub struct NewLibrary<'a> {
     pub manager_id: &'a i32,
     pub created_at: &'a str,
     pub updated_at: &'a str,
+    #[diesel(embed)]
+    pub details: LibraryDetails,
+}
+
+// Partial update; columns left as None are not touched.
+#[derive(Debug, Default, AsChangeset)]
+#[diesel(table_name = library_schema)]
+pub struct LibraryChanges<'a> {
+    pub name: Option<&'a str>,
+    pub address: Option<&'a str>,
+    pub manager_id: Option<&'a i32>,
+    // Some(None) falls back to the configured default.
+    pub loan_retention_days: Option<Option<i32>>,
+    // Replaces all contact and location columns when Some.
+    #[diesel(embed)]
+    pub details: Option<LibraryDetails>,
+}
+
+impl LibraryChanges<'_> {
+    pub fn is_empty(&self) -> bool {
+        self.name.is_none()
+            && self.address.is_none()
+            && self.manager_id.is_none()
+            && self.loan_retention_days.is_none()
+            && self.details.is_none()
+    }
 }
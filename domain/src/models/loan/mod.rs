@@ -0,0 +1,54 @@
+use diesel::sql_types::{Integer, Nullable, Text};
+use diesel::{Insertable, Queryable, QueryableByName};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use crate::schema::holds as holds_schema;
+
+// One loan of a member, with the book and library it was booked to.
+#[derive(Debug, Clone, QueryableByName)]
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct Loan {
+    #[diesel(sql_type = Integer)]
+    pub id: i32,
+    #[diesel(sql_type = Integer)]
+    pub book_id: i32,
+    #[diesel(sql_type = Text)]
+    pub title: String,
+    #[diesel(sql_type = Text)]
+    pub author: String,
+    #[diesel(sql_type = Nullable<Integer>)]
+    pub library_id: Option<i32>,
+    #[diesel(sql_type = Nullable<Text>)]
+    pub library_name: Option<String>,
+    #[diesel(sql_type = Text)]
+    pub borrowed_at: String,
+    #[diesel(sql_type = Nullable<Text>)]
+    pub due_at: Option<String>,
+    #[diesel(sql_type = Nullable<Text>)]
+    pub returned_at: Option<String>,
+    #[diesel(sql_type = Integer)]
+    pub renewals: i32,
+}
+
+// A member waiting for a book, optionally at one library.
+#[derive(Debug, Clone, Queryable)]
+#[derive(Serialize, Deserialize, ToSchema)]
+#[diesel(table_name = holds_schema)]
+pub struct Hold {
+    pub id: i32,
+    pub member_id: i32,
+    pub book_id: i32,
+    pub library_id: Option<i32>,
+    pub placed_at: String,
+    pub fulfilled_at: Option<String>,
+    pub cancelled_at: Option<String>,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = holds_schema)]
+pub struct NewHold<'a> {
+    pub member_id: &'a i32,
+    pub book_id: &'a i32,
+    pub library_id: Option<&'a i32>,
+    pub placed_at: &'a str,
+}
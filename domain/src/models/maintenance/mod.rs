@@ -0,0 +1,58 @@
+use diesel::QueryableByName;
+use diesel::sql_types::{BigInt, Integer, Nullable};
+use serde::{Deserialize, Serialize};
+
+// Tables carried by a data export, in an order that satisfies their foreign keys on import. Together with
+// `RUNTIME_TABLES` they cover the whole schema, so an export is a full backup of the data.
+pub const DATA_TABLES: [&str; 22] = [
+    "members",
+    "library",
+    "users",
+    "user_roles",
+    "api_keys",
+    "books",
+    "library_books",
+    "library_members",
+    "borrowed_books",
+    "loan_notices",
+    "holds",
+    "transfers",
+    "library_hours",
+    "library_closures",
+    "library_staff",
+    "library_handovers",
+    "vendors",
+    "budgets",
+    "purchase_orders",
+    "purchase_order_lines",
+    "audit_log",
+    "job_runs",
+];
+
+// Tables an export leaves out: sessions, job leases and the migration record only mean something to the
+// running installation.
+pub const RUNTIME_TABLES: [&str; 4] = ["refresh_tokens", "job_locks", "schema_migrations", "sqlite_sequence"];
+
+// The outcome of one integrity check; `keys` identifies the first offending rows.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct IntegrityCheck {
+    pub name: String,
+    pub description: String,
+    pub count: usize,
+    pub keys: Vec<String>,
+}
+
+// A holding whose stock is below the loans booked against it. `quantity` is None when the library has no
+// holding row for the book at all.
+#[derive(Debug, QueryableByName)]
+#[derive(Serialize, Deserialize)]
+pub struct StockChange {
+    #[diesel(sql_type = Integer)]
+    pub library_id: i32,
+    #[diesel(sql_type = Integer)]
+    pub book_id: i32,
+    #[diesel(sql_type = Nullable<Integer>)]
+    pub quantity: Option<i32>,
+    #[diesel(sql_type = BigInt)]
+    pub open_loans: i64,
+}
@@ -1,13 +1,20 @@
 #![allow(unused_imports)]
 #![allow(unused_braces)]
 
-use diesel::{Queryable, Insertable};
+use diesel::{AsChangeset, Queryable, Insertable};
 use diesel::sql_types::{Integer, Text, Timestamp};
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 use crate::schema::members as members_schema;
 
+pub const MEMBER_ACTIVE: &str = "active";
+pub const MEMBER_EXPIRED: &str = "expired";
+pub const MEMBER_SUSPENDED: &str = "suspended";
+pub const MEMBER_BANNED: &str = "banned";
+pub const MEMBER_STATUSES: [&str; 4] = [MEMBER_ACTIVE, MEMBER_EXPIRED, MEMBER_SUSPENDED, MEMBER_BANNED];
+
 #[derive(Debug, Queryable)]
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, ToSchema)]
 #[diesel(table_name = members_schema)]
 pub struct Member {
     pub id: i32,
@@ -15,6 +22,83 @@ pub struct Member {
     pub email: String,
     pub created_at: String,
     pub updated_at: String,
+    pub version: i32,
+    // Preferred language for notices, e.g. `en`; None falls back to notifications.default_language.
+    pub language: Option<String>,
+    // False stops due-date reminders; overdue notices are always sent.
+    pub notify_reminders: bool,
+    // active, expired, suspended or banned, as last stored; see `effective_status`.
+    pub status: String,
+    pub status_reason: Option<String>,
+    // Dates (YYYY-MM-DD). A suspension lasts through `status_until`, or indefinitely without one.
+    pub status_since: Option<String>,
+    pub status_until: Option<String>,
+    // Last day of the membership; None never expires.
+    pub expires_at: Option<String>,
+    // `normalize_email(email)`, unique across members; None only on duplicates that predate the constraint.
+    #[serde(skip)]
+    pub email_normalized: Option<String>,
+    // When the member's personal data was erased; the row stays so loans still count in statistics.
+    pub erased_at: Option<String>,
+    // Keeps returned loans linked to the member past the libraries' retention periods.
+    pub keep_loan_history: bool,
+}
+
+impl Member {
+    // The status as of `today` (YYYY-MM-DD), even before the `member_status` job has stored it:
+    // a suspension past its end date is over and a membership past its last day has expired.
+    pub fn effective_status(&self, today: &str) -> &str {
+        match self.status.as_str() {
+            MEMBER_BANNED => MEMBER_BANNED,
+            MEMBER_SUSPENDED if self.status_until.as_deref().is_none_or(|until| until >= today) => MEMBER_SUSPENDED,
+            MEMBER_EXPIRED => MEMBER_EXPIRED,
+            _ if self.expires_at.as_deref().is_some_and(|expires| expires < today) => MEMBER_EXPIRED,
+            _ => MEMBER_ACTIVE,
+        }
+    }
+
+    // Whether `other`'s ban or suspension outlasts this member's as of `today`: a ban beats any suspension,
+    // an open-ended suspension beats one with an end date, and a later end date beats an earlier one.
+    pub fn block_outlasted_by(&self, other: &Member, today: &str) -> bool {
+        fn severity<'a>(member: &'a Member, today: &str) -> (u8, Option<&'a str>) {
+            match (member.effective_status(today), member.status_until.as_deref()) {
+                (MEMBER_BANNED, _) => (3, None),
+                (MEMBER_SUSPENDED, None) => (2, None),
+                (MEMBER_SUSPENDED, until) => (1, until),
+                _ => (0, None),
+            }
+        }
+        severity(other, today) > severity(self, today)
+    }
+
+    // Why the member may not be assigned to a library's staff, or None when they may.
+    pub fn staff_block(&self) -> Option<String> {
+        if self.erased_at.is_some() {
+            return Some(format!("Member {} has been erased", self.id));
+        }
+        (self.status == MEMBER_BANNED).then(|| format!("Member {} is banned", self.id))
+    }
+
+    // Why the member may not borrow or place holds as of `today`, or None when they may.
+    pub fn circulation_block(&self, today: &str) -> Option<String> {
+        if self.erased_at.is_some() {
+            return Some(format!("Member {} has been erased", self.id));
+        }
+        let reason = self.status_reason.as_deref().map(|reason| format!(": {}", reason)).unwrap_or_default();
+        match self.effective_status(today) {
+            MEMBER_BANNED => Some(format!("Member {} is banned{}", self.id, reason)),
+            MEMBER_SUSPENDED => match &self.status_until {
+                Some(until) => Some(format!("Member {} is suspended through {}{}", self.id, until, reason)),
+                None => Some(format!("Member {} is suspended{}", self.id, reason)),
+            },
+            MEMBER_EXPIRED => Some(format!(
+                "The membership of member {} expired on {}; renew it to borrow again",
+                self.id,
+                self.expires_at.as_deref().unwrap_or("an earlier date")
+            )),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Debug, Insertable)]
@@ -24,4 +108,216 @@ pub struct NewMember<'a> {
     pub email: &'a str,
     pub created_at: &'a str,
     pub updated_at: &'a str,
+    pub language: Option<&'a str>,
+    pub expires_at: Option<&'a str>,
+    pub email_normalized: Option<&'a str>,
+}
+
+// Partial update; columns left as None are not touched.
+#[derive(Debug, Default, AsChangeset)]
+#[diesel(table_name = members_schema)]
+pub struct MemberChanges<'a> {
+    pub name: Option<&'a str>,
+    pub email: Option<&'a str>,
+    // Some(None) clears the language.
+    pub language: Option<Option<&'a str>>,
+    pub notify_reminders: Option<bool>,
+    pub status: Option<&'a str>,
+    pub status_reason: Option<Option<&'a str>>,
+    pub status_since: Option<Option<&'a str>>,
+    pub status_until: Option<Option<&'a str>>,
+    pub expires_at: Option<Option<&'a str>>,
+    pub keep_loan_history: Option<bool>,
+}
+
+impl MemberChanges<'_> {
+    pub fn is_empty(&self) -> bool {
+        self.name.is_none()
+            && self.email.is_none()
+            && self.language.is_none()
+            && self.notify_reminders.is_none()
+            && self.status.is_none()
+            && self.status_reason.is_none()
+            && self.status_since.is_none()
+            && self.status_until.is_none()
+            && self.expires_at.is_none()
+            && self.keep_loan_history.is_none()
+    }
+}
+
+// Emails are unique regardless of case and surrounding blanks.
+pub fn normalize_email(email: &str) -> String {
+    email.trim().to_lowercase()
+}
+
+fn normalize_name(name: &str) -> String {
+    name.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase()
+}
+
+// The mailbox behind an address: without a `+tag` and dots in the local part, so `Ada.L+books@x.org` and
+// `adal@x.org` match.
+fn mailbox(email: &str) -> String {
+    let email = normalize_email(email);
+    match email.split_once('@') {
+        Some((local, domain)) => {
+            let local = local.split('+').next().unwrap_or_default().replace('.', "");
+            format!("{}@{}", local, domain)
+        }
+        None => email,
+    }
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let cost = if ca == *cb { 0 } else { 1 };
+            current.push((previous[j] + cost).min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+// Two member records that probably describe the same person.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct DuplicateCandidate {
+    pub member_id: i32,
+    pub duplicate_id: i32,
+    pub name: String,
+    pub emails: Vec<String>,
+    // "same email" or "same name, similar email".
+    pub reason: String,
+}
+
+// Pairs with the same normalized email, or the same normalized name and emails that share a mailbox or
+// differ by at most two characters. The older record comes first.
+pub fn duplicate_candidates(members: &[Member]) -> Vec<DuplicateCandidate> {
+    let mut by_name: std::collections::BTreeMap<String, Vec<&Member>> = std::collections::BTreeMap::new();
+    let mut by_email: std::collections::BTreeMap<String, Vec<&Member>> = std::collections::BTreeMap::new();
+    for member in members.iter().filter(|member| member.erased_at.is_none()) {
+        by_name.entry(normalize_name(&member.name)).or_default().push(member);
+        by_email.entry(normalize_email(&member.email)).or_default().push(member);
+    }
+
+    let mut candidates = Vec::new();
+    let mut pair = |a: &Member, b: &Member, reason: &str| {
+        let (a, b) = if a.id < b.id { (a, b) } else { (b, a) };
+        if candidates.iter().any(|c: &DuplicateCandidate| c.member_id == a.id && c.duplicate_id == b.id) {
+            return;
+        }
+        candidates.push(DuplicateCandidate {
+            member_id: a.id,
+            duplicate_id: b.id,
+            name: a.name.clone(),
+            emails: vec![a.email.clone(), b.email.clone()],
+            reason: reason.to_string(),
+        });
+    };
+
+    for group in by_email.values() {
+        for (i, a) in group.iter().enumerate() {
+            for b in &group[i + 1..] {
+                pair(a, b, "same email");
+            }
+        }
+    }
+    for group in by_name.values() {
+        for (i, a) in group.iter().enumerate() {
+            for b in &group[i + 1..] {
+                let (email_a, email_b) = (normalize_email(&a.email), normalize_email(&b.email));
+                if email_a != email_b && (mailbox(&email_a) == mailbox(&email_b) || edit_distance(&email_a, &email_b) <= 2) {
+                    pair(a, b, "same name, similar email");
+                }
+            }
+        }
+    }
+
+    candidates.sort_by_key(|c| (c.member_id, c.duplicate_id));
+    candidates
+}
+
+// What a merge moved from the duplicate to the member that was kept.
+#[derive(Debug, Default, Serialize, Deserialize, ToSchema)]
+pub struct MergeSummary {
+    pub loans: usize,
+    pub library_memberships: usize,
+    pub holds: usize,
+    pub accounts: usize,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn member(id: i32, name: &str, email: &str) -> Member {
+        Member {
+            id,
+            name: name.to_string(),
+            email: email.to_string(),
+            created_at: "2026-01-01".to_string(),
+            updated_at: "2026-01-01".to_string(),
+            version: 1,
+            language: None,
+            notify_reminders: true,
+            status: MEMBER_ACTIVE.to_string(),
+            status_reason: None,
+            status_since: None,
+            status_until: None,
+            expires_at: None,
+            email_normalized: Some(normalize_email(email)),
+            erased_at: None,
+            keep_loan_history: false,
+        }
+    }
+
+    fn suspended(until: Option<&str>) -> Member {
+        Member { status: MEMBER_SUSPENDED.to_string(), status_until: until.map(str::to_string), ..member(2, "Ada", "a@x.org") }
+    }
+
+    #[test]
+    fn edit_distance_counts_single_character_edits() {
+        assert_eq!(edit_distance("", ""), 0);
+        assert_eq!(edit_distance("ada", ""), 3);
+        assert_eq!(edit_distance("ada@x.org", "ada@x.org"), 0);
+        assert_eq!(edit_distance("ada@x.org", "adda@x.org"), 1);
+        assert_eq!(edit_distance("ada@x.org", "ade@y.org"), 2);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+    }
+
+    #[test]
+    fn duplicates_match_on_email_or_name_with_a_similar_email() {
+        let members = vec![
+            member(1, "Ada Lovelace", "ada@x.org"),
+            member(2, "Someone Else", " ADA@x.org"),
+            member(3, "ada  lovelace", "A.da+books@x.org"),
+            member(4, "Ada Lovelace", "adda@x.org"),
+            member(5, "Ada Lovelace", "charles@y.org"),
+            Member { erased_at: Some("2026-02-01".to_string()), ..member(6, "Ada Lovelace", "ada@x.org") },
+        ];
+
+        let pairs: Vec<(i32, i32, &str)> = duplicate_candidates(&members)
+            .iter()
+            .map(|c| (c.member_id, c.duplicate_id, if c.reason == "same email" { "email" } else { "name" }))
+            .collect();
+        assert_eq!(pairs, vec![(1, 2, "email"), (1, 3, "name"), (1, 4, "name")]);
+    }
+
+    #[test]
+    fn the_longer_block_wins_a_merge() {
+        let today = "2026-03-01";
+        let active = member(1, "Ada", "a@x.org");
+        let banned = Member { status: MEMBER_BANNED.to_string(), ..member(2, "Ada", "a@x.org") };
+
+        assert!(active.block_outlasted_by(&banned, today));
+        assert!(!banned.block_outlasted_by(&suspended(None), today));
+        assert!(suspended(Some("2026-04-01")).block_outlasted_by(&suspended(None), today));
+        assert!(suspended(Some("2026-04-01")).block_outlasted_by(&suspended(Some("2026-05-01")), today));
+        assert!(!suspended(Some("2026-05-01")).block_outlasted_by(&suspended(Some("2026-04-01")), today));
+        // A suspension that already ended is no block.
+        assert!(!active.block_outlasted_by(&suspended(Some("2026-02-01")), today));
+        assert!(!active.block_outlasted_by(&active, today));
+    }
 }
@@ -1,3 +1,16 @@
+pub mod acquisition;
+pub mod audit;
+pub mod calendar;
 pub mod library;
+pub mod loan;
+pub mod maintenance;
 pub mod member;
-pub mod book;
\ No newline at end of file
+pub mod notice;
+pub mod report;
+pub mod book;
+pub mod job;
+pub mod user;
+pub mod role;
+pub mod staff;
+pub mod stats;
+pub mod transfer;
\ No newline at end of file
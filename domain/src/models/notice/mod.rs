@@ -0,0 +1,83 @@
+use diesel::sql_types::{Integer, Nullable, Text};
+use diesel::{Insertable, Queryable, QueryableByName};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+use crate::schema::loan_notices as loan_notices_schema;
+
+pub const NOTICE_REMINDER: &str = "reminder";
+pub const NOTICE_OVERDUE: &str = "overdue";
+pub const NOTICE_KINDS: [&str; 2] = [NOTICE_REMINDER, NOTICE_OVERDUE];
+
+// One notice that went out; a loan gets at most one of each kind per due date.
+#[derive(Debug, Queryable)]
+#[derive(Serialize, Deserialize, ToSchema)]
+#[diesel(table_name = loan_notices_schema)]
+pub struct LoanNotice {
+    pub id: i32,
+    pub member_id: i32,
+    pub book_id: i32,
+    pub borrowed_at: String,
+    pub kind: String,
+    pub channel: String,
+    pub recipient: String,
+    pub sent_at: String,
+    // The due date the notice was about; a renewal moves it and makes the loan due for notices again.
+    pub due_at: Option<String>,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = loan_notices_schema)]
+pub struct NewLoanNotice<'a> {
+    pub member_id: &'a i32,
+    pub book_id: &'a i32,
+    pub borrowed_at: &'a str,
+    pub kind: &'a str,
+    pub channel: &'a str,
+    pub recipient: &'a str,
+    pub sent_at: &'a str,
+    pub due_at: Option<&'a str>,
+}
+
+// A loan that needs a notice, with everything the templates can mention.
+#[derive(Debug, Clone, QueryableByName)]
+pub struct DueLoan {
+    #[diesel(sql_type = Integer)]
+    pub member_id: i32,
+    #[diesel(sql_type = Text)]
+    pub member_name: String,
+    #[diesel(sql_type = Text)]
+    pub member_email: String,
+    #[diesel(sql_type = Nullable<Text>)]
+    pub language: Option<String>,
+    #[diesel(sql_type = Integer)]
+    pub book_id: i32,
+    #[diesel(sql_type = Text)]
+    pub book_title: String,
+    #[diesel(sql_type = Text)]
+    pub borrowed_at: String,
+    #[diesel(sql_type = Text)]
+    pub due_at: String,
+    // The member's library that holds the book, if any; picks library specific templates.
+    #[diesel(sql_type = Nullable<Integer>)]
+    pub library_id: Option<i32>,
+    #[diesel(sql_type = Nullable<Text>)]
+    pub library_name: Option<String>,
+}
+
+// A rendered message, ready for a notifier.
+#[derive(Debug, Clone)]
+pub struct Notice {
+    pub kind: String,
+    pub recipient_name: String,
+    pub recipient_email: String,
+    pub subject: String,
+    pub body: String,
+}
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct NoticeFilter {
+    pub member_id: Option<i32>,
+    pub kind: Option<String>,
+    pub limit: Option<i64>,
+}
@@ -0,0 +1,142 @@
+use diesel::QueryableByName;
+use diesel::sql_types::{BigInt, Double, Integer, Nullable, Text};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+pub const FORMAT_JSON: &str = "json";
+pub const FORMAT_CSV: &str = "csv";
+
+// Query parameters shared by every report. Dates are whole days (YYYY-MM-DD), both ends inclusive.
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ReportFilter {
+    pub from: Option<String>,
+    pub to: Option<String>,
+    pub library_id: Option<i32>,
+    pub limit: Option<i64>,
+    // `json` (the default) or `csv`.
+    pub format: Option<String>,
+}
+
+// A report's period as the half-open timestamp range [from, until), resolved from a `ReportFilter`.
+#[derive(Debug, Clone)]
+pub struct ReportScope {
+    pub from: String,
+    pub until: String,
+    pub library_id: Option<i32>,
+    pub limit: i64,
+}
+
+#[derive(Debug, QueryableByName)]
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct TitleLoans {
+    #[diesel(sql_type = Integer)]
+    pub book_id: i32,
+    #[diesel(sql_type = Text)]
+    pub title: String,
+    #[diesel(sql_type = Text)]
+    pub author: String,
+    #[diesel(sql_type = BigInt)]
+    pub loans: i64,
+}
+
+#[derive(Debug, QueryableByName)]
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct AuthorLoans {
+    #[diesel(sql_type = Text)]
+    pub author: String,
+    #[diesel(sql_type = BigInt)]
+    pub titles: i64,
+    #[diesel(sql_type = BigInt)]
+    pub loans: i64,
+}
+
+// Loans still out past their due date, and loans returned late in the period. Loans booked before libraries
+// were recorded have no library.
+#[derive(Debug, QueryableByName)]
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct LibraryOverdue {
+    #[diesel(sql_type = Nullable<Integer>)]
+    pub library_id: Option<i32>,
+    #[diesel(sql_type = Nullable<Text>)]
+    pub library_name: Option<String>,
+    #[diesel(sql_type = BigInt)]
+    pub overdue: i64,
+    #[diesel(sql_type = BigInt)]
+    pub returned_late: i64,
+    #[diesel(sql_type = Nullable<Text>)]
+    pub oldest_due_at: Option<String>,
+}
+
+#[derive(Debug, QueryableByName)]
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct LibraryUtilization {
+    #[diesel(sql_type = Integer)]
+    pub library_id: i32,
+    #[diesel(sql_type = Text)]
+    pub library_name: String,
+    #[diesel(sql_type = BigInt)]
+    pub copies: i64,
+    #[diesel(sql_type = BigInt)]
+    pub loans: i64,
+    #[diesel(sql_type = Double)]
+    pub loans_per_copy: f64,
+}
+
+#[derive(Debug, QueryableByName)]
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct MemberActivity {
+    #[diesel(sql_type = Integer)]
+    pub member_id: i32,
+    #[diesel(sql_type = Text)]
+    pub member_name: String,
+    #[diesel(sql_type = BigInt)]
+    pub loans: i64,
+    #[diesel(sql_type = BigInt)]
+    pub titles: i64,
+    #[diesel(sql_type = BigInt)]
+    pub overdue: i64,
+    #[diesel(sql_type = Nullable<Text>)]
+    pub last_borrowed_at: Option<String>,
+}
+
+#[derive(Debug, QueryableByName)]
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct Acquisition {
+    #[diesel(sql_type = Integer)]
+    pub library_id: i32,
+    #[diesel(sql_type = Text)]
+    pub library_name: String,
+    #[diesel(sql_type = Integer)]
+    pub book_id: i32,
+    #[diesel(sql_type = Text)]
+    pub title: String,
+    #[diesel(sql_type = Text)]
+    pub author: String,
+    #[diesel(sql_type = Integer)]
+    pub quantity: i32,
+    #[diesel(sql_type = Text)]
+    pub added_at: String,
+}
+
+// Amounts are in cents. Committed counts every copy ordered, spent only those received.
+#[derive(Debug, QueryableByName)]
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct BudgetSpending {
+    #[diesel(sql_type = Integer)]
+    pub library_id: i32,
+    #[diesel(sql_type = Text)]
+    pub library_name: String,
+    #[diesel(sql_type = Integer)]
+    pub fiscal_year: i32,
+    #[diesel(sql_type = BigInt)]
+    pub orders: i64,
+    #[diesel(sql_type = BigInt)]
+    pub budget_cents: i64,
+    #[diesel(sql_type = BigInt)]
+    pub committed_cents: i64,
+    #[diesel(sql_type = BigInt)]
+    pub spent_cents: i64,
+    #[diesel(sql_type = BigInt)]
+    pub remaining_cents: i64,
+}
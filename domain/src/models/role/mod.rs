@@ -0,0 +1,129 @@
+#![allow(unused_imports)]
+#![allow(unused_braces)]
+
+use std::fmt;
+
+use diesel::{Queryable, Insertable};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use crate::schema::user_roles as user_roles_schema;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    SystemAdmin,
+    LibraryManager,
+    Librarian,
+    Member,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Permission {
+    ManageSystem,
+    ManageLibrary,
+    ManageStaff,
+    ManageHoldings,
+    ManageMembers,
+    ManageLoans,
+    ViewReports,
+    ViewOwnLoans,
+}
+
+impl Role {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::SystemAdmin => "system_admin",
+            Role::LibraryManager => "library_manager",
+            Role::Librarian => "librarian",
+            Role::Member => "member",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Role> {
+        match value {
+            "system_admin" => Some(Role::SystemAdmin),
+            "library_manager" => Some(Role::LibraryManager),
+            "librarian" => Some(Role::Librarian),
+            "member" => Some(Role::Member),
+            _ => None,
+        }
+    }
+
+    pub fn permissions(&self) -> &'static [Permission] {
+        match self {
+            Role::SystemAdmin => &[
+                Permission::ManageSystem,
+                Permission::ManageLibrary,
+                Permission::ManageStaff,
+                Permission::ManageHoldings,
+                Permission::ManageMembers,
+                Permission::ManageLoans,
+                Permission::ViewReports,
+                Permission::ViewOwnLoans,
+            ],
+            Role::LibraryManager => &[
+                Permission::ManageLibrary,
+                Permission::ManageStaff,
+                Permission::ManageHoldings,
+                Permission::ManageMembers,
+                Permission::ManageLoans,
+                Permission::ViewReports,
+                Permission::ViewOwnLoans,
+            ],
+            Role::Librarian => &[
+                Permission::ManageHoldings,
+                Permission::ManageMembers,
+                Permission::ManageLoans,
+                Permission::ViewOwnLoans,
+            ],
+            Role::Member => &[Permission::ViewOwnLoans],
+        }
+    }
+
+    pub fn grants(&self, permission: Permission) -> bool {
+        self.permissions().contains(&permission)
+    }
+}
+
+impl Permission {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Permission::ManageSystem => "manage_system",
+            Permission::ManageLibrary => "manage_library",
+            Permission::ManageStaff => "manage_staff",
+            Permission::ManageHoldings => "manage_holdings",
+            Permission::ManageMembers => "manage_members",
+            Permission::ManageLoans => "manage_loans",
+            Permission::ViewReports => "view_reports",
+            Permission::ViewOwnLoans => "view_own_loans",
+        }
+    }
+}
+
+impl fmt::Display for Permission {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+// A role granted to a user; library_id None means the grant applies to every library.
+#[derive(Debug, Queryable)]
+#[derive(Serialize, Deserialize, ToSchema)]
+#[diesel(table_name = user_roles_schema)]
+pub struct UserRole {
+    pub id: i32,
+    pub user_id: i32,
+    pub role: String,
+    pub library_id: Option<i32>,
+    pub created_at: String,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = user_roles_schema)]
+pub struct NewUserRole<'a> {
+    pub user_id: &'a i32,
+    pub role: &'a str,
+    pub library_id: Option<&'a i32>,
+    pub created_at: &'a str,
+}
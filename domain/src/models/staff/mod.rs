@@ -0,0 +1,70 @@
+use diesel::{Insertable, Queryable};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use crate::models::role::Role;
+use crate::schema::library_handovers as library_handovers_schema;
+use crate::schema::library_staff as library_staff_schema;
+
+// Roles a member can be assigned at a library.
+pub const STAFF_ROLES: [Role; 2] = [Role::LibraryManager, Role::Librarian];
+
+// A member working at a library in a role from `started_at` until the day before `ended_at`, both YYYY-MM-DD.
+// Ended assignments stay as the library's staff history.
+#[derive(Debug, Clone, Queryable)]
+#[derive(Serialize, Deserialize, ToSchema)]
+#[diesel(table_name = library_staff_schema)]
+pub struct StaffAssignment {
+    pub id: i32,
+    pub library_id: i32,
+    pub member_id: i32,
+    // library_manager or librarian
+    pub role: String,
+    pub started_at: String,
+    // None while open-ended.
+    pub ended_at: Option<String>,
+    pub created_at: String,
+}
+
+impl StaffAssignment {
+    pub fn is_active(&self, today: &str) -> bool {
+        self.started_at.as_str() <= today && self.ended_at.as_deref().is_none_or(|ended| ended > today)
+    }
+
+    // Whether the assignment shares a day with the period from `started_at` until the day before `ended_at`.
+    pub fn overlaps(&self, started_at: &str, ended_at: Option<&str>) -> bool {
+        ended_at.is_none_or(|ended| self.started_at.as_str() < ended)
+            && self.ended_at.as_deref().is_none_or(|ended| started_at < ended)
+    }
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = library_staff_schema)]
+pub struct NewStaffAssignment<'a> {
+    pub library_id: &'a i32,
+    pub member_id: &'a i32,
+    pub role: &'a str,
+    pub started_at: &'a str,
+    pub ended_at: Option<&'a str>,
+    pub created_at: &'a str,
+}
+
+// The library's manager_id moving from one member to another.
+#[derive(Debug, Clone, Queryable)]
+#[derive(Serialize, Deserialize, ToSchema)]
+#[diesel(table_name = library_handovers_schema)]
+pub struct Handover {
+    pub id: i32,
+    pub library_id: i32,
+    pub from_member_id: Option<i32>,
+    pub to_member_id: i32,
+    pub handed_over_at: String,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = library_handovers_schema)]
+pub struct NewHandover<'a> {
+    pub library_id: &'a i32,
+    pub from_member_id: Option<&'a i32>,
+    pub to_member_id: &'a i32,
+    pub handed_over_at: &'a str,
+}
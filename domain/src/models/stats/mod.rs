@@ -0,0 +1,21 @@
+#![allow(unused_imports)]
+
+use diesel::QueryableByName;
+use diesel::sql_types::{BigInt, Integer};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LoanCounts {
+    pub active: i64,
+    pub overdue: i64,
+}
+
+// Copies on the shelf: the library's stock minus the loans of those titles.
+#[derive(Debug, QueryableByName)]
+#[derive(Serialize, Deserialize)]
+pub struct LibraryAvailability {
+    #[diesel(sql_type = Integer)]
+    pub library_id: i32,
+    #[diesel(sql_type = BigInt)]
+    pub available: i64,
+}
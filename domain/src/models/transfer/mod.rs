@@ -0,0 +1,81 @@
+use diesel::{Insertable, Queryable};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+use crate::schema::transfers as transfers_schema;
+
+pub const TRANSFER_REQUESTED: &str = "requested";
+pub const TRANSFER_APPROVED: &str = "approved";
+pub const TRANSFER_IN_TRANSIT: &str = "in_transit";
+pub const TRANSFER_RECEIVED: &str = "received";
+pub const TRANSFER_RETURNED: &str = "returned";
+pub const TRANSFER_CANCELLED: &str = "cancelled";
+
+pub const TRANSFER_STATUSES: [&str; 6] = [
+    TRANSFER_REQUESTED,
+    TRANSFER_APPROVED,
+    TRANSFER_IN_TRANSIT,
+    TRANSFER_RECEIVED,
+    TRANSFER_RETURNED,
+    TRANSFER_CANCELLED,
+];
+
+// Allowed status changes. Copies leave the lending library when shipped, join the borrowing library when
+// received and go back to the lender when returned.
+pub const TRANSFER_TRANSITIONS: [(&str, &str); 6] = [
+    (TRANSFER_REQUESTED, TRANSFER_APPROVED),
+    (TRANSFER_REQUESTED, TRANSFER_CANCELLED),
+    (TRANSFER_APPROVED, TRANSFER_CANCELLED),
+    (TRANSFER_APPROVED, TRANSFER_IN_TRANSIT),
+    (TRANSFER_IN_TRANSIT, TRANSFER_RECEIVED),
+    (TRANSFER_RECEIVED, TRANSFER_RETURNED),
+];
+
+pub fn can_transition(from: &str, to: &str) -> bool {
+    TRANSFER_TRANSITIONS.contains(&(from, to))
+}
+
+// Copies of one book moving from `from_library_id` to `to_library_id`, optionally for a waiting member.
+#[derive(Debug, Clone, Queryable)]
+#[derive(Serialize, Deserialize, ToSchema)]
+#[diesel(table_name = transfers_schema)]
+pub struct Transfer {
+    pub id: i32,
+    pub book_id: i32,
+    pub from_library_id: i32,
+    pub to_library_id: i32,
+    pub member_id: Option<i32>,
+    pub quantity: i32,
+    pub status: String,
+    pub note: Option<String>,
+    pub requested_by: Option<i32>,
+    pub requested_at: String,
+    pub approved_at: Option<String>,
+    pub shipped_at: Option<String>,
+    pub received_at: Option<String>,
+    pub returned_at: Option<String>,
+    pub cancelled_at: Option<String>,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = transfers_schema)]
+pub struct NewTransfer<'a> {
+    pub book_id: &'a i32,
+    pub from_library_id: &'a i32,
+    pub to_library_id: &'a i32,
+    pub member_id: Option<&'a i32>,
+    pub quantity: &'a i32,
+    pub status: &'a str,
+    pub note: Option<&'a str>,
+    pub requested_by: Option<&'a i32>,
+    pub requested_at: &'a str,
+}
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct TransferFilter {
+    // Transfers from or to this library.
+    pub library_id: Option<i32>,
+    pub status: Option<String>,
+    pub book_id: Option<i32>,
+    pub limit: Option<i64>,
+}
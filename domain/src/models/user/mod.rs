@@ -0,0 +1,84 @@
+#![allow(unused_imports)]
+#![allow(unused_braces)]
+
+use diesel::{Queryable, Insertable};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use crate::schema::api_keys as api_keys_schema;
+use crate::schema::refresh_tokens as refresh_tokens_schema;
+use crate::schema::users as users_schema;
+
+pub const ACCOUNT_STAFF: &str = "staff";
+pub const ACCOUNT_MEMBER: &str = "member";
+
+#[derive(Debug, Queryable)]
+#[derive(Serialize, Deserialize, ToSchema)]
+#[diesel(table_name = users_schema)]
+pub struct User {
+    pub id: i32,
+    pub username: String,
+    #[serde(skip_serializing)]
+    pub password_hash: String,
+    pub account_type: String,
+    pub member_id: Option<i32>,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = users_schema)]
+pub struct NewUser<'a> {
+    pub username: &'a str,
+    pub password_hash: &'a str,
+    pub account_type: &'a str,
+    pub member_id: Option<&'a i32>,
+    pub created_at: &'a str,
+    pub updated_at: &'a str,
+}
+
+// Only the SHA-256 of a refresh token is stored; the token itself is handed to the client once.
+#[derive(Debug, Queryable)]
+#[diesel(table_name = refresh_tokens_schema)]
+pub struct RefreshToken {
+    pub id: i32,
+    pub user_id: i32,
+    pub token_hash: String,
+    pub expires_at: String,
+    pub revoked_at: Option<String>,
+    pub created_at: String,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = refresh_tokens_schema)]
+pub struct NewRefreshToken<'a> {
+    pub user_id: &'a i32,
+    pub token_hash: &'a str,
+    pub expires_at: &'a str,
+    pub created_at: &'a str,
+}
+
+// API keys are shown once on creation; afterwards only their prefix identifies them.
+#[derive(Debug, Queryable)]
+#[derive(Serialize, Deserialize, ToSchema)]
+#[diesel(table_name = api_keys_schema)]
+pub struct ApiKey {
+    pub id: i32,
+    pub user_id: i32,
+    pub name: String,
+    pub prefix: String,
+    #[serde(skip_serializing)]
+    pub key_hash: String,
+    pub created_at: String,
+    pub last_used_at: Option<String>,
+    pub revoked_at: Option<String>,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = api_keys_schema)]
+pub struct NewApiKey<'a> {
+    pub user_id: &'a i32,
+    pub name: &'a str,
+    pub prefix: &'a str,
+    pub key_hash: &'a str,
+    pub created_at: &'a str,
+}
@@ -8,6 +8,17 @@ table! {
         email -> Text,
         created_at -> Text,
         updated_at -> Text,
+        version -> Integer,
+        language -> Nullable<Text>,
+        notify_reminders -> Bool,
+        status -> Text,
+        status_reason -> Nullable<Text>,
+        status_since -> Nullable<Text>,
+        status_until -> Nullable<Text>,
+        expires_at -> Nullable<Text>,
+        email_normalized -> Nullable<Text>,
+        erased_at -> Nullable<Text>,
+        keep_loan_history -> Bool,
     }
 }
 
@@ -19,6 +30,17 @@ table! {
         created_at -> Text,
         updated_at -> Text,
         manager_id -> Integer,
+        version -> Integer,
+        loan_retention_days -> Nullable<Integer>,
+        street -> Nullable<Text>,
+        city -> Nullable<Text>,
+        postal_code -> Nullable<Text>,
+        country -> Nullable<Text>,
+        phone -> Nullable<Text>,
+        email -> Nullable<Text>,
+        website -> Nullable<Text>,
+        latitude -> Nullable<Double>,
+        longitude -> Nullable<Double>,
     }
 }
 
@@ -29,6 +51,8 @@ table! {
         author -> Text,
         created_at -> Text,
         updated_at -> Text,
+        version -> Integer,
+        isbn -> Nullable<Text>,
     }
 }
 
@@ -38,6 +62,7 @@ table! {
         library_id -> Integer,
         book_id -> Integer,
         quantity -> Integer,
+        added_at -> Nullable<Text>,
     }
 }
 
@@ -49,14 +74,243 @@ table! {
 }
 
 table! {
-    borrowed_books (member_id, book_id) {
+    borrowed_books (id) {
+        id -> Integer,
+        member_id -> Nullable<Integer>,
+        book_id -> Integer,
+        library_id -> Nullable<Integer>,
+        borrowed_at -> Text,
+        due_at -> Nullable<Text>,
+        returned_at -> Nullable<Text>,
+        renewals -> Integer,
+        anonymized_at -> Nullable<Text>,
+    }
+}
+
+table! {
+    holds (id) {
+        id -> Integer,
+        member_id -> Integer,
+        book_id -> Integer,
+        library_id -> Nullable<Integer>,
+        placed_at -> Text,
+        fulfilled_at -> Nullable<Text>,
+        cancelled_at -> Nullable<Text>,
+    }
+}
+
+table! {
+    transfers (id) {
+        id -> Integer,
+        book_id -> Integer,
+        from_library_id -> Integer,
+        to_library_id -> Integer,
+        member_id -> Nullable<Integer>,
+        quantity -> Integer,
+        status -> Text,
+        note -> Nullable<Text>,
+        requested_by -> Nullable<Integer>,
+        requested_at -> Text,
+        approved_at -> Nullable<Text>,
+        shipped_at -> Nullable<Text>,
+        received_at -> Nullable<Text>,
+        returned_at -> Nullable<Text>,
+        cancelled_at -> Nullable<Text>,
+    }
+}
+
+table! {
+    job_runs (id) {
+        id -> Integer,
+        job -> Text,
+        trigger -> Text,
+        scheduled_for -> Nullable<Text>,
+        instance -> Text,
+        status -> Text,
+        started_at -> Text,
+        finished_at -> Nullable<Text>,
+        output -> Nullable<Text>,
+        error -> Nullable<Text>,
+    }
+}
+
+table! {
+    job_locks (job) {
+        job -> Text,
+        holder -> Text,
+        locked_until -> Text,
+    }
+}
+
+table! {
+    loan_notices (id) {
+        id -> Integer,
         member_id -> Integer,
         book_id -> Integer,
         borrowed_at -> Text,
+        kind -> Text,
+        channel -> Text,
+        recipient -> Text,
+        sent_at -> Text,
+        due_at -> Nullable<Text>,
+    }
+}
+
+table! {
+    users (id) {
+        id -> Integer,
+        username -> Text,
+        password_hash -> Text,
+        account_type -> Text,
+        member_id -> Nullable<Integer>,
+        created_at -> Text,
+        updated_at -> Text,
+    }
+}
+
+table! {
+    refresh_tokens (id) {
+        id -> Integer,
+        user_id -> Integer,
+        token_hash -> Text,
+        expires_at -> Text,
+        revoked_at -> Nullable<Text>,
+        created_at -> Text,
+    }
+}
+
+table! {
+    api_keys (id) {
+        id -> Integer,
+        user_id -> Integer,
+        name -> Text,
+        prefix -> Text,
+        key_hash -> Text,
+        created_at -> Text,
+        last_used_at -> Nullable<Text>,
+        revoked_at -> Nullable<Text>,
+    }
+}
+
+table! {
+    user_roles (id) {
+        id -> Integer,
+        user_id -> Integer,
+        role -> Text,
+        library_id -> Nullable<Integer>,
+        created_at -> Text,
+    }
+}
+
+table! {
+    audit_log (id) {
+        id -> Integer,
+        occurred_at -> Text,
+        request_id -> Text,
+        actor_user_id -> Nullable<Integer>,
+        actor_username -> Nullable<Text>,
+        action -> Text,
+        entity -> Text,
+        entity_id -> Text,
+        before_json -> Nullable<Text>,
+        after_json -> Nullable<Text>,
+        diff_json -> Nullable<Text>,
+    }
+}
+
+table! {
+    library_hours (library_id, weekday) {
+        library_id -> Integer,
+        weekday -> Integer,
+        opens_at -> Text,
+        closes_at -> Text,
+    }
+}
+
+table! {
+    library_closures (id) {
+        id -> Integer,
+        library_id -> Integer,
+        date -> Text,
+        reason -> Text,
+    }
+}
+
+table! {
+    library_staff (id) {
+        id -> Integer,
+        library_id -> Integer,
+        member_id -> Integer,
+        role -> Text,
+        started_at -> Text,
+        ended_at -> Nullable<Text>,
+        created_at -> Text,
+    }
+}
+
+table! {
+    library_handovers (id) {
+        id -> Integer,
+        library_id -> Integer,
+        from_member_id -> Nullable<Integer>,
+        to_member_id -> Integer,
+        handed_over_at -> Text,
+    }
+}
+
+table! {
+    vendors (id) {
+        id -> Integer,
+        name -> Text,
+        email -> Nullable<Text>,
+        phone -> Nullable<Text>,
+        created_at -> Text,
+        updated_at -> Text,
+    }
+}
+
+table! {
+    budgets (id) {
+        id -> Integer,
+        library_id -> Integer,
+        fiscal_year -> Integer,
+        amount_cents -> BigInt,
+        created_at -> Text,
+        updated_at -> Text,
+    }
+}
+
+table! {
+    purchase_orders (id) {
+        id -> Integer,
+        library_id -> Integer,
+        vendor_id -> Integer,
+        fiscal_year -> Integer,
+        status -> Text,
+        note -> Nullable<Text>,
+        created_by -> Nullable<Integer>,
+        created_at -> Text,
+        ordered_at -> Nullable<Text>,
+        closed_at -> Nullable<Text>,
+    }
+}
+
+table! {
+    purchase_order_lines (id) {
+        id -> Integer,
+        order_id -> Integer,
+        book_id -> Nullable<Integer>,
+        isbn -> Nullable<Text>,
+        title -> Text,
+        author -> Text,
+        quantity -> Integer,
+        unit_price_cents -> BigInt,
+        received_quantity -> Integer,
     }
 }
 
 // Define the relationships
+
 joinable!(books -> library (id));
 joinable!(library -> members (manager_id));
 joinable!(library_books -> library (library_id));
@@ -65,6 +319,27 @@ joinable!(library_members -> library (library_id));
 joinable!(library_members -> members (member_id));
 joinable!(borrowed_books -> members (member_id));
 joinable!(borrowed_books -> books (book_id));
+joinable!(borrowed_books -> library (library_id));
+joinable!(holds -> members (member_id));
+joinable!(holds -> books (book_id));
+joinable!(transfers -> books (book_id));
+joinable!(loan_notices -> members (member_id));
+joinable!(loan_notices -> books (book_id));
+joinable!(users -> members (member_id));
+joinable!(refresh_tokens -> users (user_id));
+joinable!(api_keys -> users (user_id));
+joinable!(user_roles -> users (user_id));
+joinable!(user_roles -> library (library_id));
+joinable!(library_hours -> library (library_id));
+joinable!(library_closures -> library (library_id));
+joinable!(library_staff -> library (library_id));
+joinable!(library_staff -> members (member_id));
+joinable!(library_handovers -> library (library_id));
+joinable!(budgets -> library (library_id));
+joinable!(purchase_orders -> library (library_id));
+joinable!(purchase_orders -> vendors (vendor_id));
+joinable!(purchase_order_lines -> purchase_orders (order_id));
+joinable!(purchase_order_lines -> books (book_id));
 
 // Allow tables to appear in the same query
 allow_tables_to_appear_in_same_query!(
@@ -73,4 +348,20 @@ allow_tables_to_appear_in_same_query!(
     books,
     library_books,
     library_members,
+    borrowed_books,
+    holds,
+    transfers,
+    loan_notices,
+    users,
+    refresh_tokens,
+    api_keys,
+    user_roles,
+    library_hours,
+    library_closures,
+    library_staff,
+    library_handovers,
+    vendors,
+    budgets,
+    purchase_orders,
+    purchase_order_lines,
 );
\ No newline at end of file
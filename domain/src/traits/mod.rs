@@ -1,33 +1,240 @@
 use diesel::QueryResult;
 
-use crate::models::{book::Book, library::Library, member::Member};
+use crate::models::acquisition::{Budget, NewOrderLine, NewPurchaseOrder, NewVendor, OrderFilter, OrderLine, PurchaseOrder, Vendor};
+use crate::models::audit::{AuditEntry, AuditFilter, NewAuditEntry};
+use crate::models::book::{Book, BookChanges, LibraryBook};
+use crate::models::calendar::{Closure, NewClosure, OpeningHours};
+use crate::models::job::{JobRun, NewJobRun};
+use crate::models::library::{Library, LibraryChanges, LibraryDetails, LibraryStock};
+use crate::models::loan::{Hold, Loan, NewHold};
+use crate::models::maintenance::{IntegrityCheck, StockChange};
+use crate::models::member::{Member, MemberChanges, MergeSummary};
+use crate::models::notice::{DueLoan, LoanNotice, NewLoanNotice, Notice, NoticeFilter};
+use crate::models::report::{Acquisition, AuthorLoans, BudgetSpending, LibraryOverdue, LibraryUtilization, MemberActivity, ReportScope, TitleLoans};
+use crate::models::role::UserRole;
+use crate::models::staff::{Handover, NewStaffAssignment, StaffAssignment};
+use crate::models::stats::{LibraryAvailability, LoanCounts};
+use crate::models::transfer::{NewTransfer, Transfer, TransferFilter};
+use crate::models::user::{ApiKey, RefreshToken, User};
+
+// Create methods return the stored row. Update and delete methods take the version the caller last saw and affect no rows when it is stale.
 
 pub trait BookRepositoryTrait {
-    fn create_book(&mut self, title: &str, author: &str, library_id: &i32) -> QueryResult<usize>;
-    fn update_book(&mut self, id: &i32, title: &str, author: &str) -> QueryResult<usize>;
-    fn delete_book(&mut self, id: &i32) -> QueryResult<usize>;
+    fn create_book(&mut self, title: &str, author: &str, isbn: Option<&str>, library_id: &i32) -> QueryResult<Book>;
+    fn update_book(&mut self, id: &i32, title: &str, author: &str, isbn: Option<&str>, version: &i32) -> QueryResult<usize>;
+    fn patch_book(&mut self, id: &i32, changes: &BookChanges, version: &i32) -> QueryResult<usize>;
+    fn delete_book(&mut self, id: &i32, version: &i32) -> QueryResult<usize>;
     fn get_book_by_id(&mut self, id: &i32) -> QueryResult<Book>;
     fn get_books(&mut self) -> QueryResult<Vec<Book>>;
     fn get_books_by_library_id(&mut self, library_id: &i32) -> QueryResult<Vec<Book>>;
+    // `isbn` as returned by `normalize_isbn`.
+    fn get_book_by_isbn(&mut self, isbn: &str) -> QueryResult<Book>;
 }
 pub trait LibraryRepositoryTrait {
-    fn create_library(&mut self, name: &str, address: &str, manager_id: &i32) -> QueryResult<usize>;
+    fn create_library(&mut self, name: &str, address: &str, manager_id: &i32, details: &LibraryDetails) -> QueryResult<Library>;
     fn get_libraries(&mut self) -> QueryResult<Vec<Library>>;
     fn get_library_by_id(&mut self, id: &i32) -> QueryResult<Library>;
-    fn update_library(&mut self, id: &i32, name: &str, address: &str, manager_id: &i32) -> QueryResult<usize>;
-    fn delete_library(&mut self, id: &i32) -> QueryResult<usize>;
+    fn get_libraries_by_manager_id(&mut self, manager_id: &i32) -> QueryResult<Vec<Library>>;
+    fn update_library(&mut self, id: &i32, name: &str, address: &str, manager_id: &i32, details: &LibraryDetails, version: &i32) -> QueryResult<usize>;
+    fn patch_library(&mut self, id: &i32, changes: &LibraryChanges, version: &i32) -> QueryResult<usize>;
+    fn delete_library(&mut self, id: &i32, version: &i32) -> QueryResult<usize>;
     fn add_book(&mut self, library_id: &i32, book_id: &i32) -> QueryResult<usize>;
     fn add_book_quantity(&mut self, library_id: &i32, book_id: &i32, quantity: &i32) -> QueryResult<usize>;
+    fn get_library_book(&mut self, library_id: &i32, book_id: &i32) -> QueryResult<LibraryBook>;
+    // Libraries with both a latitude and a longitude.
+    fn get_located_libraries(&mut self) -> QueryResult<Vec<Library>>;
+    // Copies of the book on the shelf in each library holding it.
+    fn get_book_stock(&mut self, book_id: &i32) -> QueryResult<Vec<LibraryStock>>;
 }
 
 pub trait MemberRepositoryTrait {
-    fn create_member(&mut self, name: &str, email: &str) -> QueryResult<usize>;
+    fn create_member(&mut self, name: &str, email: &str, language: Option<&str>, expires_at: Option<&str>) -> QueryResult<Member>;
     fn get_members(&mut self) -> QueryResult<Vec<Member>>;
     fn get_member_by_id(&mut self, id: &i32) -> QueryResult<Member>;
-    fn update_member(&mut self, id: &i32, name: &str, email: &str) -> QueryResult<usize>;
-    fn delete_member(&mut self, id: &i32) -> QueryResult<usize>;
+    fn update_member(&mut self, id: &i32, name: &str, email: &str, language: Option<&str>, version: &i32) -> QueryResult<usize>;
+    fn patch_member(&mut self, id: &i32, changes: &MemberChanges, version: &i32) -> QueryResult<usize>;
+    fn delete_member(&mut self, id: &i32, version: &i32) -> QueryResult<usize>;
     fn get_members_by_library_id(&mut self, library_id: &i32) -> QueryResult<Vec<Member>>;
-    fn borrow_book(&mut self, member_id: &i32, book_id: &i32) -> QueryResult<usize>;
+    // Books the loan to `library_id`, or to the member's lowest-numbered library stocking the book when None.
+    // Returns 0 without booking it when no copy is on the shelf there.
+    fn borrow_book(&mut self, member_id: &i32, book_id: &i32, library_id: Option<&i32>, due_at: &str) -> QueryResult<usize>;
+    // The library `borrow_book` books the loan to when given none.
+    fn loan_library(&mut self, member_id: &i32, book_id: &i32) -> QueryResult<Option<i32>>;
     fn return_book(&mut self, member_id: &i32, book_id: &i32) -> QueryResult<usize>;
     fn get_borrowed_books(&mut self, member_id: &i32, library_id: &i32) -> QueryResult<Vec<Book>>;
-}
\ No newline at end of file
+    // Open loans when `open`, otherwise returned ones; newest first.
+    fn get_loans(&mut self, member_id: &i32, open: bool) -> QueryResult<Vec<Loan>>;
+    // Moves the due date of an open loan; affects no rows once the loan has been renewed `max_renewals` times
+    // or another member holds the book.
+    fn renew_loan(&mut self, member_id: &i32, loan_id: &i32, due_at: &str, max_renewals: &i32) -> QueryResult<usize>;
+    fn place_hold(&mut self, hold: &NewHold) -> QueryResult<Hold>;
+    // Active holds of the member.
+    fn get_holds(&mut self, member_id: &i32) -> QueryResult<Vec<Hold>>;
+    // Active holds on the book by other members.
+    fn count_waiting_holds(&mut self, book_id: &i32, member_id: &i32) -> QueryResult<i64>;
+    fn cancel_hold(&mut self, member_id: &i32, hold_id: &i32) -> QueryResult<usize>;
+    // Stores the status that took effect by `today`: lifts suspensions that ended and expires memberships
+    // past their last day. Returns (lifted, expired).
+    fn refresh_member_statuses(&mut self, today: &str) -> QueryResult<(usize, usize)>;
+    // Moves loans, library memberships, holds, notices, accounts and transfers of `from_id` to `into_id`, then
+    // deletes `from_id`. Fines follow the loans they are computed from.
+    fn merge_members(&mut self, into_id: &i32, from_id: &i32) -> QueryResult<MergeSummary>;
+    // Holds of the member in any state; newest first.
+    fn get_hold_history(&mut self, member_id: &i32) -> QueryResult<Vec<Hold>>;
+    fn get_member_library_ids(&mut self, member_id: &i32) -> QueryResult<Vec<i32>>;
+    // Anonymizes the member row and deletes the member's accounts, holds, notices and memberships; loans are kept.
+    // Affects no rows while the member has open loans or was already erased.
+    fn erase_member(&mut self, id: &i32, at: &str) -> QueryResult<usize>;
+    // Unlinks loans returned longer ago than their library's retention period (`default_days` when it has none)
+    // from members who do not keep their history, and drops the notices of those loans. Returns the ids of the loans changed.
+    fn anonymize_loans(&mut self, now: &str, default_days: &i64) -> QueryResult<Vec<i32>>;
+}
+
+pub trait UserRepositoryTrait {
+    fn create_user(&mut self, username: &str, password_hash: &str, account_type: &str, member_id: Option<&i32>) -> QueryResult<User>;
+    fn get_users(&mut self) -> QueryResult<Vec<User>>;
+    fn get_user_by_id(&mut self, id: &i32) -> QueryResult<User>;
+    fn get_user_by_username(&mut self, username: &str) -> QueryResult<User>;
+    fn count_users(&mut self) -> QueryResult<i64>;
+    fn create_refresh_token(&mut self, user_id: &i32, token_hash: &str, expires_at: &str) -> QueryResult<usize>;
+    fn get_refresh_token(&mut self, token_hash: &str) -> QueryResult<RefreshToken>;
+    fn revoke_refresh_token(&mut self, token_hash: &str) -> QueryResult<usize>;
+    // Deletes refresh tokens that expired or were revoked before `before`.
+    fn purge_refresh_tokens(&mut self, before: &str) -> QueryResult<usize>;
+    fn create_api_key(&mut self, user_id: &i32, name: &str, prefix: &str, key_hash: &str) -> QueryResult<ApiKey>;
+    fn get_api_keys(&mut self, user_id: &i32) -> QueryResult<Vec<ApiKey>>;
+    fn get_api_key_by_hash(&mut self, key_hash: &str) -> QueryResult<ApiKey>;
+    fn touch_api_key(&mut self, id: &i32) -> QueryResult<usize>;
+    fn revoke_api_key(&mut self, user_id: &i32, id: &i32) -> QueryResult<usize>;
+    fn get_user_roles(&mut self, user_id: &i32) -> QueryResult<Vec<UserRole>>;
+    fn grant_role(&mut self, user_id: &i32, role: &str, library_id: Option<&i32>) -> QueryResult<UserRole>;
+    fn revoke_role(&mut self, user_id: &i32, id: &i32) -> QueryResult<usize>;
+}
+
+// The audit log is append-only: there is deliberately no way to change or remove an entry.
+pub trait AuditRepositoryTrait {
+    fn record(&mut self, entry: &NewAuditEntry) -> QueryResult<usize>;
+    fn get_entries(&mut self, filter: &AuditFilter) -> QueryResult<Vec<AuditEntry>>;
+}
+
+// Read-only aggregates over the whole catalogue, used for monitoring.
+pub trait StatsRepositoryTrait {
+    fn get_loan_counts(&mut self, now: &str) -> QueryResult<LoanCounts>;
+    fn get_available_copies(&mut self) -> QueryResult<Vec<LibraryAvailability>>;
+}
+
+pub trait TransferRepositoryTrait {
+    fn create_transfer(&mut self, transfer: &NewTransfer) -> QueryResult<Transfer>;
+    fn get_transfer(&mut self, id: &i32) -> QueryResult<Transfer>;
+    fn get_transfers(&mut self, filter: &TransferFilter) -> QueryResult<Vec<Transfer>>;
+    // Copies of the book at the library that are not on loan.
+    fn available_copies(&mut self, library_id: &i32, book_id: &i32) -> QueryResult<i64>;
+    // Moves the transfer from `from` to `to` and its copies between the libraries' stock in one transaction.
+    // Affects no rows when the transfer is no longer in `from` or the sending side lacks available copies.
+    fn advance_transfer(&mut self, id: &i32, from: &str, to: &str, at: &str) -> QueryResult<usize>;
+}
+
+pub trait CalendarRepositoryTrait {
+    fn get_hours(&mut self, library_id: &i32) -> QueryResult<Vec<OpeningHours>>;
+    // Replaces all opening hours of the library; an empty list leaves it open every day.
+    fn set_hours(&mut self, library_id: &i32, hours: &[OpeningHours]) -> QueryResult<usize>;
+    // Closures on or after `from` and before `to`, by date; either bound may be left open.
+    fn get_closures(&mut self, library_id: &i32, from: Option<&str>, to: Option<&str>) -> QueryResult<Vec<Closure>>;
+    fn add_closure(&mut self, closure: &NewClosure) -> QueryResult<Closure>;
+    fn delete_closure(&mut self, library_id: &i32, id: &i32) -> QueryResult<usize>;
+    // Moves open loans of the library due on `date` (YYYY-MM-DD) to `to`, keeping the time of day.
+    fn postpone_due_dates(&mut self, library_id: &i32, date: &str, to: &str) -> QueryResult<usize>;
+}
+
+pub trait StaffRepositoryTrait {
+    // Assignments active on `day` (YYYY-MM-DD), or the whole history when None; newest first.
+    fn get_staff(&mut self, library_id: &i32, day: Option<&str>) -> QueryResult<Vec<StaffAssignment>>;
+    fn get_assignment(&mut self, library_id: &i32, id: &i32) -> QueryResult<StaffAssignment>;
+    fn add_assignment(&mut self, assignment: &NewStaffAssignment) -> QueryResult<StaffAssignment>;
+    fn set_assignment_dates(&mut self, library_id: &i32, id: &i32, started_at: &str, ended_at: Option<&str>) -> QueryResult<usize>;
+    // Assignments of the member active on `day`, at any library.
+    fn get_member_assignments(&mut self, member_id: &i32, day: &str) -> QueryResult<Vec<StaffAssignment>>;
+    // Manager changes of the library, newest first.
+    fn get_handovers(&mut self, library_id: &i32) -> QueryResult<Vec<Handover>>;
+}
+
+pub trait AcquisitionRepositoryTrait {
+    fn create_vendor(&mut self, vendor: &NewVendor) -> QueryResult<Vendor>;
+    fn get_vendors(&mut self) -> QueryResult<Vec<Vendor>>;
+    fn get_vendor(&mut self, id: &i32) -> QueryResult<Vendor>;
+    fn update_vendor(&mut self, id: &i32, name: &str, email: Option<&str>, phone: Option<&str>) -> QueryResult<usize>;
+    fn get_budgets(&mut self, library_id: &i32) -> QueryResult<Vec<Budget>>;
+    fn get_budget(&mut self, library_id: &i32, fiscal_year: &i32) -> QueryResult<Budget>;
+    // Creates the library's budget for the year or replaces its amount.
+    fn set_budget(&mut self, library_id: &i32, fiscal_year: &i32, amount_cents: &i64) -> QueryResult<Budget>;
+    // Total of the library's ordered and partially received orders for the year, plus what arrived on received ones.
+    fn committed_cents(&mut self, library_id: &i32, fiscal_year: &i32) -> QueryResult<i64>;
+    fn create_order(&mut self, order: &NewPurchaseOrder, lines: &[NewOrderLine]) -> QueryResult<PurchaseOrder>;
+    fn get_orders(&mut self, filter: &OrderFilter) -> QueryResult<Vec<PurchaseOrder>>;
+    fn get_order(&mut self, id: &i32) -> QueryResult<PurchaseOrder>;
+    fn get_order_lines(&mut self, order_id: &i32) -> QueryResult<Vec<OrderLine>>;
+    // Moves the order from `from` to `to`, stamping ordered_at or closed_at. Affects no rows when the order is
+    // no longer in `from`.
+    fn advance_order(&mut self, id: &i32, from: &str, to: &str, at: &str) -> QueryResult<usize>;
+    // Books `(line id, quantity)` as delivered: adds the copies to the order's library, creating catalogue
+    // entries for lines without a book, and marks the order partially or fully received. Affects no rows when
+    // the order is not awaiting delivery, no copies are listed, or a quantity is not positive or exceeds what is
+    // outstanding on its line.
+    fn receive_order(&mut self, id: &i32, receipts: &[(i32, i32)], at: &str) -> QueryResult<usize>;
+}
+
+// Whole-database operations for the admin CLI; not used by the HTTP server.
+pub trait MaintenanceRepositoryTrait {
+    fn check_integrity(&mut self) -> QueryResult<Vec<IntegrityCheck>>;
+    // Holdings with fewer copies than open loans; with `apply`, raises each to its loan count first.
+    fn recompute_stock(&mut self, apply: bool) -> QueryResult<Vec<StockChange>>;
+    // Rebuilds every index and refreshes the query planner's statistics.
+    fn reindex(&mut self) -> QueryResult<()>;
+    // JSON object with one array of rows per table in `DATA_TABLES`.
+    fn export_data(&mut self) -> QueryResult<String>;
+    // Loads an export into empty tables, keeping ids; returns the number of rows inserted.
+    fn import_data(&mut self, data: &str) -> QueryResult<usize>;
+}
+
+// Circulation reports over loans borrowed in the scope's period, narrowed to its library when set.
+pub trait ReportRepositoryTrait {
+    fn top_titles(&mut self, scope: &ReportScope) -> QueryResult<Vec<TitleLoans>>;
+    fn top_authors(&mut self, scope: &ReportScope) -> QueryResult<Vec<AuthorLoans>>;
+    // Counts loans due in the period; `now` decides which open loans are overdue.
+    fn overdue_by_library(&mut self, scope: &ReportScope, now: &str) -> QueryResult<Vec<LibraryOverdue>>;
+    fn utilization(&mut self, scope: &ReportScope) -> QueryResult<Vec<LibraryUtilization>>;
+    fn member_activity(&mut self, scope: &ReportScope, now: &str) -> QueryResult<Vec<MemberActivity>>;
+    // Copies added to libraries in the period.
+    fn acquisitions(&mut self, scope: &ReportScope) -> QueryResult<Vec<Acquisition>>;
+    // Budgets against the purchase orders charged to them; only orders placed in the period count.
+    fn budget_spending(&mut self, scope: &ReportScope) -> QueryResult<Vec<BudgetSpending>>;
+}
+
+// Leases are time-limited so a crashed instance cannot hold a job forever.
+pub trait JobRepositoryTrait {
+    // True when `holder` now holds the lease on `job` until `until`; false while another holder's lease is current.
+    fn acquire_lease(&mut self, job: &str, holder: &str, now: &str, until: &str) -> QueryResult<bool>;
+    // Moves the end of `holder`'s lease to `until`; false when the lease is no longer theirs.
+    fn renew_lease(&mut self, job: &str, holder: &str, until: &str) -> QueryResult<bool>;
+    fn release_lease(&mut self, job: &str, holder: &str) -> QueryResult<usize>;
+    // None when a run for the same scheduled time is already recorded.
+    fn start_run(&mut self, run: &NewJobRun) -> QueryResult<Option<JobRun>>;
+    fn finish_run(&mut self, id: &i32, status: &str, output: Option<&str>, error: Option<&str>) -> QueryResult<usize>;
+    fn get_run(&mut self, id: &i32) -> QueryResult<JobRun>;
+    fn get_runs(&mut self, job: &str, limit: i64) -> QueryResult<Vec<JobRun>>;
+    fn get_latest_runs(&mut self) -> QueryResult<Vec<JobRun>>;
+    fn purge_runs(&mut self, before: &str) -> QueryResult<usize>;
+}
+
+pub trait NoticeRepositoryTrait {
+    // Loans due in [from, until) that have not had a notice of `kind` yet.
+    fn find_due_loans(&mut self, kind: &str, from: &str, until: &str) -> QueryResult<Vec<DueLoan>>;
+    fn record_notice(&mut self, notice: &NewLoanNotice) -> QueryResult<usize>;
+    fn get_notices(&mut self, filter: &NoticeFilter) -> QueryResult<Vec<LoanNotice>>;
+}
+
+// A delivery channel for notices (email, log, ...). Sending may block; callers run it off the async executor.
+pub trait Notifier {
+    fn channel(&self) -> &'static str;
+    fn send(&self, notice: &Notice) -> Result<(), String>;
+}
@@ -0,0 +1,686 @@
+use std::collections::BTreeMap;
+use std::env;
+use std::fmt;
+use std::fs;
+use std::net::ToSocketAddrs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use dotenvy::dotenv;
+use serde::{Deserialize, Serialize};
+
+use domain::models::notice::NOTICE_KINDS;
+
+// Looked up when neither --config nor LIBRARY_CONFIG names a file; a missing default file is not an error.
+pub const DEFAULT_CONFIG_FILE: &str = "library.toml";
+pub const LOG_LEVELS: [&str; 5] = ["trace", "debug", "info", "warn", "error"];
+pub const LOG_FORMATS: [&str; 2] = ["pretty", "json"];
+pub const FEATURES: [&str; 5] = ["audit_log", "api_keys", "metrics", "notifications", "jobs"];
+pub const NOTIFICATION_CHANNELS: [&str; 3] = ["log", "file", "smtp"];
+// Background jobs with their default schedules (sec min hour day-of-month month day-of-week).
+pub const JOBS: [(&str, &str); 6] = [
+    ("loan_notices", "0 0 * * * *"),
+    ("member_status", "0 5 0 * * *"),
+    ("loan_history", "0 10 0 * * *"),
+    ("purge_refresh_tokens", "0 30 3 * * *"),
+    ("purge_job_runs", "0 45 3 * * *"),
+    ("wal_checkpoint", "0 0 4 * * *"),
+];
+// A schedule of "off" disables a job; it can still be triggered by hand.
+pub const JOB_SCHEDULE_OFF: &str = "off";
+
+// Effective server configuration. Values are layered: built-in defaults, then the TOML file,
+// then environment variables, then command line flags (applied by the binary).
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub server: ServerConfig,
+    pub database: DatabaseConfig,
+    pub auth: AuthConfig,
+    pub logging: LoggingConfig,
+    pub loans: LoansConfig,
+    pub notifications: NotificationsConfig,
+    pub jobs: JobsConfig,
+    pub api: ApiConfig,
+    pub features: FeaturesConfig,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    pub bind: Vec<String>,
+    // None lets actix start one worker per physical core.
+    pub workers: Option<usize>,
+    pub keep_alive_seconds: u64,
+    pub client_request_timeout_ms: u64,
+    pub shutdown_timeout_seconds: u64,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        ServerConfig {
+            bind: vec!["127.0.0.1:8080".to_string()],
+            workers: None,
+            keep_alive_seconds: 5,
+            client_request_timeout_ms: 5000,
+            shutdown_timeout_seconds: 30,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DatabaseConfig {
+    pub url: String,
+    pub pool_max_size: u32,
+    pub pool_min_idle: Option<u32>,
+    pub connection_timeout_seconds: u64,
+    pub busy_timeout_ms: u32,
+}
+
+impl Default for DatabaseConfig {
+    fn default() -> Self {
+        DatabaseConfig {
+            url: "sqlite://my_database.db".to_string(),
+            pool_max_size: 10,
+            pool_min_idle: None,
+            connection_timeout_seconds: 30,
+            busy_timeout_ms: 5000,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AuthConfig {
+    // Without a secret a random one is generated per process and tokens do not survive a restart.
+    pub jwt_secret: Option<String>,
+    pub access_token_ttl_seconds: i64,
+    pub refresh_token_ttl_seconds: i64,
+    pub bootstrap_admin_username: Option<String>,
+    pub bootstrap_admin_password: Option<String>,
+}
+
+impl Default for AuthConfig {
+    fn default() -> Self {
+        AuthConfig {
+            jwt_secret: None,
+            access_token_ttl_seconds: 15 * 60,
+            refresh_token_ttl_seconds: 30 * 24 * 60 * 60,
+            bootstrap_admin_username: None,
+            bootstrap_admin_password: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LoggingConfig {
+    pub level: String,
+    pub format: String,
+    // OTLP/HTTP collector for spans, e.g. http://localhost:4318; needs a build with the `otel` feature.
+    pub otlp_endpoint: Option<String>,
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        LoggingConfig {
+            level: "info".to_string(),
+            format: "pretty".to_string(),
+            otlp_endpoint: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LoansConfig {
+    pub loan_period_days: i64,
+    // How often a member may renew one loan; each renewal sets the due date loan_period_days from now.
+    pub max_renewals: i32,
+    // Charged per started day a loan is overdue, in the currency's minor unit; 0 charges none.
+    pub fine_per_day_cents: i64,
+    // New members' memberships run this many days; 0 never expires them.
+    pub membership_days: i64,
+    // Returned loans are unlinked from their members this many days after the return, unless the library sets its
+    // own period or the member opted to keep their history; 0 keeps them linked.
+    pub history_retention_days: i64,
+}
+
+impl Default for LoansConfig {
+    fn default() -> Self {
+        LoansConfig { loan_period_days: 14, max_renewals: 2, fine_per_day_cents: 0, membership_days: 0, history_retention_days: 0 }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NotificationsConfig {
+    // log, file or smtp.
+    pub channel: String,
+    // Notices are appended here, one JSON object per line, when channel = "file".
+    pub file_path: String,
+    // Reminders go out this many days before a loan is due; 0 sends none.
+    pub reminder_days_before: i64,
+    // Used for members without a language of their own.
+    pub default_language: String,
+    pub smtp: SmtpConfig,
+    // Override the built-in English texts, per language and optionally per library.
+    pub templates: Vec<NoticeTemplate>,
+}
+
+impl Default for NotificationsConfig {
+    fn default() -> Self {
+        NotificationsConfig {
+            channel: "log".to_string(),
+            file_path: "notices.log".to_string(),
+            reminder_days_before: 2,
+            default_language: "en".to_string(),
+            smtp: SmtpConfig::default(),
+            templates: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SmtpConfig {
+    pub host: String,
+    pub port: u16,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub from: String,
+    // Upgrade to TLS with STARTTLS; leave off for a local sink such as MailHog or smtp4dev.
+    pub starttls: bool,
+}
+
+impl Default for SmtpConfig {
+    fn default() -> Self {
+        SmtpConfig {
+            host: "localhost".to_string(),
+            port: 25,
+            username: None,
+            password: None,
+            from: "Library <library@localhost>".to_string(),
+            starttls: false,
+        }
+    }
+}
+
+// Subject and body may use {member_name}, {book_title}, {due_date}, {days} and {library_name}.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NoticeTemplate {
+    // reminder or overdue.
+    pub kind: String,
+    pub language: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub library_id: Option<i32>,
+    pub subject: String,
+    pub body: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct JobsConfig {
+    // How long a server may hold a job's lock before another one may take over.
+    pub lease_seconds: u64,
+    pub keep_runs_days: i64,
+    // Overrides of the default schedules in `JOBS`, by job name.
+    pub schedules: BTreeMap<String, String>,
+}
+
+impl Default for JobsConfig {
+    fn default() -> Self {
+        JobsConfig { lease_seconds: 5 * 60, keep_runs_days: 30, schedules: BTreeMap::new() }
+    }
+}
+
+impl JobsConfig {
+    pub fn schedule(&self, job: &str) -> Option<&str> {
+        self.schedules
+            .get(job)
+            .map(String::as_str)
+            .or_else(|| JOBS.iter().find(|(name, _)| *name == job).map(|(_, schedule)| *schedule))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ApiConfig {
+    // Keep serving the pre-versioning paths (`/books`, ...) next to `/v1`, marked as deprecated.
+    pub unversioned_routes: bool,
+    // Date (YYYY-MM-DD, UTC) announced in the `Sunset` header of the unversioned paths.
+    pub unversioned_sunset: String,
+}
+
+impl Default for ApiConfig {
+    fn default() -> Self {
+        ApiConfig { unversioned_routes: true, unversioned_sunset: "2027-04-30".to_string() }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FeaturesConfig {
+    pub audit_log: bool,
+    pub api_keys: bool,
+    pub metrics: bool,
+    pub notifications: bool,
+    // Run scheduled jobs in this process; turn off on instances that should only serve requests.
+    pub jobs: bool,
+}
+
+impl Default for FeaturesConfig {
+    fn default() -> Self {
+        FeaturesConfig { audit_log: true, api_keys: true, metrics: true, notifications: true, jobs: true }
+    }
+}
+
+impl FeaturesConfig {
+    pub fn set(&mut self, name: &str, enabled: bool) -> Result<(), String> {
+        match name {
+            "audit_log" => self.audit_log = enabled,
+            "api_keys" => self.api_keys = enabled,
+            "metrics" => self.metrics = enabled,
+            "notifications" => self.notifications = enabled,
+            "jobs" => self.jobs = enabled,
+            _ => return Err(format!("unknown feature `{}` (expected one of: {})", name, FEATURES.join(", "))),
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Read(PathBuf, std::io::Error),
+    Parse(PathBuf, String),
+    Invalid(Vec<String>),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Read(path, e) => write!(f, "Failed to read config file {}: {}", path.display(), e),
+            ConfigError::Parse(path, e) => write!(f, "Failed to parse config file {}: {}", path.display(), e),
+            ConfigError::Invalid(problems) => write!(f, "Invalid configuration:\n  - {}", problems.join("\n  - ")),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl Config {
+    // Defaults, then the TOML file, then the environment (including a .env file).
+    // `path` comes from --config; LIBRARY_CONFIG and library.toml are the fallbacks.
+    pub fn load(path: Option<&Path>) -> Result<Config, ConfigError> {
+        dotenv().ok();
+
+        let explicit = path.map(Path::to_path_buf).or_else(|| env::var("LIBRARY_CONFIG").ok().map(PathBuf::from));
+        let mut config = match explicit {
+            Some(path) => Config::from_file(&path)?,
+            None if Path::new(DEFAULT_CONFIG_FILE).exists() => Config::from_file(Path::new(DEFAULT_CONFIG_FILE))?,
+            None => Config::default(),
+        };
+
+        config.apply_env().map_err(ConfigError::Invalid)?;
+        Ok(config)
+    }
+
+    pub fn from_file(path: &Path) -> Result<Config, ConfigError> {
+        let contents = fs::read_to_string(path).map_err(|e| ConfigError::Read(path.to_path_buf(), e))?;
+        toml::from_str(&contents).map_err(|e| ConfigError::Parse(path.to_path_buf(), e.to_string()))
+    }
+
+    // DATABASE_URL, JWT_SECRET, ACCESS_TOKEN_TTL_SECONDS, REFRESH_TOKEN_TTL_SECONDS and BOOTSTRAP_ADMIN_*
+    // keep their existing names; everything else uses the LIBRARY_ prefix.
+    fn apply_env(&mut self) -> Result<(), Vec<String>> {
+        let mut problems = Vec::new();
+
+        if let Some(value) = env_var("LIBRARY_BIND") {
+            self.server.bind = value.split(',').map(|addr| addr.trim().to_string()).filter(|addr| !addr.is_empty()).collect();
+        }
+        if let Some(value) = env_var("LIBRARY_WORKERS") {
+            self.server.workers = parse_env("LIBRARY_WORKERS", &value, &mut problems).or(self.server.workers);
+        }
+        parse_into("LIBRARY_KEEP_ALIVE_SECONDS", &mut self.server.keep_alive_seconds, &mut problems);
+        parse_into("LIBRARY_CLIENT_REQUEST_TIMEOUT_MS", &mut self.server.client_request_timeout_ms, &mut problems);
+        parse_into("LIBRARY_SHUTDOWN_TIMEOUT_SECONDS", &mut self.server.shutdown_timeout_seconds, &mut problems);
+
+        if let Some(value) = env_var("DATABASE_URL") {
+            self.database.url = value;
+        }
+        parse_into("LIBRARY_DB_POOL_MAX_SIZE", &mut self.database.pool_max_size, &mut problems);
+        if let Some(value) = env_var("LIBRARY_DB_POOL_MIN_IDLE") {
+            self.database.pool_min_idle = parse_env("LIBRARY_DB_POOL_MIN_IDLE", &value, &mut problems).or(self.database.pool_min_idle);
+        }
+        parse_into("LIBRARY_DB_CONNECTION_TIMEOUT_SECONDS", &mut self.database.connection_timeout_seconds, &mut problems);
+        parse_into("LIBRARY_DB_BUSY_TIMEOUT_MS", &mut self.database.busy_timeout_ms, &mut problems);
+
+        if let Some(value) = env_var("JWT_SECRET") {
+            self.auth.jwt_secret = Some(value);
+        }
+        parse_into("ACCESS_TOKEN_TTL_SECONDS", &mut self.auth.access_token_ttl_seconds, &mut problems);
+        parse_into("REFRESH_TOKEN_TTL_SECONDS", &mut self.auth.refresh_token_ttl_seconds, &mut problems);
+        if let Some(value) = env_var("BOOTSTRAP_ADMIN_USERNAME") {
+            self.auth.bootstrap_admin_username = Some(value);
+        }
+        if let Some(value) = env_var("BOOTSTRAP_ADMIN_PASSWORD") {
+            self.auth.bootstrap_admin_password = Some(value);
+        }
+
+        if let Some(value) = env_var("LIBRARY_LOG_LEVEL") {
+            self.logging.level = value;
+        }
+        if let Some(value) = env_var("LIBRARY_LOG_FORMAT") {
+            self.logging.format = value;
+        }
+        if let Some(value) = env_var("OTEL_EXPORTER_OTLP_ENDPOINT") {
+            self.logging.otlp_endpoint = Some(value);
+        }
+
+        parse_into("LIBRARY_LOAN_PERIOD_DAYS", &mut self.loans.loan_period_days, &mut problems);
+        parse_into("LIBRARY_MAX_RENEWALS", &mut self.loans.max_renewals, &mut problems);
+        parse_into("LIBRARY_FINE_PER_DAY_CENTS", &mut self.loans.fine_per_day_cents, &mut problems);
+        parse_into("LIBRARY_MEMBERSHIP_DAYS", &mut self.loans.membership_days, &mut problems);
+        parse_into("LIBRARY_HISTORY_RETENTION_DAYS", &mut self.loans.history_retention_days, &mut problems);
+
+        if let Some(value) = env_var("LIBRARY_NOTIFICATIONS_CHANNEL") {
+            self.notifications.channel = value;
+        }
+        if let Some(value) = env_var("LIBRARY_NOTIFICATIONS_FILE") {
+            self.notifications.file_path = value;
+        }
+        if let Some(value) = env_var("LIBRARY_SMTP_HOST") {
+            self.notifications.smtp.host = value;
+        }
+        parse_into("LIBRARY_SMTP_PORT", &mut self.notifications.smtp.port, &mut problems);
+        if let Some(value) = env_var("LIBRARY_SMTP_USERNAME") {
+            self.notifications.smtp.username = Some(value);
+        }
+        if let Some(value) = env_var("LIBRARY_SMTP_PASSWORD") {
+            self.notifications.smtp.password = Some(value);
+        }
+        if let Some(value) = env_var("LIBRARY_SMTP_FROM") {
+            self.notifications.smtp.from = value;
+        }
+
+        parse_into("LIBRARY_API_UNVERSIONED_ROUTES", &mut self.api.unversioned_routes, &mut problems);
+        if let Some(value) = env_var("LIBRARY_API_UNVERSIONED_SUNSET") {
+            self.api.unversioned_sunset = value;
+        }
+
+        parse_into("LIBRARY_FEATURE_AUDIT_LOG", &mut self.features.audit_log, &mut problems);
+        parse_into("LIBRARY_FEATURE_API_KEYS", &mut self.features.api_keys, &mut problems);
+        parse_into("LIBRARY_FEATURE_METRICS", &mut self.features.metrics, &mut problems);
+        parse_into("LIBRARY_FEATURE_NOTIFICATIONS", &mut self.features.notifications, &mut problems);
+        parse_into("LIBRARY_FEATURE_JOBS", &mut self.features.jobs, &mut problems);
+
+        if problems.is_empty() { Ok(()) } else { Err(problems) }
+    }
+
+    // Collects every problem instead of stopping at the first one so a broken deployment is fixed in one pass.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let mut problems = Vec::new();
+
+        if self.server.bind.is_empty() {
+            problems.push("server.bind must list at least one address".to_string());
+        }
+        for addr in &self.server.bind {
+            if addr.to_socket_addrs().is_err() {
+                problems.push(format!("server.bind: `{}` is not a valid host:port address", addr));
+            }
+        }
+        if self.server.workers == Some(0) {
+            problems.push("server.workers must be greater than 0".to_string());
+        }
+        if self.server.client_request_timeout_ms == 0 {
+            problems.push("server.client_request_timeout_ms must be greater than 0".to_string());
+        }
+
+        if self.database.url.trim().is_empty() {
+            problems.push("database.url must not be empty".to_string());
+        }
+        if self.database.pool_max_size == 0 {
+            problems.push("database.pool_max_size must be greater than 0".to_string());
+        }
+        if let Some(min_idle) = self.database.pool_min_idle {
+            if min_idle > self.database.pool_max_size {
+                problems.push("database.pool_min_idle must not exceed database.pool_max_size".to_string());
+            }
+        }
+        if self.database.connection_timeout_seconds == 0 {
+            problems.push("database.connection_timeout_seconds must be greater than 0".to_string());
+        }
+
+        if let Some(secret) = &self.auth.jwt_secret {
+            if secret.len() < 32 {
+                problems.push("auth.jwt_secret must be at least 32 characters".to_string());
+            }
+        }
+        if self.auth.access_token_ttl_seconds <= 0 {
+            problems.push("auth.access_token_ttl_seconds must be greater than 0".to_string());
+        }
+        if self.auth.refresh_token_ttl_seconds <= 0 {
+            problems.push("auth.refresh_token_ttl_seconds must be greater than 0".to_string());
+        }
+        if self.auth.bootstrap_admin_username.is_some() != self.auth.bootstrap_admin_password.is_some() {
+            problems.push("auth.bootstrap_admin_username and auth.bootstrap_admin_password must be set together".to_string());
+        }
+        if let Some(password) = &self.auth.bootstrap_admin_password {
+            if password.len() < 8 {
+                problems.push("auth.bootstrap_admin_password must be at least 8 characters".to_string());
+            }
+        }
+
+        if !LOG_LEVELS.contains(&self.logging.level.to_lowercase().as_str()) {
+            problems.push(format!("logging.level must be one of: {}", LOG_LEVELS.join(", ")));
+        }
+        if !LOG_FORMATS.contains(&self.logging.format.as_str()) {
+            problems.push(format!("logging.format must be one of: {}", LOG_FORMATS.join(", ")));
+        }
+
+        if self.loans.loan_period_days <= 0 {
+            problems.push("loans.loan_period_days must be greater than 0".to_string());
+        }
+        if self.loans.max_renewals < 0 {
+            problems.push("loans.max_renewals must not be negative".to_string());
+        }
+        if self.loans.fine_per_day_cents < 0 {
+            problems.push("loans.fine_per_day_cents must not be negative".to_string());
+        }
+        if self.loans.membership_days < 0 {
+            problems.push("loans.membership_days must not be negative".to_string());
+        }
+        if self.loans.history_retention_days < 0 {
+            problems.push("loans.history_retention_days must not be negative".to_string());
+        }
+
+        let notifications = &self.notifications;
+        if !NOTIFICATION_CHANNELS.contains(&notifications.channel.as_str()) {
+            problems.push(format!("notifications.channel must be one of: {}", NOTIFICATION_CHANNELS.join(", ")));
+        }
+        if notifications.channel == "file" && notifications.file_path.trim().is_empty() {
+            problems.push("notifications.file_path must not be empty when notifications.channel is `file`".to_string());
+        }
+        if notifications.reminder_days_before < 0 {
+            problems.push("notifications.reminder_days_before must not be negative".to_string());
+        }
+        if notifications.default_language.trim().is_empty() {
+            problems.push("notifications.default_language must not be empty".to_string());
+        }
+        if notifications.channel == "smtp" {
+            if notifications.smtp.host.trim().is_empty() {
+                problems.push("notifications.smtp.host must not be empty".to_string());
+            }
+            if notifications.smtp.from.parse::<lettre::message::Mailbox>().is_err() {
+                problems.push(format!("notifications.smtp.from: `{}` is not a valid mailbox", notifications.smtp.from));
+            }
+            if notifications.smtp.username.is_some() != notifications.smtp.password.is_some() {
+                problems.push("notifications.smtp.username and notifications.smtp.password must be set together".to_string());
+            }
+        }
+        for (index, template) in notifications.templates.iter().enumerate() {
+            if !NOTICE_KINDS.contains(&template.kind.as_str()) {
+                problems.push(format!("notifications.templates[{}].kind must be one of: {}", index, NOTICE_KINDS.join(", ")));
+            }
+            if template.language.trim().is_empty() {
+                problems.push(format!("notifications.templates[{}].language must not be empty", index));
+            }
+        }
+
+        if self.jobs.lease_seconds == 0 {
+            problems.push("jobs.lease_seconds must be greater than 0".to_string());
+        }
+        if self.jobs.keep_runs_days <= 0 {
+            problems.push("jobs.keep_runs_days must be greater than 0".to_string());
+        }
+        for (job, schedule) in &self.jobs.schedules {
+            if !JOBS.iter().any(|(name, _)| name == job) {
+                let names: Vec<&str> = JOBS.iter().map(|(name, _)| *name).collect();
+                problems.push(format!("jobs.schedules: unknown job `{}` (expected one of: {})", job, names.join(", ")));
+            } else if schedule != JOB_SCHEDULE_OFF && cron::Schedule::from_str(schedule).is_err() {
+                problems.push(format!("jobs.schedules.{}: `{}` is not a valid cron expression or `off`", job, schedule));
+            }
+        }
+
+        if chrono::NaiveDate::parse_from_str(&self.api.unversioned_sunset, "%Y-%m-%d").is_err() {
+            problems.push("api.unversioned_sunset must be a date like 2027-04-30".to_string());
+        }
+
+        if problems.is_empty() { Ok(()) } else { Err(ConfigError::Invalid(problems)) }
+    }
+
+    // TOML for --print-config, with secrets masked so the output is safe to paste into a ticket.
+    pub fn to_redacted_toml(&self) -> String {
+        let mut redacted = self.clone();
+        let mask = |value: &mut Option<String>| {
+            if value.is_some() {
+                *value = Some("<redacted>".to_string());
+            }
+        };
+        mask(&mut redacted.auth.jwt_secret);
+        mask(&mut redacted.auth.bootstrap_admin_password);
+        mask(&mut redacted.notifications.smtp.password);
+
+        toml::to_string_pretty(&redacted).expect("Failed to serialize configuration")
+    }
+}
+
+fn env_var(name: &str) -> Option<String> {
+    env::var(name).ok().filter(|value| !value.trim().is_empty())
+}
+
+fn parse_env<T: std::str::FromStr>(name: &str, value: &str, problems: &mut Vec<String>) -> Option<T> {
+    match value.trim().parse() {
+        Ok(parsed) => Some(parsed),
+        Err(_) => {
+            problems.push(format!("{}: cannot parse `{}`", name, value));
+            None
+        }
+    }
+}
+
+fn parse_into<T: std::str::FromStr>(name: &str, target: &mut T, problems: &mut Vec<String>) {
+    if let Some(value) = env_var(name) {
+        if let Some(parsed) = parse_env(name, &value, problems) {
+            *target = parsed;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    // The environment is shared by every test thread; tests that set variables hold this lock.
+    static ENV: Mutex<()> = Mutex::new(());
+
+    fn with_env<T>(vars: &[(&str, &str)], run: impl FnOnce() -> T) -> T {
+        let _guard = ENV.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        for (name, value) in vars {
+            env::set_var(name, value);
+        }
+        let result = run();
+        for (name, _) in vars {
+            env::remove_var(name);
+        }
+        result
+    }
+
+    fn problems(result: Result<(), ConfigError>) -> Vec<String> {
+        match result {
+            Err(ConfigError::Invalid(problems)) => problems,
+            other => panic!("expected an invalid configuration, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn the_environment_wins_over_the_file_and_the_file_over_the_defaults() {
+        let path = env::temp_dir().join(format!("library-config-{}.toml", std::process::id()));
+        fs::write(&path, "[database]\npool_max_size = 20\npool_min_idle = 3\n\n[loans]\nmax_renewals = 5\n").unwrap();
+
+        let config = with_env(&[("LIBRARY_DB_POOL_MAX_SIZE", "30")], || Config::load(Some(&path)));
+        fs::remove_file(&path).unwrap();
+        let config = config.unwrap();
+
+        assert_eq!(config.database.pool_max_size, 30);
+        assert_eq!(config.database.pool_min_idle, Some(3));
+        assert_eq!(config.loans.max_renewals, 5);
+        assert_eq!(config.loans.loan_period_days, 14);
+    }
+
+    #[test]
+    fn every_unparsable_variable_is_reported() {
+        let mut config = Config::default();
+        let result = with_env(&[("LIBRARY_WORKERS", "many"), ("LIBRARY_MAX_RENEWALS", "two"), ("LIBRARY_LOAN_PERIOD_DAYS", "21")], || {
+            config.apply_env()
+        });
+
+        assert_eq!(
+            result.unwrap_err(),
+            vec!["LIBRARY_WORKERS: cannot parse `many`".to_string(), "LIBRARY_MAX_RENEWALS: cannot parse `two`".to_string()]
+        );
+        assert_eq!(config.server.workers, None);
+        assert_eq!(config.loans.max_renewals, 2);
+        assert_eq!(config.loans.loan_period_days, 21);
+    }
+
+    #[test]
+    fn validate_reports_every_problem_at_once() {
+        assert!(Config::default().validate().is_ok());
+
+        let mut config = Config::default();
+        config.server.bind.clear();
+        config.database.pool_min_idle = Some(config.database.pool_max_size + 1);
+        config.logging.level = "loud".to_string();
+
+        assert_eq!(
+            problems(config.validate()),
+            vec![
+                "server.bind must list at least one address".to_string(),
+                "database.pool_min_idle must not exceed database.pool_max_size".to_string(),
+                format!("logging.level must be one of: {}", LOG_LEVELS.join(", ")),
+            ]
+        );
+    }
+
+    #[test]
+    fn redacted_toml_masks_the_secrets_that_are_set() {
+        let mut config = Config::default();
+        config.auth.jwt_secret = Some("a-secret-of-at-least-thirty-two-chars".to_string());
+        config.notifications.smtp.username = Some("mailer".to_string());
+        config.notifications.smtp.password = Some("hunter22".to_string());
+
+        let toml = config.to_redacted_toml();
+        let redacted: Config = toml::from_str(&toml).unwrap();
+
+        assert!(!toml.contains("a-secret-of-at-least-thirty-two-chars"));
+        assert!(!toml.contains("hunter22"));
+        assert_eq!(redacted.auth.jwt_secret.as_deref(), Some("<redacted>"));
+        assert_eq!(redacted.notifications.smtp.password.as_deref(), Some("<redacted>"));
+        assert_eq!(redacted.notifications.smtp.username.as_deref(), Some("mailer"));
+        assert_eq!(redacted.auth.bootstrap_admin_password, None);
+    }
+}
@@ -1,20 +1,150 @@
-use diesel::r2d2::{ConnectionManager, Pool};
+use diesel::connection::{Connection, Instrumentation, InstrumentationEvent, SimpleConnection};
+use diesel::r2d2::{ConnectionManager, CustomizeConnection, HandleEvent, Pool, PooledConnection};
 use diesel::sqlite::SqliteConnection;
-use dotenvy::dotenv;
-use std::env;
+use std::ops::{Deref, DerefMut};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
+use config::DatabaseConfig;
+
+pub mod config;
+pub mod migrations;
+pub mod notifications;
 pub mod repositories;
+pub mod security;
+
+// Emits one `sql` debug event per statement with its duration, inside whatever repository span is current.
+// Bind values are left out; they carry password and token hashes.
+#[derive(Default)]
+struct SqlTracing {
+    started: Option<Instant>,
+}
+
+impl Instrumentation for SqlTracing {
+    fn on_connection_event(&mut self, event: InstrumentationEvent<'_>) {
+        match event {
+            InstrumentationEvent::StartQuery { .. } => self.started = Some(Instant::now()),
+            InstrumentationEvent::FinishQuery { query, error, .. } => {
+                let elapsed_ms = self.started.take().map(|started| started.elapsed().as_secs_f64() * 1000.0);
+                let query = query.to_string();
+                let sql = query.split(" -- binds:").next().unwrap_or_default();
+                match error {
+                    Some(e) => tracing::debug!(target: "sql", sql, elapsed_ms, error = %e, "query failed"),
+                    None => tracing::debug!(target: "sql", sql, elapsed_ms, "query"),
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+// Applied to every pooled connection: WAL lets readers proceed during writes, and the busy timeout makes
+// concurrent writers wait for the lock instead of failing with SQLITE_BUSY. Foreign keys stay unenforced:
+// deletes have always left dependent rows for `library-admin check` to report, so handlers look up the rows
+// they reference before writing.
+#[derive(Debug)]
+struct SqlitePragmas {
+    busy_timeout_ms: u32,
+}
+
+impl CustomizeConnection<SqliteConnection, diesel::r2d2::Error> for SqlitePragmas {
+    fn on_acquire(&self, conn: &mut SqliteConnection) -> Result<(), diesel::r2d2::Error> {
+        conn.set_instrumentation(SqlTracing::default());
+        // busy_timeout first: switching a fresh database to WAL takes a lock other connections may be holding.
+        conn.batch_execute(&format!("PRAGMA busy_timeout = {}; PRAGMA journal_mode = WAL;", self.busy_timeout_ms))
+            .map_err(diesel::r2d2::Error::QueryError)
+    }
+}
+
+// Where a repository takes its connections from: the pool, one per call, or a connection the caller lends it, so
+// that the calls of several repositories run in one transaction the caller opened on it.
+enum Db<'c> {
+    Pool(Arc<Pool<ConnectionManager<SqliteConnection>>>),
+    Connection(&'c mut SqliteConnection),
+}
+
+impl Db<'_> {
+    fn get_conn(&mut self) -> DbConnection<'_> {
+        match self {
+            Db::Pool(pool) => DbConnection::Pooled(pool.get().expect("Failed to get a connection from the pool")),
+            Db::Connection(conn) => DbConnection::Borrowed(conn),
+        }
+    }
+}
+
+enum DbConnection<'a> {
+    Pooled(PooledConnection<ConnectionManager<SqliteConnection>>),
+    Borrowed(&'a mut SqliteConnection),
+}
+
+impl Deref for DbConnection<'_> {
+    type Target = SqliteConnection;
+
+    fn deref(&self) -> &SqliteConnection {
+        match self {
+            DbConnection::Pooled(conn) => conn,
+            DbConnection::Borrowed(conn) => conn,
+        }
+    }
+}
 
-pub fn establish_connection() -> Arc<Pool<ConnectionManager<SqliteConnection>>> {
-    dotenv().ok();
-    let database_url = env::var("DATABASE_URL").unwrap_or_else(|_| "sqlite://my_database.db".to_string());
-    let manager = ConnectionManager::<SqliteConnection>::new(database_url);
+impl DerefMut for DbConnection<'_> {
+    fn deref_mut(&mut self) -> &mut SqliteConnection {
+        match self {
+            DbConnection::Pooled(conn) => conn,
+            DbConnection::Borrowed(conn) => conn,
+        }
+    }
+}
+
+// `events` observes checkouts and timeouts, e.g. to export pool wait times.
+pub fn establish_connection(config: &DatabaseConfig, events: Box<dyn HandleEvent>) -> Arc<Pool<ConnectionManager<SqliteConnection>>> {
+    let manager = ConnectionManager::<SqliteConnection>::new(config.url.as_str());
     let pool: Arc<Pool<ConnectionManager<SqliteConnection>>> = Arc::new(
         Pool::builder()
+            .max_size(config.pool_max_size)
+            .min_idle(config.pool_min_idle)
+            .connection_timeout(Duration::from_secs(config.connection_timeout_seconds))
+            .connection_customizer(Box::new(SqlitePragmas { busy_timeout_ms: config.busy_timeout_ms }))
+            .event_handler(events)
             .build(manager)
             .expect("Failed to create pool."),
     );
 
     pool
-}
\ No newline at end of file
+}
+
+// One migrated in-memory database behind a single-connection pool, for repository tests.
+#[cfg(test)]
+pub(crate) fn test_pool() -> Arc<Pool<ConnectionManager<SqliteConnection>>> {
+    let pool = Arc::new(
+        Pool::builder()
+            .max_size(1)
+            .connection_customizer(Box::new(SqlitePragmas { busy_timeout_ms: 5000 }))
+            .build(ConnectionManager::<SqliteConnection>::new(":memory:"))
+            .expect("Failed to create pool."),
+    );
+    migrations::run_migrations(&pool);
+    pool
+}
+
+// `test_pool` with the rows most repository tests start from: member 1, who manages libraries 1 and 2, and book 1,
+// which no library holds yet. Tests insert the rest themselves.
+#[cfg(test)]
+pub(crate) fn seeded_pool() -> Arc<Pool<ConnectionManager<SqliteConnection>>> {
+    let pool = test_pool();
+    pool.get().unwrap().batch_execute("
+        INSERT INTO members (id, name, email, created_at, updated_at, email_normalized)
+            VALUES (1, 'Ada', 'ada@example.com', '2026-01-01', '2026-01-01', 'ada@example.com');
+        INSERT INTO library (id, name, address, created_at, updated_at, manager_id) VALUES (1, 'Central', 'Main St 1', '2026-01-01', '2026-01-01', 1);
+        INSERT INTO library (id, name, address, created_at, updated_at, manager_id) VALUES (2, 'North', 'North St 1', '2026-01-01', '2026-01-01', 1);
+        INSERT INTO books (id, title, author, created_at, updated_at) VALUES (1, 'Dune', 'Herbert', '2026-01-01', '2026-01-01');
+    ").unwrap();
+    pool
+}
+
+// Folds the write-ahead log back into the database file and truncates it; run once no more writes are expected.
+pub fn checkpoint_wal(pool: &Pool<ConnectionManager<SqliteConnection>>) -> Result<(), String> {
+    let mut conn = pool.get().map_err(|e| e.to_string())?;
+    conn.batch_execute("PRAGMA wal_checkpoint(TRUNCATE);").map_err(|e| e.to_string())
+}
@@ -0,0 +1,627 @@
+use std::sync::Arc;
+
+use diesel::connection::SimpleConnection;
+use diesel::r2d2::{ConnectionManager, Pool, PooledConnection};
+use diesel::sql_types::Integer;
+use diesel::{Connection, QueryableByName, RunQueryDsl, SqliteConnection};
+
+pub struct Migration {
+    pub version: i32,
+    pub name: &'static str,
+    pub sql: &'static str,
+}
+
+// Migrations are applied in order and recorded in schema_migrations; never edit one that has shipped.
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        name: "create_initial_tables",
+        sql: "
+            CREATE TABLE IF NOT EXISTS members (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                name TEXT NOT NULL,
+                email TEXT NOT NULL,
+                created_at TEXT NOT NULL,
+                updated_at TEXT NOT NULL
+            );
+
+            CREATE TABLE IF NOT EXISTS library (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                name TEXT NOT NULL,
+                address TEXT NOT NULL,
+                created_at TEXT NOT NULL,
+                updated_at TEXT NOT NULL,
+                manager_id INTEGER NOT NULL,
+                FOREIGN KEY (manager_id) REFERENCES members(id)
+            );
+
+            CREATE TABLE IF NOT EXISTS books (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                title TEXT NOT NULL,
+                author TEXT NOT NULL,
+                created_at TEXT NOT NULL,
+                updated_at TEXT NOT NULL
+            );
+
+            CREATE TABLE IF NOT EXISTS library_books (
+                library_id INTEGER NOT NULL,
+                book_id INTEGER NOT NULL,
+                quantity INTEGER NOT NULL,
+                FOREIGN KEY (library_id) REFERENCES library(id),
+                FOREIGN KEY (book_id) REFERENCES books(id),
+                PRIMARY KEY (library_id, book_id)
+            );
+
+            CREATE TABLE IF NOT EXISTS library_members (
+                library_id INTEGER NOT NULL,
+                member_id INTEGER NOT NULL,
+                FOREIGN KEY (library_id) REFERENCES library(id),
+                FOREIGN KEY (member_id) REFERENCES members(id),
+                PRIMARY KEY (library_id, member_id)
+            );
+
+            CREATE TABLE IF NOT EXISTS borrowed_books (
+                member_id INTEGER NOT NULL,
+                book_id INTEGER NOT NULL,
+                borrowed_at TEXT NOT NULL,
+                FOREIGN KEY (member_id) REFERENCES members(id),
+                FOREIGN KEY (book_id) REFERENCES books(id),
+                PRIMARY KEY (member_id, book_id)
+            );
+        ",
+    },
+    Migration {
+        version: 2,
+        name: "add_row_versions",
+        sql: "
+            ALTER TABLE members ADD COLUMN version INTEGER NOT NULL DEFAULT 1;
+            ALTER TABLE library ADD COLUMN version INTEGER NOT NULL DEFAULT 1;
+            ALTER TABLE books ADD COLUMN version INTEGER NOT NULL DEFAULT 1;
+        ",
+    },
+    Migration {
+        version: 3,
+        name: "create_auth_tables",
+        sql: "
+            CREATE TABLE users (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                username TEXT NOT NULL UNIQUE,
+                password_hash TEXT NOT NULL,
+                account_type TEXT NOT NULL CHECK (account_type IN ('staff', 'member')),
+                member_id INTEGER,
+                created_at TEXT NOT NULL,
+                updated_at TEXT NOT NULL,
+                FOREIGN KEY (member_id) REFERENCES members(id)
+            );
+
+            CREATE TABLE refresh_tokens (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                user_id INTEGER NOT NULL,
+                token_hash TEXT NOT NULL UNIQUE,
+                expires_at TEXT NOT NULL,
+                revoked_at TEXT,
+                created_at TEXT NOT NULL,
+                FOREIGN KEY (user_id) REFERENCES users(id)
+            );
+
+            CREATE TABLE api_keys (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                user_id INTEGER NOT NULL,
+                name TEXT NOT NULL,
+                prefix TEXT NOT NULL,
+                key_hash TEXT NOT NULL UNIQUE,
+                created_at TEXT NOT NULL,
+                last_used_at TEXT,
+                revoked_at TEXT,
+                FOREIGN KEY (user_id) REFERENCES users(id)
+            );
+        ",
+    },
+    Migration {
+        version: 4,
+        name: "create_user_roles",
+        sql: "
+            CREATE TABLE user_roles (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                user_id INTEGER NOT NULL,
+                role TEXT NOT NULL CHECK (role IN ('system_admin', 'library_manager', 'librarian', 'member')),
+                library_id INTEGER,
+                created_at TEXT NOT NULL,
+                FOREIGN KEY (user_id) REFERENCES users(id),
+                FOREIGN KEY (library_id) REFERENCES library(id),
+                CHECK (role <> 'system_admin' OR library_id IS NULL)
+            );
+
+            CREATE UNIQUE INDEX user_roles_unique ON user_roles (user_id, role, COALESCE(library_id, 0));
+
+            -- Staff accounts could do everything before roles existed; keep it that way until an admin narrows them.
+            INSERT INTO user_roles (user_id, role, library_id, created_at)
+                SELECT id, 'system_admin', NULL, datetime('now') FROM users WHERE account_type = 'staff';
+        ",
+    },
+    Migration {
+        version: 5,
+        name: "create_audit_log",
+        sql: "
+            CREATE TABLE audit_log (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                occurred_at TEXT NOT NULL,
+                request_id TEXT NOT NULL,
+                actor_user_id INTEGER,
+                actor_username TEXT,
+                action TEXT NOT NULL,
+                entity TEXT NOT NULL,
+                entity_id TEXT NOT NULL,
+                before_json TEXT,
+                after_json TEXT,
+                diff_json TEXT
+            );
+
+            CREATE INDEX audit_log_entity ON audit_log (entity, entity_id);
+
+            CREATE TRIGGER audit_log_no_update BEFORE UPDATE ON audit_log
+            BEGIN
+                SELECT RAISE(ABORT, 'audit_log is append-only');
+            END;
+
+            CREATE TRIGGER audit_log_no_delete BEFORE DELETE ON audit_log
+            BEGIN
+                SELECT RAISE(ABORT, 'audit_log is append-only');
+            END;
+        ",
+    },
+    Migration {
+        version: 6,
+        name: "add_loan_due_dates",
+        // Existing loans get the default 14 day loan period.
+        sql: "
+            ALTER TABLE borrowed_books ADD COLUMN due_at TEXT;
+            UPDATE borrowed_books SET due_at = datetime(borrowed_at, '+14 days') WHERE due_at IS NULL;
+        ",
+    },
+    Migration {
+        version: 7,
+        name: "create_loan_notices",
+        // A loan is identified by member, book and borrowed_at; the unique index keeps one notice per kind per loan.
+        sql: "
+            ALTER TABLE members ADD COLUMN language TEXT;
+
+            CREATE TABLE loan_notices (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                member_id INTEGER NOT NULL,
+                book_id INTEGER NOT NULL,
+                borrowed_at TEXT NOT NULL,
+                kind TEXT NOT NULL CHECK (kind IN ('reminder', 'overdue')),
+                channel TEXT NOT NULL,
+                recipient TEXT NOT NULL,
+                sent_at TEXT NOT NULL,
+                FOREIGN KEY (member_id) REFERENCES members(id),
+                FOREIGN KEY (book_id) REFERENCES books(id)
+            );
+
+            CREATE UNIQUE INDEX loan_notices_unique ON loan_notices (member_id, book_id, borrowed_at, kind);
+        ",
+    },
+    Migration {
+        version: 8,
+        name: "create_job_tables",
+        // job_locks holds one lease per job across every server sharing the database; the partial unique index
+        // lets only one instance record a run for a given scheduled time.
+        sql: "
+            CREATE TABLE job_runs (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                job TEXT NOT NULL,
+                trigger TEXT NOT NULL CHECK (trigger IN ('schedule', 'manual')),
+                scheduled_for TEXT,
+                instance TEXT NOT NULL,
+                status TEXT NOT NULL CHECK (status IN ('running', 'succeeded', 'failed')),
+                started_at TEXT NOT NULL,
+                finished_at TEXT,
+                output TEXT,
+                error TEXT
+            );
+
+            CREATE INDEX job_runs_job ON job_runs (job, id);
+            CREATE UNIQUE INDEX job_runs_scheduled ON job_runs (job, scheduled_for) WHERE scheduled_for IS NOT NULL;
+
+            CREATE TABLE job_locks (
+                job TEXT PRIMARY KEY,
+                holder TEXT NOT NULL,
+                locked_until TEXT NOT NULL
+            );
+        ",
+    },
+    Migration {
+        version: 9,
+        name: "keep_loan_history",
+        sql: "
+            CREATE TABLE borrowed_books_new (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                member_id INTEGER NOT NULL,
+                book_id INTEGER NOT NULL,
+                library_id INTEGER,
+                borrowed_at TEXT NOT NULL,
+                due_at TEXT,
+                returned_at TEXT,
+                FOREIGN KEY (member_id) REFERENCES members(id),
+                FOREIGN KEY (book_id) REFERENCES books(id),
+                FOREIGN KEY (library_id) REFERENCES library(id)
+            );
+
+            INSERT INTO borrowed_books_new (member_id, book_id, library_id, borrowed_at, due_at)
+            SELECT bb.member_id, bb.book_id,
+                   (SELECT MIN(lm.library_id)
+                    FROM library_members lm
+                    JOIN library_books lb ON lb.library_id = lm.library_id AND lb.book_id = bb.book_id
+                    WHERE lm.member_id = bb.member_id),
+                   bb.borrowed_at, bb.due_at
+            FROM borrowed_books bb;
+
+            DROP TABLE borrowed_books;
+            ALTER TABLE borrowed_books_new RENAME TO borrowed_books;
+
+            CREATE UNIQUE INDEX borrowed_books_open ON borrowed_books (member_id, book_id) WHERE returned_at IS NULL;
+            CREATE INDEX borrowed_books_borrowed_at ON borrowed_books (borrowed_at);
+
+            ALTER TABLE library_books ADD COLUMN added_at TEXT;
+            UPDATE library_books SET added_at = (SELECT b.created_at FROM books b WHERE b.id = library_books.book_id);
+        ",
+    },
+    Migration {
+        version: 10,
+        name: "create_transfers",
+        sql: "
+            CREATE TABLE transfers (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                book_id INTEGER NOT NULL,
+                from_library_id INTEGER NOT NULL,
+                to_library_id INTEGER NOT NULL,
+                member_id INTEGER,
+                quantity INTEGER NOT NULL CHECK (quantity > 0),
+                status TEXT NOT NULL CHECK (status IN ('requested', 'approved', 'in_transit', 'received', 'returned', 'cancelled')),
+                note TEXT,
+                requested_by INTEGER,
+                requested_at TEXT NOT NULL,
+                approved_at TEXT,
+                shipped_at TEXT,
+                received_at TEXT,
+                returned_at TEXT,
+                cancelled_at TEXT,
+                FOREIGN KEY (book_id) REFERENCES books(id),
+                FOREIGN KEY (from_library_id) REFERENCES library(id),
+                FOREIGN KEY (to_library_id) REFERENCES library(id),
+                FOREIGN KEY (member_id) REFERENCES members(id),
+                FOREIGN KEY (requested_by) REFERENCES users(id),
+                CHECK (from_library_id <> to_library_id)
+            );
+
+            CREATE INDEX transfers_from_library ON transfers (from_library_id, status);
+            CREATE INDEX transfers_to_library ON transfers (to_library_id, status);
+        ",
+    },
+    Migration {
+        version: 11,
+        name: "member_portal",
+        sql: "
+            ALTER TABLE borrowed_books ADD COLUMN renewals INTEGER NOT NULL DEFAULT 0;
+            ALTER TABLE members ADD COLUMN notify_reminders BOOLEAN NOT NULL DEFAULT 1;
+
+            -- A member waiting for a book. A hold is active until it is fulfilled by a loan or cancelled.
+            CREATE TABLE holds (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                member_id INTEGER NOT NULL,
+                book_id INTEGER NOT NULL,
+                library_id INTEGER,
+                placed_at TEXT NOT NULL,
+                fulfilled_at TEXT,
+                cancelled_at TEXT,
+                FOREIGN KEY (member_id) REFERENCES members(id),
+                FOREIGN KEY (book_id) REFERENCES books(id),
+                FOREIGN KEY (library_id) REFERENCES library(id)
+            );
+
+            CREATE UNIQUE INDEX holds_active ON holds (member_id, book_id) WHERE fulfilled_at IS NULL AND cancelled_at IS NULL;
+            CREATE INDEX holds_book ON holds (book_id, placed_at);
+        ",
+    },
+    Migration {
+        version: 12,
+        name: "member_status",
+        sql: "
+            ALTER TABLE members ADD COLUMN status TEXT NOT NULL DEFAULT 'active'
+                CHECK (status IN ('active', 'expired', 'suspended', 'banned'));
+            ALTER TABLE members ADD COLUMN status_reason TEXT;
+            ALTER TABLE members ADD COLUMN status_since TEXT;
+            ALTER TABLE members ADD COLUMN status_until TEXT;
+            ALTER TABLE members ADD COLUMN expires_at TEXT;
+        ",
+    },
+    Migration {
+        version: 13,
+        name: "unique_member_emails",
+        sql: "
+            ALTER TABLE members ADD COLUMN email_normalized TEXT;
+            UPDATE members SET email_normalized = lower(trim(email));
+
+            -- Existing duplicates keep the oldest record's claim on the address; the others are left for
+            -- GET /members/duplicates and a merge.
+            UPDATE members SET email_normalized = NULL
+            WHERE id NOT IN (SELECT MIN(id) FROM members GROUP BY lower(trim(email)));
+
+            CREATE UNIQUE INDEX members_email_normalized ON members (email_normalized);
+        ",
+    },
+    Migration {
+        version: 14,
+        name: "member_erasure",
+        // The audit log stays append-only, except that erasing a member may clear snapshots and actor names.
+        sql: "
+            ALTER TABLE members ADD COLUMN erased_at TEXT;
+
+            DROP TRIGGER audit_log_no_update;
+            CREATE TRIGGER audit_log_no_update BEFORE UPDATE ON audit_log
+            WHEN NEW.id IS NOT OLD.id
+              OR NEW.occurred_at IS NOT OLD.occurred_at
+              OR NEW.request_id IS NOT OLD.request_id
+              OR NEW.action IS NOT OLD.action
+              OR NEW.entity IS NOT OLD.entity
+              OR NEW.entity_id IS NOT OLD.entity_id
+              OR (NEW.actor_user_id IS NOT OLD.actor_user_id AND NEW.actor_user_id IS NOT NULL)
+              OR (NEW.actor_username IS NOT OLD.actor_username AND NEW.actor_username IS NOT NULL)
+              OR (NEW.before_json IS NOT OLD.before_json AND NEW.before_json IS NOT NULL)
+              OR (NEW.after_json IS NOT OLD.after_json AND NEW.after_json IS NOT NULL)
+              OR (NEW.diff_json IS NOT OLD.diff_json AND NEW.diff_json IS NOT NULL)
+            BEGIN
+                SELECT RAISE(ABORT, 'audit_log is append-only');
+            END;
+        ",
+    },
+    Migration {
+        version: 15,
+        name: "loan_retention",
+        // Rebuilds borrowed_books so member_id can be cleared once a returned loan is anonymized.
+        sql: "
+            CREATE TABLE borrowed_books_new (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                member_id INTEGER,
+                book_id INTEGER NOT NULL,
+                library_id INTEGER,
+                borrowed_at TEXT NOT NULL,
+                due_at TEXT,
+                returned_at TEXT,
+                renewals INTEGER NOT NULL DEFAULT 0,
+                anonymized_at TEXT,
+                FOREIGN KEY (member_id) REFERENCES members(id),
+                FOREIGN KEY (book_id) REFERENCES books(id),
+                FOREIGN KEY (library_id) REFERENCES library(id)
+            );
+
+            INSERT INTO borrowed_books_new (id, member_id, book_id, library_id, borrowed_at, due_at, returned_at, renewals)
+            SELECT id, member_id, book_id, library_id, borrowed_at, due_at, returned_at, renewals
+            FROM borrowed_books;
+
+            DROP TABLE borrowed_books;
+            ALTER TABLE borrowed_books_new RENAME TO borrowed_books;
+
+            CREATE UNIQUE INDEX borrowed_books_open ON borrowed_books (member_id, book_id) WHERE returned_at IS NULL;
+            CREATE INDEX borrowed_books_borrowed_at ON borrowed_books (borrowed_at);
+            CREATE INDEX borrowed_books_returned_at ON borrowed_books (returned_at) WHERE member_id IS NOT NULL;
+
+            ALTER TABLE library ADD COLUMN loan_retention_days INTEGER CHECK (loan_retention_days >= 0);
+            ALTER TABLE members ADD COLUMN keep_loan_history BOOLEAN NOT NULL DEFAULT 0;
+        ",
+    },
+    Migration {
+        version: 16,
+        name: "library_calendar",
+        // Weekdays run 1 (Monday) to 7 (Sunday), times are HH:MM local to the library. A library without any
+        // hours is open every day but its closures.
+        sql: "
+            CREATE TABLE library_hours (
+                library_id INTEGER NOT NULL,
+                weekday INTEGER NOT NULL CHECK (weekday BETWEEN 1 AND 7),
+                opens_at TEXT NOT NULL,
+                closes_at TEXT NOT NULL,
+                PRIMARY KEY (library_id, weekday),
+                FOREIGN KEY (library_id) REFERENCES library(id)
+            );
+
+            CREATE TABLE library_closures (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                library_id INTEGER NOT NULL,
+                date TEXT NOT NULL,
+                reason TEXT NOT NULL,
+                FOREIGN KEY (library_id) REFERENCES library(id)
+            );
+
+            CREATE UNIQUE INDEX library_closures_date ON library_closures (library_id, date);
+        ",
+    },
+    Migration {
+        version: 17,
+        name: "library_contact",
+        sql: "
+            ALTER TABLE library ADD COLUMN street TEXT;
+            ALTER TABLE library ADD COLUMN city TEXT;
+            ALTER TABLE library ADD COLUMN postal_code TEXT;
+            ALTER TABLE library ADD COLUMN country TEXT;
+            ALTER TABLE library ADD COLUMN phone TEXT;
+            ALTER TABLE library ADD COLUMN email TEXT;
+            ALTER TABLE library ADD COLUMN website TEXT;
+            ALTER TABLE library ADD COLUMN latitude REAL CHECK (latitude BETWEEN -90 AND 90);
+            ALTER TABLE library ADD COLUMN longitude REAL CHECK (longitude BETWEEN -180 AND 180);
+        ",
+    },
+    Migration {
+        version: 18,
+        name: "library_staff",
+        sql: "
+            CREATE TABLE library_staff (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                library_id INTEGER NOT NULL,
+                member_id INTEGER NOT NULL,
+                role TEXT NOT NULL CHECK (role IN ('library_manager', 'librarian')),
+                started_at TEXT NOT NULL,
+                ended_at TEXT CHECK (ended_at IS NULL OR ended_at >= started_at),
+                created_at TEXT NOT NULL,
+                FOREIGN KEY (library_id) REFERENCES library(id),
+                FOREIGN KEY (member_id) REFERENCES members(id)
+            );
+
+            CREATE INDEX library_staff_library ON library_staff (library_id);
+            CREATE INDEX library_staff_member ON library_staff (member_id);
+
+            CREATE TABLE library_handovers (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                library_id INTEGER NOT NULL,
+                from_member_id INTEGER,
+                to_member_id INTEGER NOT NULL,
+                handed_over_at TEXT NOT NULL,
+                FOREIGN KEY (library_id) REFERENCES library(id),
+                FOREIGN KEY (from_member_id) REFERENCES members(id),
+                FOREIGN KEY (to_member_id) REFERENCES members(id)
+            );
+
+            CREATE INDEX library_handovers_library ON library_handovers (library_id);
+
+            INSERT INTO library_staff (library_id, member_id, role, started_at, created_at)
+                SELECT id, manager_id, 'library_manager', substr(created_at, 1, 10), created_at FROM library;
+        ",
+    },
+    Migration {
+        version: 19,
+        name: "acquisitions",
+        sql: "
+            ALTER TABLE books ADD COLUMN isbn TEXT;
+
+            CREATE UNIQUE INDEX books_isbn ON books (isbn);
+
+            CREATE TABLE vendors (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                name TEXT NOT NULL,
+                email TEXT,
+                phone TEXT,
+                created_at TEXT NOT NULL,
+                updated_at TEXT NOT NULL
+            );
+
+            CREATE TABLE budgets (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                library_id INTEGER NOT NULL,
+                fiscal_year INTEGER NOT NULL,
+                amount_cents INTEGER NOT NULL CHECK (amount_cents >= 0),
+                created_at TEXT NOT NULL,
+                updated_at TEXT NOT NULL,
+                UNIQUE (library_id, fiscal_year),
+                FOREIGN KEY (library_id) REFERENCES library(id)
+            );
+
+            CREATE TABLE purchase_orders (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                library_id INTEGER NOT NULL,
+                vendor_id INTEGER NOT NULL,
+                fiscal_year INTEGER NOT NULL,
+                status TEXT NOT NULL CHECK (status IN ('draft', 'ordered', 'partially_received', 'received', 'cancelled')),
+                note TEXT,
+                created_by INTEGER,
+                created_at TEXT NOT NULL,
+                ordered_at TEXT,
+                closed_at TEXT,
+                FOREIGN KEY (library_id) REFERENCES library(id),
+                FOREIGN KEY (vendor_id) REFERENCES vendors(id),
+                FOREIGN KEY (created_by) REFERENCES users(id)
+            );
+
+            CREATE INDEX purchase_orders_library ON purchase_orders (library_id, fiscal_year);
+            CREATE INDEX purchase_orders_vendor ON purchase_orders (vendor_id);
+
+            CREATE TABLE purchase_order_lines (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                order_id INTEGER NOT NULL,
+                book_id INTEGER,
+                isbn TEXT,
+                title TEXT NOT NULL,
+                author TEXT NOT NULL,
+                quantity INTEGER NOT NULL CHECK (quantity > 0),
+                unit_price_cents INTEGER NOT NULL CHECK (unit_price_cents >= 0),
+                received_quantity INTEGER NOT NULL DEFAULT 0 CHECK (received_quantity BETWEEN 0 AND quantity),
+                FOREIGN KEY (order_id) REFERENCES purchase_orders(id),
+                FOREIGN KEY (book_id) REFERENCES books(id) ON DELETE SET NULL
+            );
+
+            CREATE INDEX purchase_order_lines_order ON purchase_order_lines (order_id);
+        ",
+    },
+    Migration {
+        version: 20,
+        name: "loan_notices_due_at",
+        // Notices are kept per due date, so a renewed loan gets a fresh reminder and overdue notice. Notices no
+        // longer matching a loan get a NULL due_at here; the next loan_history run that unlinks loans deletes them.
+        sql: "
+            ALTER TABLE loan_notices ADD COLUMN due_at TEXT;
+
+            UPDATE loan_notices SET due_at = (
+                SELECT bb.due_at FROM borrowed_books bb
+                WHERE bb.member_id = loan_notices.member_id AND bb.book_id = loan_notices.book_id
+                  AND bb.borrowed_at = loan_notices.borrowed_at
+            );
+
+            DROP INDEX loan_notices_unique;
+            CREATE UNIQUE INDEX loan_notices_unique ON loan_notices (member_id, book_id, borrowed_at, due_at, kind);
+        ",
+    },
+    Migration {
+        version: 21,
+        name: "pad_opening_hours",
+        // Opening hours were stored as sent, so "9:00" sorted after "10:00" and broke the iCalendar export.
+        sql: "
+            UPDATE library_hours SET opens_at = '0' || opens_at WHERE length(opens_at) = 4;
+            UPDATE library_hours SET closes_at = '0' || closes_at WHERE length(closes_at) = 4;
+        ",
+    },
+];
+
+#[derive(QueryableByName)]
+struct AppliedVersion {
+    #[diesel(sql_type = Integer)]
+    version: i32,
+}
+
+pub fn latest_version() -> i32 {
+    MIGRATIONS.last().map(|migration| migration.version).unwrap_or(0)
+}
+
+pub fn applied_version(conn: &mut SqliteConnection) -> diesel::QueryResult<i32> {
+    conn.batch_execute("
+        CREATE TABLE IF NOT EXISTS schema_migrations (
+            version INTEGER PRIMARY KEY,
+            name TEXT NOT NULL,
+            applied_at TEXT NOT NULL
+        );
+    ")?;
+
+    let applied: AppliedVersion = diesel::sql_query("SELECT COALESCE(MAX(version), 0) AS version FROM schema_migrations")
+        .get_result(conn)?;
+
+    Ok(applied.version)
+}
+
+pub fn run_migrations(pool: &Arc<Pool<ConnectionManager<SqliteConnection>>>) {
+    let mut conn: PooledConnection<ConnectionManager<SqliteConnection>> = pool.get().expect("Failed to get a connection from the pool");
+
+    let current = applied_version(&mut conn).expect("Failed to read schema version");
+
+    for migration in MIGRATIONS.iter().filter(|m| m.version > current) {
+        conn.transaction::<_, diesel::result::Error, _>(|connection| {
+            connection.batch_execute(migration.sql)?;
+
+            diesel::sql_query("INSERT INTO schema_migrations (version, name, applied_at) VALUES (?, ?, ?)")
+                .bind::<Integer, _>(migration.version)
+                .bind::<diesel::sql_types::Text, _>(migration.name)
+                .bind::<diesel::sql_types::Text, _>(chrono::offset::Utc::now().naive_utc().to_string())
+                .execute(connection)?;
+
+            Ok(())
+        }).unwrap_or_else(|e| panic!("Failed to apply migration {} ({}): {}", migration.version, migration.name, e));
+    }
+}
@@ -0,0 +1,184 @@
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use lettre::message::{header::ContentType, Mailbox};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{Message, SmtpTransport, Transport};
+
+use domain::models::notice::{DueLoan, Notice, NOTICE_OVERDUE, NOTICE_REMINDER};
+use domain::traits::Notifier;
+
+use crate::config::{NoticeTemplate, NotificationsConfig, SmtpConfig};
+
+const DEFAULT_REMINDER_SUBJECT: &str = "Reminder: \"{book_title}\" is due on {due_date}";
+const DEFAULT_REMINDER_BODY: &str = "Dear {member_name},
+
+\"{book_title}\" from {library_name} is due back on {due_date}, in {days} day(s). Please return or renew it by then.
+";
+const DEFAULT_OVERDUE_SUBJECT: &str = "Overdue: \"{book_title}\" was due on {due_date}";
+const DEFAULT_OVERDUE_BODY: &str = "Dear {member_name},
+
+\"{book_title}\" from {library_name} was due back on {due_date} and is now {days} day(s) overdue. Please return it as soon as possible.
+";
+
+// Picks the most specific template for a loan: library and member language, then any library in that language,
+// then the same two in the default language, then the built-in English text.
+pub fn render_notice(config: &NotificationsConfig, kind: &str, loan: &DueLoan, days: i64) -> Notice {
+    let language = loan.language.as_deref().unwrap_or(&config.default_language);
+    let find = |language: &str, library_id: Option<i32>| {
+        config
+            .templates
+            .iter()
+            .find(|t| t.kind == kind && t.language == language && t.library_id == library_id)
+    };
+    let template = loan
+        .library_id
+        .and_then(|library_id| find(language, Some(library_id)))
+        .or_else(|| find(language, None))
+        .or_else(|| loan.library_id.and_then(|library_id| find(&config.default_language, Some(library_id))))
+        .or_else(|| find(&config.default_language, None));
+
+    let (subject, body) = match (template, kind) {
+        (Some(NoticeTemplate { subject, body, .. }), _) => (subject.as_str(), body.as_str()),
+        (None, NOTICE_OVERDUE) => (DEFAULT_OVERDUE_SUBJECT, DEFAULT_OVERDUE_BODY),
+        (None, _) => (DEFAULT_REMINDER_SUBJECT, DEFAULT_REMINDER_BODY),
+    };
+
+    let due_date = loan.due_at.get(..10).unwrap_or(&loan.due_at);
+    let library_name = loan.library_name.as_deref().unwrap_or("the library");
+    let fill = |text: &str| {
+        text.replace("{member_name}", &loan.member_name)
+            .replace("{book_title}", &loan.book_title)
+            .replace("{due_date}", due_date)
+            .replace("{days}", &days.to_string())
+            .replace("{library_name}", library_name)
+    };
+
+    Notice {
+        kind: kind.to_string(),
+        recipient_name: loan.member_name.clone(),
+        recipient_email: loan.member_email.clone(),
+        subject: fill(subject),
+        body: fill(body),
+    }
+}
+
+pub fn build_notifier(config: &NotificationsConfig) -> Result<Box<dyn Notifier + Send + Sync>, String> {
+    match config.channel.as_str() {
+        "smtp" => Ok(Box::new(SmtpNotifier::new(&config.smtp)?)),
+        "file" => Ok(Box::new(FileNotifier::new(PathBuf::from(&config.file_path)))),
+        _ => Ok(Box::new(LogNotifier)),
+    }
+}
+
+// Writes notices to the application log; useful in development and as a dry run.
+pub struct LogNotifier;
+
+impl Notifier for LogNotifier {
+    fn channel(&self) -> &'static str {
+        "log"
+    }
+
+    fn send(&self, notice: &Notice) -> Result<(), String> {
+        tracing::info!(
+            kind = %notice.kind,
+            to = %notice.recipient_email,
+            subject = %notice.subject,
+            body = %notice.body,
+            "Loan notice"
+        );
+        Ok(())
+    }
+}
+
+// Appends notices to a file as JSON Lines, for pick-up by another system or for inspection in tests.
+pub struct FileNotifier {
+    path: PathBuf,
+    lock: Mutex<()>,
+}
+
+impl FileNotifier {
+    pub fn new(path: PathBuf) -> FileNotifier {
+        FileNotifier { path, lock: Mutex::new(()) }
+    }
+}
+
+impl Notifier for FileNotifier {
+    fn channel(&self) -> &'static str {
+        "file"
+    }
+
+    fn send(&self, notice: &Notice) -> Result<(), String> {
+        let line = serde_json::json!({
+            "sent_at": chrono::offset::Utc::now().naive_utc().to_string(),
+            "kind": notice.kind,
+            "to": notice.recipient_email,
+            "subject": notice.subject,
+            "body": notice.body,
+        });
+
+        let _guard = self.lock.lock().unwrap();
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .map_err(|e| format!("Failed to open {}: {}", self.path.display(), e))?;
+        writeln!(file, "{}", line).map_err(|e| format!("Failed to write {}: {}", self.path.display(), e))
+    }
+}
+
+pub struct SmtpNotifier {
+    transport: SmtpTransport,
+    from: Mailbox,
+}
+
+impl SmtpNotifier {
+    pub fn new(config: &SmtpConfig) -> Result<SmtpNotifier, String> {
+        let from = config.from.parse::<Mailbox>().map_err(|e| format!("Invalid notifications.smtp.from: {}", e))?;
+
+        let mut builder = if config.starttls {
+            SmtpTransport::starttls_relay(&config.host).map_err(|e| format!("Invalid SMTP relay: {}", e))?
+        } else {
+            SmtpTransport::builder_dangerous(&config.host)
+        };
+        builder = builder.port(config.port);
+        if let (Some(username), Some(password)) = (&config.username, &config.password) {
+            builder = builder.credentials(Credentials::new(username.clone(), password.clone()));
+        }
+
+        Ok(SmtpNotifier { transport: builder.build(), from })
+    }
+}
+
+impl Notifier for SmtpNotifier {
+    fn channel(&self) -> &'static str {
+        "smtp"
+    }
+
+    fn send(&self, notice: &Notice) -> Result<(), String> {
+        let to = Mailbox::new(
+            Some(notice.recipient_name.clone()),
+            notice.recipient_email.parse().map_err(|e| format!("Invalid recipient {}: {}", notice.recipient_email, e))?,
+        );
+        let message = Message::builder()
+            .from(self.from.clone())
+            .to(to)
+            .subject(&notice.subject)
+            .header(ContentType::TEXT_PLAIN)
+            .body(notice.body.clone())
+            .map_err(|e| format!("Failed to build message: {}", e))?;
+
+        self.transport.send(&message).map(|_| ()).map_err(|e| format!("Failed to send mail: {}", e))
+    }
+}
+
+// Whole days left until the due date for reminders, or past it for overdue notices.
+pub fn notice_days(kind: &str, due_at: &str, now: &chrono::NaiveDateTime) -> i64 {
+    let due = chrono::NaiveDateTime::parse_from_str(due_at, "%Y-%m-%d %H:%M:%S%.f").unwrap_or(*now);
+    match kind {
+        NOTICE_REMINDER => (due.date() - now.date()).num_days().max(0),
+        _ => (now.date() - due.date()).num_days().max(0),
+    }
+}
@@ -0,0 +1,442 @@
+use diesel::prelude::*;
+use diesel::r2d2::{ConnectionManager, Pool};
+use diesel::result::QueryResult;
+use diesel::sql_types::{BigInt, Integer};
+use diesel::sqlite::SqliteConnection;
+use domain::models::acquisition::{
+    Budget, NewOrderLine, NewPurchaseOrder, NewVendor, OrderFilter, OrderLine, PurchaseOrder, Vendor, ORDER_CANCELLED,
+    ORDER_ORDERED, ORDER_PARTIALLY_RECEIVED, ORDER_RECEIVED,
+};
+use domain::models::book::NewBook;
+use domain::schema::books::dsl as books_dsl;
+use domain::schema::budgets::dsl as budgets_dsl;
+use domain::schema::purchase_order_lines::dsl as lines_dsl;
+use domain::schema::purchase_orders::dsl as orders_dsl;
+use domain::schema::vendors::dsl as vendors_dsl;
+use domain::traits::AcquisitionRepositoryTrait;
+use std::sync::Arc;
+use tracing::instrument;
+
+use crate::{Db, DbConnection};
+
+use crate::repositories::transfer_repository::put_copies;
+
+// A received order only counts what arrived: one closed short releases its outstanding copies.
+const COMMITTED_QUERY: &str = "
+    SELECT COALESCE(SUM(CASE WHEN po.status = 'received' THEN pol.received_quantity ELSE pol.quantity END
+                        * pol.unit_price_cents), 0) AS cents
+    FROM purchase_order_lines pol
+    JOIN purchase_orders po ON po.id = pol.order_id
+    WHERE po.library_id = ? AND po.fiscal_year = ?
+      AND po.status IN ('ordered', 'partially_received', 'received')
+";
+
+#[derive(QueryableByName)]
+struct Cents {
+    #[diesel(sql_type = BigInt)]
+    cents: i64,
+}
+
+fn committed(conn: &mut SqliteConnection, library_id: &i32, fiscal_year: &i32) -> QueryResult<i64> {
+    let row: Cents = diesel::sql_query(COMMITTED_QUERY)
+        .bind::<Integer, _>(library_id)
+        .bind::<Integer, _>(fiscal_year)
+        .get_result(conn)?;
+
+    Ok(row.cents)
+}
+
+// The line's book: its own, the catalogue entry with its ISBN, or a new one created from the line.
+fn line_book(conn: &mut SqliteConnection, line: &OrderLine, at: &str) -> QueryResult<i32> {
+    if let Some(book_id) = line.book_id {
+        return Ok(book_id);
+    }
+
+    let existing = match &line.isbn {
+        Some(isbn) => books_dsl::books
+            .filter(books_dsl::isbn.eq(isbn))
+            .select(books_dsl::id)
+            .first::<i32>(conn)
+            .optional()?,
+        None => None,
+    };
+    let book_id = match existing {
+        Some(book_id) => book_id,
+        None => {
+            diesel::insert_into(books_dsl::books)
+                .values(&NewBook {
+                    title: &line.title,
+                    author: &line.author,
+                    isbn: line.isbn.as_deref(),
+                    created_at: at,
+                    updated_at: at,
+                })
+                .execute(conn)?;
+
+            books_dsl::books
+                .select(books_dsl::id)
+                .order(books_dsl::id.desc())
+                .first(conn)?
+        }
+    };
+
+    diesel::update(lines_dsl::purchase_order_lines.find(line.id))
+        .set(lines_dsl::book_id.eq(book_id))
+        .execute(conn)?;
+    Ok(book_id)
+}
+
+pub struct AcquisitionRepository<'c> {
+    db: Db<'c>,
+}
+
+impl<'c> AcquisitionRepository<'c> {
+    pub fn new(pool: Arc<Pool<ConnectionManager<SqliteConnection>>>) -> Self {
+        AcquisitionRepository { db: Db::Pool(pool) }
+    }
+
+    pub fn with_connection(conn: &'c mut SqliteConnection) -> Self {
+        AcquisitionRepository { db: Db::Connection(conn) }
+    }
+
+    fn get_conn(&mut self) -> DbConnection<'_> {
+        self.db.get_conn()
+    }
+}
+
+impl AcquisitionRepositoryTrait for AcquisitionRepository<'_> {
+    #[instrument(level = "debug", skip_all)]
+    fn create_vendor(&mut self, vendor: &NewVendor) -> QueryResult<Vendor> {
+        let mut conn = self.get_conn();
+
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            diesel::insert_into(vendors_dsl::vendors)
+                .values(vendor)
+                .execute(conn)?;
+
+            vendors_dsl::vendors
+                .order(vendors_dsl::id.desc())
+                .first(conn)
+        })
+    }
+
+    #[instrument(level = "debug", skip_all)]
+    fn get_vendors(&mut self) -> QueryResult<Vec<Vendor>> {
+        let mut conn = self.get_conn();
+
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            vendors_dsl::vendors
+                .order(vendors_dsl::name.asc())
+                .load(conn)
+        })
+    }
+
+    #[instrument(level = "debug", skip_all)]
+    fn get_vendor(&mut self, id: &i32) -> QueryResult<Vendor> {
+        let mut conn = self.get_conn();
+
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            vendors_dsl::vendors.find(id).first(conn)
+        })
+    }
+
+    #[instrument(level = "debug", skip_all)]
+    fn update_vendor(&mut self, id: &i32, name: &str, email: Option<&str>, phone: Option<&str>) -> QueryResult<usize> {
+        let date: String = chrono::offset::Utc::now().naive_utc().to_string();
+        let mut conn = self.get_conn();
+
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            diesel::update(vendors_dsl::vendors.find(id))
+                .set((vendors_dsl::name.eq(name), vendors_dsl::email.eq(email), vendors_dsl::phone.eq(phone), vendors_dsl::updated_at.eq(&date)))
+                .execute(conn)
+        })
+    }
+
+    #[instrument(level = "debug", skip_all)]
+    fn get_budgets(&mut self, library_id: &i32) -> QueryResult<Vec<Budget>> {
+        let mut conn = self.get_conn();
+
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            budgets_dsl::budgets
+                .filter(budgets_dsl::library_id.eq(library_id))
+                .order(budgets_dsl::fiscal_year.desc())
+                .load(conn)
+        })
+    }
+
+    #[instrument(level = "debug", skip_all)]
+    fn get_budget(&mut self, library_id: &i32, fiscal_year: &i32) -> QueryResult<Budget> {
+        let mut conn = self.get_conn();
+
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            budgets_dsl::budgets
+                .filter(budgets_dsl::library_id.eq(library_id).and(budgets_dsl::fiscal_year.eq(fiscal_year)))
+                .first(conn)
+        })
+    }
+
+    #[instrument(level = "debug", skip_all)]
+    fn set_budget(&mut self, library_id: &i32, fiscal_year: &i32, amount_cents: &i64) -> QueryResult<Budget> {
+        let date: String = chrono::offset::Utc::now().naive_utc().to_string();
+        let mut conn = self.get_conn();
+
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            diesel::insert_into(budgets_dsl::budgets)
+                .values((
+                    budgets_dsl::library_id.eq(library_id),
+                    budgets_dsl::fiscal_year.eq(fiscal_year),
+                    budgets_dsl::amount_cents.eq(amount_cents),
+                    budgets_dsl::created_at.eq(&date),
+                    budgets_dsl::updated_at.eq(&date),
+                ))
+                .on_conflict((budgets_dsl::library_id, budgets_dsl::fiscal_year))
+                .do_update()
+                .set((budgets_dsl::amount_cents.eq(amount_cents), budgets_dsl::updated_at.eq(&date)))
+                .execute(conn)?;
+
+            budgets_dsl::budgets
+                .filter(budgets_dsl::library_id.eq(library_id).and(budgets_dsl::fiscal_year.eq(fiscal_year)))
+                .first(conn)
+        })
+    }
+
+    #[instrument(level = "debug", skip_all)]
+    fn committed_cents(&mut self, library_id: &i32, fiscal_year: &i32) -> QueryResult<i64> {
+        let mut conn = self.get_conn();
+
+        conn.transaction::<_, diesel::result::Error, _>(|conn| committed(conn, library_id, fiscal_year))
+    }
+
+    #[instrument(level = "debug", skip_all)]
+    fn create_order(&mut self, order: &NewPurchaseOrder, lines: &[NewOrderLine]) -> QueryResult<PurchaseOrder> {
+        let mut conn = self.get_conn();
+
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            diesel::insert_into(orders_dsl::purchase_orders)
+                .values(order)
+                .execute(conn)?;
+
+            let created: PurchaseOrder = orders_dsl::purchase_orders
+                .order(orders_dsl::id.desc())
+                .first(conn)?;
+
+            for line in lines {
+                diesel::insert_into(lines_dsl::purchase_order_lines)
+                    .values((lines_dsl::order_id.eq(created.id), line))
+                    .execute(conn)?;
+            }
+
+            Ok(created)
+        })
+    }
+
+    #[instrument(level = "debug", skip_all)]
+    fn get_orders(&mut self, filter: &OrderFilter) -> QueryResult<Vec<PurchaseOrder>> {
+        let mut conn = self.get_conn();
+
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            let mut query = orders_dsl::purchase_orders.into_boxed();
+            if let Some(library_id) = filter.library_id {
+                query = query.filter(orders_dsl::library_id.eq(library_id));
+            }
+            if let Some(vendor_id) = filter.vendor_id {
+                query = query.filter(orders_dsl::vendor_id.eq(vendor_id));
+            }
+            if let Some(fiscal_year) = filter.fiscal_year {
+                query = query.filter(orders_dsl::fiscal_year.eq(fiscal_year));
+            }
+            if let Some(status) = &filter.status {
+                query = query.filter(orders_dsl::status.eq(status));
+            }
+            if let Some(limit) = filter.limit {
+                query = query.limit(limit);
+            }
+
+            query
+                .order(orders_dsl::id.desc())
+                .load(conn)
+        })
+    }
+
+    #[instrument(level = "debug", skip_all)]
+    fn get_order(&mut self, id: &i32) -> QueryResult<PurchaseOrder> {
+        let mut conn = self.get_conn();
+
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            orders_dsl::purchase_orders.find(id).first(conn)
+        })
+    }
+
+    #[instrument(level = "debug", skip_all)]
+    fn get_order_lines(&mut self, order_id: &i32) -> QueryResult<Vec<OrderLine>> {
+        let mut conn = self.get_conn();
+
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            lines_dsl::purchase_order_lines
+                .filter(lines_dsl::order_id.eq(order_id))
+                .order(lines_dsl::id.asc())
+                .load(conn)
+        })
+    }
+
+    #[instrument(level = "debug", skip_all)]
+    fn advance_order(&mut self, id: &i32, from: &str, to: &str, at: &str) -> QueryResult<usize> {
+        let mut conn = self.get_conn();
+
+        let advanced = conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            let target = orders_dsl::purchase_orders.filter(orders_dsl::id.eq(id).and(orders_dsl::status.eq(from)));
+            let updated = match to {
+                ORDER_ORDERED => diesel::update(target).set((orders_dsl::status.eq(to), orders_dsl::ordered_at.eq(at))).execute(conn)?,
+                ORDER_CANCELLED | ORDER_RECEIVED => diesel::update(target).set((orders_dsl::status.eq(to), orders_dsl::closed_at.eq(at))).execute(conn)?,
+                _ => 0,
+            };
+            if updated == 0 || to != ORDER_ORDERED {
+                return Ok(updated);
+            }
+
+            // Placing the order must leave the budget for its year intact.
+            let order: PurchaseOrder = orders_dsl::purchase_orders.find(id).first(conn)?;
+            let budget: Option<i64> = budgets_dsl::budgets
+                .filter(budgets_dsl::library_id.eq(order.library_id).and(budgets_dsl::fiscal_year.eq(order.fiscal_year)))
+                .select(budgets_dsl::amount_cents)
+                .first(conn)
+                .optional()?;
+            match budget {
+                Some(amount) if committed(conn, &order.library_id, &order.fiscal_year)? <= amount => Ok(updated),
+                _ => Err(diesel::result::Error::RollbackTransaction),
+            }
+        });
+
+        match advanced {
+            Err(diesel::result::Error::RollbackTransaction) => Ok(0),
+            other => other,
+        }
+    }
+
+    #[instrument(level = "debug", skip_all)]
+    fn receive_order(&mut self, id: &i32, receipts: &[(i32, i32)], at: &str) -> QueryResult<usize> {
+        // A receipt without copies would still move an ordered order to partially received.
+        if receipts.is_empty() {
+            return Ok(0);
+        }
+        let mut conn = self.get_conn();
+
+        let received = conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            let order: PurchaseOrder = orders_dsl::purchase_orders.find(id).first(conn)?;
+            if order.status != ORDER_ORDERED && order.status != ORDER_PARTIALLY_RECEIVED {
+                return Err(diesel::result::Error::RollbackTransaction);
+            }
+
+            for (line_id, quantity) in receipts {
+                let line: OrderLine = lines_dsl::purchase_order_lines
+                    .filter(lines_dsl::id.eq(line_id).and(lines_dsl::order_id.eq(id)))
+                    .first(conn)
+                    .optional()?
+                    .ok_or(diesel::result::Error::RollbackTransaction)?;
+                if *quantity <= 0 || *quantity > line.outstanding() {
+                    return Err(diesel::result::Error::RollbackTransaction);
+                }
+
+                let book_id = line_book(conn, &line, at)?;
+                put_copies(conn, &order.library_id, &book_id, quantity, at)?;
+                diesel::update(lines_dsl::purchase_order_lines.find(line.id))
+                    .set(lines_dsl::received_quantity.eq(lines_dsl::received_quantity + quantity))
+                    .execute(conn)?;
+            }
+
+            let outstanding = lines_dsl::purchase_order_lines
+                .filter(lines_dsl::order_id.eq(id).and(lines_dsl::received_quantity.lt(lines_dsl::quantity)))
+                .count()
+                .get_result::<i64>(conn)?;
+            let target = orders_dsl::purchase_orders.find(id);
+            if outstanding == 0 {
+                diesel::update(target).set((orders_dsl::status.eq(ORDER_RECEIVED), orders_dsl::closed_at.eq(at))).execute(conn)
+            } else {
+                diesel::update(target).set(orders_dsl::status.eq(ORDER_PARTIALLY_RECEIVED)).execute(conn)
+            }
+        });
+
+        match received {
+            Err(diesel::result::Error::RollbackTransaction) => Ok(0),
+            other => other,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use diesel::connection::SimpleConnection;
+    use domain::models::acquisition::ORDER_DRAFT;
+
+    const AT: &str = "2026-03-01 10:00:00";
+
+    fn repo() -> AcquisitionRepository<'static> {
+        let pool = crate::seeded_pool();
+        pool.get().unwrap()
+            .batch_execute("INSERT INTO vendors (id, name, created_at, updated_at) VALUES (1, 'Books Ltd', '2026-01-01', '2026-01-01')")
+            .unwrap();
+        AcquisitionRepository::new(pool)
+    }
+
+    // A draft for `quantity` copies of book 1 at 200 cents each.
+    fn draft(repo: &mut AcquisitionRepository, quantity: i32) -> PurchaseOrder {
+        let order = NewPurchaseOrder {
+            library_id: &1,
+            vendor_id: &1,
+            fiscal_year: &2026,
+            status: ORDER_DRAFT,
+            note: None,
+            created_by: None,
+            created_at: AT,
+        };
+        let line = NewOrderLine { book_id: Some(1), isbn: None, title: "Dune".to_string(), author: "Herbert".to_string(), quantity, unit_price_cents: 200 };
+        repo.create_order(&order, &[line]).unwrap()
+    }
+
+    fn stock(repo: &mut AcquisitionRepository) -> i32 {
+        use domain::schema::library_books::dsl as library_books_dsl;
+        library_books_dsl::library_books.select(library_books_dsl::quantity).first(&mut *repo.get_conn()).unwrap_or(0)
+    }
+
+    #[test]
+    fn orders_are_placed_only_within_the_budget() {
+        let mut repo = repo();
+        let first = draft(&mut repo, 4);
+        assert_eq!(repo.advance_order(&first.id, ORDER_DRAFT, ORDER_ORDERED, AT).unwrap(), 0, "no budget yet");
+
+        repo.set_budget(&1, &2026, &1000).unwrap();
+        assert_eq!(repo.advance_order(&first.id, ORDER_DRAFT, ORDER_ORDERED, AT).unwrap(), 1);
+        assert_eq!(repo.committed_cents(&1, &2026).unwrap(), 800);
+
+        let second = draft(&mut repo, 2);
+        assert_eq!(repo.advance_order(&second.id, ORDER_DRAFT, ORDER_ORDERED, AT).unwrap(), 0, "over budget");
+        assert_eq!(repo.get_order(&second.id).unwrap().status, ORDER_DRAFT);
+        assert_eq!(repo.committed_cents(&1, &2026).unwrap(), 800);
+    }
+
+    #[test]
+    fn receiving_stocks_the_library_and_closing_short_releases_the_rest() {
+        let mut repo = repo();
+        repo.set_budget(&1, &2026, &1000).unwrap();
+        let order = draft(&mut repo, 4);
+        repo.advance_order(&order.id, ORDER_DRAFT, ORDER_ORDERED, AT).unwrap();
+        let line = repo.get_order_lines(&order.id).unwrap().remove(0);
+
+        assert_eq!(repo.receive_order(&order.id, &[], AT).unwrap(), 0, "nothing delivered");
+        assert_eq!(repo.receive_order(&order.id, &[(line.id, 0)], AT).unwrap(), 0, "no copies delivered");
+        assert_eq!(repo.get_order(&order.id).unwrap().status, ORDER_ORDERED);
+        assert_eq!(repo.receive_order(&order.id, &[(line.id, 5)], AT).unwrap(), 0, "more than outstanding");
+        assert_eq!(repo.receive_order(&order.id, &[(line.id, 1)], AT).unwrap(), 1);
+        assert_eq!(stock(&mut repo), 1);
+        assert_eq!(repo.get_order(&order.id).unwrap().status, ORDER_PARTIALLY_RECEIVED);
+
+        assert_eq!(repo.advance_order(&order.id, ORDER_PARTIALLY_RECEIVED, ORDER_RECEIVED, AT).unwrap(), 1);
+        let closed = repo.get_order(&order.id).unwrap();
+        assert_eq!(closed.status, ORDER_RECEIVED);
+        assert_eq!(closed.closed_at.as_deref(), Some(AT));
+        assert_eq!(repo.committed_cents(&1, &2026).unwrap(), 200);
+        assert_eq!(repo.receive_order(&order.id, &[(line.id, 1)], AT).unwrap(), 0, "closed orders take no deliveries");
+        assert_eq!(stock(&mut repo), 1);
+    }
+}
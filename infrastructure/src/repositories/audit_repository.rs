@@ -0,0 +1,73 @@
+use diesel::prelude::*;
+use diesel::r2d2::{ConnectionManager, Pool};
+use diesel::result::QueryResult;
+use diesel::sqlite::SqliteConnection;
+use domain::models::audit::{AuditEntry, AuditFilter, NewAuditEntry};
+use domain::schema::audit_log::dsl as audit_dsl;
+use domain::traits::AuditRepositoryTrait;
+use std::sync::Arc;
+use tracing::instrument;
+
+use crate::{Db, DbConnection};
+
+const DEFAULT_LIMIT: i64 = 1000;
+
+pub struct AuditRepository<'c> {
+    db: Db<'c>,
+}
+
+impl<'c> AuditRepository<'c> {
+    pub fn new(pool: Arc<Pool<ConnectionManager<SqliteConnection>>>) -> Self {
+        AuditRepository { db: Db::Pool(pool) }
+    }
+
+    pub fn with_connection(conn: &'c mut SqliteConnection) -> Self {
+        AuditRepository { db: Db::Connection(conn) }
+    }
+
+    fn get_conn(&mut self) -> DbConnection<'_> {
+        self.db.get_conn()
+    }
+}
+
+impl AuditRepositoryTrait for AuditRepository<'_> {
+    #[instrument(level = "debug", skip_all)]
+    fn record(&mut self, entry: &NewAuditEntry) -> QueryResult<usize> {
+        let mut conn = self.get_conn();
+
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            diesel::insert_into(audit_dsl::audit_log)
+                .values(entry)
+                .execute(conn)
+        })
+    }
+
+    #[instrument(level = "debug", skip_all)]
+    fn get_entries(&mut self, filter: &AuditFilter) -> QueryResult<Vec<AuditEntry>> {
+        let mut conn = self.get_conn();
+
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            let mut query = audit_dsl::audit_log.into_boxed();
+            if let Some(entity) = &filter.entity {
+                query = query.filter(audit_dsl::entity.eq(entity));
+            }
+            if let Some(id) = &filter.id {
+                query = query.filter(audit_dsl::entity_id.eq(id));
+            }
+            if let Some(actor_user_id) = &filter.actor_user_id {
+                query = query.filter(audit_dsl::actor_user_id.eq(actor_user_id));
+            }
+            if let Some(from) = &filter.from {
+                query = query.filter(audit_dsl::occurred_at.ge(from));
+            }
+            if let Some(to) = &filter.to {
+                query = query.filter(audit_dsl::occurred_at.lt(to));
+            }
+
+            query
+                .order(audit_dsl::id.asc())
+                .limit(filter.limit.unwrap_or(DEFAULT_LIMIT))
+                .load(conn)
+        })
+    }
+}
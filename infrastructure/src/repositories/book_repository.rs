@@ -1,35 +1,45 @@
 use diesel::prelude::*;
-use diesel::r2d2::{ConnectionManager, Pool, PooledConnection};
+use diesel::r2d2::{ConnectionManager, Pool};
 use diesel::result::QueryResult;
 use diesel::sqlite::SqliteConnection;
-use domain::models::book::{Book, NewBook, NewLibraryBook};
+use domain::models::book::{Book, BookChanges, NewBook, NewLibraryBook};
 use domain::schema::books::dsl as books_dsl;
 use domain::schema::library_books::dsl as library_books_dsl;
+use domain::schema::purchase_order_lines::dsl as order_lines_dsl;
 use domain::traits::BookRepositoryTrait;
 use std::sync::Arc;
+use tracing::instrument;
 
+use crate::{Db, DbConnection};
 
-pub struct BookRepository {
-    pool: Arc<Arc<Pool<ConnectionManager<SqliteConnection>>>>,
+
+pub struct BookRepository<'c> {
+    db: Db<'c>,
 }
 
-impl BookRepository {
-    pub fn new(pool: Arc<Arc<Pool<ConnectionManager<SqliteConnection>>>>) -> Self {
-        BookRepository { pool }
+impl<'c> BookRepository<'c> {
+    pub fn new(pool: Arc<Pool<ConnectionManager<SqliteConnection>>>) -> Self {
+        BookRepository { db: Db::Pool(pool) }
+    }
+
+    pub fn with_connection(conn: &'c mut SqliteConnection) -> Self {
+        BookRepository { db: Db::Connection(conn) }
     }
 
-    fn get_conn(&self) -> PooledConnection<ConnectionManager<SqliteConnection>> {
-        self.pool.get().expect("Failed to get a connection from the pool")
+    fn get_conn(&mut self) -> DbConnection<'_> {
+        self.db.get_conn()
     }
 }
 
-impl BookRepositoryTrait for BookRepository {
-    fn create_book(&mut self, title: &str, author: &str, library_id: &i32) -> QueryResult<usize> {
+impl BookRepositoryTrait for BookRepository<'_> {
+    #[instrument(level = "debug", skip_all)]
+    fn create_book(&mut self, title: &str, author: &str, isbn: Option<&str>, library_id: &i32) -> QueryResult<Book> {
         let mut conn = self.get_conn();
         let date: String = chrono::offset::Utc::now().naive_utc().to_string();
         let new_book = NewBook {
             title,
             author,
+            isbn,
             created_at: date.as_str(),
             updated_at: date.as_str(),
         };
@@ -48,33 +58,62 @@ impl BookRepositoryTrait for BookRepository {
                 book_id: &book_id,
                 library_id: &library_id,
                 quantity: &1,
+                added_at: date.as_str(),
             };
 
             diesel::insert_into(library_books_dsl::library_books)
                 .values(&new_library_book)
-                .execute(conn)
+                .execute(conn)?;
+
+            books_dsl::books.find(book_id).first(conn)
         })
     }
     
-    fn update_book(&mut self, id: &i32, title: &str, author: &str) -> QueryResult<usize> {
+    #[instrument(level = "debug", skip_all)]
+    fn update_book(&mut self, id: &i32, title: &str, author: &str, isbn: Option<&str>, version: &i32) -> QueryResult<usize> {
+        let date: String = chrono::offset::Utc::now().naive_utc().to_string();
         let mut conn = self.get_conn();
 
         conn.transaction::<_, diesel::result::Error, _>(|conn| {
-            diesel::update(books_dsl::books.find(id))
-                .set((books_dsl::title.eq(title), books_dsl::author.eq(author)))
+            diesel::update(books_dsl::books.find(id).filter(books_dsl::version.eq(version)))
+                .set((books_dsl::title.eq(title), books_dsl::author.eq(author), books_dsl::isbn.eq(isbn), books_dsl::updated_at.eq(date.as_str()), books_dsl::version.eq(version + 1)))
                 .execute(conn)
         })
     }
     
-    fn delete_book(&mut self, id: &i32) -> QueryResult<usize> {
+    #[instrument(level = "debug", skip_all)]
+    fn patch_book(&mut self, id: &i32, changes: &BookChanges, version: &i32) -> QueryResult<usize> {
+        let date: String = chrono::offset::Utc::now().naive_utc().to_string();
         let mut conn = self.get_conn();
 
         conn.transaction::<_, diesel::result::Error, _>(|conn| {
-            diesel::delete(books_dsl::books.find(id))
+            diesel::update(books_dsl::books.find(id).filter(books_dsl::version.eq(version)))
+                .set((changes, books_dsl::updated_at.eq(date.as_str()), books_dsl::version.eq(version + 1)))
                 .execute(conn)
         })
     }
+
+    #[instrument(level = "debug", skip_all)]
+    fn delete_book(&mut self, id: &i32, version: &i32) -> QueryResult<usize> {
+        let mut conn = self.get_conn();
+
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            let deleted = diesel::delete(books_dsl::books.find(id).filter(books_dsl::version.eq(version)))
+                .execute(conn)?;
+
+            // Order lines keep their title, author and ISBN; foreign keys are not enforced, so the
+            // ON DELETE SET NULL is done here.
+            if deleted == 1 {
+                diesel::update(order_lines_dsl::purchase_order_lines.filter(order_lines_dsl::book_id.eq(id)))
+                    .set(order_lines_dsl::book_id.eq(None::<i32>))
+                    .execute(conn)?;
+            }
+
+            Ok(deleted)
+        })
+    }
     
+    #[instrument(level = "debug", skip_all)]
     fn get_book_by_id(&mut self, id: &i32) -> QueryResult<Book> {
         let mut conn = self.get_conn();
 
@@ -83,6 +122,7 @@ impl BookRepositoryTrait for BookRepository {
         })
     }
     
+    #[instrument(level = "debug", skip_all)]
     fn get_books(&mut self) -> QueryResult<Vec<Book>> {
         let mut conn = self.get_conn();
 
@@ -91,6 +131,7 @@ impl BookRepositoryTrait for BookRepository {
         })
     }
     
+    #[instrument(level = "debug", skip_all)]
     fn get_books_by_library_id(&mut self, library_id: &i32) -> QueryResult<Vec<Book>> {
         let mut conn = self.get_conn();
 
@@ -105,4 +146,15 @@ impl BookRepositoryTrait for BookRepository {
                 .load(conn)
         })
     }
+
+    #[instrument(level = "debug", skip_all)]
+    fn get_book_by_isbn(&mut self, isbn: &str) -> QueryResult<Book> {
+        let mut conn = self.get_conn();
+
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            books_dsl::books
+                .filter(books_dsl::isbn.eq(isbn))
+                .first(conn)
+        })
+    }
 }
@@ -0,0 +1,125 @@
+use diesel::prelude::*;
+use diesel::r2d2::{ConnectionManager, Pool};
+use diesel::result::QueryResult;
+use diesel::sql_types::{Integer, Text};
+use diesel::sqlite::SqliteConnection;
+use domain::models::calendar::{Closure, NewClosure, OpeningHours};
+use domain::schema::library_closures::dsl as closures_dsl;
+use domain::schema::library_hours::dsl as hours_dsl;
+use domain::traits::CalendarRepositoryTrait;
+use std::sync::Arc;
+use tracing::instrument;
+
+use crate::{Db, DbConnection};
+
+// Due dates are stored as "YYYY-MM-DD HH:MM:SS..."; only the date part is replaced.
+const POSTPONE_QUERY: &str = "
+    UPDATE borrowed_books SET due_at = ? || substr(due_at, 11)
+    WHERE library_id = ? AND returned_at IS NULL AND substr(due_at, 1, 10) = ?
+";
+
+pub struct CalendarRepository<'c> {
+    db: Db<'c>,
+}
+
+impl<'c> CalendarRepository<'c> {
+    pub fn new(pool: Arc<Pool<ConnectionManager<SqliteConnection>>>) -> Self {
+        CalendarRepository { db: Db::Pool(pool) }
+    }
+
+    pub fn with_connection(conn: &'c mut SqliteConnection) -> Self {
+        CalendarRepository { db: Db::Connection(conn) }
+    }
+
+    fn get_conn(&mut self) -> DbConnection<'_> {
+        self.db.get_conn()
+    }
+}
+
+impl CalendarRepositoryTrait for CalendarRepository<'_> {
+    #[instrument(level = "debug", skip_all)]
+    fn get_hours(&mut self, library_id: &i32) -> QueryResult<Vec<OpeningHours>> {
+        let mut conn = self.get_conn();
+
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            hours_dsl::library_hours
+                .filter(hours_dsl::library_id.eq(library_id))
+                .order(hours_dsl::weekday)
+                .load(conn)
+        })
+    }
+
+    #[instrument(level = "debug", skip_all)]
+    fn set_hours(&mut self, library_id: &i32, hours: &[OpeningHours]) -> QueryResult<usize> {
+        let mut conn = self.get_conn();
+
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            diesel::delete(hours_dsl::library_hours.filter(hours_dsl::library_id.eq(library_id)))
+                .execute(conn)?;
+
+            diesel::insert_into(hours_dsl::library_hours)
+                .values(hours)
+                .execute(conn)
+        })
+    }
+
+    #[instrument(level = "debug", skip_all)]
+    fn get_closures(&mut self, library_id: &i32, from: Option<&str>, to: Option<&str>) -> QueryResult<Vec<Closure>> {
+        let mut conn = self.get_conn();
+
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            let mut query = closures_dsl::library_closures
+                .filter(closures_dsl::library_id.eq(library_id))
+                .into_boxed();
+            if let Some(from) = from {
+                query = query.filter(closures_dsl::date.ge(from));
+            }
+            if let Some(to) = to {
+                query = query.filter(closures_dsl::date.lt(to));
+            }
+
+            query
+                .order(closures_dsl::date)
+                .load(conn)
+        })
+    }
+
+    #[instrument(level = "debug", skip_all)]
+    fn add_closure(&mut self, closure: &NewClosure) -> QueryResult<Closure> {
+        let mut conn = self.get_conn();
+
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            diesel::insert_into(closures_dsl::library_closures)
+                .values(closure)
+                .execute(conn)?;
+
+            closures_dsl::library_closures
+                .order(closures_dsl::id.desc())
+                .first(conn)
+        })
+    }
+
+    #[instrument(level = "debug", skip_all)]
+    fn delete_closure(&mut self, library_id: &i32, id: &i32) -> QueryResult<usize> {
+        let mut conn = self.get_conn();
+
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            diesel::delete(closures_dsl::library_closures
+                .filter(closures_dsl::id.eq(id).and(closures_dsl::library_id.eq(library_id))))
+                .execute(conn)
+        })
+    }
+
+    #[instrument(level = "debug", skip_all)]
+    fn postpone_due_dates(&mut self, library_id: &i32, date: &str, to: &str) -> QueryResult<usize> {
+        let mut conn = self.get_conn();
+
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            diesel::sql_query(POSTPONE_QUERY)
+                .bind::<Text, _>(to)
+                .bind::<Integer, _>(library_id)
+                .bind::<Text, _>(date)
+                .execute(conn)
+        })
+    }
+}
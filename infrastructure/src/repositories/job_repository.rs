@@ -0,0 +1,194 @@
+use diesel::prelude::*;
+use diesel::r2d2::{ConnectionManager, Pool};
+use diesel::result::QueryResult;
+use diesel::sql_types::Text;
+use diesel::sqlite::SqliteConnection;
+use domain::models::job::{JobRun, NewJobRun, STATUS_RUNNING};
+use domain::schema::job_runs::dsl as runs_dsl;
+use domain::traits::JobRepositoryTrait;
+use std::sync::Arc;
+use tracing::instrument;
+
+use crate::{Db, DbConnection};
+
+// Takes the lease when it is free or expired; otherwise the WHERE clause leaves the row untouched. A holder
+// that already has the lease does not get it again, so a job never runs twice at once on one instance either.
+const ACQUIRE_LEASE_QUERY: &str = "
+    INSERT INTO job_locks (job, holder, locked_until) VALUES (?, ?, ?)
+    ON CONFLICT (job) DO UPDATE SET holder = excluded.holder, locked_until = excluded.locked_until
+    WHERE job_locks.locked_until < ?
+";
+
+pub struct JobRepository<'c> {
+    db: Db<'c>,
+}
+
+impl<'c> JobRepository<'c> {
+    pub fn new(pool: Arc<Pool<ConnectionManager<SqliteConnection>>>) -> Self {
+        JobRepository { db: Db::Pool(pool) }
+    }
+
+    pub fn with_connection(conn: &'c mut SqliteConnection) -> Self {
+        JobRepository { db: Db::Connection(conn) }
+    }
+
+    fn get_conn(&mut self) -> DbConnection<'_> {
+        self.db.get_conn()
+    }
+}
+
+impl JobRepositoryTrait for JobRepository<'_> {
+    #[instrument(level = "debug", skip_all)]
+    fn acquire_lease(&mut self, job: &str, holder: &str, now: &str, until: &str) -> QueryResult<bool> {
+        let mut conn = self.get_conn();
+
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            let affected = diesel::sql_query(ACQUIRE_LEASE_QUERY)
+                .bind::<Text, _>(job)
+                .bind::<Text, _>(holder)
+                .bind::<Text, _>(until)
+                .bind::<Text, _>(now)
+                .execute(conn)?;
+
+            Ok(affected == 1)
+        })
+    }
+
+    #[instrument(level = "debug", skip_all)]
+    fn renew_lease(&mut self, job: &str, holder: &str, until: &str) -> QueryResult<bool> {
+        use domain::schema::job_locks::dsl as locks_dsl;
+        let mut conn = self.get_conn();
+
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            let renewed = diesel::update(locks_dsl::job_locks.filter(locks_dsl::job.eq(job).and(locks_dsl::holder.eq(holder))))
+                .set(locks_dsl::locked_until.eq(until))
+                .execute(conn)?;
+
+            Ok(renewed == 1)
+        })
+    }
+
+    #[instrument(level = "debug", skip_all)]
+    fn release_lease(&mut self, job: &str, holder: &str) -> QueryResult<usize> {
+        use domain::schema::job_locks::dsl as locks_dsl;
+        let mut conn = self.get_conn();
+
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            diesel::delete(locks_dsl::job_locks.filter(locks_dsl::job.eq(job).and(locks_dsl::holder.eq(holder))))
+                .execute(conn)
+        })
+    }
+
+    #[instrument(level = "debug", skip_all)]
+    fn start_run(&mut self, run: &NewJobRun) -> QueryResult<Option<JobRun>> {
+        let mut conn = self.get_conn();
+
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            let inserted = diesel::insert_or_ignore_into(runs_dsl::job_runs)
+                .values(run)
+                .execute(conn)?;
+            if inserted == 0 {
+                return Ok(None);
+            }
+
+            runs_dsl::job_runs
+                .order(runs_dsl::id.desc())
+                .first(conn)
+                .map(Some)
+        })
+    }
+
+    #[instrument(level = "debug", skip_all)]
+    fn finish_run(&mut self, id: &i32, status: &str, output: Option<&str>, error: Option<&str>) -> QueryResult<usize> {
+        let mut conn = self.get_conn();
+        let date: String = chrono::offset::Utc::now().naive_utc().to_string();
+
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            diesel::update(runs_dsl::job_runs.find(id))
+                .set((
+                    runs_dsl::status.eq(status),
+                    runs_dsl::finished_at.eq(date.as_str()),
+                    runs_dsl::output.eq(output),
+                    runs_dsl::error.eq(error),
+                ))
+                .execute(conn)
+        })
+    }
+
+    #[instrument(level = "debug", skip_all)]
+    fn get_run(&mut self, id: &i32) -> QueryResult<JobRun> {
+        let mut conn = self.get_conn();
+
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            runs_dsl::job_runs.find(id).first(conn)
+        })
+    }
+
+    #[instrument(level = "debug", skip_all)]
+    fn get_runs(&mut self, job: &str, limit: i64) -> QueryResult<Vec<JobRun>> {
+        let mut conn = self.get_conn();
+
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            runs_dsl::job_runs
+                .filter(runs_dsl::job.eq(job))
+                .order(runs_dsl::id.desc())
+                .limit(limit)
+                .load(conn)
+        })
+    }
+
+    #[instrument(level = "debug", skip_all)]
+    fn get_latest_runs(&mut self) -> QueryResult<Vec<JobRun>> {
+        let mut conn = self.get_conn();
+
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            diesel::sql_query("SELECT * FROM job_runs WHERE id IN (SELECT MAX(id) FROM job_runs GROUP BY job)")
+                .load(conn)
+        })
+    }
+
+    #[instrument(level = "debug", skip_all)]
+    fn purge_runs(&mut self, before: &str) -> QueryResult<usize> {
+        let mut conn = self.get_conn();
+
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            diesel::delete(runs_dsl::job_runs.filter(runs_dsl::started_at.lt(before).and(runs_dsl::status.ne(STATUS_RUNNING))))
+                .execute(conn)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn repo() -> JobRepository<'static> {
+        JobRepository::new(crate::test_pool())
+    }
+
+    #[test]
+    fn lease_is_taken_only_when_free_or_expired() {
+        let mut repo = repo();
+        assert!(repo.acquire_lease("job", "a/1", "2026-01-01 10:00:00", "2026-01-01 10:05:00").unwrap());
+        assert!(!repo.acquire_lease("job", "b/1", "2026-01-01 10:01:00", "2026-01-01 10:06:00").unwrap());
+        // The same instance does not get a lease it already holds.
+        assert!(!repo.acquire_lease("job", "a/1", "2026-01-01 10:01:00", "2026-01-01 10:06:00").unwrap());
+        assert!(repo.acquire_lease("job", "b/1", "2026-01-01 10:05:01", "2026-01-01 10:10:01").unwrap());
+        assert!(repo.acquire_lease("other", "a/1", "2026-01-01 10:05:01", "2026-01-01 10:10:01").unwrap());
+    }
+
+    #[test]
+    fn lease_is_renewed_and_released_only_by_its_holder() {
+        let mut repo = repo();
+        assert!(repo.acquire_lease("job", "a/1", "2026-01-01 10:00:00", "2026-01-01 10:05:00").unwrap());
+        assert!(!repo.renew_lease("job", "b/1", "2026-01-01 10:09:00").unwrap());
+        assert!(repo.renew_lease("job", "a/1", "2026-01-01 10:09:00").unwrap());
+        assert!(!repo.acquire_lease("job", "b/1", "2026-01-01 10:06:00", "2026-01-01 10:11:00").unwrap());
+
+        repo.release_lease("job", "b/1").unwrap();
+        assert!(!repo.acquire_lease("job", "b/1", "2026-01-01 10:06:00", "2026-01-01 10:11:00").unwrap());
+        repo.release_lease("job", "a/1").unwrap();
+        assert!(repo.acquire_lease("job", "b/1", "2026-01-01 10:06:00", "2026-01-01 10:11:00").unwrap());
+        assert!(!repo.renew_lease("job", "a/1", "2026-01-01 10:12:00").unwrap());
+    }
+}
@@ -1,31 +1,53 @@
 use std::sync::Arc;
 
 use diesel::prelude::*;
-use diesel::r2d2::{ConnectionManager, Pool, PooledConnection};
+use diesel::r2d2::{ConnectionManager, Pool};
 use diesel::result::QueryResult;
 use diesel::sqlite::SqliteConnection;
-use domain::models::library::{Library, NewLibrary};
+use diesel::sql_types::Integer;
+use domain::models::book::LibraryBook;
+use domain::models::library::{Library, LibraryChanges, LibraryDetails, LibraryStock, NewLibrary};
 use domain::schema::library::dsl as library_dsl;
+use domain::schema::{budgets, library_closures, library_handovers, library_hours, library_staff};
 use domain::schema::library_books::dsl as library_books_dsl;
 use domain::traits::LibraryRepositoryTrait;
+use tracing::instrument;
 
-pub struct LibraryRepository {
-    pool: Arc<Arc<Pool<ConnectionManager<SqliteConnection>>>>,
+use crate::{Db, DbConnection};
+
+use crate::repositories::staff_repository::{hand_over, start_manager};
+
+// Copies on the shelf per holding of a book: the quantity minus the open loans booked to that library.
+const STOCK_QUERY: &str = "
+    SELECT lb.library_id,
+           lb.quantity - (SELECT COUNT(*) FROM borrowed_books bb
+                          WHERE bb.library_id = lb.library_id AND bb.book_id = lb.book_id AND bb.returned_at IS NULL) AS available
+    FROM library_books lb
+    WHERE lb.book_id = ?
+";
+
+pub struct LibraryRepository<'c> {
+    db: Db<'c>,
 }
 
-impl LibraryRepository {
-    pub fn new(pool: Arc<Arc<Pool<ConnectionManager<SqliteConnection>>>>) -> Self {
-        LibraryRepository { pool }
+impl<'c> LibraryRepository<'c> {
+    pub fn new(pool: Arc<Pool<ConnectionManager<SqliteConnection>>>) -> Self {
+        LibraryRepository { db: Db::Pool(pool) }
     }
 
-    fn get_conn(&self) -> PooledConnection<ConnectionManager<SqliteConnection>> {
-        self.pool.get().expect("Failed to get a connection from the pool")
+    pub fn with_connection(conn: &'c mut SqliteConnection) -> Self {
+        LibraryRepository { db: Db::Connection(conn) }
+    }
+
+    fn get_conn(&mut self) -> DbConnection<'_> {
+        self.db.get_conn()
     }
 }
 
 
-impl LibraryRepositoryTrait for LibraryRepository {
-    fn create_library(&mut self, name: &str, address: &str, manager_id: &i32) -> QueryResult<usize> {
+impl LibraryRepositoryTrait for LibraryRepository<'_> {
+    #[instrument(level = "debug", skip_all)]
+    fn create_library(&mut self, name: &str, address: &str, manager_id: &i32, details: &LibraryDetails) -> QueryResult<Library> {
         let mut conn = self.get_conn();
         let date: String = chrono::offset::Utc::now().naive_utc().to_string();
         let new_library = NewLibrary {
@@ -34,15 +56,23 @@ impl LibraryRepositoryTrait for LibraryRepository {
             manager_id,
             created_at: date.as_str(),
             updated_at: date.as_str(),
+            details: details.clone(),
         };
 
         conn.transaction::<_, diesel::result::Error, _>(|conn| {
             diesel::insert_into(library_dsl::library)
                 .values(&new_library)
-                .execute(conn)
+                .execute(conn)?;
+
+            let created: Library = library_dsl::library
+                .order(library_dsl::id.desc())
+                .first(conn)?;
+            start_manager(conn, &created.id, manager_id, &date)?;
+            Ok(created)
         })
     }
 
+    #[instrument(level = "debug", skip_all)]
     fn get_libraries(&mut self) -> QueryResult<Vec<Library>> {
         let mut conn = self.get_conn();
         
@@ -51,6 +81,7 @@ impl LibraryRepositoryTrait for LibraryRepository {
         })
     }
 
+    #[instrument(level = "debug", skip_all)]
     fn get_library_by_id(&mut self, id: &i32) -> QueryResult<Library> {
         let mut conn = self.get_conn();
 
@@ -59,36 +90,87 @@ impl LibraryRepositoryTrait for LibraryRepository {
         })
     }
 
-    fn update_library(&mut self, id: &i32, name: &str, address: &str, manager_id: &i32) -> QueryResult<usize> {
+    #[instrument(level = "debug", skip_all)]
+    fn get_libraries_by_manager_id(&mut self, manager_id: &i32) -> QueryResult<Vec<Library>> {
         let mut conn = self.get_conn();
 
         conn.transaction::<_, diesel::result::Error, _>(|conn| {
-            diesel::update(library_dsl::library.find(id))
-                .set((library_dsl::name.eq(name), library_dsl::address.eq(address), library_dsl::manager_id.eq(manager_id)))
-                .execute(conn)
+            library_dsl::library
+                .filter(library_dsl::manager_id.eq(manager_id))
+                .load(conn)
+        })
+    }
+
+    #[instrument(level = "debug", skip_all)]
+    fn update_library(&mut self, id: &i32, name: &str, address: &str, manager_id: &i32, details: &LibraryDetails, version: &i32) -> QueryResult<usize> {
+        let date: String = chrono::offset::Utc::now().naive_utc().to_string();
+        let mut conn = self.get_conn();
+
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            let previous: i32 = library_dsl::library.find(id).select(library_dsl::manager_id).first(conn)?;
+            let updated = diesel::update(library_dsl::library.find(id).filter(library_dsl::version.eq(version)))
+                .set((library_dsl::name.eq(name), library_dsl::address.eq(address), library_dsl::manager_id.eq(manager_id), details, library_dsl::updated_at.eq(date.as_str()), library_dsl::version.eq(version + 1)))
+                .execute(conn)?;
+            if updated > 0 && previous != *manager_id {
+                hand_over(conn, id, &previous, manager_id, &date)?;
+            }
+            Ok(updated)
+        })
+    }
+
+    #[instrument(level = "debug", skip_all)]
+    fn patch_library(&mut self, id: &i32, changes: &LibraryChanges, version: &i32) -> QueryResult<usize> {
+        let date: String = chrono::offset::Utc::now().naive_utc().to_string();
+        let mut conn = self.get_conn();
+
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            let previous: i32 = library_dsl::library.find(id).select(library_dsl::manager_id).first(conn)?;
+            let updated = diesel::update(library_dsl::library.find(id).filter(library_dsl::version.eq(version)))
+                .set((changes, library_dsl::updated_at.eq(date.as_str()), library_dsl::version.eq(version + 1)))
+                .execute(conn)?;
+            if let Some(manager_id) = changes.manager_id.filter(|manager_id| updated > 0 && **manager_id != previous) {
+                hand_over(conn, id, &previous, manager_id, &date)?;
+            }
+            Ok(updated)
         })
     }
 
-    fn delete_library(&mut self, id: &i32) -> QueryResult<usize> {
+    #[instrument(level = "debug", skip_all)]
+    fn delete_library(&mut self, id: &i32, version: &i32) -> QueryResult<usize> {
         let mut conn = self.get_conn();
 
         conn.transaction::<_, diesel::result::Error, _>(|conn| {
-            diesel::delete(library_dsl::library.find(id))
+            let current = library_dsl::library.find(id).filter(library_dsl::version.eq(version)).count().get_result::<i64>(conn)?;
+            if current == 0 {
+                return Ok(0);
+            }
+            // The calendar, staff history and budgets go with the library; its purchase orders keep it.
+            diesel::delete(library_hours::table.filter(library_hours::library_id.eq(id))).execute(conn)?;
+            diesel::delete(library_closures::table.filter(library_closures::library_id.eq(id))).execute(conn)?;
+            diesel::delete(library_staff::table.filter(library_staff::library_id.eq(id))).execute(conn)?;
+            diesel::delete(library_handovers::table.filter(library_handovers::library_id.eq(id))).execute(conn)?;
+            diesel::delete(budgets::table.filter(budgets::library_id.eq(id))).execute(conn)?;
+
+            diesel::delete(library_dsl::library.find(id).filter(library_dsl::version.eq(version)))
                 .execute(conn)
         })
     }
 
+    #[instrument(level = "debug", skip_all)]
     fn add_book(&mut self, library_id: &i32, book_id: &i32) -> QueryResult<usize> {
         let mut conn = self.get_conn();
 
+        let date: String = chrono::offset::Utc::now().naive_utc().to_string();
+
         conn.transaction::<_, diesel::result::Error, _>(|conn| {
-            let new_library_book = (library_books_dsl::library_id.eq(library_id), library_books_dsl::book_id.eq(book_id), library_books_dsl::quantity.eq(1));
+            let new_library_book = (library_books_dsl::library_id.eq(library_id), library_books_dsl::book_id.eq(book_id), library_books_dsl::quantity.eq(1), library_books_dsl::added_at.eq(&date));
             diesel::insert_into(library_books_dsl::library_books)
                 .values(&new_library_book)
                 .execute(conn)
         })
     }
 
+    #[instrument(level = "debug", skip_all)]
     fn add_book_quantity(&mut self, library_id: &i32, book_id: &i32, quantity: &i32) -> QueryResult<usize> {
         let mut conn = self.get_conn();
 
@@ -98,4 +180,37 @@ impl LibraryRepositoryTrait for LibraryRepository {
                 .execute(conn)
         })
     }
+
+    #[instrument(level = "debug", skip_all)]
+    fn get_library_book(&mut self, library_id: &i32, book_id: &i32) -> QueryResult<LibraryBook> {
+        let mut conn = self.get_conn();
+
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            library_books_dsl::library_books
+                .find((library_id, book_id))
+                .first(conn)
+        })
+    }
+
+    #[instrument(level = "debug", skip_all)]
+    fn get_located_libraries(&mut self) -> QueryResult<Vec<Library>> {
+        let mut conn = self.get_conn();
+
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            library_dsl::library
+                .filter(library_dsl::latitude.is_not_null().and(library_dsl::longitude.is_not_null()))
+                .load(conn)
+        })
+    }
+
+    #[instrument(level = "debug", skip_all)]
+    fn get_book_stock(&mut self, book_id: &i32) -> QueryResult<Vec<LibraryStock>> {
+        let mut conn = self.get_conn();
+
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            diesel::sql_query(STOCK_QUERY)
+                .bind::<Integer, _>(book_id)
+                .load(conn)
+        })
+    }
 }
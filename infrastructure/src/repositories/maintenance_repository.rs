@@ -0,0 +1,339 @@
+use diesel::connection::SimpleConnection;
+use diesel::prelude::*;
+use diesel::r2d2::{ConnectionManager, Pool};
+use diesel::result::QueryResult;
+use diesel::sql_types::{BigInt, Text};
+use diesel::sqlite::SqliteConnection;
+use domain::models::maintenance::{IntegrityCheck, StockChange, DATA_TABLES};
+use domain::schema::library_books::dsl as library_books_dsl;
+use domain::traits::MaintenanceRepositoryTrait;
+use std::sync::Arc;
+use tracing::instrument;
+
+use crate::migrations::applied_version;
+use crate::{Db, DbConnection};
+
+// How many offending keys a check reports; the count covers all of them.
+const SAMPLE_KEYS: usize = 20;
+
+// Name, description and a query returning one `key` per offending row.
+const INTEGRITY_CHECKS: &[(&str, &str, &str)] = &[
+    (
+        "orphan_holdings",
+        "Holdings of a library or book that no longer exists",
+        "SELECT lb.library_id || ':' || lb.book_id AS key FROM library_books lb
+         WHERE NOT EXISTS (SELECT 1 FROM library l WHERE l.id = lb.library_id)
+            OR NOT EXISTS (SELECT 1 FROM books b WHERE b.id = lb.book_id)",
+    ),
+    (
+        "orphan_library_members",
+        "Library memberships of a library or member that no longer exists",
+        "SELECT lm.library_id || ':' || lm.member_id AS key FROM library_members lm
+         WHERE NOT EXISTS (SELECT 1 FROM library l WHERE l.id = lm.library_id)
+            OR NOT EXISTS (SELECT 1 FROM members m WHERE m.id = lm.member_id)",
+    ),
+    (
+        "loans_on_deleted_books",
+        "Loans of a book that no longer exists",
+        "SELECT CAST(bb.id AS TEXT) AS key FROM borrowed_books bb
+         WHERE NOT EXISTS (SELECT 1 FROM books b WHERE b.id = bb.book_id)",
+    ),
+    (
+        "loans_of_deleted_members",
+        "Loans of a member that no longer exists",
+        "SELECT CAST(bb.id AS TEXT) AS key FROM borrowed_books bb
+         WHERE bb.member_id IS NOT NULL AND NOT EXISTS (SELECT 1 FROM members m WHERE m.id = bb.member_id)",
+    ),
+    (
+        "loans_in_deleted_libraries",
+        "Loans booked to a library that no longer exists",
+        "SELECT CAST(bb.id AS TEXT) AS key FROM borrowed_books bb
+         WHERE bb.library_id IS NOT NULL AND NOT EXISTS (SELECT 1 FROM library l WHERE l.id = bb.library_id)",
+    ),
+    (
+        "negative_quantities",
+        "Holdings with a negative quantity",
+        "SELECT lb.library_id || ':' || lb.book_id AS key FROM library_books lb WHERE lb.quantity < 0",
+    ),
+    (
+        "overlent_holdings",
+        "Holdings with more open loans than copies (fix with recompute-stock)",
+        "SELECT bb.library_id || ':' || bb.book_id AS key FROM borrowed_books bb
+         LEFT JOIN library_books lb ON lb.library_id = bb.library_id AND lb.book_id = bb.book_id
+         WHERE bb.returned_at IS NULL AND bb.library_id IS NOT NULL
+         GROUP BY bb.library_id, bb.book_id
+         HAVING lb.quantity IS NULL OR COUNT(*) > lb.quantity",
+    ),
+    (
+        "libraries_without_manager",
+        "Libraries whose manager is not a member",
+        "SELECT CAST(l.id AS TEXT) AS key FROM library l
+         WHERE NOT EXISTS (SELECT 1 FROM members m WHERE m.id = l.manager_id)",
+    ),
+    (
+        "users_of_deleted_members",
+        "Member accounts linked to a member that no longer exists",
+        "SELECT u.username AS key FROM users u
+         WHERE u.member_id IS NOT NULL AND NOT EXISTS (SELECT 1 FROM members m WHERE m.id = u.member_id)",
+    ),
+    (
+        "roles_in_deleted_libraries",
+        "Role grants scoped to a library that no longer exists",
+        "SELECT CAST(r.id AS TEXT) AS key FROM user_roles r
+         WHERE r.library_id IS NOT NULL AND NOT EXISTS (SELECT 1 FROM library l WHERE l.id = r.library_id)",
+    ),
+];
+
+// Loans booked to libraries and books that still exist; the rest is reported by the integrity checks.
+const STOCK_QUERY: &str = "
+    SELECT bb.library_id AS library_id, bb.book_id AS book_id, lb.quantity AS quantity, COUNT(*) AS open_loans
+    FROM borrowed_books bb
+    JOIN library l ON l.id = bb.library_id
+    JOIN books b ON b.id = bb.book_id
+    LEFT JOIN library_books lb ON lb.library_id = bb.library_id AND lb.book_id = bb.book_id
+    WHERE bb.returned_at IS NULL
+    GROUP BY bb.library_id, bb.book_id
+    HAVING lb.quantity IS NULL OR COUNT(*) > lb.quantity
+    ORDER BY bb.library_id, bb.book_id
+";
+
+#[derive(QueryableByName)]
+struct KeyRow {
+    #[diesel(sql_type = Text)]
+    key: String,
+}
+
+#[derive(QueryableByName)]
+struct NameRow {
+    #[diesel(sql_type = Text)]
+    name: String,
+}
+
+#[derive(QueryableByName)]
+struct CountRow {
+    #[diesel(sql_type = BigInt)]
+    count: i64,
+}
+
+#[derive(QueryableByName)]
+struct DataRow {
+    #[diesel(sql_type = Text)]
+    data: String,
+}
+
+fn invalid_data(message: String) -> diesel::result::Error {
+    diesel::result::Error::QueryBuilderError(message.into())
+}
+
+fn table_columns(conn: &mut SqliteConnection, table: &str) -> QueryResult<Vec<String>> {
+    let columns: Vec<NameRow> = diesel::sql_query("SELECT name FROM pragma_table_info(?) ORDER BY cid")
+        .bind::<Text, _>(table)
+        .load(conn)?;
+
+    Ok(columns.into_iter().map(|column| column.name).collect())
+}
+
+pub struct MaintenanceRepository<'c> {
+    db: Db<'c>,
+}
+
+impl<'c> MaintenanceRepository<'c> {
+    pub fn new(pool: Arc<Pool<ConnectionManager<SqliteConnection>>>) -> Self {
+        MaintenanceRepository { db: Db::Pool(pool) }
+    }
+
+    pub fn with_connection(conn: &'c mut SqliteConnection) -> Self {
+        MaintenanceRepository { db: Db::Connection(conn) }
+    }
+
+    fn get_conn(&mut self) -> DbConnection<'_> {
+        self.db.get_conn()
+    }
+}
+
+impl MaintenanceRepositoryTrait for MaintenanceRepository<'_> {
+    #[instrument(level = "debug", skip_all)]
+    fn check_integrity(&mut self) -> QueryResult<Vec<IntegrityCheck>> {
+        let mut conn = self.get_conn();
+
+        INTEGRITY_CHECKS
+            .iter()
+            .map(|(name, description, query)| {
+                let keys: Vec<KeyRow> = diesel::sql_query(*query).load(&mut *conn)?;
+                Ok(IntegrityCheck {
+                    name: name.to_string(),
+                    description: description.to_string(),
+                    count: keys.len(),
+                    keys: keys.into_iter().take(SAMPLE_KEYS).map(|row| row.key).collect(),
+                })
+            })
+            .collect()
+    }
+
+    #[instrument(level = "debug", skip_all)]
+    fn recompute_stock(&mut self, apply: bool) -> QueryResult<Vec<StockChange>> {
+        let mut conn = self.get_conn();
+        let date: String = chrono::offset::Utc::now().naive_utc().to_string();
+
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            let changes: Vec<StockChange> = diesel::sql_query(STOCK_QUERY).load(conn)?;
+            if !apply {
+                return Ok(changes);
+            }
+
+            for change in &changes {
+                let quantity = change.open_loans as i32;
+                match change.quantity {
+                    Some(_) => {
+                        diesel::update(library_books_dsl::library_books
+                            .filter(library_books_dsl::library_id.eq(change.library_id).and(library_books_dsl::book_id.eq(change.book_id))))
+                            .set(library_books_dsl::quantity.eq(quantity))
+                            .execute(conn)?;
+                    }
+                    None => {
+                        diesel::insert_into(library_books_dsl::library_books)
+                            .values((
+                                library_books_dsl::library_id.eq(change.library_id),
+                                library_books_dsl::book_id.eq(change.book_id),
+                                library_books_dsl::quantity.eq(quantity),
+                                library_books_dsl::added_at.eq(&date),
+                            ))
+                            .execute(conn)?;
+                    }
+                }
+            }
+
+            Ok(changes)
+        })
+    }
+
+    #[instrument(level = "debug", skip_all)]
+    fn reindex(&mut self) -> QueryResult<()> {
+        let mut conn = self.get_conn();
+
+        conn.batch_execute("REINDEX; ANALYZE;")
+    }
+
+    #[instrument(level = "debug", skip_all)]
+    fn export_data(&mut self) -> QueryResult<String> {
+        let mut conn = self.get_conn();
+
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            let mut tables = serde_json::Map::new();
+            for table in DATA_TABLES {
+                let pairs: Vec<String> = table_columns(conn, table)?
+                    .iter()
+                    .map(|column| format!("'{0}', \"{0}\"", column))
+                    .collect();
+                let rows: DataRow = diesel::sql_query(format!(
+                    "SELECT COALESCE(json_group_array(json_object({})), '[]') AS data FROM (SELECT * FROM \"{}\" ORDER BY rowid)",
+                    pairs.join(", "),
+                    table
+                ))
+                .get_result(conn)?;
+                let rows: serde_json::Value =
+                    serde_json::from_str(&rows.data).map_err(|e| invalid_data(format!("Failed to read {}: {}", table, e)))?;
+                tables.insert(table.to_string(), rows);
+            }
+
+            let export = serde_json::json!({
+                "schema_version": applied_version(conn)?,
+                "tables": tables,
+            });
+            serde_json::to_string_pretty(&export).map_err(|e| invalid_data(e.to_string()))
+        })
+    }
+
+    #[instrument(level = "debug", skip_all)]
+    fn import_data(&mut self, data: &str) -> QueryResult<usize> {
+        let export: serde_json::Value =
+            serde_json::from_str(data).map_err(|e| invalid_data(format!("Not a data export: {}", e)))?;
+        let mut conn = self.get_conn();
+
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            let current = applied_version(conn)?;
+            if export["schema_version"].as_i64() != Some(current as i64) {
+                return Err(invalid_data(format!(
+                    "The export is from schema version {}, the database is at {}",
+                    export["schema_version"], current
+                )));
+            }
+
+            let mut inserted = 0;
+            for table in DATA_TABLES {
+                let existing: CountRow = diesel::sql_query(format!("SELECT COUNT(*) AS count FROM \"{}\"", table)).get_result(conn)?;
+                if existing.count > 0 {
+                    return Err(invalid_data(format!("Table {} is not empty; import into a fresh database", table)));
+                }
+
+                let rows = match &export["tables"][table] {
+                    serde_json::Value::Array(rows) => serde_json::Value::Array(rows.clone()).to_string(),
+                    serde_json::Value::Null => continue,
+                    _ => return Err(invalid_data(format!("tables.{} must be an array", table))),
+                };
+                let columns = table_columns(conn, table)?;
+                let names: Vec<String> = columns.iter().map(|column| format!("\"{}\"", column)).collect();
+                let values: Vec<String> = columns.iter().map(|column| format!("json_extract(value, '$.{}')", column)).collect();
+                inserted += diesel::sql_query(format!(
+                    "INSERT INTO \"{}\" ({}) SELECT {} FROM json_each(?)",
+                    table,
+                    names.join(", "),
+                    values.join(", ")
+                ))
+                .bind::<Text, _>(rows)
+                .execute(conn)?;
+            }
+
+            Ok(inserted)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use domain::models::maintenance::RUNTIME_TABLES;
+
+    const SAMPLE_DATA: &str = "
+        INSERT INTO members (id, name, email, created_at, updated_at, email_normalized)
+            VALUES (3, 'Ada', 'Ada@example.com', '2026-01-01', '2026-01-01', 'ada@example.com');
+        INSERT INTO library (id, name, address, created_at, updated_at, manager_id, latitude, longitude)
+            VALUES (5, 'Central', 'Main St 1', '2026-01-01', '2026-01-01', 3, 41.01, 28.97);
+        INSERT INTO users (id, username, password_hash, account_type, member_id, created_at, updated_at)
+            VALUES (7, 'ada', 'hash', 'member', 3, '2026-01-01', '2026-01-01');
+        INSERT INTO books (id, title, author, created_at, updated_at, isbn)
+            VALUES (9, 'Dune', 'Herbert', '2026-01-01', '2026-01-01', '9780441013593');
+        INSERT INTO library_books (library_id, book_id, quantity, added_at) VALUES (5, 9, 2, '2026-01-01');
+        INSERT INTO vendors (id, name, created_at, updated_at) VALUES (2, 'Books Ltd', '2026-01-01', '2026-01-01');
+        INSERT INTO budgets (library_id, fiscal_year, amount_cents, created_at, updated_at)
+            VALUES (5, 2026, 100000, '2026-01-01', '2026-01-01');
+        INSERT INTO audit_log (occurred_at, request_id, actor_username, action, entity, entity_id)
+            VALUES ('2026-01-01', 'req', 'ada', 'create', 'book', '9');
+    ";
+
+    #[test]
+    fn export_covers_every_table() {
+        let mut conn = crate::test_pool().get().unwrap();
+        let tables: Vec<NameRow> =
+            diesel::sql_query("SELECT name FROM sqlite_master WHERE type = 'table' ORDER BY name").load(&mut *conn).unwrap();
+        for table in tables {
+            assert!(
+                DATA_TABLES.contains(&table.name.as_str()) || RUNTIME_TABLES.contains(&table.name.as_str()),
+                "table {} is neither exported nor listed as a runtime table",
+                table.name
+            );
+        }
+    }
+
+    #[test]
+    fn export_round_trips_through_import() {
+        let source = crate::test_pool();
+        source.get().unwrap().batch_execute(SAMPLE_DATA).unwrap();
+        let export = MaintenanceRepository::new(source).export_data().unwrap();
+
+        let mut target = MaintenanceRepository::new(crate::test_pool());
+        assert_eq!(target.import_data(&export).unwrap(), 8);
+        assert_eq!(target.export_data().unwrap(), export);
+        assert!(target.import_data(&export).is_err());
+    }
+}
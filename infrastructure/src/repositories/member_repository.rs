@@ -1,48 +1,160 @@
 use diesel::prelude::*;
 use diesel::result::QueryResult;
 use diesel::sqlite::SqliteConnection;
-use domain::models::member::{Member, NewMember};
+use domain::models::member::{normalize_email, Member, MemberChanges, MergeSummary, NewMember, MEMBER_ACTIVE, MEMBER_EXPIRED, MEMBER_SUSPENDED};
 use domain::models::book::{Book, NewBorrowedBook};
+use domain::models::loan::{Hold, Loan, NewHold};
 use domain::schema::members::dsl;
-use domain::schema::{borrowed_books, library_books};
+use domain::schema::{borrowed_books, library, library_books, library_handovers, library_staff, loan_notices, transfers, users};
+use domain::schema::holds::dsl as holds_dsl;
 use domain::schema::library_members::dsl as library_members_dsl;
 use domain::schema::members as members_schema;
 use domain::traits::MemberRepositoryTrait;
-use diesel::r2d2::{ConnectionManager, Pool, PooledConnection};
+use diesel::r2d2::{ConnectionManager, Pool};
+use diesel::sql_types::{BigInt, Bool, Integer, Text};
 use std::sync::Arc;
+use tracing::instrument;
 
-pub struct MemberRepository {
-    pool: Arc<Arc<Pool<ConnectionManager<SqliteConnection>>>>,
+use crate::repositories::transfer_repository::available;
+use crate::{Db, DbConnection};
+
+const LOANS_QUERY: &str = "
+    SELECT bb.id, bb.book_id, b.title, b.author, bb.library_id, l.name AS library_name,
+           bb.borrowed_at, bb.due_at, bb.returned_at, bb.renewals
+    FROM borrowed_books bb
+    JOIN books b ON b.id = bb.book_id
+    LEFT JOIN library l ON l.id = bb.library_id
+    WHERE bb.member_id = ? AND (bb.returned_at IS NULL) = ?
+    ORDER BY bb.borrowed_at DESC, bb.id DESC
+";
+
+// Renews only while the loan is open, under the renewal limit and nobody else is waiting for the book.
+const RENEW_QUERY: &str = "
+    UPDATE borrowed_books SET due_at = ?, renewals = renewals + 1
+    WHERE id = ? AND member_id = ? AND returned_at IS NULL AND renewals < ?
+      AND NOT EXISTS (
+          SELECT 1 FROM holds h
+          WHERE h.book_id = borrowed_books.book_id AND h.member_id <> borrowed_books.member_id
+            AND h.fulfilled_at IS NULL AND h.cancelled_at IS NULL
+      )
+";
+
+// Statements that move everything owned by one member record (the second bind) to another (the first). Holds and
+// notices the kept member already has for the same book are dropped instead of duplicated.
+const MERGE_LIBRARIES_QUERY: &str = "
+    INSERT OR IGNORE INTO library_members (library_id, member_id)
+    SELECT library_id, ? FROM library_members WHERE member_id = ?
+";
+const MERGE_HOLDS_QUERY: &str = "
+    UPDATE holds SET cancelled_at = ?
+    WHERE member_id = ? AND fulfilled_at IS NULL AND cancelled_at IS NULL
+      AND book_id IN (SELECT book_id FROM holds WHERE member_id = ? AND fulfilled_at IS NULL AND cancelled_at IS NULL)
+";
+const MERGE_NOTICES_QUERY: &str = "UPDATE OR IGNORE loan_notices SET member_id = ? WHERE member_id = ?";
+
+// Run by `erase_member` with the member id bound to every `?`. Accounts go with their tokens, keys and roles,
+// and the audit trail loses the snapshots and actor names that could identify the member. Loans stay for statistics.
+const ERASE_QUERIES: [&str; 12] = [
+    "DELETE FROM refresh_tokens WHERE user_id IN (SELECT id FROM users WHERE member_id = ?)",
+    "DELETE FROM api_keys WHERE user_id IN (SELECT id FROM users WHERE member_id = ?)",
+    "DELETE FROM user_roles WHERE user_id IN (SELECT id FROM users WHERE member_id = ?)",
+    "UPDATE transfers SET requested_by = NULL WHERE requested_by IN (SELECT id FROM users WHERE member_id = ?)",
+    "UPDATE audit_log SET actor_user_id = NULL, actor_username = NULL
+     WHERE actor_user_id IN (SELECT id FROM users WHERE member_id = ?)",
+    "UPDATE audit_log SET before_json = NULL, after_json = NULL, diff_json = NULL
+     WHERE entity = 'user' AND entity_id IN (SELECT CAST(id AS TEXT) FROM users WHERE member_id = ?)",
+    "UPDATE audit_log SET before_json = NULL, after_json = NULL, diff_json = NULL
+     WHERE entity = 'member' AND entity_id = CAST(? AS TEXT)",
+    "DELETE FROM users WHERE member_id = ?",
+    "DELETE FROM holds WHERE member_id = ?",
+    "DELETE FROM loan_notices WHERE member_id = ?",
+    "DELETE FROM library_members WHERE member_id = ?",
+    "UPDATE transfers SET member_id = NULL WHERE member_id = ?",
+];
+
+// Returned loans due to be unlinked from their member. A retention period of 0 keeps loans linked. `now` is bound
+// as YYYY-MM-DD HH:MM:SS so datetime() can shift it.
+const ANONYMIZE_QUERY: &str = "
+    SELECT id FROM borrowed_books
+    WHERE member_id IS NOT NULL AND returned_at IS NOT NULL
+      AND NOT EXISTS (SELECT 1 FROM members m WHERE m.id = borrowed_books.member_id AND m.keep_loan_history)
+      AND COALESCE((SELECT l.loan_retention_days FROM library l WHERE l.id = borrowed_books.library_id), ?) > 0
+      AND returned_at < datetime(?, '-' || COALESCE((SELECT l.loan_retention_days FROM library l WHERE l.id = borrowed_books.library_id), ?) || ' days')
+";
+
+// Notices are kept per member, book and borrowed_at; once the loan is no longer linked they would still tie the two.
+const ORPHAN_NOTICES_QUERY: &str = "
+    DELETE FROM loan_notices
+    WHERE NOT EXISTS (
+        SELECT 1 FROM borrowed_books bb
+        WHERE bb.member_id = loan_notices.member_id AND bb.book_id = loan_notices.book_id AND bb.borrowed_at = loan_notices.borrowed_at
+    )
+";
+
+#[derive(QueryableByName)]
+struct LoanId {
+    #[diesel(sql_type = Integer)]
+    id: i32,
+}
+
+// Without an explicit library a loan is booked against the member's lowest-numbered library that stocks the book.
+fn default_loan_library(conn: &mut SqliteConnection, member_id: &i32, book_id: &i32) -> QueryResult<Option<i32>> {
+    let library_ids: Vec<i32> = library_members_dsl::library_members
+        .filter(library_members_dsl::member_id.eq(member_id))
+        .select(library_members_dsl::library_id)
+        .load(conn)?;
+    library_books::dsl::library_books
+        .filter(library_books::dsl::book_id.eq(book_id).and(library_books::dsl::library_id.eq_any(library_ids)))
+        .select(diesel::dsl::min(library_books::dsl::library_id))
+        .first(conn)
+}
+
+pub struct MemberRepository<'c> {
+    db: Db<'c>,
 }
 
-impl MemberRepository {
-    pub fn new(pool: Arc<Arc<Pool<ConnectionManager<SqliteConnection>>>>) -> Self {
-        MemberRepository { pool }
+impl<'c> MemberRepository<'c> {
+    pub fn new(pool: Arc<Pool<ConnectionManager<SqliteConnection>>>) -> Self {
+        MemberRepository { db: Db::Pool(pool) }
     }
 
-    fn get_conn(&self) -> PooledConnection<ConnectionManager<SqliteConnection>> {
-        self.pool.get().expect("Failed to get a connection from the pool")
+    pub fn with_connection(conn: &'c mut SqliteConnection) -> Self {
+        MemberRepository { db: Db::Connection(conn) }
+    }
+
+    fn get_conn(&mut self) -> DbConnection<'_> {
+        self.db.get_conn()
     }
 }
 
-impl MemberRepositoryTrait for MemberRepository {
-    fn create_member(&mut self, name: &str, email: &str) -> QueryResult<usize> {
+impl MemberRepositoryTrait for MemberRepository<'_> {
+    #[instrument(level = "debug", skip_all)]
+    fn create_member(&mut self, name: &str, email: &str, language: Option<&str>, expires_at: Option<&str>) -> QueryResult<Member> {
         let mut conn = self.get_conn();
         let date = chrono::offset::Utc::now().naive_utc().to_string();
+        let email_normalized = normalize_email(email);
         let new_member = NewMember {
             name,
             email,
             created_at: date.as_str(),
             updated_at: date.as_str(),
+            language,
+            expires_at,
+            email_normalized: Some(email_normalized.as_str()),
         };
 
         conn.transaction::<_, diesel::result::Error, _>(|conn| {
             diesel::insert_into(dsl::members)
                 .values(&new_member)
-                .execute(conn)
+                .execute(conn)?;
+
+            dsl::members
+                .order(dsl::id.desc())
+                .first(conn)
         })
     }
 
+    #[instrument(level = "debug", skip_all)]
     fn get_members(&mut self) -> QueryResult<Vec<Member>> {
         let mut conn = self.get_conn();
 
@@ -51,6 +163,7 @@ impl MemberRepositoryTrait for MemberRepository {
         })
     }
 
+    #[instrument(level = "debug", skip_all)]
     fn get_member_by_id(&mut self, id: &i32) -> QueryResult<Member> {
         let mut conn = self.get_conn();
 
@@ -59,28 +172,44 @@ impl MemberRepositoryTrait for MemberRepository {
         })
     }
 
-    fn update_member(&mut self, id: &i32, name: &str, email: &str) -> QueryResult<usize> {
+    #[instrument(level = "debug", skip_all)]
+    fn update_member(&mut self, id: &i32, name: &str, email: &str, language: Option<&str>, version: &i32) -> QueryResult<usize> {
         let date: String = chrono::offset::Utc::now().naive_utc().to_string();
         
         let mut conn = self.get_conn();
 
         conn.transaction::<_, diesel::result::Error, _>(|conn| {
-            diesel::update(dsl::members.find(id))
-                .set((dsl::name.eq(name), dsl::email.eq(email), dsl::updated_at.eq(date.as_str())))
+            diesel::update(dsl::members.find(id).filter(dsl::version.eq(version)))
+                .set((dsl::name.eq(name), dsl::email.eq(email), dsl::email_normalized.eq(normalize_email(email)), dsl::language.eq(language), dsl::updated_at.eq(date.as_str()), dsl::version.eq(version + 1)))
                 .execute(conn)
         })
 
     }
 
-    fn delete_member(&mut self, id: &i32) -> QueryResult<usize> {
+    #[instrument(level = "debug", skip_all)]
+    fn patch_member(&mut self, id: &i32, changes: &MemberChanges, version: &i32) -> QueryResult<usize> {
+        let date: String = chrono::offset::Utc::now().naive_utc().to_string();
+        let email_normalized = changes.email.map(normalize_email);
+        let mut conn = self.get_conn();
+
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            diesel::update(dsl::members.find(id).filter(dsl::version.eq(version)))
+                .set((changes, email_normalized.as_deref().map(|email| dsl::email_normalized.eq(email)), dsl::updated_at.eq(date.as_str()), dsl::version.eq(version + 1)))
+                .execute(conn)
+        })
+    }
+
+    #[instrument(level = "debug", skip_all)]
+    fn delete_member(&mut self, id: &i32, version: &i32) -> QueryResult<usize> {
         let mut conn = self.get_conn();
 
         conn.transaction::<_, diesel::result::Error, _>(|conn| {
-            diesel::delete(dsl::members.find(id))
+            diesel::delete(dsl::members.find(id).filter(dsl::version.eq(version)))
                 .execute(conn)
         })
     }
 
+    #[instrument(level = "debug", skip_all)]
     fn get_members_by_library_id(&mut self, library_id: &i32) -> QueryResult<Vec<Member>> {
         let mut conn = self.get_conn();
 
@@ -96,39 +225,74 @@ impl MemberRepositoryTrait for MemberRepository {
         })
     }
 
-    fn borrow_book(&mut self, member_id: &i32, book_id: &i32) -> QueryResult<usize> {
+    #[instrument(level = "debug", skip_all)]
+    fn borrow_book(&mut self, member_id: &i32, book_id: &i32, library_id: Option<&i32>, due_at: &str) -> QueryResult<usize> {
         let date: String = chrono::offset::Utc::now().naive_utc().to_string();
-        let new_borrow = NewBorrowedBook {
-            member_id,
-            book_id,
-            borrowed_at: date.as_str(),
-        };
-
         let mut conn = self.get_conn();
 
         conn.transaction::<_, diesel::result::Error, _>(|conn| {
-            diesel::insert_into(borrowed_books::dsl::borrowed_books)
+            let library_id: Option<i32> = match library_id {
+                Some(library_id) => Some(*library_id),
+                None => default_loan_library(conn, member_id, book_id)?,
+            };
+            // Counted in the same transaction as the insert, so two loans cannot take the last copy.
+            match library_id {
+                Some(library_id) if available(conn, &library_id, book_id)? > 0 => {}
+                _ => return Ok(0),
+            }
+
+            let new_borrow = NewBorrowedBook {
+                member_id,
+                book_id,
+                library_id,
+                borrowed_at: date.as_str(),
+                due_at,
+            };
+            let inserted = diesel::insert_into(borrowed_books::dsl::borrowed_books)
                 .values(&new_borrow)
-                .execute(conn)
+                .execute(conn)?;
+
+            // The loan fulfils the member's hold on the book, if any.
+            diesel::update(holds_dsl::holds
+                .filter(holds_dsl::member_id.eq(member_id).and(holds_dsl::book_id.eq(book_id)))
+                .filter(holds_dsl::fulfilled_at.is_null().and(holds_dsl::cancelled_at.is_null())))
+                .set(holds_dsl::fulfilled_at.eq(&date))
+                .execute(conn)?;
+
+            Ok(inserted)
         })
     }
 
+    #[instrument(level = "debug", skip_all)]
+    fn loan_library(&mut self, member_id: &i32, book_id: &i32) -> QueryResult<Option<i32>> {
+        let mut conn = self.get_conn();
+
+        conn.transaction::<_, diesel::result::Error, _>(|conn| default_loan_library(conn, member_id, book_id))
+    }
+
+    #[instrument(level = "debug", skip_all)]
     fn return_book(&mut self, member_id: &i32, book_id: &i32) -> QueryResult<usize> {
+        let date: String = chrono::offset::Utc::now().naive_utc().to_string();
         let mut conn = self.get_conn();
 
+        // Returned loans stay in borrowed_books for reporting.
         conn.transaction::<_, diesel::result::Error, _>(|conn| {
-            diesel::delete(borrowed_books::dsl::borrowed_books
-                .filter(borrowed_books::dsl::member_id.eq(member_id).and(borrowed_books::dsl::book_id.eq(book_id))))
+            diesel::update(borrowed_books::dsl::borrowed_books
+                .filter(borrowed_books::dsl::member_id.eq(member_id).and(borrowed_books::dsl::book_id.eq(book_id)))
+                .filter(borrowed_books::dsl::returned_at.is_null()))
+                .set(borrowed_books::dsl::returned_at.eq(&date))
                 .execute(conn)
         })
     }
     
+    #[instrument(level = "debug", skip_all)]
     fn get_borrowed_books(&mut self, member_id: &i32, library_id: &i32) -> QueryResult<Vec<Book>> {
         let mut conn = self.get_conn();
 
     conn.transaction::<_, diesel::result::Error, _>(|conn| {
         let book_ids: Vec<i32> = borrowed_books::dsl::borrowed_books
             .filter(borrowed_books::dsl::member_id.eq(member_id))
+            .filter(borrowed_books::dsl::returned_at.is_null())
             .select(borrowed_books::dsl::book_id)
             .load(conn)?;
 
@@ -146,4 +310,503 @@ impl MemberRepositoryTrait for MemberRepository {
             .load(conn)
     })
     }
+
+    #[instrument(level = "debug", skip_all)]
+    fn get_loans(&mut self, member_id: &i32, open: bool) -> QueryResult<Vec<Loan>> {
+        let mut conn = self.get_conn();
+
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            diesel::sql_query(LOANS_QUERY)
+                .bind::<Integer, _>(member_id)
+                .bind::<Bool, _>(open)
+                .load(conn)
+        })
+    }
+
+    #[instrument(level = "debug", skip_all)]
+    fn renew_loan(&mut self, member_id: &i32, loan_id: &i32, due_at: &str, max_renewals: &i32) -> QueryResult<usize> {
+        let mut conn = self.get_conn();
+
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            diesel::sql_query(RENEW_QUERY)
+                .bind::<Text, _>(due_at)
+                .bind::<Integer, _>(loan_id)
+                .bind::<Integer, _>(member_id)
+                .bind::<Integer, _>(max_renewals)
+                .execute(conn)
+        })
+    }
+
+    #[instrument(level = "debug", skip_all)]
+    fn place_hold(&mut self, hold: &NewHold) -> QueryResult<Hold> {
+        let mut conn = self.get_conn();
+
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            diesel::insert_into(holds_dsl::holds)
+                .values(hold)
+                .execute(conn)?;
+
+            holds_dsl::holds
+                .order(holds_dsl::id.desc())
+                .first(conn)
+        })
+    }
+
+    #[instrument(level = "debug", skip_all)]
+    fn get_holds(&mut self, member_id: &i32) -> QueryResult<Vec<Hold>> {
+        let mut conn = self.get_conn();
+
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            holds_dsl::holds
+                .filter(holds_dsl::member_id.eq(member_id))
+                .filter(holds_dsl::fulfilled_at.is_null().and(holds_dsl::cancelled_at.is_null()))
+                .order(holds_dsl::placed_at)
+                .load(conn)
+        })
+    }
+
+    #[instrument(level = "debug", skip_all)]
+    fn count_waiting_holds(&mut self, book_id: &i32, member_id: &i32) -> QueryResult<i64> {
+        let mut conn = self.get_conn();
+
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            holds_dsl::holds
+                .filter(holds_dsl::book_id.eq(book_id).and(holds_dsl::member_id.ne(member_id)))
+                .filter(holds_dsl::fulfilled_at.is_null().and(holds_dsl::cancelled_at.is_null()))
+                .count()
+                .get_result(conn)
+        })
+    }
+
+    #[instrument(level = "debug", skip_all)]
+    fn cancel_hold(&mut self, member_id: &i32, hold_id: &i32) -> QueryResult<usize> {
+        let date: String = chrono::offset::Utc::now().naive_utc().to_string();
+        let mut conn = self.get_conn();
+
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            diesel::update(holds_dsl::holds
+                .filter(holds_dsl::id.eq(hold_id).and(holds_dsl::member_id.eq(member_id)))
+                .filter(holds_dsl::fulfilled_at.is_null().and(holds_dsl::cancelled_at.is_null())))
+                .set(holds_dsl::cancelled_at.eq(&date))
+                .execute(conn)
+        })
+    }
+
+    #[instrument(level = "debug", skip_all)]
+    fn refresh_member_statuses(&mut self, today: &str) -> QueryResult<(usize, usize)> {
+        let mut conn = self.get_conn();
+
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            let lifted = diesel::update(dsl::members
+                .filter(dsl::status.eq(MEMBER_SUSPENDED).and(dsl::status_until.lt(today))))
+                .set((
+                    dsl::status.eq(MEMBER_ACTIVE),
+                    dsl::status_reason.eq(None::<&str>),
+                    dsl::status_since.eq(today),
+                    dsl::status_until.eq(None::<&str>),
+                ))
+                .execute(conn)?;
+            let expired = diesel::update(dsl::members
+                .filter(dsl::status.eq(MEMBER_ACTIVE).and(dsl::expires_at.lt(today))))
+                .set((
+                    dsl::status.eq(MEMBER_EXPIRED),
+                    dsl::status_reason.eq(None::<&str>),
+                    dsl::status_since.eq(today),
+                ))
+                .execute(conn)?;
+
+            Ok((lifted, expired))
+        })
+    }
+
+    #[instrument(level = "debug", skip_all)]
+    fn merge_members(&mut self, into_id: &i32, from_id: &i32) -> QueryResult<MergeSummary> {
+        let date: String = chrono::offset::Utc::now().naive_utc().to_string();
+        let mut conn = self.get_conn();
+
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            // Fails with a unique violation when both records have the same book out.
+            let loans = diesel::update(borrowed_books::table.filter(borrowed_books::member_id.eq(from_id)))
+                .set(borrowed_books::member_id.eq(into_id))
+                .execute(conn)?;
+
+            let library_memberships = diesel::sql_query(MERGE_LIBRARIES_QUERY)
+                .bind::<Integer, _>(into_id)
+                .bind::<Integer, _>(from_id)
+                .execute(conn)?;
+            diesel::delete(library_members_dsl::library_members.filter(library_members_dsl::member_id.eq(from_id)))
+                .execute(conn)?;
+
+            diesel::sql_query(MERGE_HOLDS_QUERY)
+                .bind::<Text, _>(&date)
+                .bind::<Integer, _>(from_id)
+                .bind::<Integer, _>(into_id)
+                .execute(conn)?;
+            let holds = diesel::update(holds_dsl::holds.filter(holds_dsl::member_id.eq(from_id)))
+                .set(holds_dsl::member_id.eq(into_id))
+                .execute(conn)?;
+
+            diesel::sql_query(MERGE_NOTICES_QUERY)
+                .bind::<Integer, _>(into_id)
+                .bind::<Integer, _>(from_id)
+                .execute(conn)?;
+            diesel::delete(loan_notices::table.filter(loan_notices::member_id.eq(from_id)))
+                .execute(conn)?;
+
+            let accounts = diesel::update(users::table.filter(users::member_id.eq(from_id)))
+                .set(users::member_id.eq(into_id))
+                .execute(conn)?;
+            diesel::update(transfers::table.filter(transfers::member_id.eq(from_id)))
+                .set(transfers::member_id.eq(into_id))
+                .execute(conn)?;
+            diesel::update(library::table.filter(library::manager_id.eq(from_id)))
+                .set(library::manager_id.eq(into_id))
+                .execute(conn)?;
+            diesel::update(library_staff::table.filter(library_staff::member_id.eq(from_id)))
+                .set(library_staff::member_id.eq(into_id))
+                .execute(conn)?;
+            diesel::update(library_handovers::table.filter(library_handovers::from_member_id.eq(from_id)))
+                .set(library_handovers::from_member_id.eq(into_id))
+                .execute(conn)?;
+            diesel::update(library_handovers::table.filter(library_handovers::to_member_id.eq(from_id)))
+                .set(library_handovers::to_member_id.eq(into_id))
+                .execute(conn)?;
+
+            let from: Member = dsl::members.find(from_id).first(conn)?;
+            diesel::delete(dsl::members.find(from_id))
+                .execute(conn)?;
+
+            // A ban or suspension of the duplicate carries over when it outlasts the kept record's own.
+            let into: Member = dsl::members.find(into_id).first(conn)?;
+            if into.block_outlasted_by(&from, &date[..10]) {
+                diesel::update(dsl::members.find(into_id))
+                    .set((
+                        dsl::status.eq(&from.status),
+                        dsl::status_reason.eq(&from.status_reason),
+                        dsl::status_since.eq(&from.status_since),
+                        dsl::status_until.eq(&from.status_until),
+                        dsl::updated_at.eq(&date),
+                        dsl::version.eq(dsl::version + 1),
+                    ))
+                    .execute(conn)?;
+            }
+
+            // Duplicates that predate the unique email index have no email_normalized; the kept record
+            // claims its address once no other member holds it.
+            if into.email_normalized.is_none() {
+                let email = normalize_email(&into.email);
+                let taken: i64 = dsl::members.filter(dsl::email_normalized.eq(&email)).count().get_result(conn)?;
+                if taken == 0 {
+                    diesel::update(dsl::members.find(into_id))
+                        .set(dsl::email_normalized.eq(&email))
+                        .execute(conn)?;
+                }
+            }
+
+            Ok(MergeSummary { loans, library_memberships, holds, accounts })
+        })
+    }
+
+    #[instrument(level = "debug", skip_all)]
+    fn get_hold_history(&mut self, member_id: &i32) -> QueryResult<Vec<Hold>> {
+        let mut conn = self.get_conn();
+
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            holds_dsl::holds
+                .filter(holds_dsl::member_id.eq(member_id))
+                .order(holds_dsl::id.desc())
+                .load(conn)
+        })
+    }
+
+    #[instrument(level = "debug", skip_all)]
+    fn get_member_library_ids(&mut self, member_id: &i32) -> QueryResult<Vec<i32>> {
+        let mut conn = self.get_conn();
+
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            library_members_dsl::library_members
+                .filter(library_members_dsl::member_id.eq(member_id))
+                .select(library_members_dsl::library_id)
+                .order(library_members_dsl::library_id)
+                .load(conn)
+        })
+    }
+
+    #[instrument(level = "debug", skip_all)]
+    fn erase_member(&mut self, id: &i32, at: &str) -> QueryResult<usize> {
+        let email = format!("erased-{}@invalid", id);
+        let mut conn = self.get_conn();
+
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            let open: i64 = borrowed_books::table
+                .filter(borrowed_books::member_id.eq(id).and(borrowed_books::returned_at.is_null()))
+                .count()
+                .get_result(conn)?;
+            if open > 0 {
+                return Ok(0);
+            }
+
+            let erased = diesel::update(dsl::members.find(id).filter(dsl::erased_at.is_null()))
+                .set((
+                    dsl::name.eq("Erased member"),
+                    dsl::email.eq(email.as_str()),
+                    dsl::email_normalized.eq(None::<&str>),
+                    dsl::language.eq(None::<&str>),
+                    dsl::notify_reminders.eq(false),
+                    dsl::status_reason.eq(None::<&str>),
+                    dsl::erased_at.eq(at),
+                    dsl::updated_at.eq(at),
+                    dsl::version.eq(dsl::version + 1),
+                ))
+                .execute(conn)?;
+            if erased == 0 {
+                return Ok(0);
+            }
+
+            for query in ERASE_QUERIES {
+                diesel::sql_query(query)
+                    .bind::<Integer, _>(id)
+                    .execute(conn)?;
+            }
+
+            Ok(erased)
+        })
+    }
+
+    #[instrument(level = "debug", skip_all)]
+    fn anonymize_loans(&mut self, now: &str, default_days: &i64) -> QueryResult<Vec<i32>> {
+        let mut conn = self.get_conn();
+
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            let loan_ids: Vec<i32> = diesel::sql_query(ANONYMIZE_QUERY)
+                .bind::<BigInt, _>(default_days)
+                .bind::<Text, _>(now)
+                .bind::<BigInt, _>(default_days)
+                .load::<LoanId>(conn)?
+                .into_iter()
+                .map(|loan| loan.id)
+                .collect();
+            if !loan_ids.is_empty() {
+                diesel::update(borrowed_books::table.filter(borrowed_books::id.eq_any(&loan_ids)))
+                    .set((borrowed_books::member_id.eq(None::<i32>), borrowed_books::anonymized_at.eq(now)))
+                    .execute(conn)?;
+                diesel::sql_query(ORPHAN_NOTICES_QUERY).execute(conn)?;
+            }
+
+            Ok(loan_ids)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use diesel::connection::SimpleConnection;
+    use domain::models::member::MEMBER_BANNED;
+    use domain::schema::{audit_log, refresh_tokens};
+
+    // Two records of one person from before the unique email index: only the older one holds the address.
+    fn repo_with_duplicates() -> MemberRepository<'static> {
+        let pool = crate::seeded_pool();
+        pool.get().unwrap()
+            .batch_execute("INSERT INTO members (id, name, email, created_at, updated_at) VALUES (2, 'Ada', 'Ada@Example.com', '2026-02-01', '2026-02-01')")
+            .unwrap();
+        MemberRepository::new(pool)
+    }
+
+    #[test]
+    fn merge_hands_the_email_to_the_kept_member() {
+        let mut repo = repo_with_duplicates();
+        repo.merge_members(&2, &1).unwrap();
+
+        let kept = repo.get_member_by_id(&2).unwrap();
+        assert_eq!(kept.email_normalized.as_deref(), Some("ada@example.com"));
+        assert!(repo.create_member("Ada", " ADA@example.com", None, None).is_err());
+    }
+
+    #[test]
+    fn merge_keeps_the_longer_block() {
+        let mut repo = repo_with_duplicates();
+        repo.get_conn()
+            .batch_execute("UPDATE members SET status = 'banned', status_reason = 'fraud', status_since = '2020-06-01' WHERE id = 1")
+            .unwrap();
+        repo.merge_members(&2, &1).unwrap();
+
+        let kept = repo.get_member_by_id(&2).unwrap();
+        assert_eq!(kept.status, MEMBER_BANNED);
+        assert_eq!(kept.status_reason.as_deref(), Some("fraud"));
+        assert_eq!(kept.version, 2);
+    }
+
+    #[test]
+    fn borrow_needs_a_copy_on_the_shelf() {
+        let mut repo = repo_with_duplicates();
+        repo.get_conn()
+            .batch_execute("INSERT INTO library_books (library_id, book_id, quantity) VALUES (1, 1, 1)")
+            .unwrap();
+
+        assert_eq!(repo.borrow_book(&1, &1, Some(&2), "2026-02-01").unwrap(), 0);
+        assert_eq!(repo.borrow_book(&1, &1, Some(&1), "2026-02-01").unwrap(), 1);
+        assert_eq!(repo.borrow_book(&2, &1, Some(&1), "2026-02-01").unwrap(), 0);
+        repo.return_book(&1, &1).unwrap();
+        assert_eq!(repo.borrow_book(&2, &1, Some(&1), "2026-02-01").unwrap(), 1);
+    }
+
+    const NOW: &str = "2026-06-01 10:00:00";
+
+    // Central keeps returned loans 10 days, North has no period of its own; both loans were returned 20 days
+    // before `NOW`.
+    fn repo_with_returned_loans() -> MemberRepository<'static> {
+        let mut repo = MemberRepository::new(crate::seeded_pool());
+        repo.get_conn()
+            .batch_execute("
+                UPDATE library SET loan_retention_days = 10 WHERE id = 1;
+                INSERT INTO borrowed_books (id, member_id, book_id, library_id, borrowed_at, returned_at)
+                    VALUES (1, 1, 1, 1, '2026-04-01 10:00:00', '2026-05-12 10:00:00');
+                INSERT INTO borrowed_books (id, member_id, book_id, library_id, borrowed_at, returned_at)
+                    VALUES (2, 1, 1, 2, '2026-04-02 10:00:00', '2026-05-12 10:00:00');
+                INSERT INTO borrowed_books (id, member_id, book_id, library_id, borrowed_at)
+                    VALUES (3, 1, 1, 2, '2026-01-01 10:00:00');
+            ")
+            .unwrap();
+        repo
+    }
+
+    #[test]
+    fn anonymize_uses_the_library_period_before_the_default() {
+        let mut repo = repo_with_returned_loans();
+
+        assert_eq!(repo.anonymize_loans(NOW, &30).unwrap(), vec![1]);
+        assert_eq!(repo.anonymize_loans(NOW, &15).unwrap(), vec![2]);
+        // The open loan stays linked however old it is.
+        assert_eq!(repo.get_loans(&1, true).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn anonymize_skips_a_zero_period() {
+        let mut repo = repo_with_returned_loans();
+        repo.get_conn().batch_execute("UPDATE library SET loan_retention_days = 0 WHERE id = 1").unwrap();
+
+        assert_eq!(repo.anonymize_loans(NOW, &0).unwrap(), Vec::<i32>::new());
+        assert_eq!(repo.anonymize_loans(NOW, &15).unwrap(), vec![2]);
+    }
+
+    #[test]
+    fn anonymize_skips_members_who_keep_their_history() {
+        let mut repo = repo_with_returned_loans();
+        repo.get_conn().batch_execute("UPDATE members SET keep_loan_history = 1 WHERE id = 1").unwrap();
+
+        assert_eq!(repo.anonymize_loans(NOW, &15).unwrap(), Vec::<i32>::new());
+        assert_eq!(repo.get_loans(&1, false).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn anonymize_drops_the_notices_of_unlinked_loans() {
+        let mut repo = repo_with_returned_loans();
+        repo.get_conn()
+            .batch_execute("
+                INSERT INTO loan_notices (member_id, book_id, borrowed_at, kind, channel, recipient, sent_at, due_at)
+                    VALUES (1, 1, '2026-04-01 10:00:00', 'overdue', 'email', 'ada@example.com', '2026-05-01', '2026-04-30');
+                INSERT INTO loan_notices (member_id, book_id, borrowed_at, kind, channel, recipient, sent_at, due_at)
+                    VALUES (1, 1, '2026-04-02 10:00:00', 'overdue', 'email', 'ada@example.com', '2026-05-02', '2026-05-01');
+            ")
+            .unwrap();
+
+        repo.anonymize_loans(NOW, &30).unwrap();
+        let kept: Vec<String> = loan_notices::table
+            .select(loan_notices::borrowed_at)
+            .load(&mut *repo.get_conn())
+            .unwrap();
+        assert_eq!(kept, vec!["2026-04-02 10:00:00"]);
+    }
+
+    // Ada signed in as user 7 and changed a book; entries 2 and 3 snapshot her member and user records, entry 4
+    // belongs to somebody else.
+    fn repo_with_history() -> MemberRepository<'static> {
+        let mut repo = MemberRepository::new(crate::seeded_pool());
+        repo.get_conn()
+            .batch_execute("
+                INSERT INTO users (id, username, password_hash, account_type, member_id, created_at, updated_at)
+                    VALUES (7, 'ada', 'hash', 'member', 1, '2026-01-01', '2026-01-01');
+                INSERT INTO refresh_tokens (user_id, token_hash, expires_at, created_at) VALUES (7, 'token', '2026-12-31', '2026-01-01');
+                INSERT INTO audit_log (id, occurred_at, request_id, actor_user_id, actor_username, action, entity, entity_id, before_json, after_json, diff_json)
+                    VALUES (1, '2026-01-02', 'r1', 7, 'ada', 'update', 'book', '1', '{\"title\":\"Dun\"}', '{\"title\":\"Dune\"}', '[]');
+                INSERT INTO audit_log (id, occurred_at, request_id, action, entity, entity_id, after_json)
+                    VALUES (2, '2026-01-01', 'r0', 'create', 'member', '1', '{\"name\":\"Ada\"}');
+                INSERT INTO audit_log (id, occurred_at, request_id, action, entity, entity_id, after_json)
+                    VALUES (3, '2026-01-01', 'r0', 'create', 'user', '7', '{\"username\":\"ada\"}');
+                INSERT INTO audit_log (id, occurred_at, request_id, action, entity, entity_id, after_json)
+                    VALUES (4, '2026-01-01', 'r0', 'create', 'member', '2', '{\"name\":\"Grace\"}');
+            ")
+            .unwrap();
+        repo
+    }
+
+    type Entry = (i32, Option<i32>, Option<String>, Option<String>, Option<String>);
+
+    fn entries(repo: &mut MemberRepository) -> Vec<Entry> {
+        audit_log::table
+            .select((audit_log::id, audit_log::actor_user_id, audit_log::actor_username, audit_log::before_json, audit_log::after_json))
+            .order(audit_log::id)
+            .load(&mut *repo.get_conn())
+            .unwrap()
+    }
+
+    #[test]
+    fn erase_clears_personal_data_and_keeps_the_audit_trail() {
+        let mut repo = repo_with_history();
+
+        assert_eq!(repo.erase_member(&1, NOW).unwrap(), 1);
+        let member = repo.get_member_by_id(&1).unwrap();
+        assert_eq!(member.name, "Erased member");
+        assert_eq!(member.email, "erased-1@invalid");
+        assert_eq!(member.email_normalized, None);
+        assert_eq!(member.erased_at.as_deref(), Some(NOW));
+
+        let mut conn = repo.get_conn();
+        assert_eq!(users::table.count().get_result::<i64>(&mut *conn).unwrap(), 0);
+        assert_eq!(refresh_tokens::table.count().get_result::<i64>(&mut *conn).unwrap(), 0);
+        drop(conn);
+
+        assert_eq!(
+            entries(&mut repo),
+            vec![
+                (1, None, None, Some("{\"title\":\"Dun\"}".to_string()), Some("{\"title\":\"Dune\"}".to_string())),
+                (2, None, None, None, None),
+                (3, None, None, None, None),
+                (4, None, None, None, Some("{\"name\":\"Grace\"}".to_string())),
+            ]
+        );
+        assert_eq!(repo.erase_member(&1, NOW).unwrap(), 0, "already erased");
+    }
+
+    #[test]
+    fn erase_waits_for_open_loans() {
+        let mut repo = repo_with_history();
+        repo.get_conn()
+            .batch_execute("INSERT INTO borrowed_books (member_id, book_id, borrowed_at) VALUES (1, 1, '2026-05-01 10:00:00')")
+            .unwrap();
+
+        assert_eq!(repo.erase_member(&1, NOW).unwrap(), 0);
+        assert_eq!(repo.get_member_by_id(&1).unwrap().name, "Ada");
+        assert_eq!(entries(&mut repo)[0].2.as_deref(), Some("ada"));
+    }
+
+    #[test]
+    fn the_audit_log_only_allows_clearing_personal_data() {
+        let mut repo = repo_with_history();
+        let mut conn = repo.get_conn();
+
+        for change in [
+            "UPDATE audit_log SET action = 'delete' WHERE id = 1",
+            "UPDATE audit_log SET entity_id = '2' WHERE id = 1",
+            "UPDATE audit_log SET actor_username = 'grace' WHERE id = 1",
+            "UPDATE audit_log SET before_json = '{}' WHERE id = 2",
+            "UPDATE audit_log SET after_json = '{}' WHERE id = 1",
+        ] {
+            assert!(conn.batch_execute(change).is_err(), "{}", change);
+        }
+        conn.batch_execute("UPDATE audit_log SET actor_user_id = NULL, actor_username = NULL, diff_json = NULL WHERE id = 1")
+            .unwrap();
+    }
 }
@@ -0,0 +1,147 @@
+use diesel::prelude::*;
+use diesel::r2d2::{ConnectionManager, Pool};
+use diesel::result::QueryResult;
+use diesel::sql_types::Text;
+use diesel::sqlite::SqliteConnection;
+use domain::models::notice::{DueLoan, LoanNotice, NewLoanNotice, NoticeFilter};
+use domain::schema::loan_notices::dsl as notices_dsl;
+use domain::traits::NoticeRepositoryTrait;
+use std::sync::Arc;
+use tracing::instrument;
+
+use crate::{Db, DbConnection};
+
+const DEFAULT_LIMIT: i64 = 1000;
+
+const DUE_LOANS_QUERY: &str = "
+    SELECT bb.member_id, m.name AS member_name, m.email AS member_email, m.language,
+           bb.book_id, b.title AS book_title, bb.borrowed_at, bb.due_at,
+           l.id AS library_id, l.name AS library_name
+    FROM borrowed_books bb
+    JOIN members m ON m.id = bb.member_id
+    JOIN books b ON b.id = bb.book_id
+    LEFT JOIN library l ON l.id = bb.library_id
+    WHERE bb.returned_at IS NULL AND bb.due_at IS NOT NULL AND bb.due_at >= ? AND bb.due_at < ?
+      AND (m.notify_reminders OR ? <> 'reminder')
+      AND NOT EXISTS (
+          SELECT 1 FROM loan_notices n
+          WHERE n.member_id = bb.member_id AND n.book_id = bb.book_id AND n.borrowed_at = bb.borrowed_at
+            AND n.due_at = bb.due_at AND n.kind = ?
+      )
+    ORDER BY bb.due_at
+";
+
+pub struct NoticeRepository<'c> {
+    db: Db<'c>,
+}
+
+impl<'c> NoticeRepository<'c> {
+    pub fn new(pool: Arc<Pool<ConnectionManager<SqliteConnection>>>) -> Self {
+        NoticeRepository { db: Db::Pool(pool) }
+    }
+
+    pub fn with_connection(conn: &'c mut SqliteConnection) -> Self {
+        NoticeRepository { db: Db::Connection(conn) }
+    }
+
+    fn get_conn(&mut self) -> DbConnection<'_> {
+        self.db.get_conn()
+    }
+}
+
+impl NoticeRepositoryTrait for NoticeRepository<'_> {
+    #[instrument(level = "debug", skip_all)]
+    fn find_due_loans(&mut self, kind: &str, from: &str, until: &str) -> QueryResult<Vec<DueLoan>> {
+        let mut conn = self.get_conn();
+
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            diesel::sql_query(DUE_LOANS_QUERY)
+                .bind::<Text, _>(from)
+                .bind::<Text, _>(until)
+                .bind::<Text, _>(kind)
+                .bind::<Text, _>(kind)
+                .load(conn)
+        })
+    }
+
+    // Ignores a notice that is already recorded, so two overlapping scans cannot fail each other.
+    #[instrument(level = "debug", skip_all)]
+    fn record_notice(&mut self, notice: &NewLoanNotice) -> QueryResult<usize> {
+        let mut conn = self.get_conn();
+
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            diesel::insert_or_ignore_into(notices_dsl::loan_notices)
+                .values(notice)
+                .execute(conn)
+        })
+    }
+
+    #[instrument(level = "debug", skip_all)]
+    fn get_notices(&mut self, filter: &NoticeFilter) -> QueryResult<Vec<LoanNotice>> {
+        let mut conn = self.get_conn();
+
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            let mut query = notices_dsl::loan_notices.into_boxed();
+            if let Some(member_id) = &filter.member_id {
+                query = query.filter(notices_dsl::member_id.eq(member_id));
+            }
+            if let Some(kind) = &filter.kind {
+                query = query.filter(notices_dsl::kind.eq(kind));
+            }
+
+            query
+                .order(notices_dsl::id.desc())
+                .limit(filter.limit.unwrap_or(DEFAULT_LIMIT))
+                .load(conn)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use diesel::connection::SimpleConnection;
+    use domain::models::notice::NOTICE_OVERDUE;
+
+    fn repo_with_loan() -> NoticeRepository<'static> {
+        let pool = crate::seeded_pool();
+        pool.get().unwrap()
+            .batch_execute("INSERT INTO borrowed_books (member_id, book_id, borrowed_at, due_at) VALUES (1, 1, '2026-01-01 10:00:00', '2026-01-15 10:00:00')")
+            .unwrap();
+        NoticeRepository::new(pool)
+    }
+
+    fn overdue(repo: &mut NoticeRepository) -> Vec<DueLoan> {
+        repo.find_due_loans(NOTICE_OVERDUE, "", "2026-03-01 00:00:00").unwrap()
+    }
+
+    fn record(repo: &mut NoticeRepository, loan: &DueLoan) -> usize {
+        repo.record_notice(&NewLoanNotice {
+            member_id: &loan.member_id,
+            book_id: &loan.book_id,
+            borrowed_at: &loan.borrowed_at,
+            kind: NOTICE_OVERDUE,
+            channel: "log",
+            recipient: &loan.member_email,
+            sent_at: "2026-02-01 00:00:00",
+            due_at: Some(&loan.due_at),
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn a_notice_is_sent_once_per_due_date() {
+        let mut repo = repo_with_loan();
+        let loans = overdue(&mut repo);
+        assert_eq!(loans.len(), 1);
+        assert_eq!(record(&mut repo, &loans[0]), 1);
+        assert!(overdue(&mut repo).is_empty());
+        assert_eq!(record(&mut repo, &loans[0]), 0);
+
+        repo.get_conn().batch_execute("UPDATE borrowed_books SET due_at = '2026-02-15 10:00:00', renewals = 1").unwrap();
+        let renewed = overdue(&mut repo);
+        assert_eq!(renewed.len(), 1);
+        assert_eq!(record(&mut repo, &renewed[0]), 1);
+        assert!(overdue(&mut repo).is_empty());
+    }
+}
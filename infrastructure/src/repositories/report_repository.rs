@@ -0,0 +1,199 @@
+use diesel::prelude::*;
+use diesel::r2d2::{ConnectionManager, Pool};
+use diesel::result::QueryResult;
+use diesel::sql_types::{BigInt, Integer, Nullable, Text};
+use diesel::sqlite::SqliteConnection;
+use domain::models::report::{Acquisition, AuthorLoans, BudgetSpending, LibraryOverdue, LibraryUtilization, MemberActivity, ReportScope, TitleLoans};
+use domain::traits::ReportRepositoryTrait;
+use std::sync::Arc;
+use tracing::instrument;
+
+use crate::{Db, DbConnection};
+
+// Each query takes the period bounds, then the library id twice (`? IS NULL OR ... = ?`), then the limit.
+const TOP_TITLES_QUERY: &str = "
+    SELECT b.id AS book_id, b.title, b.author, COUNT(*) AS loans
+    FROM borrowed_books bb
+    JOIN books b ON b.id = bb.book_id
+    WHERE bb.borrowed_at >= ? AND bb.borrowed_at < ?
+      AND (? IS NULL OR bb.library_id = ?)
+    GROUP BY b.id
+    ORDER BY loans DESC, b.title
+    LIMIT ?
+";
+
+const TOP_AUTHORS_QUERY: &str = "
+    SELECT b.author, COUNT(DISTINCT b.id) AS titles, COUNT(*) AS loans
+    FROM borrowed_books bb
+    JOIN books b ON b.id = bb.book_id
+    WHERE bb.borrowed_at >= ? AND bb.borrowed_at < ?
+      AND (? IS NULL OR bb.library_id = ?)
+    GROUP BY b.author
+    ORDER BY loans DESC, b.author
+    LIMIT ?
+";
+
+// Numbered parameters: `now`, the period, the library id and the limit.
+const OVERDUE_QUERY: &str = "
+    SELECT bb.library_id, l.name AS library_name,
+           SUM(CASE WHEN bb.returned_at IS NULL AND bb.due_at < ?1 THEN 1 ELSE 0 END) AS overdue,
+           SUM(CASE WHEN bb.returned_at > bb.due_at THEN 1 ELSE 0 END) AS returned_late,
+           MIN(CASE WHEN bb.returned_at IS NULL AND bb.due_at < ?1 THEN bb.due_at END) AS oldest_due_at
+    FROM borrowed_books bb
+    LEFT JOIN library l ON l.id = bb.library_id
+    WHERE bb.due_at IS NOT NULL AND bb.due_at >= ?2 AND bb.due_at < ?3
+      AND (?4 IS NULL OR bb.library_id = ?4)
+    GROUP BY bb.library_id
+    HAVING overdue > 0 OR returned_late > 0
+    ORDER BY overdue DESC, bb.library_id
+    LIMIT ?5
+";
+
+// Copies are the current stock; loans are those borrowed in the period.
+const UTILIZATION_QUERY: &str = "
+    SELECT library_id, library_name, copies, loans,
+           CASE WHEN copies > 0 THEN CAST(loans AS REAL) / copies ELSE 0.0 END AS loans_per_copy
+    FROM (
+        SELECT l.id AS library_id, l.name AS library_name,
+               COALESCE((SELECT SUM(lb.quantity) FROM library_books lb WHERE lb.library_id = l.id), 0) AS copies,
+               (SELECT COUNT(*) FROM borrowed_books bb
+                WHERE bb.library_id = l.id AND bb.borrowed_at >= ? AND bb.borrowed_at < ?) AS loans
+        FROM library l
+    )
+    WHERE (? IS NULL OR library_id = ?)
+    ORDER BY loans_per_copy DESC, library_id
+    LIMIT ?
+";
+
+// Preceded by `now`.
+const MEMBER_ACTIVITY_QUERY: &str = "
+    SELECT m.id AS member_id, m.name AS member_name, COUNT(*) AS loans, COUNT(DISTINCT bb.book_id) AS titles,
+           SUM(CASE WHEN bb.returned_at IS NULL AND bb.due_at < ? THEN 1 ELSE 0 END) AS overdue,
+           MAX(bb.borrowed_at) AS last_borrowed_at
+    FROM borrowed_books bb
+    JOIN members m ON m.id = bb.member_id
+    WHERE bb.borrowed_at >= ? AND bb.borrowed_at < ?
+      AND (? IS NULL OR bb.library_id = ?)
+    GROUP BY m.id
+    ORDER BY loans DESC, m.id
+    LIMIT ?
+";
+
+const ACQUISITIONS_QUERY: &str = "
+    SELECT lb.library_id, l.name AS library_name, lb.book_id, b.title, b.author, lb.quantity, lb.added_at
+    FROM library_books lb
+    JOIN library l ON l.id = lb.library_id
+    JOIN books b ON b.id = lb.book_id
+    WHERE lb.added_at >= ? AND lb.added_at < ?
+      AND (? IS NULL OR lb.library_id = ?)
+    ORDER BY lb.added_at DESC, lb.library_id, lb.book_id
+    LIMIT ?
+";
+
+// The period applies to when orders were placed.
+const BUDGET_SPENDING_QUERY: &str = "
+    SELECT bu.library_id, l.name AS library_name, bu.fiscal_year, COUNT(DISTINCT po.id) AS orders,
+           bu.amount_cents AS budget_cents,
+           COALESCE(SUM(CASE WHEN po.status = 'received' THEN pol.received_quantity ELSE pol.quantity END
+                        * pol.unit_price_cents), 0) AS committed_cents,
+           COALESCE(SUM(pol.received_quantity * pol.unit_price_cents), 0) AS spent_cents,
+           bu.amount_cents - COALESCE(SUM(CASE WHEN po.status = 'received' THEN pol.received_quantity ELSE pol.quantity END
+                                          * pol.unit_price_cents), 0) AS remaining_cents
+    FROM budgets bu
+    JOIN library l ON l.id = bu.library_id
+    LEFT JOIN purchase_orders po ON po.library_id = bu.library_id AND po.fiscal_year = bu.fiscal_year
+          AND po.status IN ('ordered', 'partially_received', 'received')
+          AND po.ordered_at >= ? AND po.ordered_at < ?
+    LEFT JOIN purchase_order_lines pol ON pol.order_id = po.id
+    WHERE (? IS NULL OR bu.library_id = ?)
+    GROUP BY bu.id
+    ORDER BY bu.fiscal_year DESC, l.name
+    LIMIT ?
+";
+
+pub struct ReportRepository<'c> {
+    db: Db<'c>,
+}
+
+impl<'c> ReportRepository<'c> {
+    pub fn new(pool: Arc<Pool<ConnectionManager<SqliteConnection>>>) -> Self {
+        ReportRepository { db: Db::Pool(pool) }
+    }
+
+    pub fn with_connection(conn: &'c mut SqliteConnection) -> Self {
+        ReportRepository { db: Db::Connection(conn) }
+    }
+
+    fn get_conn(&mut self) -> DbConnection<'_> {
+        self.db.get_conn()
+    }
+
+    fn load_scoped<T>(&mut self, query: &str, scope: &ReportScope) -> QueryResult<Vec<T>>
+    where
+        T: QueryableByName<diesel::sqlite::Sqlite> + 'static,
+    {
+        let mut conn = self.get_conn();
+
+        diesel::sql_query(query)
+            .bind::<Text, _>(&scope.from)
+            .bind::<Text, _>(&scope.until)
+            .bind::<Nullable<Integer>, _>(scope.library_id)
+            .bind::<Nullable<Integer>, _>(scope.library_id)
+            .bind::<BigInt, _>(scope.limit)
+            .load(&mut *conn)
+    }
+}
+
+impl ReportRepositoryTrait for ReportRepository<'_> {
+    #[instrument(level = "debug", skip_all)]
+    fn top_titles(&mut self, scope: &ReportScope) -> QueryResult<Vec<TitleLoans>> {
+        self.load_scoped(TOP_TITLES_QUERY, scope)
+    }
+
+    #[instrument(level = "debug", skip_all)]
+    fn top_authors(&mut self, scope: &ReportScope) -> QueryResult<Vec<AuthorLoans>> {
+        self.load_scoped(TOP_AUTHORS_QUERY, scope)
+    }
+
+    #[instrument(level = "debug", skip_all)]
+    fn overdue_by_library(&mut self, scope: &ReportScope, now: &str) -> QueryResult<Vec<LibraryOverdue>> {
+        let mut conn = self.get_conn();
+
+        diesel::sql_query(OVERDUE_QUERY)
+            .bind::<Text, _>(now)
+            .bind::<Text, _>(&scope.from)
+            .bind::<Text, _>(&scope.until)
+            .bind::<Nullable<Integer>, _>(scope.library_id)
+            .bind::<BigInt, _>(scope.limit)
+            .load(&mut *conn)
+    }
+
+    #[instrument(level = "debug", skip_all)]
+    fn utilization(&mut self, scope: &ReportScope) -> QueryResult<Vec<LibraryUtilization>> {
+        self.load_scoped(UTILIZATION_QUERY, scope)
+    }
+
+    #[instrument(level = "debug", skip_all)]
+    fn member_activity(&mut self, scope: &ReportScope, now: &str) -> QueryResult<Vec<MemberActivity>> {
+        let mut conn = self.get_conn();
+
+        diesel::sql_query(MEMBER_ACTIVITY_QUERY)
+            .bind::<Text, _>(now)
+            .bind::<Text, _>(&scope.from)
+            .bind::<Text, _>(&scope.until)
+            .bind::<Nullable<Integer>, _>(scope.library_id)
+            .bind::<Nullable<Integer>, _>(scope.library_id)
+            .bind::<BigInt, _>(scope.limit)
+            .load(&mut *conn)
+    }
+
+    #[instrument(level = "debug", skip_all)]
+    fn acquisitions(&mut self, scope: &ReportScope) -> QueryResult<Vec<Acquisition>> {
+        self.load_scoped(ACQUISITIONS_QUERY, scope)
+    }
+
+    #[instrument(level = "debug", skip_all)]
+    fn budget_spending(&mut self, scope: &ReportScope) -> QueryResult<Vec<BudgetSpending>> {
+        self.load_scoped(BUDGET_SPENDING_QUERY, scope)
+    }
+}
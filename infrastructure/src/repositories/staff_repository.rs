@@ -0,0 +1,222 @@
+use diesel::prelude::*;
+use diesel::r2d2::{ConnectionManager, Pool};
+use diesel::result::QueryResult;
+use diesel::sqlite::SqliteConnection;
+use domain::models::role::Role;
+use domain::models::staff::{Handover, NewHandover, NewStaffAssignment, StaffAssignment};
+use domain::schema::library_handovers::dsl as handovers_dsl;
+use domain::schema::library_staff::dsl as staff_dsl;
+use domain::traits::StaffRepositoryTrait;
+use std::sync::Arc;
+use tracing::instrument;
+
+use crate::{Db, DbConnection};
+
+// Starts a manager assignment for the member at the library as of `now`, unless one is already active.
+pub fn start_manager(conn: &mut SqliteConnection, library_id: &i32, member_id: &i32, now: &str) -> QueryResult<()> {
+    let today = &now[..10];
+    let active: i64 = staff_dsl::library_staff
+        .filter(staff_dsl::library_id.eq(library_id).and(staff_dsl::member_id.eq(member_id)))
+        .filter(staff_dsl::role.eq(Role::LibraryManager.as_str()))
+        .filter(staff_dsl::started_at.le(today))
+        .filter(staff_dsl::ended_at.is_null().or(staff_dsl::ended_at.gt(today)))
+        .count()
+        .get_result(conn)?;
+    if active == 0 {
+        diesel::insert_into(staff_dsl::library_staff)
+            .values(&NewStaffAssignment {
+                library_id,
+                member_id,
+                role: Role::LibraryManager.as_str(),
+                started_at: today,
+                ended_at: None,
+                created_at: now,
+            })
+            .execute(conn)?;
+    }
+    Ok(())
+}
+
+// Moves the library's management from one member to another: ends the outgoing manager's assignment,
+// starts one for the incoming manager and records the handover. Runs inside the caller's transaction.
+pub fn hand_over(conn: &mut SqliteConnection, library_id: &i32, from_member_id: &i32, to_member_id: &i32, now: &str) -> QueryResult<()> {
+    let today = &now[..10];
+    diesel::update(staff_dsl::library_staff
+        .filter(staff_dsl::library_id.eq(library_id).and(staff_dsl::member_id.eq(from_member_id)))
+        .filter(staff_dsl::role.eq(Role::LibraryManager.as_str()))
+        .filter(staff_dsl::started_at.le(today))
+        .filter(staff_dsl::ended_at.is_null().or(staff_dsl::ended_at.gt(today))))
+        .set(staff_dsl::ended_at.eq(today))
+        .execute(conn)?;
+    start_manager(conn, library_id, to_member_id, now)?;
+
+    diesel::insert_into(handovers_dsl::library_handovers)
+        .values(&NewHandover {
+            library_id,
+            from_member_id: Some(from_member_id),
+            to_member_id,
+            handed_over_at: now,
+        })
+        .execute(conn)?;
+    Ok(())
+}
+
+pub struct StaffRepository<'c> {
+    db: Db<'c>,
+}
+
+impl<'c> StaffRepository<'c> {
+    pub fn new(pool: Arc<Pool<ConnectionManager<SqliteConnection>>>) -> Self {
+        StaffRepository { db: Db::Pool(pool) }
+    }
+
+    pub fn with_connection(conn: &'c mut SqliteConnection) -> Self {
+        StaffRepository { db: Db::Connection(conn) }
+    }
+
+    fn get_conn(&mut self) -> DbConnection<'_> {
+        self.db.get_conn()
+    }
+}
+
+impl StaffRepositoryTrait for StaffRepository<'_> {
+    #[instrument(level = "debug", skip_all)]
+    fn get_staff(&mut self, library_id: &i32, day: Option<&str>) -> QueryResult<Vec<StaffAssignment>> {
+        let mut conn = self.get_conn();
+
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            let mut query = staff_dsl::library_staff
+                .filter(staff_dsl::library_id.eq(library_id))
+                .into_boxed();
+            if let Some(day) = day {
+                query = query
+                    .filter(staff_dsl::started_at.le(day))
+                    .filter(staff_dsl::ended_at.is_null().or(staff_dsl::ended_at.gt(day)));
+            }
+            query
+                .order((staff_dsl::started_at.desc(), staff_dsl::id.desc()))
+                .load(conn)
+        })
+    }
+
+    #[instrument(level = "debug", skip_all)]
+    fn get_assignment(&mut self, library_id: &i32, id: &i32) -> QueryResult<StaffAssignment> {
+        let mut conn = self.get_conn();
+
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            staff_dsl::library_staff
+                .find(id)
+                .filter(staff_dsl::library_id.eq(library_id))
+                .first(conn)
+        })
+    }
+
+    #[instrument(level = "debug", skip_all)]
+    fn add_assignment(&mut self, assignment: &NewStaffAssignment) -> QueryResult<StaffAssignment> {
+        let mut conn = self.get_conn();
+
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            diesel::insert_into(staff_dsl::library_staff)
+                .values(assignment)
+                .execute(conn)?;
+
+            staff_dsl::library_staff
+                .order(staff_dsl::id.desc())
+                .first(conn)
+        })
+    }
+
+    #[instrument(level = "debug", skip_all)]
+    fn set_assignment_dates(&mut self, library_id: &i32, id: &i32, started_at: &str, ended_at: Option<&str>) -> QueryResult<usize> {
+        let mut conn = self.get_conn();
+
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            diesel::update(staff_dsl::library_staff.find(id).filter(staff_dsl::library_id.eq(library_id)))
+                .set((staff_dsl::started_at.eq(started_at), staff_dsl::ended_at.eq(ended_at)))
+                .execute(conn)
+        })
+    }
+
+    #[instrument(level = "debug", skip_all)]
+    fn get_member_assignments(&mut self, member_id: &i32, day: &str) -> QueryResult<Vec<StaffAssignment>> {
+        let mut conn = self.get_conn();
+
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            staff_dsl::library_staff
+                .filter(staff_dsl::member_id.eq(member_id))
+                .filter(staff_dsl::started_at.le(day))
+                .filter(staff_dsl::ended_at.is_null().or(staff_dsl::ended_at.gt(day)))
+                .load(conn)
+        })
+    }
+
+    #[instrument(level = "debug", skip_all)]
+    fn get_handovers(&mut self, library_id: &i32) -> QueryResult<Vec<Handover>> {
+        let mut conn = self.get_conn();
+
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            handovers_dsl::library_handovers
+                .filter(handovers_dsl::library_id.eq(library_id))
+                .order(handovers_dsl::id.desc())
+                .load(conn)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use diesel::connection::SimpleConnection;
+
+    const TODAY: &str = "2026-03-10";
+
+    // Member 1 was a librarian at Central until yesterday, is one at North until today, and will be one at
+    // Central again from tomorrow.
+    fn repo() -> StaffRepository<'static> {
+        let pool = crate::seeded_pool();
+        pool.get().unwrap()
+            .batch_execute("
+                INSERT INTO library_staff (library_id, member_id, role, started_at, ended_at, created_at)
+                    VALUES (1, 1, 'librarian', '2026-01-01', '2026-03-09', '2026-01-01');
+                INSERT INTO library_staff (library_id, member_id, role, started_at, ended_at, created_at)
+                    VALUES (2, 1, 'librarian', '2026-01-01', '2026-03-10', '2026-01-01');
+                INSERT INTO library_staff (library_id, member_id, role, started_at, ended_at, created_at)
+                    VALUES (1, 1, 'librarian', '2026-03-11', NULL, '2026-01-01');
+            ")
+            .unwrap();
+        StaffRepository::new(pool)
+    }
+
+    fn libraries(assignments: Vec<StaffAssignment>) -> Vec<i32> {
+        assignments.iter().map(|assignment| assignment.library_id).collect()
+    }
+
+    #[test]
+    fn an_assignment_is_active_from_its_start_until_the_day_before_its_end() {
+        let mut repo = repo();
+
+        assert_eq!(libraries(repo.get_member_assignments(&1, "2026-03-09").unwrap()), vec![2]);
+        assert_eq!(libraries(repo.get_member_assignments(&1, TODAY).unwrap()), Vec::<i32>::new());
+        assert_eq!(libraries(repo.get_member_assignments(&1, "2026-03-11").unwrap()), vec![1]);
+        assert_eq!(repo.get_staff(&1, Some(TODAY)).unwrap().len(), 0);
+        assert_eq!(repo.get_staff(&1, None).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn hand_over_ends_the_outgoing_managers_assignment() {
+        let mut repo = repo();
+        let mut conn = repo.get_conn();
+        conn.batch_execute("INSERT INTO members (id, name, email, created_at, updated_at) VALUES (2, 'Grace', 'grace@example.com', '2026-01-01', '2026-01-01')")
+            .unwrap();
+        start_manager(&mut conn, &1, &1, "2026-01-01 09:00:00").unwrap();
+        hand_over(&mut conn, &1, &1, &2, "2026-03-10 09:00:00").unwrap();
+        drop(conn);
+
+        let managers = |repo: &mut StaffRepository, member_id: i32| -> Vec<i32> {
+            let assignments = repo.get_member_assignments(&member_id, TODAY).unwrap();
+            libraries(assignments.into_iter().filter(|assignment| assignment.role == Role::LibraryManager.as_str()).collect())
+        };
+        assert_eq!(managers(&mut repo, 1), Vec::<i32>::new());
+        assert_eq!(managers(&mut repo, 2), vec![1]);
+    }
+}
@@ -0,0 +1,68 @@
+use diesel::prelude::*;
+use diesel::r2d2::{ConnectionManager, Pool};
+use diesel::result::QueryResult;
+use diesel::sqlite::SqliteConnection;
+use domain::models::stats::{LibraryAvailability, LoanCounts};
+use domain::schema::borrowed_books::dsl as borrowed_books_dsl;
+use domain::traits::StatsRepositoryTrait;
+use std::sync::Arc;
+use tracing::instrument;
+
+use crate::{Db, DbConnection};
+
+pub struct StatsRepository<'c> {
+    db: Db<'c>,
+}
+
+impl<'c> StatsRepository<'c> {
+    pub fn new(pool: Arc<Pool<ConnectionManager<SqliteConnection>>>) -> Self {
+        StatsRepository { db: Db::Pool(pool) }
+    }
+
+    pub fn with_connection(conn: &'c mut SqliteConnection) -> Self {
+        StatsRepository { db: Db::Connection(conn) }
+    }
+
+    fn get_conn(&mut self) -> DbConnection<'_> {
+        self.db.get_conn()
+    }
+}
+
+impl StatsRepositoryTrait for StatsRepository<'_> {
+    #[instrument(level = "debug", skip_all)]
+    fn get_loan_counts(&mut self, now: &str) -> QueryResult<LoanCounts> {
+        let mut conn = self.get_conn();
+
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            let active: i64 = borrowed_books_dsl::borrowed_books
+                .filter(borrowed_books_dsl::returned_at.is_null())
+                .count()
+                .get_result(conn)?;
+            let overdue: i64 = borrowed_books_dsl::borrowed_books
+                .filter(borrowed_books_dsl::returned_at.is_null())
+                .filter(borrowed_books_dsl::due_at.lt(now))
+                .count()
+                .get_result(conn)?;
+
+            Ok(LoanCounts { active, overdue })
+        })
+    }
+
+    // Loans count against the library they were booked to.
+    #[instrument(level = "debug", skip_all)]
+    fn get_available_copies(&mut self) -> QueryResult<Vec<LibraryAvailability>> {
+        let mut conn = self.get_conn();
+
+        diesel::sql_query("
+            SELECT lb.library_id AS library_id,
+                   SUM(lb.quantity) - SUM((
+                       SELECT COUNT(*) FROM borrowed_books bb
+                       WHERE bb.book_id = lb.book_id AND bb.library_id = lb.library_id AND bb.returned_at IS NULL
+                   )) AS available
+            FROM library_books lb
+            GROUP BY lb.library_id
+            ORDER BY lb.library_id
+        ")
+        .load(&mut *conn)
+    }
+}
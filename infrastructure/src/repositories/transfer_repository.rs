@@ -0,0 +1,242 @@
+use diesel::prelude::*;
+use diesel::r2d2::{ConnectionManager, Pool};
+use diesel::result::QueryResult;
+use diesel::sql_types::{BigInt, Integer, Text};
+use diesel::sqlite::SqliteConnection;
+use domain::models::transfer::{
+    NewTransfer, Transfer, TransferFilter, TRANSFER_APPROVED, TRANSFER_CANCELLED, TRANSFER_IN_TRANSIT, TRANSFER_RECEIVED,
+    TRANSFER_RETURNED,
+};
+use domain::schema::library_books::dsl as library_books_dsl;
+use domain::schema::transfers::dsl as transfers_dsl;
+use domain::traits::TransferRepositoryTrait;
+use std::sync::Arc;
+use tracing::instrument;
+
+use crate::{Db, DbConnection};
+
+// Copies on the shelf: the holding's quantity minus the open loans booked to that library.
+const AVAILABLE_QUERY: &str = "
+    SELECT COALESCE((SELECT quantity FROM library_books WHERE library_id = ? AND book_id = ?), 0)
+         - (SELECT COUNT(*) FROM borrowed_books WHERE library_id = ? AND book_id = ? AND returned_at IS NULL) AS available
+";
+
+// Adds copies to a library, creating the holding when it has none yet.
+const RECEIVE_QUERY: &str = "
+    INSERT INTO library_books (library_id, book_id, quantity, added_at) VALUES (?, ?, ?, ?)
+    ON CONFLICT (library_id, book_id) DO UPDATE SET quantity = quantity + excluded.quantity
+";
+
+#[derive(QueryableByName)]
+struct Available {
+    #[diesel(sql_type = BigInt)]
+    available: i64,
+}
+
+pub(crate) fn available(conn: &mut SqliteConnection, library_id: &i32, book_id: &i32) -> QueryResult<i64> {
+    let row: Available = diesel::sql_query(AVAILABLE_QUERY)
+        .bind::<Integer, _>(library_id)
+        .bind::<Integer, _>(book_id)
+        .bind::<Integer, _>(library_id)
+        .bind::<Integer, _>(book_id)
+        .get_result(conn)?;
+
+    Ok(row.available)
+}
+
+// Takes copies off a library's shelf; rolls back when fewer are available.
+fn take_copies(conn: &mut SqliteConnection, library_id: &i32, book_id: &i32, quantity: &i32) -> QueryResult<()> {
+    if available(conn, library_id, book_id)? < *quantity as i64 {
+        return Err(diesel::result::Error::RollbackTransaction);
+    }
+
+    diesel::update(library_books_dsl::library_books.filter(library_books_dsl::library_id.eq(library_id).and(library_books_dsl::book_id.eq(book_id))))
+        .set(library_books_dsl::quantity.eq(library_books_dsl::quantity - quantity))
+        .execute(conn)?;
+    Ok(())
+}
+
+// Adds copies to a library's stock.
+pub fn put_copies(conn: &mut SqliteConnection, library_id: &i32, book_id: &i32, quantity: &i32, at: &str) -> QueryResult<()> {
+    diesel::sql_query(RECEIVE_QUERY)
+        .bind::<Integer, _>(library_id)
+        .bind::<Integer, _>(book_id)
+        .bind::<Integer, _>(quantity)
+        .bind::<Text, _>(at)
+        .execute(conn)?;
+    Ok(())
+}
+
+pub struct TransferRepository<'c> {
+    db: Db<'c>,
+}
+
+impl<'c> TransferRepository<'c> {
+    pub fn new(pool: Arc<Pool<ConnectionManager<SqliteConnection>>>) -> Self {
+        TransferRepository { db: Db::Pool(pool) }
+    }
+
+    pub fn with_connection(conn: &'c mut SqliteConnection) -> Self {
+        TransferRepository { db: Db::Connection(conn) }
+    }
+
+    fn get_conn(&mut self) -> DbConnection<'_> {
+        self.db.get_conn()
+    }
+}
+
+impl TransferRepositoryTrait for TransferRepository<'_> {
+    #[instrument(level = "debug", skip_all)]
+    fn create_transfer(&mut self, transfer: &NewTransfer) -> QueryResult<Transfer> {
+        let mut conn = self.get_conn();
+
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            diesel::insert_into(transfers_dsl::transfers)
+                .values(transfer)
+                .execute(conn)?;
+
+            transfers_dsl::transfers
+                .order(transfers_dsl::id.desc())
+                .first(conn)
+        })
+    }
+
+    #[instrument(level = "debug", skip_all)]
+    fn get_transfer(&mut self, id: &i32) -> QueryResult<Transfer> {
+        let mut conn = self.get_conn();
+
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            transfers_dsl::transfers.find(id).first(conn)
+        })
+    }
+
+    #[instrument(level = "debug", skip_all)]
+    fn get_transfers(&mut self, filter: &TransferFilter) -> QueryResult<Vec<Transfer>> {
+        let mut conn = self.get_conn();
+
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            let mut query = transfers_dsl::transfers.into_boxed();
+            if let Some(library_id) = filter.library_id {
+                query = query.filter(transfers_dsl::from_library_id.eq(library_id).or(transfers_dsl::to_library_id.eq(library_id)));
+            }
+            if let Some(status) = &filter.status {
+                query = query.filter(transfers_dsl::status.eq(status));
+            }
+            if let Some(book_id) = filter.book_id {
+                query = query.filter(transfers_dsl::book_id.eq(book_id));
+            }
+            if let Some(limit) = filter.limit {
+                query = query.limit(limit);
+            }
+
+            query
+                .order(transfers_dsl::id.desc())
+                .load(conn)
+        })
+    }
+
+    #[instrument(level = "debug", skip_all)]
+    fn available_copies(&mut self, library_id: &i32, book_id: &i32) -> QueryResult<i64> {
+        let mut conn = self.get_conn();
+
+        conn.transaction::<_, diesel::result::Error, _>(|conn| available(conn, library_id, book_id))
+    }
+
+    #[instrument(level = "debug", skip_all)]
+    fn advance_transfer(&mut self, id: &i32, from: &str, to: &str, at: &str) -> QueryResult<usize> {
+        let mut conn = self.get_conn();
+
+        let advanced = conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            let target = transfers_dsl::transfers.filter(transfers_dsl::id.eq(id).and(transfers_dsl::status.eq(from)));
+            let updated = match to {
+                TRANSFER_APPROVED => diesel::update(target).set((transfers_dsl::status.eq(to), transfers_dsl::approved_at.eq(at))).execute(conn)?,
+                TRANSFER_IN_TRANSIT => diesel::update(target).set((transfers_dsl::status.eq(to), transfers_dsl::shipped_at.eq(at))).execute(conn)?,
+                TRANSFER_RECEIVED => diesel::update(target).set((transfers_dsl::status.eq(to), transfers_dsl::received_at.eq(at))).execute(conn)?,
+                TRANSFER_RETURNED => diesel::update(target).set((transfers_dsl::status.eq(to), transfers_dsl::returned_at.eq(at))).execute(conn)?,
+                TRANSFER_CANCELLED => diesel::update(target).set((transfers_dsl::status.eq(to), transfers_dsl::cancelled_at.eq(at))).execute(conn)?,
+                _ => 0,
+            };
+            if updated == 0 {
+                return Ok(0);
+            }
+
+            let transfer: Transfer = transfers_dsl::transfers.find(id).first(conn)?;
+            match to {
+                TRANSFER_IN_TRANSIT => take_copies(conn, &transfer.from_library_id, &transfer.book_id, &transfer.quantity)?,
+                TRANSFER_RECEIVED => put_copies(conn, &transfer.to_library_id, &transfer.book_id, &transfer.quantity, at)?,
+                TRANSFER_RETURNED => {
+                    take_copies(conn, &transfer.to_library_id, &transfer.book_id, &transfer.quantity)?;
+                    put_copies(conn, &transfer.from_library_id, &transfer.book_id, &transfer.quantity, at)?;
+                }
+                _ => {}
+            }
+
+            Ok(updated)
+        });
+
+        match advanced {
+            Err(diesel::result::Error::RollbackTransaction) => Ok(0),
+            other => other,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use diesel::connection::SimpleConnection;
+    use domain::models::transfer::TRANSFER_REQUESTED;
+
+    const AT: &str = "2026-03-01 10:00:00";
+
+    // Library 1 holds 3 copies of book 1, one of them on loan; library 2 holds none.
+    fn repo() -> TransferRepository<'static> {
+        let pool = crate::seeded_pool();
+        pool.get().unwrap().batch_execute("
+            INSERT INTO library_books (library_id, book_id, quantity) VALUES (1, 1, 3);
+            INSERT INTO borrowed_books (member_id, book_id, library_id, borrowed_at, due_at) VALUES (1, 1, 1, '2026-02-20', '2026-03-20');
+        ").unwrap();
+        TransferRepository::new(pool)
+    }
+
+    fn approved(repo: &mut TransferRepository, quantity: i32) -> Transfer {
+        let transfer = repo.create_transfer(&NewTransfer {
+            book_id: &1,
+            from_library_id: &1,
+            to_library_id: &2,
+            member_id: None,
+            quantity: &quantity,
+            status: TRANSFER_REQUESTED,
+            note: None,
+            requested_by: None,
+            requested_at: AT,
+        }).unwrap();
+        assert_eq!(repo.advance_transfer(&transfer.id, TRANSFER_REQUESTED, TRANSFER_APPROVED, AT).unwrap(), 1);
+        transfer
+    }
+
+    #[test]
+    fn copies_move_between_libraries_with_the_transfer() {
+        let mut repo = repo();
+        let transfer = approved(&mut repo, 2);
+
+        assert_eq!(repo.advance_transfer(&transfer.id, TRANSFER_APPROVED, TRANSFER_IN_TRANSIT, AT).unwrap(), 1);
+        assert_eq!((repo.available_copies(&1, &1).unwrap(), repo.available_copies(&2, &1).unwrap()), (0, 0));
+        assert_eq!(repo.advance_transfer(&transfer.id, TRANSFER_IN_TRANSIT, TRANSFER_RECEIVED, AT).unwrap(), 1);
+        assert_eq!((repo.available_copies(&1, &1).unwrap(), repo.available_copies(&2, &1).unwrap()), (0, 2));
+        assert_eq!(repo.advance_transfer(&transfer.id, TRANSFER_RECEIVED, TRANSFER_RETURNED, AT).unwrap(), 1);
+        assert_eq!((repo.available_copies(&1, &1).unwrap(), repo.available_copies(&2, &1).unwrap()), (2, 0));
+        // Steps out of order change nothing.
+        assert_eq!(repo.advance_transfer(&transfer.id, TRANSFER_IN_TRANSIT, TRANSFER_RECEIVED, AT).unwrap(), 0);
+    }
+
+    #[test]
+    fn copies_on_loan_are_not_shipped() {
+        let mut repo = repo();
+        let transfer = approved(&mut repo, 3);
+
+        assert_eq!(repo.advance_transfer(&transfer.id, TRANSFER_APPROVED, TRANSFER_IN_TRANSIT, AT).unwrap(), 0);
+        assert_eq!(repo.get_transfer(&transfer.id).unwrap().status, TRANSFER_APPROVED);
+        assert_eq!(repo.available_copies(&1, &1).unwrap(), 2);
+    }
+}
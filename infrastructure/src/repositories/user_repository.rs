@@ -0,0 +1,266 @@
+use diesel::prelude::*;
+use diesel::r2d2::{ConnectionManager, Pool};
+use diesel::result::QueryResult;
+use diesel::sqlite::SqliteConnection;
+use domain::models::role::{NewUserRole, UserRole};
+use domain::models::user::{ApiKey, NewApiKey, NewRefreshToken, NewUser, RefreshToken, User};
+use domain::schema::api_keys::dsl as api_keys_dsl;
+use domain::schema::refresh_tokens::dsl as refresh_tokens_dsl;
+use domain::schema::user_roles::dsl as user_roles_dsl;
+use domain::schema::users::dsl as users_dsl;
+use domain::traits::UserRepositoryTrait;
+use std::sync::Arc;
+use tracing::instrument;
+
+use crate::{Db, DbConnection};
+
+pub struct UserRepository<'c> {
+    db: Db<'c>,
+}
+
+impl<'c> UserRepository<'c> {
+    pub fn new(pool: Arc<Pool<ConnectionManager<SqliteConnection>>>) -> Self {
+        UserRepository { db: Db::Pool(pool) }
+    }
+
+    pub fn with_connection(conn: &'c mut SqliteConnection) -> Self {
+        UserRepository { db: Db::Connection(conn) }
+    }
+
+    fn get_conn(&mut self) -> DbConnection<'_> {
+        self.db.get_conn()
+    }
+}
+
+impl UserRepositoryTrait for UserRepository<'_> {
+    #[instrument(level = "debug", skip_all)]
+    fn create_user(&mut self, username: &str, password_hash: &str, account_type: &str, member_id: Option<&i32>) -> QueryResult<User> {
+        let mut conn = self.get_conn();
+        let date: String = chrono::offset::Utc::now().naive_utc().to_string();
+        let new_user = NewUser {
+            username,
+            password_hash,
+            account_type,
+            member_id,
+            created_at: date.as_str(),
+            updated_at: date.as_str(),
+        };
+
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            diesel::insert_into(users_dsl::users)
+                .values(&new_user)
+                .execute(conn)?;
+
+            users_dsl::users
+                .order(users_dsl::id.desc())
+                .first(conn)
+        })
+    }
+
+    #[instrument(level = "debug", skip_all)]
+    fn get_users(&mut self) -> QueryResult<Vec<User>> {
+        let mut conn = self.get_conn();
+
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            users_dsl::users.load(conn)
+        })
+    }
+
+    #[instrument(level = "debug", skip_all)]
+    fn get_user_by_id(&mut self, id: &i32) -> QueryResult<User> {
+        let mut conn = self.get_conn();
+
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            users_dsl::users.find(id).first(conn)
+        })
+    }
+
+    #[instrument(level = "debug", skip_all)]
+    fn get_user_by_username(&mut self, username: &str) -> QueryResult<User> {
+        let mut conn = self.get_conn();
+
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            users_dsl::users
+                .filter(users_dsl::username.eq(username))
+                .first(conn)
+        })
+    }
+
+    #[instrument(level = "debug", skip_all)]
+    fn count_users(&mut self) -> QueryResult<i64> {
+        let mut conn = self.get_conn();
+
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            users_dsl::users.count().get_result(conn)
+        })
+    }
+
+    #[instrument(level = "debug", skip_all)]
+    fn create_refresh_token(&mut self, user_id: &i32, token_hash: &str, expires_at: &str) -> QueryResult<usize> {
+        let mut conn = self.get_conn();
+        let date: String = chrono::offset::Utc::now().naive_utc().to_string();
+        let new_token = NewRefreshToken {
+            user_id,
+            token_hash,
+            expires_at,
+            created_at: date.as_str(),
+        };
+
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            diesel::insert_into(refresh_tokens_dsl::refresh_tokens)
+                .values(&new_token)
+                .execute(conn)
+        })
+    }
+
+    #[instrument(level = "debug", skip_all)]
+    fn get_refresh_token(&mut self, token_hash: &str) -> QueryResult<RefreshToken> {
+        let mut conn = self.get_conn();
+
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            refresh_tokens_dsl::refresh_tokens
+                .filter(refresh_tokens_dsl::token_hash.eq(token_hash))
+                .first(conn)
+        })
+    }
+
+    #[instrument(level = "debug", skip_all)]
+    fn revoke_refresh_token(&mut self, token_hash: &str) -> QueryResult<usize> {
+        let mut conn = self.get_conn();
+        let date: String = chrono::offset::Utc::now().naive_utc().to_string();
+
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            diesel::update(refresh_tokens_dsl::refresh_tokens
+                .filter(refresh_tokens_dsl::token_hash.eq(token_hash).and(refresh_tokens_dsl::revoked_at.is_null())))
+                .set(refresh_tokens_dsl::revoked_at.eq(date.as_str()))
+                .execute(conn)
+        })
+    }
+
+    #[instrument(level = "debug", skip_all)]
+    fn purge_refresh_tokens(&mut self, before: &str) -> QueryResult<usize> {
+        let mut conn = self.get_conn();
+
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            diesel::delete(refresh_tokens_dsl::refresh_tokens
+                .filter(refresh_tokens_dsl::expires_at.lt(before).or(refresh_tokens_dsl::revoked_at.lt(before))))
+                .execute(conn)
+        })
+    }
+
+    #[instrument(level = "debug", skip_all)]
+    fn create_api_key(&mut self, user_id: &i32, name: &str, prefix: &str, key_hash: &str) -> QueryResult<ApiKey> {
+        let mut conn = self.get_conn();
+        let date: String = chrono::offset::Utc::now().naive_utc().to_string();
+        let new_key = NewApiKey {
+            user_id,
+            name,
+            prefix,
+            key_hash,
+            created_at: date.as_str(),
+        };
+
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            diesel::insert_into(api_keys_dsl::api_keys)
+                .values(&new_key)
+                .execute(conn)?;
+
+            api_keys_dsl::api_keys
+                .order(api_keys_dsl::id.desc())
+                .first(conn)
+        })
+    }
+
+    #[instrument(level = "debug", skip_all)]
+    fn get_api_keys(&mut self, user_id: &i32) -> QueryResult<Vec<ApiKey>> {
+        let mut conn = self.get_conn();
+
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            api_keys_dsl::api_keys
+                .filter(api_keys_dsl::user_id.eq(user_id))
+                .load(conn)
+        })
+    }
+
+    #[instrument(level = "debug", skip_all)]
+    fn get_api_key_by_hash(&mut self, key_hash: &str) -> QueryResult<ApiKey> {
+        let mut conn = self.get_conn();
+
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            api_keys_dsl::api_keys
+                .filter(api_keys_dsl::key_hash.eq(key_hash))
+                .first(conn)
+        })
+    }
+
+    #[instrument(level = "debug", skip_all)]
+    fn touch_api_key(&mut self, id: &i32) -> QueryResult<usize> {
+        let mut conn = self.get_conn();
+        let date: String = chrono::offset::Utc::now().naive_utc().to_string();
+
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            diesel::update(api_keys_dsl::api_keys.find(id))
+                .set(api_keys_dsl::last_used_at.eq(date.as_str()))
+                .execute(conn)
+        })
+    }
+
+    #[instrument(level = "debug", skip_all)]
+    fn revoke_api_key(&mut self, user_id: &i32, id: &i32) -> QueryResult<usize> {
+        let mut conn = self.get_conn();
+        let date: String = chrono::offset::Utc::now().naive_utc().to_string();
+
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            diesel::update(api_keys_dsl::api_keys
+                .find(id)
+                .filter(api_keys_dsl::user_id.eq(user_id).and(api_keys_dsl::revoked_at.is_null())))
+                .set(api_keys_dsl::revoked_at.eq(date.as_str()))
+                .execute(conn)
+        })
+    }
+
+    #[instrument(level = "debug", skip_all)]
+    fn get_user_roles(&mut self, user_id: &i32) -> QueryResult<Vec<UserRole>> {
+        let mut conn = self.get_conn();
+
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            user_roles_dsl::user_roles
+                .filter(user_roles_dsl::user_id.eq(user_id))
+                .load(conn)
+        })
+    }
+
+    #[instrument(level = "debug", skip_all)]
+    fn grant_role(&mut self, user_id: &i32, role: &str, library_id: Option<&i32>) -> QueryResult<UserRole> {
+        let mut conn = self.get_conn();
+        let date: String = chrono::offset::Utc::now().naive_utc().to_string();
+        let new_role = NewUserRole {
+            user_id,
+            role,
+            library_id,
+            created_at: date.as_str(),
+        };
+
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            diesel::insert_into(user_roles_dsl::user_roles)
+                .values(&new_role)
+                .execute(conn)?;
+
+            user_roles_dsl::user_roles
+                .order(user_roles_dsl::id.desc())
+                .first(conn)
+        })
+    }
+
+    #[instrument(level = "debug", skip_all)]
+    fn revoke_role(&mut self, user_id: &i32, id: &i32) -> QueryResult<usize> {
+        let mut conn = self.get_conn();
+
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            diesel::delete(user_roles_dsl::user_roles
+                .find(id)
+                .filter(user_roles_dsl::user_id.eq(user_id)))
+                .execute(conn)
+        })
+    }
+}
@@ -0,0 +1,264 @@
+use std::collections::BTreeMap;
+use std::io::{self, BufRead, Read, Write};
+use std::path::PathBuf;
+use std::process;
+use std::sync::Arc;
+
+use api::audit::{in_transaction, snapshot, Actor, AuditEvent};
+use clap::{Parser, Subcommand};
+use diesel::r2d2::{ConnectionManager, NopEventHandler, Pool};
+use diesel::sqlite::SqliteConnection;
+use domain::models::maintenance::StockChange;
+use domain::models::role::Role;
+use domain::models::user::ACCOUNT_STAFF;
+use domain::traits::{MaintenanceRepositoryTrait, UserRepositoryTrait};
+use infrastructure::config::{Config, ConfigError};
+use infrastructure::establish_connection;
+use infrastructure::migrations::{applied_version, latest_version, run_migrations};
+use infrastructure::repositories::maintenance_repository::MaintenanceRepository;
+use infrastructure::repositories::user_repository::UserRepository;
+use infrastructure::security::hash_password;
+
+type DbPool = Arc<Pool<ConnectionManager<SqliteConnection>>>;
+
+// Operational tasks run directly against the database, next to or instead of the HTTP server.
+#[derive(Debug, Parser)]
+#[command(name = "library-admin", version, about = "Library automation maintenance tasks")]
+struct Cli {
+    /// TOML config file (defaults to $LIBRARY_CONFIG, then ./library.toml if present)
+    #[arg(short, long, value_name = "FILE")]
+    config: Option<PathBuf>,
+
+    /// SQLite database path or URL
+    #[arg(long, value_name = "URL")]
+    database_url: Option<String>,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Apply pending schema migrations
+    Migrate,
+    /// Create a staff account with the system_admin role; the password is read from
+    /// $LIBRARY_ADMIN_PASSWORD or the first line of stdin
+    CreateAdmin {
+        #[arg(long)]
+        username: String,
+    },
+    /// Write members, libraries, books, holdings and loans as JSON
+    Export {
+        /// Output file (defaults to stdout)
+        #[arg(short, long, value_name = "FILE")]
+        output: Option<PathBuf>,
+    },
+    /// Load an export into a freshly migrated, empty database
+    Import {
+        /// Export file (defaults to stdin)
+        #[arg(value_name = "FILE")]
+        input: Option<PathBuf>,
+    },
+    /// Rebuild every index and refresh the query planner's statistics
+    Reindex,
+    /// Raise holdings that have fewer copies than open loans
+    RecomputeStock {
+        /// Only list the holdings that would change
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Report orphaned rows, negative quantities and loans on deleted books; exits with 1 when any are found
+    Check,
+}
+
+fn load_config(cli: &Cli) -> Result<Config, ConfigError> {
+    let mut config = Config::load(cli.config.as_deref())?;
+    if let Some(url) = &cli.database_url {
+        config.database.url = url.clone();
+    }
+    config.validate()?;
+
+    Ok(config)
+}
+
+fn schema_version(pool: &DbPool) -> Result<i32, String> {
+    let mut conn = pool.get().map_err(|e| e.to_string())?;
+    applied_version(&mut conn).map_err(|e| e.to_string())
+}
+
+// Every command but `migrate` expects the schema the binary was built for.
+fn require_current_schema(pool: &DbPool) -> Result<(), String> {
+    let applied = schema_version(pool)?;
+    if applied != latest_version() {
+        return Err(format!(
+            "The database is at schema version {}, expected {}; run `library-admin migrate` first",
+            applied,
+            latest_version()
+        ));
+    }
+    Ok(())
+}
+
+fn read_password() -> Result<String, String> {
+    if let Ok(password) = std::env::var("LIBRARY_ADMIN_PASSWORD") {
+        return Ok(password);
+    }
+
+    let mut line = String::new();
+    io::stdin().lock().read_line(&mut line).map_err(|e| e.to_string())?;
+    Ok(line.trim_end_matches(['\r', '\n']).to_string())
+}
+
+fn create_admin(pool: &DbPool, username: &str) -> Result<(), String> {
+    let password = read_password()?;
+    if password.len() < 8 {
+        return Err("The password must be at least 8 characters".to_string());
+    }
+
+    let password_hash = hash_password(&password).map_err(|e| format!("Failed to hash the password: {}", e))?;
+    let mut user_repo = UserRepository::new(pool.clone());
+    let user = user_repo
+        .create_user(username, &password_hash, ACCOUNT_STAFF, None)
+        .map_err(|e| format!("Failed to create {}: {}", username, e))?;
+    user_repo
+        .grant_role(&user.id, Role::SystemAdmin.as_str(), None)
+        .map_err(|e| format!("Failed to grant system_admin: {}", e))?;
+
+    println!("Created {} (user {}) with system_admin", username, user.id);
+    Ok(())
+}
+
+fn export(pool: &DbPool, output: Option<PathBuf>) -> Result<(), String> {
+    let data = MaintenanceRepository::new(pool.clone()).export_data().map_err(|e| format!("Failed to export: {}", e))?;
+    match output {
+        Some(path) => std::fs::write(&path, data).map_err(|e| format!("Failed to write {}: {}", path.display(), e)),
+        None => writeln!(io::stdout(), "{}", data).map_err(|e| e.to_string()),
+    }
+}
+
+fn import(pool: &DbPool, audit_log: bool, input: Option<PathBuf>) -> Result<(), String> {
+    let (source, data) = match input {
+        Some(path) => (
+            path.display().to_string(),
+            std::fs::read_to_string(&path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?,
+        ),
+        None => {
+            let mut data = String::new();
+            io::stdin().read_to_string(&mut data).map_err(|e| e.to_string())?;
+            ("stdin".to_string(), data)
+        }
+    };
+
+    let mut conn = pool.get().map_err(|e| e.to_string())?;
+    let rows = in_transaction(&mut conn, audit_log, &Actor::system("import"), |tx| {
+        let rows = tx.maintenance().import_data(&data)?;
+        tx.record(AuditEvent {
+            action: "import",
+            entity: "database",
+            entity_id: source,
+            before: None,
+            after: snapshot(&BTreeMap::from([("rows", rows)])),
+        })?;
+        Ok(rows)
+    })
+    .map_err(|e| format!("Failed to import: {}", e))?;
+    println!("Imported {} rows", rows);
+    Ok(())
+}
+
+fn recompute_stock(pool: &DbPool, audit_log: bool, dry_run: bool) -> Result<(), String> {
+    let mut conn = pool.get().map_err(|e| e.to_string())?;
+    let changes = in_transaction(&mut conn, audit_log, &Actor::system("recompute_stock"), |tx| {
+        let changes = tx.maintenance().recompute_stock(!dry_run)?;
+        if !dry_run {
+            for change in &changes {
+                let after = StockChange { quantity: Some(change.open_loans as i32), ..*change };
+                tx.record(AuditEvent {
+                    action: if change.quantity.is_some() { "update" } else { "create" },
+                    entity: "library_book",
+                    entity_id: format!("{}:{}", change.library_id, change.book_id),
+                    before: change.quantity.and_then(|_| snapshot(change)),
+                    after: snapshot(&after),
+                })?;
+            }
+        }
+        Ok(changes)
+    })
+    .map_err(|e| format!("Failed to recompute stock: {}", e))?;
+
+    for change in &changes {
+        let quantity = change.quantity.map(|quantity| quantity.to_string()).unwrap_or_else(|| "none".to_string());
+        println!(
+            "library {} book {}: quantity {} -> {} (open loans)",
+            change.library_id, change.book_id, quantity, change.open_loans
+        );
+    }
+    match (changes.len(), dry_run) {
+        (0, _) => println!("Stock covers every open loan"),
+        (count, true) => println!("{} holdings would change; run without --dry-run to apply", count),
+        (count, false) => println!("Updated {} holdings", count),
+    }
+    Ok(())
+}
+
+// Returns whether every check passed.
+fn check(pool: &DbPool) -> Result<bool, String> {
+    let checks = MaintenanceRepository::new(pool.clone())
+        .check_integrity()
+        .map_err(|e| format!("Failed to check integrity: {}", e))?;
+
+    for check in &checks {
+        if check.count == 0 {
+            println!("ok      {}", check.name);
+            continue;
+        }
+        println!("FAILED  {}: {} ({})", check.name, check.description, check.count);
+        println!("        {}", check.keys.join(", "));
+    }
+    Ok(checks.iter().all(|check| check.count == 0))
+}
+
+fn run(cli: Cli, config: &Config) -> Result<bool, String> {
+    let pool = establish_connection(&config.database, Box::new(NopEventHandler));
+
+    if let Command::Migrate = cli.command {
+        let before = schema_version(&pool)?;
+        run_migrations(&pool);
+        println!("Schema at version {} (was {})", latest_version(), before);
+        return Ok(true);
+    }
+
+    require_current_schema(&pool)?;
+    match cli.command {
+        Command::Migrate => unreachable!(),
+        Command::CreateAdmin { username } => create_admin(&pool, &username).map(|_| true),
+        Command::Export { output } => export(&pool, output).map(|_| true),
+        Command::Import { input } => import(&pool, config.features.audit_log, input).map(|_| true),
+        Command::Reindex => MaintenanceRepository::new(pool.clone())
+            .reindex()
+            .map(|_| {
+                println!("Rebuilt indexes and statistics");
+                true
+            })
+            .map_err(|e| format!("Failed to reindex: {}", e)),
+        Command::RecomputeStock { dry_run } => recompute_stock(&pool, config.features.audit_log, dry_run).map(|_| true),
+        Command::Check => check(&pool),
+    }
+}
+
+fn main() {
+    let cli = Cli::parse();
+    let config = load_config(&cli).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        process::exit(2);
+    });
+
+    match run(cli, &config) {
+        Ok(true) => {}
+        Ok(false) => process::exit(1),
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+    }
+}
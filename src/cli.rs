@@ -0,0 +1,94 @@
+use std::path::PathBuf;
+
+use clap::builder::PossibleValuesParser;
+use clap::Parser;
+use infrastructure::config::{Config, FEATURES};
+
+// Command line flags; anything given here overrides the config file and the environment.
+#[derive(Debug, Parser)]
+#[command(name = "LibraryAutomation", version, about = "Library automation HTTP server")]
+pub struct Cli {
+    /// TOML config file (defaults to $LIBRARY_CONFIG, then ./library.toml if present)
+    #[arg(short, long, value_name = "FILE")]
+    pub config: Option<PathBuf>,
+
+    /// Print the effective configuration with secrets redacted and exit
+    #[arg(long)]
+    pub print_config: bool,
+
+    /// Address to listen on; repeat to bind several
+    #[arg(long, value_name = "HOST:PORT")]
+    pub bind: Vec<String>,
+
+    /// Number of HTTP worker threads
+    #[arg(long)]
+    pub workers: Option<usize>,
+
+    /// SQLite database path or URL
+    #[arg(long, value_name = "URL")]
+    pub database_url: Option<String>,
+
+    /// Maximum number of pooled database connections
+    #[arg(long)]
+    pub pool_max_size: Option<u32>,
+
+    /// Minimum number of idle pooled connections
+    #[arg(long)]
+    pub pool_min_idle: Option<u32>,
+
+    /// Seconds to wait for a pooled connection before failing
+    #[arg(long)]
+    pub pool_timeout_seconds: Option<u64>,
+
+    /// One of trace, debug, info, warn, error
+    #[arg(long)]
+    pub log_level: Option<String>,
+
+    /// Log output format: pretty or json
+    #[arg(long)]
+    pub log_format: Option<String>,
+
+    /// Turn a feature toggle on
+    #[arg(long, value_name = "FEATURE", value_parser = PossibleValuesParser::new(FEATURES))]
+    pub enable: Vec<String>,
+
+    /// Turn a feature toggle off
+    #[arg(long, value_name = "FEATURE", value_parser = PossibleValuesParser::new(FEATURES))]
+    pub disable: Vec<String>,
+}
+
+impl Cli {
+    pub fn apply(&self, config: &mut Config) -> Result<(), Vec<String>> {
+        if !self.bind.is_empty() {
+            config.server.bind = self.bind.clone();
+        }
+        if self.workers.is_some() {
+            config.server.workers = self.workers;
+        }
+        if let Some(url) = &self.database_url {
+            config.database.url = url.clone();
+        }
+        if let Some(size) = self.pool_max_size {
+            config.database.pool_max_size = size;
+        }
+        if self.pool_min_idle.is_some() {
+            config.database.pool_min_idle = self.pool_min_idle;
+        }
+        if let Some(seconds) = self.pool_timeout_seconds {
+            config.database.connection_timeout_seconds = seconds;
+        }
+        if let Some(level) = &self.log_level {
+            config.logging.level = level.clone();
+        }
+        if let Some(format) = &self.log_format {
+            config.logging.format = format.clone();
+        }
+
+        let toggles = self.enable.iter().map(|name| (name, true)).chain(self.disable.iter().map(|name| (name, false)));
+        let problems: Vec<String> = toggles
+            .filter_map(|(name, enabled)| config.features.set(name, enabled).err())
+            .collect();
+
+        if problems.is_empty() { Ok(()) } else { Err(problems) }
+    }
+}
@@ -1,24 +1,86 @@
+mod cli;
+mod telemetry;
+
+use std::process;
+use std::sync::Arc;
+use std::time::Duration;
+
+use actix_web::middleware::from_fn;
 use actix_web::{web, App, HttpServer};
-use api::controllers::book_controller::book_routes;
-use api::controllers::library_controller::library_routes;
-use api::controllers::member_controller::member_routes;
-use api::create_app_state;
+use api::jobs::start_job_scheduler;
+use api::{create_app_state, routes};
+use api::metrics::{track_requests, Metrics};
+use api::request_id::request_tracing;
+use clap::Parser;
+use infrastructure::checkpoint_wal;
+use infrastructure::config::{Config, ConfigError};
+
+use cli::Cli;
+
+// Defaults, config file and environment, then the command line, validated as a whole.
+fn load_config(cli: &Cli) -> Result<Config, ConfigError> {
+    let mut config = Config::load(cli.config.as_deref())?;
+    let mut problems = cli.apply(&mut config).err().unwrap_or_default();
+    if let Err(ConfigError::Invalid(invalid)) = config.validate() {
+        problems.extend(invalid);
+    }
+
+    if problems.is_empty() { Ok(config) } else { Err(ConfigError::Invalid(problems)) }
+}
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
-    println!("Starting server at http://127.0.0.1:8080");
+    let cli = Cli::parse();
+    let config = load_config(&cli).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        process::exit(2);
+    });
+
+    if cli.print_config {
+        print!("{}", config.to_redacted_toml());
+        return Ok(());
+    }
 
-    let app_state = create_app_state();
+    let metrics = Arc::new(Metrics::new());
+    let _telemetry = telemetry::init(&config.logging, metrics.repository_layer());
+
+    let app_state = create_app_state(&config, metrics);
     let app_data = web::Data::new(app_state);
+    let state = app_data.clone();
+    let api = config.api.clone();
+
+    start_job_scheduler(state.clone());
 
-    HttpServer::new(move || {
+    let mut server = HttpServer::new(move || {
         App::new()
             .app_data(app_data.clone())
-            .configure(book_routes)
-            .configure(library_routes)
-            .configure(member_routes)
+            .wrap(from_fn(track_requests))
+            .wrap(from_fn(request_tracing))
+            .configure(|cfg| routes(cfg, &api))
     })
-    .bind("127.0.0.1:8080")?
-    .run()
-    .await
+    .keep_alive(Duration::from_secs(config.server.keep_alive_seconds))
+    .client_request_timeout(Duration::from_millis(config.server.client_request_timeout_ms))
+    .shutdown_timeout(config.server.shutdown_timeout_seconds);
+
+    if let Some(workers) = config.server.workers {
+        server = server.workers(workers);
+    }
+    for addr in &config.server.bind {
+        server = server.bind(addr)?;
+        tracing::info!("Starting server at http://{}", addr);
+    }
+
+    // On SIGINT/SIGTERM actix stops accepting connections and drains in-flight requests for up to
+    // server.shutdown_timeout_seconds before `run` returns.
+    server.run().await?;
+
+    tracing::info!("HTTP server stopped; stopping background tasks");
+    state.background.shutdown(Duration::from_secs(config.server.shutdown_timeout_seconds)).await;
+
+    match checkpoint_wal(&state.pool) {
+        Ok(()) => tracing::info!("SQLite WAL checkpointed"),
+        Err(e) => tracing::error!(error = %e, "Failed to checkpoint SQLite WAL"),
+    }
+
+    Ok(())
 }
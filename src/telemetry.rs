@@ -0,0 +1,103 @@
+use std::io::IsTerminal;
+
+use api::metrics::RepositoryTimingLayer;
+use infrastructure::config::LoggingConfig;
+use tracing::Level;
+use tracing_subscriber::filter::Targets;
+use tracing_subscriber::fmt::format::FmtSpan;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{EnvFilter, Layer};
+
+// Keeps the span exporter alive for the life of the process and flushes it on drop.
+pub struct Telemetry {
+    #[cfg(feature = "otel")]
+    provider: Option<opentelemetry_sdk::trace::SdkTracerProvider>,
+}
+
+impl Drop for Telemetry {
+    fn drop(&mut self) {
+        #[cfg(feature = "otel")]
+        if let Some(provider) = self.provider.take() {
+            if let Err(e) = provider.shutdown() {
+                eprintln!("Failed to flush OpenTelemetry spans: {}", e);
+            }
+        }
+    }
+}
+
+// RUST_LOG, when set, takes precedence over logging.level so individual targets (e.g. `sql=debug`) can be tuned.
+fn log_filter(config: &LoggingConfig) -> EnvFilter {
+    EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(config.level.to_lowercase()))
+}
+
+// Filters are per layer: repository spans always reach the metrics layer, whatever the log level.
+pub fn init(config: &LoggingConfig, repository_timing: RepositoryTimingLayer) -> Telemetry {
+    let json = config.format == "json";
+    let json_layer = json.then(|| {
+        tracing_subscriber::fmt::layer()
+            .json()
+            .with_current_span(true)
+            .with_span_list(false)
+            .with_span_events(FmtSpan::CLOSE)
+            .with_filter(log_filter(config))
+    });
+    let pretty_layer = (!json).then(|| {
+        tracing_subscriber::fmt::layer()
+            .with_ansi(std::io::stdout().is_terminal())
+            .with_span_events(FmtSpan::CLOSE)
+            .with_filter(log_filter(config))
+    });
+    let timing_layer = repository_timing.with_filter(Targets::new().with_target("infrastructure::repositories", Level::DEBUG));
+
+    #[cfg(feature = "otel")]
+    let (otel_layer, provider) = match config.otlp_endpoint.as_deref().map(otlp_provider) {
+        Some(Ok(provider)) => {
+            use opentelemetry::trace::TracerProvider as _;
+            let layer = tracing_opentelemetry::layer()
+                .with_tracer(provider.tracer("library-automation"))
+                .with_filter(log_filter(config));
+            (Some(layer), Some(provider))
+        }
+        Some(Err(e)) => {
+            eprintln!("Failed to set up OpenTelemetry export: {}", e);
+            (None, None)
+        }
+        None => (None, None),
+    };
+    #[cfg(not(feature = "otel"))]
+    let otel_layer: Option<tracing_subscriber::layer::Identity> = None;
+
+    tracing_subscriber::registry()
+        .with(timing_layer)
+        .with(json_layer)
+        .with(pretty_layer)
+        .with(otel_layer)
+        .init();
+
+    #[cfg(not(feature = "otel"))]
+    if config.otlp_endpoint.is_some() {
+        tracing::warn!("logging.otlp_endpoint is set but this build has no `otel` feature; spans are not exported");
+    }
+
+    Telemetry {
+        #[cfg(feature = "otel")]
+        provider,
+    }
+}
+
+#[cfg(feature = "otel")]
+fn otlp_provider(endpoint: &str) -> Result<opentelemetry_sdk::trace::SdkTracerProvider, opentelemetry_otlp::ExporterBuildError> {
+    use opentelemetry_otlp::WithExportConfig;
+
+    let exporter = opentelemetry_otlp::SpanExporter::builder()
+        .with_http()
+        .with_endpoint(format!("{}/v1/traces", endpoint.trim_end_matches('/')))
+        .build()?;
+    let resource = opentelemetry_sdk::Resource::builder().with_service_name("library-automation").build();
+
+    Ok(opentelemetry_sdk::trace::SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_resource(resource)
+        .build())
+}